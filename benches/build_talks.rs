@@ -0,0 +1,28 @@
+//! Measures the build times of large talk graphs.
+//!
+//! Run it with `cargo bench --bench build_talks`. Criterion keeps the results of the previous run,
+//! so run it on two revisions to compare them.
+
+mod common;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use common::{branching_talk, build, linear_talk};
+
+/// Builds the linear and branching graphs of 5000 nodes.
+fn build_graphs(c: &mut Criterion) {
+    c.bench_function("build_linear_5000", |b| {
+        b.iter_custom(|iters| build(iters, || linear_talk(5000)))
+    });
+    c.bench_function("build_branching_5000", |b| {
+        b.iter_custom(|iters| build(iters, || branching_talk(1000)))
+    });
+}
+
+criterion_group! {
+    name = build_talks;
+    // the builds are heavy, a few samples are enough to compare two revisions
+    config = Criterion::default().sample_size(10);
+    targets = build_graphs
+}
+criterion_main!(build_talks);
@@ -0,0 +1,107 @@
+//! The fixtures shared by the benches.
+
+// each bench uses only some of the fixtures
+#![allow(dead_code)]
+
+use std::time::{Duration, Instant};
+
+use bevy::{ecs::system::CommandQueue, prelude::*};
+use bevy_talks::prelude::*;
+
+/// Returns an app with the talks plugin.
+pub fn talks_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((AssetPlugin::default(), TalksPlugin));
+    app
+}
+
+/// A linear talk with the given number of text nodes, all spoken by an actor.
+pub fn linear_talk(nodes: usize) -> TalkBuilder {
+    let mut builder = TalkBuilder::default().add_actor(Actor::new("bob", "Bob"));
+    for i in 0..nodes {
+        builder = builder.actor_say("bob", format!("Line {i}"));
+    }
+    builder
+}
+
+/// A talk made of `choices` choice nodes in a row, each with two branches of two actor nodes (5 nodes per choice).
+pub fn branching_talk(choices: usize) -> TalkBuilder {
+    let mut builder = TalkBuilder::default()
+        .add_actor(Actor::new("bob", "Bob"))
+        .add_actor(Actor::new("alice", "Alice"));
+    for i in 0..choices {
+        let branch = |who: &str| {
+            TalkBuilder::default()
+                .actor_say(who, format!("{who} {i}"))
+                .actor_say(who, format!("{who} {i} again"))
+        };
+        builder = builder.choose(vec![
+            (format!("Bob {i}"), branch("bob")),
+            (format!("Alice {i}"), branch("alice")),
+        ]);
+    }
+    builder
+}
+
+/// Returns the total time to build the talks made by `make`, each in a fresh world.
+pub fn build(iters: u64, make: impl Fn() -> TalkBuilder) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..iters {
+        let mut app = talks_app();
+        let builder = make();
+
+        let mut queue = CommandQueue::default();
+        Commands::new(&mut queue, &app.world).spawn_talk(builder);
+        let start = Instant::now();
+        queue.apply(&mut app.world);
+        total += start.elapsed();
+    }
+    total
+}
+
+/// Spawns the given number of talks with the given number of nodes, and starts them.
+pub fn spawn_talks(app: &mut App, talks: usize, nodes: usize) -> Vec<Entity> {
+    let mut queue = CommandQueue::default();
+    let mut commands = Commands::new(&mut queue, &app.world);
+    let talks: Vec<Entity> = (0..talks)
+        .map(|_| commands.spawn_talk(linear_talk(nodes)).id())
+        .collect();
+    queue.apply(&mut app.world);
+    for talk in talks.iter() {
+        app.world.send_event(NextNodeRequest::new(*talk));
+    }
+    app.update();
+    talks
+}
+
+/// Returns the total time of the updates sending the request made by `request` for each talk,
+/// checking that each talk emitted its text event.
+pub fn timed_updates(
+    iters: u64,
+    app: &mut App,
+    talks: &[Entity],
+    request: impl Fn(&mut World, Entity),
+) -> Duration {
+    app.world.resource_mut::<Events<TextNodeEvent>>().clear();
+    let mut total = Duration::ZERO;
+    for _ in 0..iters {
+        for talk in talks.iter() {
+            request(&mut app.world, *talk);
+        }
+        let start = Instant::now();
+        app.update();
+        total += start.elapsed();
+
+        let emitted = app
+            .world
+            .resource_mut::<Events<TextNodeEvent>>()
+            .drain()
+            .count();
+        assert_eq!(
+            emitted,
+            talks.len(),
+            "every talk should emit its text event"
+        );
+    }
+    total
+}
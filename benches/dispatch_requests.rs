@@ -0,0 +1,33 @@
+//! Measures the cost of dispatching a request when many talks are active at the same time.
+//!
+//! Run it with `cargo bench --bench dispatch_requests`. It measures an update advancing a single talk
+//! among the idle ones (the request is dispatched through the `CurrentNodes` index, without iterating
+//! over the current nodes of the other talks).
+
+mod common;
+
+use bevy_talks::prelude::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use common::{spawn_talks, talks_app, timed_updates};
+
+/// Advances one talk among 10, 1000 and 10000 started talks in each update.
+fn dispatch_one_request(c: &mut Criterion) {
+    let mut group = c.benchmark_group("dispatch_one_request");
+    for talks in [10, 1000, 10000] {
+        group.bench_with_input(BenchmarkId::from_parameter(talks), &talks, |b, &talks| {
+            b.iter_custom(|iters| {
+                let mut app = talks_app();
+                spawn_talks(&mut app, talks - 1, 1);
+                let advanced = spawn_talks(&mut app, 1, iters as usize + 1);
+                timed_updates(iters, &mut app, &advanced, |world, talk| {
+                    world.send_event(NextNodeRequest::new(talk));
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(dispatch_requests, dispatch_one_request);
+criterion_main!(dispatch_requests);
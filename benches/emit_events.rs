@@ -0,0 +1,36 @@
+//! Measures the cost of emitting the node events of many talks in the same frame.
+//!
+//! Run it with `cargo bench --bench emit_events`. It measures an update advancing all the talks,
+//! each emitting the events of its next node.
+
+mod common;
+
+use bevy_talks::prelude::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use common::{spawn_talks, talks_app, timed_updates};
+
+/// Advances 100 and 1000 talks in each update.
+fn advance_talks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("emit_events");
+    for talks in [100, 1000] {
+        group.bench_with_input(BenchmarkId::from_parameter(talks), &talks, |b, &talks| {
+            b.iter_custom(|iters| {
+                let mut app = talks_app();
+                let talks = spawn_talks(&mut app, talks, iters as usize + 1);
+                timed_updates(iters, &mut app, &talks, |world, talk| {
+                    world.send_event(NextNodeRequest::new(talk));
+                })
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = emit_events;
+    // the updates of many talks are heavy, a few samples are enough to compare two revisions
+    config = Criterion::default().sample_size(10);
+    targets = advance_talks
+}
+criterion_main!(emit_events);
@@ -0,0 +1,88 @@
+//! The performance budget of the crate: the main workloads, measured with criterion.
+//!
+//! Run it with `cargo bench --bench perf_budget`. The budgets of the workloads are advisory:
+//! `cargo run -p ci -- bench` runs this suite and then warns about the workloads over their budget
+//! (see `PERF_BUDGETS` in `tools/ci`), without failing. The budgets are generous on purpose, to flag the real
+//! regressions (e.g. an accidental quadratic) and not the noise of the CI runners.
+//!
+//! `cargo test --benches` runs each workload once as a smoke test.
+
+mod common;
+
+use bevy_talks::prelude::*;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use common::{branching_talk, build, linear_talk, spawn_talks, talks_app, timed_updates};
+
+/// The number of nodes of the large graphs.
+const NODES: usize = 10_000;
+
+/// The number of talks advanced in the same frame.
+const ADVANCED_TALKS: usize = 1_000;
+
+/// The number of node events emitted in the same frame.
+const EMITTED_EVENTS: usize = 10_000;
+
+/// Builds the linear and branching graphs of [`NODES`] nodes.
+fn build_graphs(c: &mut Criterion) {
+    c.bench_function("build_linear_10k", |b| {
+        b.iter_custom(|iters| build(iters, || linear_talk(NODES)))
+    });
+    c.bench_function("build_branching_10k", |b| {
+        b.iter_custom(|iters| build(iters, || branching_talk(NODES / 5)))
+    });
+}
+
+/// Advances [`ADVANCED_TALKS`] talks in each update.
+fn advance_talks(c: &mut Criterion) {
+    c.bench_function("advance_1k_talks", |b| {
+        b.iter_custom(|iters| {
+            let mut app = talks_app();
+            let talks = spawn_talks(&mut app, ADVANCED_TALKS, iters as usize + 1);
+            timed_updates(iters, &mut app, &talks, |world, talk| {
+                world.send_event(NextNodeRequest::new(talk));
+            })
+        })
+    });
+}
+
+/// Emits [`EMITTED_EVENTS`] node events in each update, refiring the current node of as many talks,
+/// so only the emission is measured and not the moves.
+fn emit_events(c: &mut Criterion) {
+    let mut app = talks_app();
+    let talks = spawn_talks(&mut app, EMITTED_EVENTS, 1);
+    c.bench_function("emit_10k_events", |b| {
+        b.iter_custom(|iters| {
+            timed_updates(iters, &mut app, &talks, |world, talk| {
+                world.send_event(RefireNodeRequest::new(talk));
+            })
+        })
+    });
+}
+
+/// Parses and validates a RON talk of [`NODES`] nodes, the same work as the asset loader without the IO.
+fn load_ron(c: &mut Criterion) {
+    let script = (1..=NODES)
+        .map(|id| {
+            let next = (id < NODES).then_some(id + 1);
+            format!(r#"(id: {id}, actors: ["bob"], text: Some("Line {id}"), next: {next:?}),"#)
+        })
+        .collect::<String>();
+    let ron = format!(r#"(actors: [(slug: "bob", name: "Bob")], script: [{script}])"#);
+
+    c.bench_function("load_ron_10k", |b| {
+        b.iter(|| {
+            let ir = TalkIr::from_ron(&ron).expect("the talk should parse");
+            assert_eq!(ir.nodes.len(), NODES);
+            TalkData::try_from(ir).expect("the talk should be valid")
+        })
+    });
+}
+
+criterion_group! {
+    name = perf_budget;
+    // the workloads are heavy, a few samples are enough to catch the regressions
+    config = Criterion::default().sample_size(10);
+    targets = build_graphs, advance_talks, emit_events, load_ron
+}
+criterion_main!(perf_budget);
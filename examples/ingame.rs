@@ -194,7 +194,7 @@ fn interact(
     input: Res<Input<KeyCode>>,
     player_query: Query<&Transform, With<Player>>,
     characters: Query<(&Transform, &Children), With<Interactable>>,
-    mut talks: Query<(Entity, &Talk, &mut ActiveTalk)>,
+    mut talks: Query<(Entity, Has<HasStarted>, &mut ActiveTalk), With<Talk>>,
     mut next_events: EventWriter<NextNodeRequest>,
     mut refire_events: EventWriter<RefireNodeRequest>,
 ) {
@@ -202,10 +202,10 @@ fn interact(
         let player_transform = player_query.single();
         for (transform, children) in &characters {
             if transform.translation.distance(player_transform.translation) < 100. {
-                let (e, talk, mut active) = talks.get_mut(children[0]).unwrap();
+                let (e, has_started, mut active) = talks.get_mut(children[0]).unwrap();
                 active.0 = !active.0; // toggle active
                 if active.0 {
-                    if !talk.has_started {
+                    if !has_started {
                         // if active and not started, start it now
                         next_events.send(NextNodeRequest::new(e));
                     } else {
@@ -1,53 +1,112 @@
 use proc_macro::{self, TokenStream};
-use proc_macro2::Ident;
+use proc_macro2::{Ident, TokenStream as TokenStream2};
 use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use syn::{parse_macro_input, DeriveInput, LitStr};
 
-#[proc_macro_derive(NodeEventEmitter)]
+mod talk;
+
+/// Derives `NodeEventEmitter` for a component, generating the `<Component>Event` it emits with the same fields
+/// (plus the `actors` names) and a `From<&Component>` impl for the event (with no actors).
+///
+/// The event derives `Debug`, so the fields must implement it. Add `#[node_event(partial_eq)]` on the component
+/// to also derive `PartialEq`, when all the fields support it, to compare the events in your tests:
+/// ```rust,ignore
+/// #[derive(NodeEventEmitter, Component)]
+/// #[node_event(partial_eq)]
+/// struct Shake {
+///     strength: f32,
+/// }
+/// ```
+///
+/// The fields are cloned into the event by default. Use `#[node_event(map = "path::to::fn")]` on a field
+/// to set it with `fn(&field) -> value` instead, and `ty = "Type"` when the event field has another type
+/// (it must implement `Reflect` and `Default` like the event):
+/// ```rust,ignore
+/// #[derive(NodeEventEmitter, Component)]
+/// struct PlaySound {
+///     #[node_event(map = "sound_path", ty = "AssetPath<'static>")]
+///     key: String,
+/// }
+/// ```
+#[proc_macro_derive(NodeEventEmitter, attributes(node_event))]
 pub fn derive_node_event_emitter(input: TokenStream) -> TokenStream {
-    let DeriveInput { ident, data, .. } = parse_macro_input!(input);
+    let DeriveInput {
+        ident, data, attrs, ..
+    } = parse_macro_input!(input);
     let struct_name = &ident;
     let event_struct_name = Ident::new(&format!("{}Event", struct_name), struct_name.span());
+    let partial_eq = match wants_partial_eq(&attrs) {
+        Ok(true) => quote! { PartialEq, },
+        Ok(false) => quote! {},
+        Err(err) => return err.into_compile_error().into(),
+    };
 
     let output = match data {
         syn::Data::Struct(data_struct) => match data_struct.fields {
             syn::Fields::Unit => {
                 quote! {
-                    #[derive(Event, Reflect, Default, Clone)]
+                    #[derive(Event, Reflect, Default, Clone, Debug, PartialEq)]
                     #[reflect(Event)]
                     pub struct #event_struct_name;
 
+                    impl From<&#struct_name> for #event_struct_name {
+                        fn from(_: &#struct_name) -> Self {
+                            #event_struct_name
+                        }
+                    }
+
                     impl NodeEventEmitter for #struct_name {
-                        fn make(&self, _actors: &[Actor]) -> Box<dyn Reflect> {
-                            Box::from(#event_struct_name)
+                        type Event = #event_struct_name;
+
+                        fn make(&self, _actors: &[Actor]) -> Self::Event {
+                            #event_struct_name::from(self)
                         }
                     }
                 }
             }
-            syn::Fields::Named(fs) => {
-                let field_names: Vec<Ident> =
-                    fs.named.iter().map(|f| f.ident.clone().unwrap()).collect();
-                let field_types: Vec<&syn::Type> = fs.named.iter().map(|f| &f.ty).collect();
+            syn::Fields::Named(fs) => match fs
+                .named
+                .iter()
+                .map(EventField::parse)
+                .collect::<syn::Result<Vec<_>>>()
+            {
+                Ok(fields) => {
+                    let field_names: Vec<&Ident> = fields.iter().map(|f| f.name).collect();
+                    let field_types: Vec<&syn::Type> = fields.iter().map(|f| f.ty()).collect();
+                    let field_values: Vec<TokenStream2> =
+                        fields.iter().map(|f| f.value()).collect();
 
-                quote! {
-                    /// The event emitted by the component.
-                    #[derive(Event, Reflect, Default, Clone)]
-                    #[reflect(Event)]
-                    pub struct #event_struct_name {
-                        actors: Vec<String>,
-                        #( #field_names: #field_types, )*
-                    }
+                    quote! {
+                        /// The event emitted by the component.
+                        #[derive(Event, Reflect, Default, Clone, Debug, #partial_eq)]
+                        #[reflect(Event)]
+                        pub struct #event_struct_name {
+                            actors: Vec<String>,
+                            #( #field_names: #field_types, )*
+                        }
 
-                    impl NodeEventEmitter for #struct_name {
-                        fn make(&self, actors: &[Actor]) -> Box<dyn Reflect> {
-                            Box::from(#event_struct_name {
-                                actors: actors.iter().map(|a| a.name.clone()).collect(),
-                                #( #field_names: self.#field_names.clone(), )*
-                            })
+                        impl From<&#struct_name> for #event_struct_name {
+                            fn from(component: &#struct_name) -> Self {
+                                Self {
+                                    actors: vec![],
+                                    #( #field_names: #field_values, )*
+                                }
+                            }
+                        }
+
+                        impl NodeEventEmitter for #struct_name {
+                            type Event = #event_struct_name;
+
+                            fn make(&self, actors: &[Actor]) -> Self::Event {
+                                let mut event = #event_struct_name::from(self);
+                                event.actors = actors.iter().map(|a| a.name.clone()).collect();
+                                event
+                            }
                         }
                     }
                 }
-            }
+                Err(err) => err.into_compile_error(),
+            },
             syn::Fields::Unnamed(_) => {
                 quote! {
                     compile_error!("NodeEventEmitter can only be derived for structs with named fields. Tuple structs are not supported yet.");
@@ -65,25 +124,153 @@ pub fn derive_node_event_emitter(input: TokenStream) -> TokenStream {
     output.into()
 }
 
+/// Reads the `#[node_event(partial_eq)]` attribute of the component, to derive `PartialEq` for the event.
+fn wants_partial_eq(attrs: &[syn::Attribute]) -> syn::Result<bool> {
+    let mut partial_eq = false;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("node_event")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("partial_eq") {
+                partial_eq = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `partial_eq`"))
+            }
+        })?;
+    }
+    Ok(partial_eq)
+}
+
+/// A field of a `NodeEventEmitter` component, with its `#[node_event(...)]` options.
+struct EventField<'a> {
+    /// The name of the field.
+    name: &'a Ident,
+    /// The type of the field in the component.
+    ty: &'a syn::Type,
+    /// The function mapping the component field to the event field.
+    map: Option<syn::ExprPath>,
+    /// The type of the field in the event, if it is not the same.
+    event_ty: Option<syn::Type>,
+}
+
+impl<'a> EventField<'a> {
+    /// Reads the field and its `#[node_event(map = "...", ty = "...")]` attributes.
+    fn parse(field: &'a syn::Field) -> syn::Result<Self> {
+        let mut event_field = Self {
+            name: field.ident.as_ref().expect("the fields are named"),
+            ty: &field.ty,
+            map: None,
+            event_ty: None,
+        };
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("node_event"))
+        {
+            attr.parse_nested_meta(|meta| {
+                let value: LitStr = meta.value()?.parse()?;
+                if meta.path.is_ident("map") {
+                    event_field.map = Some(value.parse()?);
+                } else if meta.path.is_ident("ty") {
+                    event_field.event_ty = Some(value.parse()?);
+                } else {
+                    return Err(meta.error("expected `map` or `ty`"));
+                }
+                Ok(())
+            })?;
+        }
+        if event_field.event_ty.is_some() && event_field.map.is_none() {
+            return Err(syn::Error::new_spanned(
+                field,
+                "a `ty` for the event field needs a `map` function",
+            ));
+        }
+        Ok(event_field)
+    }
+
+    /// The type of the field in the event.
+    fn ty(&self) -> &syn::Type {
+        self.event_ty.as_ref().unwrap_or(self.ty)
+    }
+
+    /// The expression of the event field value, from the `component`.
+    fn value(&self) -> TokenStream2 {
+        let name = self.name;
+        match &self.map {
+            Some(map) => quote! { #map(&component.#name) },
+            None => quote! { component.#name.clone() },
+        }
+    }
+}
+
+/// Defines a talk inline with a concise DSL, expanded to the equivalent `TalkBuilder` calls.
+///
+/// - `actors { slug: "Name", ... }` (optional, first) adds the actors to the builder.
+/// - `say "text";` adds a text node, `slug: "text";` adds a text node spoken by an actor.
+/// - `choose { "text" => { ... }, ... }` adds a choice node with a branch for each choice,
+///   `slug: choose { ... }` adds a choice node performed by an actor.
+/// - `'label:` before a `say` or `choose` names the node, `goto 'label;` jumps to it (also forward).
+///   A `goto` must be the last statement of its block, and the branches can't be empty.
+///
+/// The texts can be any expression convertible to a `String`.
+/// The labels are checked at compile time: jumping to an unknown label or defining a label twice is an error.
+///
+/// # Example
+/// ```rust
+/// use bevy_talks::prelude::*;
+///
+/// let builder: TalkBuilder = talk! {
+///     actors { bob: "Bob" }
+///     'hello: bob: "Hello! Do you want to hear a story?";
+///     choose {
+///         "Yes" => { bob: "Once upon a time..."; goto 'bye; }
+///         "Say that again?" => { goto 'hello; }
+///         "No" => { say "Maybe next time."; }
+///     }
+///     'bye: say "The End.";
+/// };
+/// ```
+#[proc_macro]
+pub fn talk(input: TokenStream) -> TokenStream {
+    let talk = parse_macro_input!(input as talk::TalkDef);
+    talk::expand(talk)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
 #[cfg(test)]
 mod tests {
-    use bevy::prelude::*;
+    use bevy::{ecs::system::CommandQueue, prelude::*};
     use bevy_talks::prelude::*;
 
     #[derive(NodeEventEmitter, Component)]
     struct TestEmpty;
 
     #[derive(NodeEventEmitter, Component)]
+    #[node_event(partial_eq)]
     struct TestNamed {
         field1: bool,
         field2: i32,
     }
 
+    /// Resolves a sound key into its asset path.
+    fn sound_path(key: &str) -> String {
+        format!("sounds/{key}.ogg")
+    }
+
+    #[derive(NodeEventEmitter, Component)]
+    struct TestMapped {
+        #[node_event(map = "sound_path")]
+        sound: String,
+        #[node_event(map = "Vec::len", ty = "usize")]
+        tags: Vec<String>,
+        volume: f32,
+    }
+
     #[test]
     fn test_empty_struct() {
         let empty = TestEmpty;
-        let boxed_event = empty.make(&[]);
-        assert!(boxed_event.is::<TestEmptyEvent>());
+        let event: TestEmptyEvent = empty.make(&[]);
+        assert_eq!(event, TestEmptyEvent);
     }
 
     #[test]
@@ -92,13 +279,31 @@ mod tests {
             field1: true,
             field2: 42,
         };
-        let boxed_event = named.make(&[]);
-        assert!(boxed_event.is::<TestNamedEvent>());
-        let event = boxed_event.downcast_ref::<TestNamedEvent>().unwrap();
+        let event: TestNamedEvent = named.make(&[]);
         assert_eq!(event.field1, true);
         assert_eq!(event.field2, 42);
     }
 
+    #[test]
+    fn test_events_debug_and_partial_eq() {
+        assert_eq!(TestEmptyEvent::from(&TestEmpty), TestEmptyEvent);
+        let named = TestNamed {
+            field1: true,
+            field2: 42,
+        };
+        let event = TestNamedEvent::from(&named);
+        assert_eq!(event, named.make(&[]));
+        assert_ne!(event, TestNamedEvent::default());
+        assert!(format!("{event:?}").contains("field2: 42"));
+
+        let mapped = TestMapped {
+            sound: "door".to_string(),
+            tags: vec![],
+            volume: 0.5,
+        };
+        assert!(format!("{:?}", TestMappedEvent::from(&mapped)).contains("sounds/door.ogg"));
+    }
+
     #[test]
     fn test_named_has_actors() {
         let named = TestNamed {
@@ -106,10 +311,75 @@ mod tests {
             field2: 42,
         };
 
-        let boxed_event = named.make(&[Actor::new("actor", "Actor")]);
-        assert!(boxed_event.is::<TestNamedEvent>());
-        let event = boxed_event.downcast_ref::<TestNamedEvent>().unwrap();
+        let event: TestNamedEvent = named.make(&[Actor::new("actor", "Actor")]);
         assert_eq!(event.actors.len(), 1);
         assert_eq!(event.actors[0], "Actor");
     }
+
+    #[test]
+    fn test_mapped_fields() {
+        let mapped = TestMapped {
+            sound: "door".to_string(),
+            tags: vec!["sfx".to_string(), "loud".to_string()],
+            volume: 0.5,
+        };
+        let event = TestMappedEvent::from(&mapped);
+        assert_eq!(event.sound, "sounds/door.ogg");
+        assert_eq!(event.tags, 2);
+        assert_eq!(event.volume, 0.5);
+        assert!(event.actors.is_empty());
+
+        let event = mapped.make(&[Actor::new("actor", "Actor")]);
+        assert_eq!(event.sound, "sounds/door.ogg");
+        assert_eq!(event.actors, vec!["Actor"]);
+    }
+
+    /// Spawns the talk and returns the app with the talk entity.
+    fn spawn(builder: TalkBuilder) -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins((AssetPlugin::default(), TalksPlugin));
+        let mut queue = CommandQueue::default();
+        let talk = Commands::new(&mut queue, &app.world)
+            .spawn_talk(builder)
+            .id();
+        queue.apply(&mut app.world);
+        (app, talk)
+    }
+
+    /// Sends the request and returns the text of the text node reached, if any.
+    fn step(app: &mut App, request: impl Event) -> Option<String> {
+        app.world.send_event(request);
+        app.update();
+        let evs = app.world.resource::<Events<TextNodeEvent>>();
+        evs.get_reader().read(evs).last().map(|e| e.text.clone())
+    }
+
+    #[test]
+    fn talk_macro_builds_the_graph() {
+        let (mut app, talk) = spawn(talk! {
+            actors { bob: "Bob" }
+            'hello: bob: "Hello";
+            bob: choose {
+                "Again" => { goto 'hello; }
+                "Skip" => { say "Skipping"; goto 'bye; }
+                "Chat" => { say format!("Nice {}", "weather"); }
+            }
+            say "Well";
+            'bye: say "Bye";
+        });
+
+        assert_eq!(step(&mut app, NextNodeRequest::new(talk)).unwrap(), "Hello");
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let evs = app.world.resource::<Events<ChoiceNodeEvent>>();
+        let event = evs.get_reader().read(evs).last().unwrap().clone();
+        assert_eq!(event.actors, vec!["Bob"]);
+        let texts: Vec<_> = event.choices.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["Again", "Skip", "Chat"]);
+
+        let skip = ChooseNodeRequest::new(talk, event.choices[1].next);
+        assert_eq!(step(&mut app, skip).unwrap(), "Skipping");
+        // the goto jumps over the converging node
+        assert_eq!(step(&mut app, NextNodeRequest::new(talk)).unwrap(), "Bye");
+    }
 }
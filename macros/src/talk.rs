@@ -0,0 +1,316 @@
+//! The `talk!` macro: a small DSL to define dialogues inline, expanded to `TalkBuilder` calls.
+
+use std::collections::HashSet;
+
+use proc_macro2::{Span, TokenStream};
+use proc_macro_crate::{crate_name, FoundCrate};
+use quote::{format_ident, quote};
+use syn::{
+    braced,
+    parse::{Parse, ParseStream},
+    Expr, Ident, Lifetime, LitStr, Result, Token,
+};
+
+/// The whole talk: the optional actors and the top level statements.
+pub struct TalkDef {
+    /// The actors to add to the builder (slug => name).
+    actors: Vec<(Ident, Expr)>,
+    /// The statements of the talk, in order.
+    stmts: Vec<Stmt>,
+}
+
+/// A statement of the DSL, with its optional label.
+struct Stmt {
+    /// The label of the node (`'name:`), to jump to it with `goto`.
+    label: Option<Lifetime>,
+    /// What the statement does.
+    kind: StmtKind,
+}
+
+/// The kinds of statements.
+enum StmtKind {
+    /// `say "text";` or `actor: "text";`
+    Say {
+        /// The actor saying the text, if any.
+        actor: Option<Ident>,
+        /// The text expression.
+        text: Expr,
+    },
+    /// `choose { "text" => { ... } }` or `actor: choose { ... }`
+    Choose {
+        /// The actor performing the choice node, if any.
+        actor: Option<Ident>,
+        /// The choice texts with their branches.
+        choices: Vec<(Expr, Vec<Stmt>)>,
+    },
+    /// `goto 'label;`
+    Goto(Lifetime),
+}
+
+impl Parse for TalkDef {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut actors = Vec::new();
+        if input.peek(Ident) && input.peek2(syn::token::Brace) {
+            let fork = input.fork();
+            if fork.parse::<Ident>()? == "actors" {
+                input.parse::<Ident>()?;
+                let content;
+                braced!(content in input);
+                while !content.is_empty() {
+                    let slug: Ident = content.parse()?;
+                    content.parse::<Token![:]>()?;
+                    let name: Expr = content.parse()?;
+                    actors.push((slug, name));
+                    if !content.is_empty() {
+                        content.parse::<Token![,]>()?;
+                    }
+                }
+            }
+        }
+        let stmts = parse_stmts(input)?;
+        Ok(TalkDef { actors, stmts })
+    }
+}
+
+/// Parses the statements until the end of the input.
+fn parse_stmts(input: ParseStream) -> Result<Vec<Stmt>> {
+    let mut stmts = Vec::new();
+    while !input.is_empty() {
+        stmts.push(input.parse()?);
+    }
+    Ok(stmts)
+}
+
+/// Returns whether the next tokens are a `choose { ... }` statement.
+fn peek_choose(input: ParseStream) -> bool {
+    input.peek2(syn::token::Brace)
+        && input
+            .fork()
+            .parse::<Ident>()
+            .is_ok_and(|ident| ident == "choose")
+}
+
+/// Parses the choices of a `choose` statement, after the `choose` keyword.
+fn parse_choices(input: ParseStream) -> Result<Vec<(Expr, Vec<Stmt>)>> {
+    let content;
+    braced!(content in input);
+    let mut choices = Vec::new();
+    while !content.is_empty() {
+        let text: Expr = content.parse()?;
+        content.parse::<Token![=>]>()?;
+        let branch;
+        braced!(branch in content);
+        let stmts = parse_stmts(&branch)?;
+        if stmts.is_empty() {
+            return Err(syn::Error::new_spanned(
+                text,
+                "a choice branch can't be empty",
+            ));
+        }
+        choices.push((text, stmts));
+        if content.peek(Token![,]) {
+            content.parse::<Token![,]>()?;
+        }
+    }
+    // the trailing semicolon is optional, like after the blocks in rust
+    if input.peek(Token![;]) {
+        input.parse::<Token![;]>()?;
+    }
+    Ok(choices)
+}
+
+impl Parse for Stmt {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let label = if input.peek(Lifetime) {
+            let label: Lifetime = input.parse()?;
+            input.parse::<Token![:]>()?;
+            Some(label)
+        } else {
+            None
+        };
+
+        let kind = if input.peek(Ident) && input.peek2(Token![:]) {
+            let actor: Ident = input.parse()?;
+            input.parse::<Token![:]>()?;
+            if peek_choose(input) {
+                input.parse::<Ident>()?;
+                StmtKind::Choose {
+                    actor: Some(actor),
+                    choices: parse_choices(input)?,
+                }
+            } else {
+                let text: Expr = input.parse()?;
+                input.parse::<Token![;]>()?;
+                StmtKind::Say {
+                    actor: Some(actor),
+                    text,
+                }
+            }
+        } else {
+            let keyword: Ident = input.parse()?;
+            match keyword.to_string().as_str() {
+                "say" => {
+                    let text: Expr = input.parse()?;
+                    input.parse::<Token![;]>()?;
+                    StmtKind::Say { actor: None, text }
+                }
+                "choose" => StmtKind::Choose {
+                    actor: None,
+                    choices: parse_choices(input)?,
+                },
+                "goto" => {
+                    let target: Lifetime = input.parse()?;
+                    input.parse::<Token![;]>()?;
+                    if let Some(label) = &label {
+                        return Err(syn::Error::new(
+                            label.span(),
+                            "labels can only be put on `say` and `choose` statements",
+                        ));
+                    }
+                    StmtKind::Goto(target)
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        keyword.span(),
+                        "expected `say`, `choose`, `goto` or `actor: ...`",
+                    ))
+                }
+            }
+        };
+
+        Ok(Stmt { label, kind })
+    }
+}
+
+/// Collects the labels defined in the statements (and their branches), erroring on duplicates.
+fn collect_labels(stmts: &[Stmt], labels: &mut Vec<Lifetime>) -> Result<()> {
+    for stmt in stmts {
+        if let Some(label) = &stmt.label {
+            if labels.iter().any(|l| l.ident == label.ident) {
+                return Err(syn::Error::new(
+                    label.span(),
+                    format!("label `{label}` is already defined"),
+                ));
+            }
+            labels.push(label.clone());
+        }
+        if let StmtKind::Choose { choices, .. } = &stmt.kind {
+            for (_, branch) in choices {
+                collect_labels(branch, labels)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the gotos jump to defined labels and end their block.
+/// `nested` is whether the statements are a choice branch, where a lone `goto` connects the choice itself.
+fn check_gotos(stmts: &[Stmt], labels: &HashSet<String>, nested: bool) -> Result<()> {
+    for (i, stmt) in stmts.iter().enumerate() {
+        match &stmt.kind {
+            StmtKind::Goto(target) => {
+                if !labels.contains(&target.ident.to_string()) {
+                    return Err(syn::Error::new(
+                        target.span(),
+                        format!("unknown label `{target}`"),
+                    ));
+                }
+                if i + 1 != stmts.len() {
+                    return Err(syn::Error::new(
+                        target.span(),
+                        "`goto` must be the last statement of its block",
+                    ));
+                }
+                if i == 0 && !nested {
+                    return Err(syn::Error::new(
+                        target.span(),
+                        "`goto` needs a node to jump from",
+                    ));
+                }
+            }
+            StmtKind::Choose { choices, .. } => {
+                for (_, branch) in choices {
+                    check_gotos(branch, labels, true)?;
+                }
+            }
+            StmtKind::Say { .. } => {}
+        }
+    }
+    Ok(())
+}
+
+/// The variable holding the id of the node with the given label.
+fn label_var(label: &Lifetime) -> Ident {
+    format_ident!("__talk_label_{}", label.ident, span = Span::mixed_site())
+}
+
+/// The path to the `bevy_talks` crate, even if renamed.
+fn crate_path() -> TokenStream {
+    match crate_name("bevy_talks") {
+        Ok(FoundCrate::Name(name)) => {
+            let name = Ident::new(&name, Span::call_site());
+            quote!(::#name)
+        }
+        _ => quote!(::bevy_talks),
+    }
+}
+
+/// Expands the statements to a `TalkBuilder` expression.
+fn expand_stmts(krate: &TokenStream, stmts: &[Stmt]) -> TokenStream {
+    let mut builder = quote!(#krate::prelude::TalkBuilder::default());
+    for stmt in stmts {
+        builder = match &stmt.kind {
+            StmtKind::Say { actor: None, text } => quote!(#builder.say(#text)),
+            StmtKind::Say {
+                actor: Some(actor),
+                text,
+            } => {
+                let slug = LitStr::new(&actor.to_string(), actor.span());
+                quote!(#builder.actor_say(#slug, #text))
+            }
+            StmtKind::Choose { actor, choices } => {
+                let choices = choices.iter().map(|(text, branch)| {
+                    let branch = expand_stmts(krate, branch);
+                    quote!((::std::convert::Into::<::std::string::String>::into(#text), #branch))
+                });
+                match actor {
+                    None => quote!(#builder.choose(::std::vec![#(#choices),*])),
+                    Some(actor) => {
+                        let slug = LitStr::new(&actor.to_string(), actor.span());
+                        quote!(#builder.actor_choose(#slug, ::std::vec![#(#choices),*]))
+                    }
+                }
+            }
+            StmtKind::Goto(target) => {
+                let var = label_var(target);
+                quote!(#builder.connect_to(#var))
+            }
+        };
+        if let Some(label) = &stmt.label {
+            let var = label_var(label);
+            builder = quote!(#builder.node_id(#var));
+        }
+    }
+    builder
+}
+
+/// Checks the labels of the talk and expands it to a block evaluating to the `TalkBuilder`.
+pub fn expand(talk: TalkDef) -> Result<TokenStream> {
+    let mut labels = Vec::new();
+    collect_labels(&talk.stmts, &mut labels)?;
+    let names = labels.iter().map(|l| l.ident.to_string()).collect();
+    check_gotos(&talk.stmts, &names, false)?;
+
+    let krate = crate_path();
+    let label_vars = labels.iter().map(label_var);
+    let actors = talk.actors.iter().map(|(slug, name)| {
+        let slug = LitStr::new(&slug.to_string(), slug.span());
+        quote!(.add_actor(#krate::prelude::Actor::new(#slug, #name)))
+    });
+    let builder = expand_stmts(&krate, &talk.stmts);
+
+    Ok(quote! {{
+        #( let #label_vars = #krate::prelude::BuildNodeId::new(); )*
+        #builder #(#actors)*
+    }})
+}
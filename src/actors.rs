@@ -1,10 +1,16 @@
 //! Main actor types
 
+#[cfg(feature = "portraits")]
+use bevy::{asset::Handle, render::texture::Image};
 use bevy::{
-    ecs::{bundle::Bundle, component::Component},
+    core::Name,
+    ecs::{bundle::Bundle, component::Component, reflect::ReflectComponent, system::Resource},
     reflect::Reflect,
+    utils::HashMap,
 };
 
+use crate::builder::{for_each_build_node_mut, TalkBuilder};
+
 /// A unique identifier for an actor in a Talk.
 ///
 /// The slug is a `String` that uniquely identifies an actor.
@@ -13,22 +19,219 @@ use bevy::{
 pub(crate) type ActorSlug = String;
 
 /// The actor component for the actor entities in a Talk.
-#[derive(Component, Reflect, Debug, Clone, PartialEq, Eq)]
+#[derive(Component, Reflect, Default, Debug, Clone, PartialEq, Eq)]
+#[reflect(Component, Default)]
 pub struct Actor {
     /// The name of the character that the actor plays.
     pub name: String,
     /// The unique slug of the character that the actor plays.
     pub slug: ActorSlug,
+    /// The voice used for the blip sounds while the actor text is revealed.
+    pub voice: Option<ActorVoice>,
+    /// The portrait image of the actor. Available with the `portraits` feature.
+    #[cfg(feature = "portraits")]
+    pub portrait: Option<Handle<Image>>,
 }
 
 impl Actor {
+    /// The slug of the built-in "player" pseudo-actor.
+    ///
+    /// Nodes performed by this slug don't need an actor entity: they are tagged with a
+    /// [`PlayerNode`](crate::prelude::PlayerNode) marker and their `TextNodeEvent` has `is_player` set.
+    /// If an actor with this slug is added to the talk, it is also connected to the nodes as usual.
+    pub const PLAYER_SLUG: &'static str = "player";
+
     /// Creates a new actor with the given name and slug.
     pub fn new(slug: impl Into<ActorSlug>, name: impl Into<String>) -> Self {
         Self {
             name: name.into(),
             slug: slug.into(),
+            voice: None,
+            #[cfg(feature = "portraits")]
+            portrait: None,
+        }
+    }
+
+    /// Sets the voice of the actor.
+    pub fn with_voice(mut self, voice: ActorVoice) -> Self {
+        self.voice = Some(voice);
+        self
+    }
+
+    /// Sets the portrait image of the actor.
+    #[cfg(feature = "portraits")]
+    pub fn with_portrait(mut self, portrait: Handle<Image>) -> Self {
+        self.portrait = Some(portrait);
+        self
+    }
+
+    /// Returns the actor with the given entity `Name` as its name if its own name is empty,
+    /// so pre-spawned NPC entities don't need to duplicate their name in the `Actor`.
+    pub(crate) fn or_entity_name(mut self, name: Option<&Name>) -> Self {
+        if let (true, Some(name)) = (self.name.is_empty(), name) {
+            self.name = name.as_str().to_string();
+        }
+        self
+    }
+}
+
+/// Resource to configure what the traversal does when a node is performed by a despawned actor.
+///
+/// In both cases a [`MissingActorEvent`](crate::prelude::MissingActorEvent) is sent.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MissingActorPolicy {
+    /// Skip the missing actor and emit the node events with the remaining actors.
+    #[default]
+    Skip,
+    /// Do not move to the next node and return a `NextActionError::MissingActor` error.
+    Halt,
+}
+
+/// The "voice bank" of an actor: a set of short blip sounds to play while the text is revealed
+/// (Animal Crossing-style).
+///
+/// The plugin doesn't play any sound by itself, the voice is passed along the `TextNodeEvent`
+/// so the typewriter/audio systems can ask which blip to play for each revealed character.
+#[derive(Reflect, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActorVoice {
+    /// The asset paths of the blip sounds.
+    pub blips: Vec<String>,
+    /// Play a blip every `chars_per_blip` revealed characters. 0 and 1 both mean every character.
+    pub chars_per_blip: usize,
+}
+
+impl ActorVoice {
+    /// Creates a new voice with the given blip sounds asset paths, blipping every character.
+    pub fn new(blips: Vec<String>) -> Self {
+        Self {
+            blips,
+            chars_per_blip: 1,
+        }
+    }
+
+    /// Sets how many revealed characters are needed to play a blip.
+    pub fn with_chars_per_blip(mut self, chars_per_blip: usize) -> Self {
+        self.chars_per_blip = chars_per_blip;
+        self
+    }
+
+    /// Returns the blip to play when the character `ch` at position `index` of the text is revealed, if any.
+    ///
+    /// Whitespace and punctuation never blip. The blip is picked from the character itself,
+    /// so the same text always sounds the same.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::ActorVoice;
+    ///
+    /// let voice = ActorVoice::new(vec!["blip_a.ogg".to_string(), "blip_b.ogg".to_string()]);
+    /// assert!(voice.blip_for(0, 'H').is_some());
+    /// assert!(voice.blip_for(1, ' ').is_none());
+    /// ```
+    pub fn blip_for(&self, index: usize, ch: char) -> Option<&str> {
+        if self.blips.is_empty() || !ch.is_alphanumeric() {
+            return None;
+        }
+        if index % self.chars_per_blip.max(1) != 0 {
+            return None;
+        }
+        let blip = ch.to_lowercase().next().unwrap_or(ch) as usize % self.blips.len();
+        Some(&self.blips[blip])
+    }
+}
+
+/// Component of the talk parent entities with the actor aliases of the talk (alias => actor slug).
+///
+/// The nodes performed under an alias are performed by the actor, but their events have the alias
+/// as the actor name until it is revealed, so an actor can appear as e.g. "???" before introducing itself.
+/// The aliases are set with the `aliases` of the RON talks or with [`TalkBuilder::alias`].
+///
+/// # Example
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// #[derive(Event)]
+/// struct StrangerIntroduced(Entity);
+///
+/// fn reveal(mut events: EventReader<StrangerIntroduced>, mut aliases: Query<&mut ActorAliases>) {
+///     for StrangerIntroduced(talk) in events.read() {
+///         if let Ok(mut aliases) = aliases.get_mut(*talk) {
+///             aliases.reveal("???");
+///         }
+///     }
+/// }
+/// ```
+#[derive(Component, Reflect, Default, Debug, Clone, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct ActorAliases {
+    /// The actor slug of each alias.
+    aliases: HashMap<String, ActorSlug>,
+    /// The revealed aliases.
+    revealed: Vec<String>,
+}
+
+impl ActorAliases {
+    /// Creates the aliases of a talk, none revealed.
+    pub(crate) fn new(aliases: HashMap<String, ActorSlug>) -> Self {
+        Self {
+            aliases,
+            revealed: vec![],
+        }
+    }
+
+    /// Returns the slug of the actor behind the alias, if it is an alias of the talk.
+    pub fn slug(&self, alias: &str) -> Option<&str> {
+        self.aliases.get(alias).map(String::as_str)
+    }
+
+    /// Reveals the alias: the next events of the nodes performed under it have the actor name.
+    /// Returns `false` if it is not an alias of the talk.
+    pub fn reveal(&mut self, alias: &str) -> bool {
+        if !self.aliases.contains_key(alias) {
+            return false;
         }
+        if !self.is_revealed(alias) {
+            self.revealed.push(alias.to_string());
+        }
+        true
+    }
+
+    /// Returns the aliases with the slugs of their actors.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.aliases.iter().map(|(a, s)| (a.as_str(), s.as_str()))
+    }
+
+    /// Returns whether the alias has been revealed.
+    pub fn is_revealed(&self, alias: &str) -> bool {
+        self.revealed.iter().any(|a| a == alias)
+    }
+
+    /// Hides all the revealed aliases again.
+    pub fn hide_all(&mut self) {
+        self.revealed.clear();
+    }
+}
+
+/// Component of the nodes performed by actors under an alias (actor slug => alias).
+#[derive(Component, Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct AliasedActors(pub(crate) HashMap<ActorSlug, String>);
+
+/// Replaces the aliases performing the nodes of the builder (branches included) with the slugs of their actors,
+/// keeping the aliases in the nodes for the events.
+pub(crate) fn resolve_builder_aliases(builder: &mut TalkBuilder) {
+    let aliases = &builder.aliases;
+    if aliases.is_empty() {
+        return;
     }
+    for_each_build_node_mut(&mut builder.queue, |node| {
+        for slug in node.actors.iter_mut() {
+            if let Some(actor) = aliases.get(slug) {
+                let alias = std::mem::replace(slug, actor.clone());
+                node.actor_aliases.insert(actor.clone(), alias);
+            }
+        }
+    });
 }
 
 /// A bundle that contains the components needed to make an entity an actor.
@@ -37,3 +240,48 @@ pub(crate) struct ActorBundle {
     /// The actor component.
     actor: Actor,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn voice_blips_only_on_letters_every_n_chars() {
+        let voice =
+            ActorVoice::new(vec!["a.ogg".to_string(), "b.ogg".to_string()]).with_chars_per_blip(2);
+
+        assert!(voice.blip_for(0, 'h').is_some());
+        assert!(voice.blip_for(1, 'e').is_none());
+        assert!(voice.blip_for(2, '!').is_none());
+        assert_eq!(voice.blip_for(4, 'a'), voice.blip_for(6, 'A'));
+        assert!(ActorVoice::default().blip_for(0, 'h').is_none());
+    }
+
+    #[test]
+    fn reveal_only_known_aliases() {
+        let mut aliases = ActorAliases::new(
+            [("???".to_string(), "stranger".to_string())]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(aliases.slug("???"), Some("stranger"));
+        assert!(!aliases.is_revealed("???"));
+        assert!(!aliases.reveal("!!!"));
+        assert!(aliases.reveal("???"));
+        assert!(aliases.reveal("???"));
+        assert!(aliases.is_revealed("???"));
+        aliases.hide_all();
+        assert!(!aliases.is_revealed("???"));
+    }
+
+    #[test]
+    fn empty_name_from_entity_name() {
+        let name = Name::new("Bob");
+        let actor = Actor::new("bob", "").or_entity_name(Some(&name));
+        assert_eq!(actor.name, "Bob");
+
+        let actor = Actor::new("bob", "Robert").or_entity_name(Some(&name));
+        assert_eq!(actor.name, "Robert");
+        assert_eq!(Actor::new("bob", "").or_entity_name(None).name, "");
+    }
+}
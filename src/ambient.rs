@@ -0,0 +1,143 @@
+//! Driver for the ambient talks (e.g. background chatter between NPCs) that advance on their own.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::prelude::{NextNodeRequest, OnEnd, Talk};
+
+/// Plugin that sends a `NextNodeRequest` to all the [`AmbientTalk`] talks every `interval` (of the game time),
+/// so they advance on their own without any input.
+///
+/// The ambient talks without an [`OnEnd`] behavior get [`OnEnd::Loop`], so they start over after the end.
+/// They are meant to be linear: the requests for the ones on a choice node fail (and are logged).
+///
+/// # Example
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// App::new()
+///     .add_plugins((
+///         DefaultPlugins,
+///         TalksPlugin,
+///         AmbientTalksPlugin { interval: Duration::from_secs(4) },
+///     ))
+///     .run();
+/// ```
+pub struct AmbientTalksPlugin {
+    /// The time between two advances of the ambient talks.
+    pub interval: Duration,
+}
+
+impl Default for AmbientTalksPlugin {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(3),
+        }
+    }
+}
+
+impl Plugin for AmbientTalksPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<AmbientTalk>()
+            .insert_resource(AmbientTimer(Timer::new(
+                self.interval,
+                TimerMode::Repeating,
+            )))
+            .add_systems(Update, (loop_ambient_talks, advance_ambient_talks).chain());
+    }
+}
+
+/// Marker component to add to a talk parent entity to have it advanced by the [`AmbientTalksPlugin`].
+#[derive(Component, Reflect, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct AmbientTalk;
+
+/// The timer of the advances of the ambient talks.
+#[derive(Resource, Debug)]
+struct AmbientTimer(Timer);
+
+/// Makes the new ambient talks without an end behavior start over after the end.
+fn loop_ambient_talks(
+    mut cmd: Commands,
+    talks: Query<Entity, (With<Talk>, With<AmbientTalk>, Without<OnEnd>)>,
+) {
+    for talk in &talks {
+        cmd.entity(talk).insert(OnEnd::Loop);
+    }
+}
+
+/// Sends a `NextNodeRequest` to all the ambient talks when the timer finishes.
+fn advance_ambient_talks(
+    mut timer: ResMut<AmbientTimer>,
+    time: Option<Res<Time>>,
+    talks: Query<Entity, (With<Talk>, With<AmbientTalk>)>,
+    mut next_requests: EventWriter<NextNodeRequest>,
+) {
+    let Some(time) = time else {
+        return;
+    };
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    next_requests.send_batch(talks.iter().map(NextNodeRequest::new));
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::Command;
+
+    use crate::{
+        prelude::*,
+        tests::{get_comp, talks_minimal_app},
+    };
+
+    use super::*;
+
+    /// Advances the game time by the given seconds and updates the app twice, so the requests are handled.
+    fn update_after(app: &mut App, secs: u64) {
+        app.world
+            .resource_mut::<Time>()
+            .advance_by(Duration::from_secs(secs));
+        app.update();
+        app.world.resource_mut::<Time>().advance_by(Duration::ZERO);
+        app.update();
+    }
+
+    /// Returns the text of the current node of the talk, if it is a text node.
+    fn current_text(app: &App, talk: Entity) -> Option<String> {
+        let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        app.world.get::<TextNode>(current).map(|t| t.0.clone())
+    }
+
+    #[test]
+    fn ambient_talks_advance_and_loop() {
+        let mut app = talks_minimal_app();
+        app.add_plugins(AmbientTalksPlugin {
+            interval: Duration::from_secs(2),
+        });
+        app.init_resource::<Time>();
+        let builder = || TalkBuilder::default().say("Nice day.").say("Indeed.");
+        let ambient = app.world.spawn((Talk::default(), AmbientTalk)).id();
+        BuildTalkCommand::new(ambient, builder()).apply(&mut app.world);
+        let other = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(other, builder()).apply(&mut app.world);
+
+        update_after(&mut app, 1);
+        assert_eq!(current_text(&app, ambient), None);
+        assert_eq!(get_comp::<OnEnd>(ambient, &mut app.world), &OnEnd::Loop);
+
+        update_after(&mut app, 1);
+        assert_eq!(current_text(&app, ambient).unwrap(), "Nice day.");
+        assert_eq!(current_text(&app, other), None);
+
+        update_after(&mut app, 2);
+        // back to the start after the end
+        assert_eq!(current_text(&app, ambient), None);
+
+        update_after(&mut app, 2);
+        assert_eq!(current_text(&app, ambient).unwrap(), "Nice day.");
+    }
+}
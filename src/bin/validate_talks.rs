@@ -0,0 +1,38 @@
+//! Validates the `.talk.ron` files under the given directories (`assets` by default), for CI pipelines.
+//!
+//! It prints an error for each invalid talk and exits with code 1 if any was found.
+
+use std::process::ExitCode;
+
+use bevy_talks::cli::validate_talks;
+
+/// Validates the directories from the arguments and prints the errors.
+fn main() -> ExitCode {
+    let mut dirs = std::env::args().skip(1).collect::<Vec<_>>();
+    if dirs.is_empty() {
+        dirs.push("assets".to_string());
+    }
+
+    let (mut checked, mut failed) = (0, 0);
+    for dir in &dirs {
+        let report = match validate_talks(dir) {
+            Ok(report) => report,
+            Err(err) => {
+                eprintln!("error: could not read {dir}: {err}");
+                return ExitCode::FAILURE;
+            }
+        };
+        for diagnostic in &report.diagnostics {
+            eprintln!("error: {diagnostic}");
+        }
+        checked += report.checked.len();
+        failed += report.diagnostics.len();
+    }
+
+    println!("Checked {checked} talk files, found {failed} errors.");
+    if failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
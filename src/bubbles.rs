@@ -0,0 +1,247 @@
+//! World-space speech bubbles shown above the speaking actors. Available with the `bubbles` feature.
+
+use aery::prelude::*;
+use bevy::{prelude::*, ui::UiSystem};
+
+use crate::prelude::{Actor, CurrentNodeChanged, CurrentNodes, PerformedBy, TextNodeEvent};
+
+/// Plugin that shows a speech bubble (a UI node with the text) above the speaking actor when a
+/// `TextNodeEvent` is emitted, and removes it when the talk moves to another node.
+///
+/// The speaking actor is the first actor of the node whose entity has a `GlobalTransform`
+/// (e.g. your NPC entities with the [`Actor`] component). The bubbles follow the actors on the screen
+/// of the first active camera. The nodes without such an actor get no bubble.
+///
+/// # Example
+/// ```rust,no_run
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// App::new()
+///     .add_plugins((DefaultPlugins, TalksPlugin, SpeechBubblesPlugin))
+///     .insert_resource(BubbleStyle {
+///         offset: Vec3::Y * 3.0,
+///         ..default()
+///     })
+///     .run();
+/// ```
+pub struct SpeechBubblesPlugin;
+
+impl Plugin for SpeechBubblesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BubbleStyle>()
+            .register_type::<BubbleOffset>()
+            .add_systems(
+                PostUpdate,
+                (show_bubbles, position_bubbles)
+                    .chain()
+                    .before(UiSystem::Layout),
+            );
+    }
+}
+
+/// Resource with the style of the speech bubbles.
+#[derive(Resource, Debug, Clone)]
+pub struct BubbleStyle {
+    /// The offset of the bubble from the actor position, in world space. Override it per actor with [`BubbleOffset`].
+    pub offset: Vec3,
+    /// The font size of the text.
+    pub font_size: f32,
+    /// The color of the text.
+    pub text_color: Color,
+    /// The background color of the bubble.
+    pub background: Color,
+    /// The padding around the text, in logical pixels.
+    pub padding: f32,
+    /// The maximum width of the bubble, in logical pixels. The text wraps past it.
+    pub max_width: f32,
+}
+
+impl Default for BubbleStyle {
+    fn default() -> Self {
+        Self {
+            offset: Vec3::Y * 2.0,
+            font_size: 18.0,
+            text_color: Color::BLACK,
+            background: Color::WHITE,
+            padding: 8.0,
+            max_width: 300.0,
+        }
+    }
+}
+
+/// Component to place on an actor entity to show its bubbles at another offset than the [`BubbleStyle`] one.
+#[derive(Component, Reflect, Default, Debug, Clone, Copy, PartialEq)]
+#[reflect(Component, Default)]
+pub struct BubbleOffset(pub Vec3);
+
+/// Component of the speech bubble UI nodes.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpeechBubble {
+    /// The talk the bubble text comes from.
+    pub talk: Entity,
+    /// The actor entity the bubble is above.
+    pub speaker: Entity,
+}
+
+/// Removes the bubbles of the talks that moved and spawns the bubbles of the new text events.
+fn show_bubbles(
+    mut cmd: Commands,
+    mut changed_events: EventReader<CurrentNodeChanged>,
+    mut text_events: EventReader<TextNodeEvent>,
+    current_nodes: Res<CurrentNodes>,
+    performers: Query<Relations<PerformedBy>>,
+    speakers: Query<&Actor, With<GlobalTransform>>,
+    bubbles: Query<(Entity, &SpeechBubble)>,
+    style: Res<BubbleStyle>,
+) {
+    let mut despawned = vec![];
+    let mut remove_bubbles = |cmd: &mut Commands, talk: Entity| {
+        for (bubble, _) in bubbles.iter().filter(|(_, b)| b.talk == talk) {
+            if !despawned.contains(&bubble) {
+                cmd.entity(bubble).despawn_recursive();
+                despawned.push(bubble);
+            }
+        }
+    };
+    for changed in changed_events.read() {
+        remove_bubbles(&mut cmd, changed.talk);
+    }
+
+    for event in text_events.read() {
+        let Some(name) = event.actors.first() else {
+            continue;
+        };
+        // the talk on a node performed by the speaking actor
+        let speaking = current_nodes.0.iter().find_map(|(talk, node)| {
+            let edges = performers.get(*node).ok()?;
+            let speaker = edges
+                .targets(PerformedBy)
+                .iter()
+                .copied()
+                .find(|a| speakers.get(*a).is_ok_and(|actor| actor.name == *name))?;
+            Some((*talk, speaker))
+        });
+        let Some((talk, speaker)) = speaking else {
+            continue;
+        };
+        // a refire replaces the bubble
+        remove_bubbles(&mut cmd, talk);
+        cmd.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    max_width: Val::Px(style.max_width),
+                    padding: UiRect::all(Val::Px(style.padding)),
+                    ..default()
+                },
+                background_color: style.background.into(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            SpeechBubble { talk, speaker },
+        ))
+        .with_children(|bubble| {
+            bubble.spawn(TextBundle::from_section(
+                event.text.clone(),
+                TextStyle {
+                    font_size: style.font_size,
+                    color: style.text_color,
+                    ..default()
+                },
+            ));
+        });
+    }
+}
+
+/// Moves the bubbles above their speakers, on the screen of the first active camera.
+/// The bubbles of the speakers off the screen (or despawned) are hidden.
+fn position_bubbles(
+    mut bubbles: Query<(&SpeechBubble, &Node, &mut Style, &mut Visibility)>,
+    speakers: Query<(&GlobalTransform, Option<&BubbleOffset>)>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    style: Res<BubbleStyle>,
+) {
+    let camera = cameras.iter().find(|(camera, _)| camera.is_active);
+    for (bubble, node, mut bubble_style, mut visibility) in &mut bubbles {
+        let position = speakers.get(bubble.speaker).ok().and_then(|(t, offset)| {
+            let offset = offset.map_or(style.offset, |o| o.0);
+            let (camera, camera_transform) = camera?;
+            camera.world_to_viewport(camera_transform, t.translation() + offset)
+        });
+        let Some(position) = position else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        // centered above the point
+        let size = node.size();
+        bubble_style.left = Val::Px(position.x - size.x / 2.0);
+        bubble_style.top = Val::Px(position.y - size.y);
+        *visibility = Visibility::Inherited;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::Command;
+
+    use crate::{prelude::*, tests::talks_minimal_app};
+
+    use super::*;
+
+    /// Returns the speech bubbles with their text.
+    fn bubbles(app: &mut App) -> Vec<(SpeechBubble, String)> {
+        let mut query = app.world.query::<(&SpeechBubble, &Children)>();
+        let bubbles: Vec<_> = query
+            .iter(&app.world)
+            .map(|(b, children)| (*b, children[0]))
+            .collect();
+        bubbles
+            .into_iter()
+            .map(|(b, text)| {
+                (
+                    b,
+                    app.world.get::<Text>(text).unwrap().sections[0]
+                        .value
+                        .clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bubble_above_the_speaker_until_advance() {
+        let mut app = talks_minimal_app();
+        app.add_plugins(SpeechBubblesPlugin);
+        let bob = app
+            .world
+            .spawn((Actor::new("bob", "Bob"), GlobalTransform::default()))
+            .id();
+        let builder = TalkBuilder::default()
+            .add_actor(Actor::new("bob", "Bob"))
+            .actor_say("bob", "Hello")
+            .say("Narrated")
+            .actor_say("bob", "Bye");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let speaker = SpeechBubble { talk, speaker: bob };
+        assert_eq!(bubbles(&mut app), vec![(speaker, "Hello".to_string())]);
+
+        // no speaker, no bubble
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert!(bubbles(&mut app).is_empty());
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(bubbles(&mut app), vec![(speaker, "Bye".to_string())]);
+
+        // a refire doesn't stack the bubbles
+        app.world.send_event(RefireNodeRequest::new(talk));
+        app.update();
+        assert_eq!(bubbles(&mut app).len(), 1);
+    }
+}
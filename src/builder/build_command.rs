@@ -1,10 +1,26 @@
 //! The Bevy Command to spawn Talk entity graphs
 
-use aery::prelude::*;
-use bevy::{ecs::system::Command, prelude::*, utils::hashbrown::HashMap};
+use aery::{prelude::*, tuple_traits::RelationEntries};
+use bevy::{
+    ecs::system::Command, log::Level, prelude::*, reflect::DynamicTypePath,
+    utils::hashbrown::HashMap,
+};
+
+use crate::actors::{resolve_builder_aliases, AliasedActors};
+use crate::config::{talks_log, LogCategory, TalksConfig};
+use crate::custom_nodes::build_custom_node;
+use crate::emit::resolve_builder_emitters;
+use crate::localization::localize_builder;
+use crate::persistence::restore_talk_state;
+use crate::validation::{validate_builder_actors, validate_builder_components};
+
+use super::lazy::{defer_branches, insert_lazy_branches};
+use super::passes::{BuildContext, BuildPass, BuildPasses};
 
 use crate::prelude::{
-    ActorSlug, Choice, ChoiceNode, CurrentNode, EndNode, FollowedBy, PerformedBy, StartNode,
+    ActorAliases, ActorSlug, BuildError, Chapter, Choice, ChoiceNode, CurrentNode, CurrentNodes,
+    EndNode, FollowedBy, Interjection, LogicNode, OrderIndex, PerformedBy, PlayerNode, RandomNode,
+    SpeakingOrder, StartNode, SwitchNode, Talk, TalkBuildFailedEvent, TalkBuiltEvent, TalkId,
 };
 
 use super::*;
@@ -31,134 +47,443 @@ impl BuildTalkCommand {
 
 impl Command for BuildTalkCommand {
     fn apply(self, world: &mut World) {
-        // spawn the start node with all the start events
-        let start = &world.spawn((StartNode, CurrentNode)).id();
+        // the parent can be despawned before the command is applied (e.g. async loading flows),
+        // and the unknown actors (typos) and unregistered components are caught before spawning anything
+        let mut builder = self.builder;
+        let valid = if world.get_entity(self.parent).is_none() {
+            Err(BuildError::MissingParent(self.parent))
+        } else {
+            validate_builder(&mut builder, world)
+        };
+        if let Err(error) = valid {
+            talks_log!(
+                world.get_resource::<TalksConfig>(),
+                LogCategory::Build,
+                Level::ERROR,
+                "Cannot build talk {:?}: {}",
+                self.parent,
+                error
+            );
+            if let Some(mut failed_events) =
+                world.get_resource_mut::<Events<TalkBuildFailedEvent>>()
+            {
+                failed_events.send(TalkBuildFailedEvent {
+                    talk: self.parent,
+                    error,
+                });
+            }
+            return;
+        }
 
-        // First pass: spawn all the node entities and add them to the map with their build node id
-        let (ents, mut node_entities) = spawn_dialogue_entities(&self.builder.queue, world);
-        let actor_ents = spawn_actor_entities(&self.builder.actors, world);
+        if let Some(metadata) = builder.metadata.clone() {
+            if let Some(mut talk) = world.get_mut::<Talk>(self.parent) {
+                talk.metadata = metadata;
+            }
+        }
+        if let Some(id) = builder.talk_id.clone() {
+            world.entity_mut(self.parent).insert(TalkId(id));
+        }
+        if !builder.aliases.is_empty() {
+            world
+                .entity_mut(self.parent)
+                .insert(ActorAliases::new(builder.aliases.clone()));
+        }
 
-        // add the start entity and all the other entities to the parent
-        let mut manager = world.entity_mut(self.parent);
-        manager.add_child(*start);
-        for e in ents {
-            manager.add_child(e);
+        // spawn the start node with all the start events
+        let start = world.spawn((StartNode, CurrentNode, OrderIndex(0))).id();
+        if let Some(mut current_nodes) = world.get_resource_mut::<CurrentNodes>() {
+            current_nodes.0.insert(self.parent, start);
         }
+        world.entity_mut(self.parent).add_child(start);
 
-        // Second pass: Extract all the components associated with the nodes
-        let component_map = prepare_node_components(&self.builder.queue, &node_entities, world);
+        if let Some(depth) = builder.lazy_depth {
+            defer_branches(&mut builder, depth);
+        }
+        let ents = build_graph(self.parent, start, &builder, &[], None, world);
+        // the start node is also part of the graph
+        let nodes = ents.len() + 1;
+
+        // move the talk to its saved node, if it was already traversed before a respawn
+        restore_talk_state(world, self.parent, start, &ents);
+
+        // the graph is ready, let the other systems know
+        if let Some(mut built_events) = world.get_resource_mut::<Events<TalkBuiltEvent>>() {
+            built_events.send(TalkBuiltEvent {
+                talk: self.parent,
+                nodes,
+            });
+        }
+    }
+}
+
+/// The command that interjects a mini dialogue graph in an active talk, right after its current node.
+/// You can create this command via the `interject` method of the [`TalkCommandsExt`](super::commands::TalkCommandsExt) trait.
+///
+/// The next request moves the talk into the interjected nodes, and from their last nodes back to the nodes that
+/// were following the current node. Once the talk moves past the interjection, the interjected nodes are despawned
+/// and the original graph is restored.
+///
+/// The interjection is skipped (with a warning) if the talk has no current node, if the current node is a choice node
+/// or if the talk already has a pending interjection.
+pub struct InterjectCommand {
+    /// The talk parent entity.
+    pub(crate) talk: Entity,
+    /// The builder with the nodes to interject.
+    pub(crate) builder: TalkBuilder,
+}
+
+impl InterjectCommand {
+    /// Create a new `InterjectCommand` for the talk with the builder of the nodes to interject.
+    pub(crate) fn new(talk: Entity, builder: TalkBuilder) -> Self {
+        Self { talk, builder }
+    }
+}
 
-        // and insert them in the world
-        component_map.into_iter().for_each(|(e, comps)| {
-            let mut entity_mut = world.entity_mut(e);
-            for (comp, comp_reflect) in comps {
-                let comp_to_insert = &**comp;
-                comp_reflect.insert(&mut entity_mut, comp_to_insert);
+impl Command for InterjectCommand {
+    fn apply(mut self, world: &mut World) {
+        let current = world
+            .get_resource::<CurrentNodes>()
+            .and_then(|c| c.get(self.talk));
+        let skip_reason = match current {
+            None => Some("it has no current node".to_string()),
+            Some(_) if self.builder.queue.is_empty() => Some("the builder is empty".to_string()),
+            Some(_) if world.get::<Interjection>(self.talk).is_some() => {
+                Some("it already has a pending interjection".to_string())
             }
+            Some(node) if world.get::<ChoiceNode>(node).is_some() => {
+                Some("its current node is a choice node".to_string())
+            }
+            Some(_) => validate_builder(&mut self.builder, world)
+                .err()
+                .map(|error| format!("the builder is invalid: {error}")),
+        };
+        let (Some(node), None) = (current, &skip_reason) else {
+            talks_log!(
+                world.get_resource::<TalksConfig>(),
+                LogCategory::Build,
+                Level::WARN,
+                "Cannot interject in talk {:?}, {}.",
+                self.talk,
+                skip_reason.as_deref().unwrap_or_default()
+            );
+            return;
+        };
+
+        // detach the current node from its followers, the interjection goes in between
+        let resume = world
+            .query::<Relations<FollowedBy>>()
+            .get(world, node)
+            .map(|edges| edges.targets(FollowedBy).to_vec())
+            .unwrap_or_default();
+        for next in resume.iter() {
+            world.entity_mut(node).unset::<FollowedBy>(*next);
+        }
+
+        let nodes = build_graph(self.talk, node, &self.builder, &resume, None, world);
+        world.entity_mut(self.talk).insert(Interjection {
+            node,
+            resume,
+            nodes,
         });
+    }
+}
+
+/// Builds the dialogue graph of the builder as children of the `parent` talk, following the `root` node.
+/// The nodes left without followers are connected to the `resume` nodes (if any).
+/// If an `entry` entity is given, it is used as the first node instead of spawning a new one.
+///
+/// The graph is built running the [`BuildPass`]es in order, each one followed by the [`BuildPasses`]
+/// registered for it. The nodes are spawned, added as children and numbered with their [`OrderIndex`]
+/// in the builder order (each node before its branches), and all the passes follow the same order,
+/// so the build is deterministic.
+///
+/// Returns the spawned node entities.
+pub(crate) fn build_graph(
+    parent: Entity,
+    root: Entity,
+    builder: &TalkBuilder,
+    resume: &[Entity],
+    entry: Option<Entity>,
+    world: &mut World,
+) -> Vec<Entity> {
+    let mut graph = GraphBuild {
+        parent,
+        root,
+        builder,
+        resume,
+        entry,
+        ents: vec![],
+        node_entities: HashMap::new(),
+        actor_ents: HashMap::new(),
+    };
+    let user_passes = world
+        .get_resource::<BuildPasses>()
+        .cloned()
+        .unwrap_or_default();
+    for pass in BuildPass::ORDER {
+        graph.run(pass, world);
+        let ctx = BuildContext {
+            talk: parent,
+            nodes: &graph.ents,
+            actors: &graph.actor_ents,
+        };
+        for user_pass in user_passes.after(pass) {
+            user_pass.run(world, &ctx);
+        }
+    }
+    graph.ents
+}
+
+/// The state of a graph being built by [`build_graph`].
+struct GraphBuild<'a> {
+    /// The talk parent entity.
+    parent: Entity,
+    /// The node the graph follows.
+    root: Entity,
+    /// The builder of the graph.
+    builder: &'a TalkBuilder,
+    /// The nodes to connect the dead-ends to.
+    resume: &'a [Entity],
+    /// The entity to use as the first node, instead of spawning a new one.
+    entry: Option<Entity>,
+    /// The spawned node entities, in the builder order.
+    ents: Vec<Entity>,
+    /// The map build node id => entity.
+    node_entities: HashMap<BuildNodeId, Entity>,
+    /// The map actor slug => entity.
+    actor_ents: HashMap<ActorSlug, Entity>,
+}
+
+impl GraphBuild<'_> {
+    /// Runs the given built-in pass.
+    fn run(&mut self, pass: BuildPass, world: &mut World) {
+        let builder = self.builder;
+        let queue = &builder.queue;
+        match pass {
+            BuildPass::SpawnNodes => self.spawn_nodes(world),
+            BuildPass::InsertComponents => {
+                let component_map = prepare_node_components(queue, &self.node_entities, world);
+                // insert them in the world, in the builder order
+                component_map.into_iter().for_each(|(e, comps)| {
+                    let mut entity_mut = world.entity_mut(e);
+                    for (comp, comp_reflect) in comps {
+                        let comp_to_insert = &**comp;
+                        comp_reflect.insert(&mut entity_mut, comp_to_insert);
+                    }
+                });
+            }
+            BuildPass::FormGraph => self.form_graph(world),
+            BuildPass::ConnectActors => {
+                connect_nodes_with_actors(queue, &self.node_entities, &self.actor_ents, world);
+            }
+            BuildPass::SpecialNodes => {
+                // store the deferred branches in their placeholders and the effects in the logic nodes
+                insert_lazy_branches(queue, &self.node_entities, world);
+                insert_logic_nodes(queue, &self.node_entities, world);
+                build_custom_nodes(queue, &self.node_entities, world);
+            }
+        }
+    }
 
-        // Third pass: connect the entities to form the graph
+    /// Spawns all the node entities and the actors, numbers the nodes and adds them to the parent.
+    fn spawn_nodes(&mut self, world: &mut World) {
+        let (mut ents, mut node_entities) = spawn_dialogue_entities(&self.builder.queue, world);
+        if let (Some(entry), Some(first)) = (self.entry, self.builder.queue.front()) {
+            let spawned = node_entities[&first.id];
+            if let Some(chapter) = world.get::<Chapter>(spawned).cloned() {
+                world.entity_mut(entry).insert(chapter);
+            }
+            world.despawn(spawned);
+            node_entities.insert(first.id, entry);
+            ents[0] = entry;
+        }
+        self.actor_ents = spawn_actor_entities(&self.builder.actors, world);
+
+        // number the nodes after the ones already in the talk (the entry node keeps its index)
+        let mut next_index = next_order_index(self.parent, world);
+        for e in ents.iter().filter(|e| Some(**e) != self.entry) {
+            world.entity_mut(*e).insert(OrderIndex(next_index));
+            next_index += 1;
+        }
+
+        // add all the entities to the parent
+        let mut manager = world.entity_mut(self.parent);
+        for e in ents.iter() {
+            manager.add_child(*e);
+        }
+        self.ents = ents;
+        self.node_entities = node_entities;
+    }
+
+    /// Connects the entities to form the graph and marks the end nodes once all the edges are in place.
+    fn form_graph(&mut self, world: &mut World) {
         form_graph(
-            *start,
+            self.root,
             &self.builder.queue,
             self.builder.connect_parent,
-            &mut node_entities,
+            &mut self.node_entities,
             world,
         );
 
-        // Fourth pass: connect the actors to the nodes
-        connect_nodes_with_actors(&self.builder.queue, node_entities, actor_ents, world);
+        let mut followers = world.query::<Relations<FollowedBy>>();
+        if !self.resume.is_empty() {
+            // connect the dead-ends to the nodes to resume from
+            let dead_ends = self
+                .ents
+                .iter()
+                .copied()
+                .filter(|e| {
+                    followers
+                        .get(world, *e)
+                        .map_or(true, |edges| edges.targets(FollowedBy).is_empty())
+                })
+                .collect::<Vec<_>>();
+            for dead_end in dead_ends {
+                for next in self.resume {
+                    world.entity_mut(dead_end).set::<FollowedBy>(*next);
+                }
+            }
+        }
+
+        mark_end_nodes(
+            &self.builder.queue,
+            &self.node_entities,
+            &mut followers,
+            world,
+        );
     }
 }
 
-/// Extract the components from the build nodes and return a map of entity => components,
-/// so they can be inserted in the world.
+/// Returns the next free [`OrderIndex`] among the nodes of the talk.
+fn next_order_index(parent: Entity, world: &World) -> u32 {
+    let Some(children) = world.get::<Children>(parent) else {
+        return 0;
+    };
+    children
+        .iter()
+        .filter_map(|child| world.get::<OrderIndex>(*child))
+        .map(|index| index.0 + 1)
+        .max()
+        .unwrap_or_default()
+}
+
+/// Extract the components from the build nodes (branches included) and return the (entity, components) pairs
+/// in the builder order, so they can be inserted in the world.
 fn prepare_node_components<'a>(
     build_nodes: &'a VecDeque<BuildNode>,
     node_entities: &HashMap<BuildNodeId, Entity>,
     world: &mut World,
-) -> HashMap<Entity, Vec<(&'a Box<dyn Reflect>, ReflectComponent)>> {
-    let mut entity_components = HashMap::new();
+) -> Vec<(Entity, Vec<(&'a Box<dyn Reflect>, ReflectComponent)>)> {
+    let build_nodes = all_build_nodes(build_nodes);
+    let mut entity_components = Vec::with_capacity(build_nodes.len());
+    let type_reg = world.resource::<AppTypeRegistry>().read();
     for build_node in build_nodes {
         let Some(entity) = node_entities.get(&build_node.id) else {
             panic!("Error retrieving node entity while adding components. It should not happen!")
         };
 
         // extract the components
-        let reflect_comps = {
-            let type_reg = world.resource::<AppTypeRegistry>().read();
-            build_node
-                .components
-                .iter()
-                .map(|component| {
-                    (
-                        component,
-                        type_reg
-                            .get_type_data::<ReflectComponent>((**component).type_id())
-                            .unwrap_or_else(|| {
-                                panic!(
-                                "Component {:?} not registered. Cannot build dialogue graph! :(",
-                                component
+        let reflect_comps = build_node
+            .components
+            .iter()
+            .map(|component| {
+                (
+                    component,
+                    type_reg
+                        .get_type_data::<ReflectComponent>((**component).type_id())
+                        .unwrap_or_else(|| {
+                            unreachable!(
+                                "Component {} was validated before building.",
+                                component.reflect_type_path()
                             )
-                            })
-                            .clone(),
-                    )
-                })
-                .collect::<Vec<_>>()
-        };
+                        })
+                        .clone(),
+                )
+            })
+            .collect::<Vec<_>>();
 
-        entity_components.insert(*entity, reflect_comps);
-
-        // recursively insert the inner nodes
-        if !build_node.choices.is_empty() {
-            for (_, inner_builder) in build_node.choices.iter() {
-                let inner_comps =
-                    prepare_node_components(&inner_builder.queue, node_entities, world);
-                entity_components.extend(inner_comps);
-            }
-        }
+        entity_components.push((*entity, reflect_comps));
     }
     entity_components
 }
 
-/// Connect the nodes to the actors.
+/// Connect the nodes (branches included) to the actors.
 fn connect_nodes_with_actors(
     build_nodes: &VecDeque<BuildNode>,
-    node_entities: HashMap<String, Entity>,
-    all_actors: HashMap<String, Entity>,
+    node_entities: &HashMap<BuildNodeId, Entity>,
+    all_actors: &HashMap<String, Entity>,
     world: &mut World,
 ) {
-    for node in build_nodes {
+    for node in all_build_nodes(build_nodes) {
         if !node.actors.is_empty() {
             let node_ent = node_entities.get(&node.id).unwrap();
 
-            for actor in node.actors.iter() {
-                let actor_ent = all_actors.get(actor).unwrap_or_else(|| {
-                    panic!(
-                        "Error! Actor {} not found while building talk from builder.",
-                        actor
-                    )
-                });
-                world.entity_mut(*node_ent).set::<PerformedBy>(*actor_ent);
+            // keep the order of the actors, the relations alone don't guarantee it
+            if node.actors.len() > 1 {
+                world
+                    .entity_mut(*node_ent)
+                    .insert(SpeakingOrder(node.actors.clone()));
+            }
+            if !node.actor_aliases.is_empty() {
+                world
+                    .entity_mut(*node_ent)
+                    .insert(AliasedActors(node.actor_aliases.clone()));
             }
-        }
 
-        // recursively connect the inner nodes
-        if !node.choices.is_empty() {
-            for (_, inner_builder) in node.choices.iter() {
-                connect_nodes_with_actors(
-                    &inner_builder.queue,
-                    node_entities.clone(),
-                    all_actors.clone(),
-                    world,
-                );
+            for actor in node.actors.iter() {
+                let is_player = actor == Actor::PLAYER_SLUG;
+                if is_player {
+                    world.entity_mut(*node_ent).insert(PlayerNode);
+                }
+
+                match all_actors.get(actor) {
+                    Some(actor_ent) => {
+                        world.entity_mut(*node_ent).set::<PerformedBy>(*actor_ent);
+                    }
+                    // the player pseudo-actor doesn't need an actor entity
+                    None if is_player => {}
+                    None => unreachable!(
+                        "Actor {} not found while building talk, it should have been validated.",
+                        actor
+                    ),
+                }
             }
         }
     }
 }
 
+/// Returns the slugs of the actors a builder can use: its own and the ones already in the world.
+fn known_actor_slugs(builder: &TalkBuilder, world: &mut World) -> Vec<ActorSlug> {
+    let mut known = world
+        .query::<&Actor>()
+        .iter(world)
+        .map(|a| a.slug.clone())
+        .collect::<Vec<_>>();
+    known.extend(builder.actors.iter().map(|a| a.slug.clone()));
+    known.sort();
+    known.dedup();
+    known
+}
+
+/// Validates the builder before spawning anything: the actors must be known and the components registered.
+/// The emitter specs are constructed here, so the invalid ones are caught too,
+/// and the texts of the current locale replace the base ones.
+pub(crate) fn validate_builder(
+    builder: &mut TalkBuilder,
+    world: &mut World,
+) -> Result<(), BuildError> {
+    localize_builder(builder, world);
+    resolve_builder_aliases(builder);
+    validate_builder_actors(builder, &known_actor_slugs(builder, world))?;
+    let registry = world.resource::<AppTypeRegistry>().read();
+    resolve_builder_emitters(builder, &registry)?;
+    validate_builder_components(builder, &registry)
+}
+
 /// Spawn the actor entities in the world and return a map of actor slug => entity.
 /// If the actor is already present in the world (identified via the slug), it will not be spawned again.
+/// The already present actors are left as they are: the ones with an empty name take the one of their `Name`
+/// component when the node events are sent.
 fn spawn_actor_entities(actors: &[Actor], world: &mut World) -> HashMap<ActorSlug, Entity> {
     let mut actor_ents = HashMap::with_capacity(actors.len());
 
@@ -169,7 +494,13 @@ fn spawn_actor_entities(actors: &[Actor], world: &mut World) -> HashMap<ActorSlu
         .map(|(e, a)| (a.slug.clone(), (e, a.clone())))
         .collect::<HashMap<String, (Entity, Actor)>>();
 
-    debug!("Already spawned actors: {:?}", already_spawned_actors);
+    talks_log!(
+        world.get_resource::<TalksConfig>(),
+        LogCategory::Build,
+        Level::DEBUG,
+        "Already spawned actors: {:?}",
+        already_spawned_actors
+    );
 
     for a in actors.iter() {
         if already_spawned_actors.contains_key(&a.slug) {
@@ -189,35 +520,37 @@ fn spawn_actor_entities(actors: &[Actor], world: &mut World) -> HashMap<ActorSlu
     actor_ents
 }
 
-/// A recursive function that spawns all the nodes from a talk builder and adds them in the given hashmap.
+/// Spawns all the nodes from a talk builder (branches included) and returns them with the map build node id => entity.
 /// It is used as the first pass of the building, so we have all the entities spawned and the `build_node_entities` map filled.
 fn spawn_dialogue_entities(
     build_nodes: &VecDeque<BuildNode>,
     world: &mut World,
 ) -> (Vec<Entity>, HashMap<BuildNodeId, Entity>) {
+    let build_nodes = all_build_nodes(build_nodes);
     let mut entities: Vec<Entity> = Vec::with_capacity(build_nodes.len());
-    let mut build_node_entities = HashMap::new();
-    for n in build_nodes.iter() {
-        let e = world.spawn_empty().id();
-        entities.push(e);
-        build_node_entities.insert(n.id.clone(), e);
-
-        for (_, inner_builder) in n.choices.iter() {
-            let (inner_ents, inner_bne) = spawn_dialogue_entities(&inner_builder.queue, world);
-            entities.extend(inner_ents);
-            build_node_entities.extend(inner_bne);
+    let mut build_node_entities = HashMap::with_capacity(build_nodes.len());
+    for n in build_nodes {
+        let mut e = world.spawn_empty();
+        if let Some(chapter) = &n.chapter {
+            e.insert(Chapter(chapter.clone()));
         }
+        let e = e.id();
+        entities.push(e);
+        build_node_entities.insert(n.id, e);
     }
     (entities, build_node_entities)
 }
 
-/// A recursive function that connects the entity nodes in the queue with `aery` relations.
+/// Connects the entity nodes in the queue (branches included) with `aery` relations.
 /// This also adds the `ChoiceNode` component!
 ///
+/// The branches are connected with a worklist of [`GraphFrame`]s instead of recursion,
+/// so deeply nested choices can't overflow the stack.
+///
 /// # Returns
 ///
 /// A tuple with the first child node and the the vector of leaf nodes spawned from the given builder.
-/// It is used internally during the recursion to connect the last nodes from the branches
+/// It is used internally to connect the last nodes from the branches
 /// of a choice node to the successive node in the queue.
 ///
 /// NB: The returned fist node is only needed because we have to store the `Entity` in the [`Choice`] struct
@@ -230,37 +563,81 @@ fn form_graph(
     node_entities: &mut HashMap<BuildNodeId, Entity>,
     world: &mut World,
 ) -> (Entity, Vec<Entity>) {
-    let mut parent = root;
-
-    let mut first_child_set = false;
-    let mut first_child_ent = root;
-
-    // Connect parent entity (choice node) to the given node.
-    if let Some(connect_node_id) = &connect_parent {
-        let entity_to_connect_to = node_entities.get(connect_node_id);
-        first_child_ent = *entity_to_connect_to.unwrap();
-        first_child_set = true;
-        if let Some(e) = entity_to_connect_to {
-            world.entity_mut(parent).set::<FollowedBy>(*e);
-            first_child_ent = *e;
-        } else {
-            error!("Attempted to connect a choice node to some specific node that is not (yet) present in the builder.");
+    let mut stack = vec![GraphFrame::new(
+        root,
+        build_nodes,
+        connect_parent,
+        node_entities,
+        world,
+    )];
+    loop {
+        let frame = stack
+            .last_mut()
+            .expect("the stack ends with the root queue");
+
+        // the choice node being processed: connect its next branch, or finish it
+        let pending = frame
+            .choice
+            .as_ref()
+            .map(|(e, node, choices)| (*e, *node, choices.len()));
+        if let Some((choice_ent, choice_node, done)) = pending {
+            if let Some(inner_builder) = choice_node.choices.get(done).map(|c| &c.branch) {
+                let branch = GraphFrame::new(
+                    choice_ent,
+                    &inner_builder.queue,
+                    inner_builder.connect_parent,
+                    node_entities,
+                    world,
+                );
+                stack.push(branch);
+            } else {
+                let (this_ent, build_node, choices) =
+                    frame.choice.take().expect("the choice node is pending");
+                // the empty branches of a switch or random node (pointing to the node itself) go on with the node after it
+                let routing = build_node.switch.is_some() || build_node.random.is_some();
+                let continues = routing && choices.iter().any(|c| c.next == this_ent);
+                if let Some(variable) = &build_node.switch {
+                    world
+                        .entity_mut(this_ent)
+                        .insert(switch_node(variable, this_ent, choices));
+                } else if let Some(weights) = &build_node.random {
+                    world
+                        .entity_mut(this_ent)
+                        .insert(random_node(weights, this_ent, &choices));
+                } else {
+                    // insert the ChoiceNode component here
+                    world.entity_mut(this_ent).insert(ChoiceNode(choices));
+                }
+                frame.previous_node_was_choice = true;
+                frame.finish_node(this_ent, build_node, node_entities, world);
+                if continues && !frame.leaves.contains(&this_ent) {
+                    frame.leaves.push(this_ent);
+                }
+            }
+            continue;
         }
-    }
 
-    let mut leaves: Vec<Entity> = vec![];
-    let mut previous_node_was_choice = false;
-
-    if !build_nodes.is_empty() && !first_child_set {
-        first_child_ent = *node_entities
-            .get(&build_nodes[0].id)
-            .expect("First entity from the builder");
-    }
-
-    let mut peekable_queue = build_nodes.iter().peekable();
-
-    // for each node in the queue, spawn it and connect it to the previous one
-    while let Some(build_node) = peekable_queue.next() {
+        // for each node in the queue, connect it to the previous one
+        let Some(build_node) = frame.nodes.next() else {
+            let done = stack.pop().expect("the frame was just processed");
+            let Some(parent_frame) = stack.last_mut() else {
+                return (done.first_child, done.leaves);
+            };
+            // the queue was a branch of the choice node of the parent frame
+            let (_, choice_node, choices) = parent_frame
+                .choice
+                .as_mut()
+                .expect("the branches belong to a choice node");
+            let i = choices.len();
+            let build_choice = &choice_node.choices[i];
+            let mut choice = Choice::new(&build_choice.text, done.first_child).with_order(i);
+            choice.description = build_choice.description.clone();
+            choice.sound = build_choice.sound.clone();
+            choice.hotkey = build_choice.hotkey;
+            choices.push(choice);
+            parent_frame.leaves.extend(done.leaves);
+            continue;
+        };
         // retrieve the child node
         let this_ent = *node_entities
             .get(&build_node.id)
@@ -268,36 +645,94 @@ fn form_graph(
 
         connect_to_previous(
             world,
-            parent,
-            &mut leaves,
-            previous_node_was_choice,
+            frame.parent,
+            &mut frame.leaves,
+            frame.previous_node_was_choice,
             this_ent,
         );
 
-        previous_node_was_choice = false;
-        if !build_node.choices.is_empty() {
+        frame.previous_node_was_choice = false;
+        if build_node.choices.is_empty() {
+            frame.finish_node(this_ent, build_node, node_entities, world);
+        } else {
             // We have to process the branches from the inner builders
             // and connect them to the choice node
-            let mut choices: Vec<Choice> = Vec::with_capacity(build_node.choices.len());
-            for (choice_text, inner_builder) in build_node.choices.iter() {
-                // recursively spawn the branches
-                let (branch_root, branch_leaves) = form_graph(
-                    this_ent,
-                    &inner_builder.queue,
-                    inner_builder.connect_parent.clone(),
-                    node_entities,
-                    world,
+            let choices = Vec::with_capacity(build_node.choices.len());
+            frame.choice = Some((this_ent, build_node, choices));
+        }
+    }
+}
+
+/// A builder queue being connected by [`form_graph`].
+struct GraphFrame<'a> {
+    /// The nodes of the queue left to connect.
+    nodes: std::iter::Peekable<std::collections::vec_deque::Iter<'a, BuildNode>>,
+    /// The node to connect the next node to.
+    parent: Entity,
+    /// The first node of the queue (or the node it connects to).
+    first_child: Entity,
+    /// The leaf nodes of the queue so far.
+    leaves: Vec<Entity>,
+    /// Whether the previous node was a choice node, so the leaves of its branches connect to the next node.
+    previous_node_was_choice: bool,
+    /// The choice node whose branches are being connected, with the choices of the branches done so far.
+    choice: Option<(Entity, &'a BuildNode, Vec<Choice>)>,
+}
+
+impl<'a> GraphFrame<'a> {
+    /// Starts connecting the queue following the `root` node.
+    fn new(
+        root: Entity,
+        build_nodes: &'a VecDeque<BuildNode>,
+        connect_parent: Option<BuildNodeId>,
+        node_entities: &HashMap<BuildNodeId, Entity>,
+        world: &mut World,
+    ) -> Self {
+        let mut first_child_set = false;
+        let mut first_child = root;
+
+        // Connect parent entity (choice node) to the given node.
+        if let Some(connect_node_id) = &connect_parent {
+            let entity_to_connect_to = node_entities.get(connect_node_id);
+            first_child = *entity_to_connect_to.unwrap();
+            first_child_set = true;
+            if let Some(e) = entity_to_connect_to {
+                world.entity_mut(root).set::<FollowedBy>(*e);
+                first_child = *e;
+            } else {
+                talks_log!(
+                    world.get_resource::<TalksConfig>(),
+                    LogCategory::Build,
+                    Level::ERROR,
+                    "Attempted to connect a choice node to some specific node that is not (yet) present in the builder."
                 );
-                choices.push(Choice::new(choice_text, branch_root));
-                leaves.extend(branch_leaves);
             }
+        }
 
-            // insert the ChoiceNode component here
-            world.entity_mut(this_ent).insert(ChoiceNode(choices));
+        if !build_nodes.is_empty() && !first_child_set {
+            first_child = *node_entities
+                .get(&build_nodes[0].id)
+                .expect("First entity from the builder");
+        }
 
-            previous_node_was_choice = true;
+        Self {
+            nodes: build_nodes.iter().peekable(),
+            parent: root,
+            first_child,
+            leaves: vec![],
+            previous_node_was_choice: false,
+            choice: None,
         }
+    }
 
+    /// Adds the extra connections of the node, and makes it the parent of the next node.
+    fn finish_node(
+        &mut self,
+        this_ent: Entity,
+        build_node: &BuildNode,
+        node_entities: &HashMap<BuildNodeId, Entity>,
+        world: &mut World,
+    ) {
         // Let's add the extra connections here
         process_manual_connections(
             node_entities,
@@ -306,20 +741,93 @@ fn form_graph(
             world,
         );
 
-        // if this is the last node, it's a leaf
-        if peekable_queue.peek().is_none() {
-            leaves.push(this_ent);
-
-            // if it was not manually connected add EndNode component
-            if build_node.manual_connections.is_empty() {
-                world.entity_mut(this_ent).insert(EndNode);
-            }
+        // if this is the last node, it's a leaf (unless it jumps somewhere else)
+        if self.nodes.peek().is_none() && build_node.manual_connections.is_empty() {
+            self.leaves.push(this_ent);
         }
         // set the new parent for the next iteration
-        parent = this_ent;
+        self.parent = this_ent;
+    }
+}
+
+/// Makes the [`SwitchNode`] of the switch node entity from the choices of its branches, the last one being the default.
+/// The empty branches point to the switch itself and become the `None` targets.
+fn switch_node(variable: &str, this_ent: Entity, mut choices: Vec<Choice>) -> SwitchNode {
+    let target = |c: &Choice| (c.next != this_ent).then_some(c.next);
+    let default = choices.pop().and_then(|c| target(&c));
+    SwitchNode {
+        variable: variable.to_string(),
+        cases: choices
+            .iter()
+            .map(|c| (c.text.clone(), target(c)))
+            .collect(),
+        default,
+    }
+}
+
+/// Makes the [`RandomNode`] of the random node entity from the weights and the choices of its branches.
+/// The empty branches point to the random node itself and become the `None` targets.
+fn random_node(weights: &[u32], this_ent: Entity, choices: &[Choice]) -> RandomNode {
+    RandomNode {
+        branches: weights
+            .iter()
+            .zip(choices)
+            .map(|(weight, c)| (*weight, (c.next != this_ent).then_some(c.next)))
+            .collect(),
+    }
+}
+
+/// Add the [`LogicNode`] component with the effect to the logic nodes.
+fn insert_logic_nodes(
+    build_nodes: &VecDeque<BuildNode>,
+    node_entities: &HashMap<BuildNodeId, Entity>,
+    world: &mut World,
+) {
+    for build_node in all_build_nodes(build_nodes) {
+        if let Some(effect) = &build_node.effect {
+            world
+                .entity_mut(node_entities[&build_node.id])
+                .insert(LogicNode(effect.clone()));
+        }
+    }
+}
+
+/// Build the custom nodes with the factories of their kinds.
+fn build_custom_nodes(
+    build_nodes: &VecDeque<BuildNode>,
+    node_entities: &HashMap<BuildNodeId, Entity>,
+    world: &mut World,
+) {
+    for build_node in all_build_nodes(build_nodes) {
+        if let Some(custom) = &build_node.custom {
+            build_custom_node(world, node_entities[&build_node.id], custom.clone());
+        }
     }
+}
 
-    (first_child_ent, leaves)
+/// Add the `EndNode` component to the end nodes of the graph.
+///
+/// The rules are:
+/// - nodes marked with `end` are always end nodes,
+/// - nodes marked with `no_end` are never end nodes,
+/// - any other node is an end node if it has no outgoing `FollowedBy` edge (dead-ends, diamonds and loops included).
+fn mark_end_nodes(
+    build_nodes: &VecDeque<BuildNode>,
+    node_entities: &HashMap<BuildNodeId, Entity>,
+    followers: &mut QueryState<Relations<FollowedBy>>,
+    world: &mut World,
+) {
+    for build_node in all_build_nodes(build_nodes) {
+        let ent = node_entities[&build_node.id];
+        let is_end = build_node.end.unwrap_or_else(|| {
+            followers
+                .get(world, ent)
+                .map_or(true, |edges| edges.targets(FollowedBy).is_empty())
+        });
+        if is_end {
+            world.entity_mut(ent).insert(EndNode);
+        }
+    }
 }
 
 /// Connect the node to the given nodes.
@@ -335,7 +843,12 @@ fn process_manual_connections(
 
         // if the node is not present, log a warning and skip it
         if entity_to_connect_to.is_none() {
-            warn!("You attempted to connect a dialogue node with that is not (yet) present in the builder. Skipping.");
+            talks_log!(
+                world.get_resource::<TalksConfig>(),
+                LogCategory::Build,
+                Level::WARN,
+                "You attempted to connect a dialogue node with that is not (yet) present in the builder. Skipping."
+            );
             continue;
         }
 
@@ -373,7 +886,8 @@ mod tests {
     use bevy::{prelude::*, utils::HashMap};
     use rstest::rstest;
 
-    use crate::tests::{count, single, talks_minimal_app};
+    use crate::prelude::{NextNodeRequest, Talk, TextNodeEvent};
+    use crate::tests::{count, get_comp, single, talks_minimal_app};
 
     use super::*;
 
@@ -435,6 +949,35 @@ mod tests {
         assert_eq!(app.world.iter_entities().count(), 4);
     }
 
+    #[test]
+    fn prespawned_actors_named_from_entity_name() {
+        let mut app = talks_minimal_app();
+        let bob = app
+            .world
+            .spawn((Actor::new("bob", ""), Name::new("Bob")))
+            .id();
+
+        let builder = TalkBuilder::default()
+            .add_actor(Actor::new("bob", ""))
+            .actor_say("bob", "Hi")
+            .actor_say("bob", "Bye");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        // the actor component is not changed by the build
+        assert_eq!(get_comp::<Actor>(bob, &mut app.world).name, "");
+
+        let spoken_name = |app: &mut App| {
+            app.world.send_event(NextNodeRequest::new(talk));
+            app.update();
+            let evs = app.world.resource::<Events<TextNodeEvent>>();
+            evs.get_reader().read(evs).last().unwrap().actors[0].clone()
+        };
+        assert_eq!(spoken_name(&mut app), "Bob");
+        // the name is resolved when the event is sent
+        app.world.entity_mut(bob).insert(Name::new("Robert"));
+        assert_eq!(spoken_name(&mut app), "Robert");
+    }
+
     #[test]
     fn test_connect_nodes_with_actors() {
         let mut app = App::new();
@@ -453,7 +996,7 @@ mod tests {
 
         let (_, node_entities) = spawn_dialogue_entities(&builder.queue, &mut app.world);
         let actor_ents = spawn_actor_entities(&builder.actors, &mut app.world);
-        connect_nodes_with_actors(&builder.queue, node_entities, actor_ents, &mut app.world);
+        connect_nodes_with_actors(&builder.queue, &node_entities, &actor_ents, &mut app.world);
 
         let nodes_with_actors = app
             .world
@@ -469,9 +1012,10 @@ mod tests {
         let mut world = World::default();
         let mut build_node_entities = HashMap::default();
         let fist_ent = world.spawn_empty().id();
-        let manual_connections = vec!["1".to_string(), "2".to_string()];
-        build_node_entities.insert("1".to_string(), world.spawn_empty().id());
-        build_node_entities.insert("2".to_string(), world.spawn_empty().id());
+        let (first_id, second_id) = (BuildNodeId::from("1"), BuildNodeId::from("2"));
+        let manual_connections = vec![first_id, second_id];
+        build_node_entities.insert(first_id, world.spawn_empty().id());
+        build_node_entities.insert(second_id, world.spawn_empty().id());
 
         process_manual_connections(
             &build_node_entities,
@@ -485,7 +1029,9 @@ mod tests {
         assert_eq!(root_ent, fist_ent);
 
         for (leaf, _) in world.query::<(Entity, Root<FollowedBy>)>().iter(&world) {
-            assert!(leaf == build_node_entities["1"] || leaf == build_node_entities["2"]);
+            assert!(
+                leaf == build_node_entities[&first_id] || leaf == build_node_entities[&second_id]
+            );
         }
     }
 
@@ -618,7 +1164,7 @@ mod integration_tests {
 
     use crate::{
         prelude::TextNode,
-        tests::{get_comp, talks_minimal_app},
+        tests::{count, get_comp, talks_minimal_app},
     };
 
     use super::*;
@@ -637,9 +1183,97 @@ mod integration_tests {
     }
 
     #[rstest]
-    #[should_panic]
-    fn test_panic_on_wrong_actor(talk_builder: TalkBuilder) {
-        build(talk_builder.actor_say("actor", "Hello"));
+    fn wrong_actor_builds_nothing(talk_builder: TalkBuilder) {
+        let mut world = build(talk_builder.actor_say("actor", "Hello"));
+        assert_eq!(world.query::<&StartNode>().iter(&world).count(), 0);
+        assert_eq!(world.query::<&TextNode>().iter(&world).count(), 0);
+        assert!(world.resource::<Events<TalkBuiltEvent>>().is_empty());
+        assert_eq!(world.resource::<Events<TalkBuildFailedEvent>>().len(), 1);
+    }
+
+    #[test]
+    fn despawned_parent_is_skipped() {
+        let mut app = talks_minimal_app();
+        let talk = app.world.spawn(Talk::default()).id();
+        app.world.despawn(talk);
+        BuildTalkCommand::new(talk, TalkBuilder::default().say("Hello")).apply(&mut app.world);
+
+        assert_eq!(count::<&TextNode>(&mut app.world), 0);
+        assert_eq!(app.world.resource::<CurrentNodes>().get(talk), None);
+        let evs = app.world.resource::<Events<TalkBuildFailedEvent>>();
+        let failed = evs.get_reader().read(evs).next().cloned();
+        let error = BuildError::MissingParent(talk);
+        assert_eq!(failed, Some(TalkBuildFailedEvent { talk, error }));
+    }
+
+    #[test]
+    fn nodes_ordered_as_the_builder() {
+        let mut app = talks_minimal_app();
+        let talk = app.world.spawn(Talk::default()).id();
+        let builder = TalkBuilder::default()
+            .say("1")
+            .choose(vec![
+                ("A", TalkBuilder::default().say("3").say("4")),
+                ("B", TalkBuilder::default().say("5")),
+            ])
+            .say("6");
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        let mut nodes = app
+            .world
+            .query::<(&OrderIndex, Option<&TextNode>)>()
+            .iter(&app.world)
+            .map(|(index, text)| (index.0, text.map(|t| t.0.clone())))
+            .collect::<Vec<_>>();
+        nodes.sort();
+        let texts = nodes
+            .iter()
+            .map(|(_, text)| text.clone().unwrap_or_default())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, vec!["", "1", "", "3", "4", "5", "6"]);
+        assert_eq!(nodes.last().unwrap().0, 6);
+
+        // the children follow the same order
+        let children = get_comp::<Children>(talk, &mut app.world).to_vec();
+        let indexes = children
+            .iter()
+            .map(|c| get_comp::<OrderIndex>(*c, &mut app.world).0)
+            .collect::<Vec<_>>();
+        assert_eq!(indexes, (0..7).collect::<Vec<_>>());
+    }
+
+    #[derive(Component, Reflect)]
+    struct Unregistered;
+
+    #[test]
+    fn unregistered_component_is_located() {
+        let mut app = talks_minimal_app();
+        let talk = app.world.spawn(Talk::default()).id();
+        let builder = TalkBuilder::default().say("Hello").choose(vec![(
+            "Go",
+            TalkBuilder::default()
+                .say("Hi")
+                .say("Bye")
+                .with_component(Unregistered),
+        )]);
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        assert_eq!(count::<&TextNode>(&mut app.world), 0);
+        let evs = app.world.resource::<Events<TalkBuildFailedEvent>>();
+        let failed = evs.get_reader().read(evs).next().cloned().unwrap();
+        let BuildError::UnregisteredComponent {
+            component,
+            location,
+            ..
+        } = failed.error
+        else {
+            panic!(
+                "Expected an unregistered component error, got {:?}",
+                failed.error
+            );
+        };
+        assert!(component.ends_with("Unregistered"));
+        assert_eq!(location, NodeLocation::Builder(1));
     }
 
     #[rstest]
@@ -752,6 +1386,41 @@ mod integration_tests {
         assert_relationship_nodes(6, 6, 1, &mut world);
     }
 
+    #[test]
+    fn jumping_branch_does_not_converge() {
+        let builder = TalkBuilder::default().say("Hello");
+        let hello = builder.last_node_id();
+        let builder = builder
+            .choose(vec![
+                (
+                    "Back",
+                    TalkBuilder::default().say("Go back").connect_to(hello),
+                ),
+                ("On", TalkBuilder::default().say("Ok")),
+            ])
+            .say("Bye");
+        let mut world = build(builder);
+
+        let nodes: HashMap<String, Entity> = world
+            .query::<(Entity, &TextNode)>()
+            .iter(&world)
+            .map(|(e, t)| (t.0.clone(), e))
+            .collect();
+        let mut followers = world.query::<Relations<FollowedBy>>();
+        let back_targets = followers
+            .get(&world, nodes["Go back"])
+            .unwrap()
+            .targets(FollowedBy)
+            .to_vec();
+        assert_eq!(back_targets, vec![nodes["Hello"]]);
+        let ok_targets = followers
+            .get(&world, nodes["Ok"])
+            .unwrap()
+            .targets(FollowedBy)
+            .to_vec();
+        assert_eq!(ok_targets, vec![nodes["Bye"]]);
+    }
+
     #[test]
     fn connect_forward_from_book_example() {
         // From the Connecting To The Same Node builder section
@@ -762,7 +1431,7 @@ mod integration_tests {
         let good_branch = TalkBuilder::default().say("something").choose(vec![
             (
                 "Bad Choice".to_string(),
-                TalkBuilder::default().connect_to(end_node_id.clone()),
+                TalkBuilder::default().connect_to(end_node_id),
             ),
             (
                 "Another Good Choice".to_string(),
@@ -839,7 +1508,7 @@ mod integration_tests {
     }
 
     #[derive(Component, Reflect, Default)]
-    #[reflect(Component)]
+    #[reflect(Component, Default)]
     struct TestComp;
     #[test]
     fn node_with_components() {
@@ -860,4 +1529,110 @@ mod integration_tests {
             .single(&app.world);
         get_comp::<TestComp>(ent, &mut app.world);
     }
+
+    #[test]
+    fn built_event_sent() {
+        let mut app = talks_minimal_app();
+        let talk = app.world.spawn_empty().id();
+        let builder = TalkBuilder::default().say("Hello").choose(vec![
+            ("Choice 1", TalkBuilder::default().say("Hi")),
+            ("Choice 2", TalkBuilder::default().say("World!")),
+        ]);
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        let evs = app.world.resource::<Events<TalkBuiltEvent>>();
+        let built = evs.get_reader().read(evs).next().cloned();
+        assert_eq!(built, Some(TalkBuiltEvent { talk, nodes: 5 }));
+    }
+
+    #[test]
+    fn metadata_copied_on_talk() {
+        let mut app = talks_minimal_app();
+        let talk = app.world.spawn(Talk::default()).id();
+        let metadata = TalkMetadata {
+            title: Some("Greetings".to_string()),
+            tags: vec!["intro".to_string()],
+            ..default()
+        };
+        let builder = TalkBuilder::default()
+            .metadata(metadata.clone())
+            .say("Hello");
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        let talk = app.world.get::<Talk>(talk).unwrap();
+        assert_eq!(talk.metadata, metadata);
+        assert!(talk.metadata.has_tag("intro"));
+    }
+
+    #[test]
+    fn explicit_end_nodes() {
+        let builder = TalkBuilder::default()
+            .say("Hello")
+            .end()
+            .say("World!")
+            .no_end();
+        let mut world = build(builder);
+        let ends = world
+            .query_filtered::<&TextNode, With<EndNode>>()
+            .iter(&world)
+            .map(|t| t.0.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(ends, vec!["Hello".to_string()]);
+    }
+
+    #[track_caller]
+    fn end_texts(world: &mut World) -> Vec<String> {
+        let mut ends = world
+            .query_filtered::<&TextNode, With<EndNode>>()
+            .iter(world)
+            .map(|t| t.0.clone())
+            .collect::<Vec<_>>();
+        ends.sort();
+        ends
+    }
+
+    #[test]
+    fn loop_has_no_end_nodes() {
+        let builder = TalkBuilder::default().say("A");
+        let first = builder.last_node_id();
+        let mut world = build(builder.say("B").connect_to(first));
+        assert!(end_texts(&mut world).is_empty());
+    }
+
+    #[test]
+    fn diamond_ends_only_after_merge() {
+        let builder = TalkBuilder::default()
+            .choose(vec![
+                ("Left", TalkBuilder::default().say("L")),
+                ("Right", TalkBuilder::default().say("R")),
+            ])
+            .say("Merge");
+        let mut world = build(builder);
+        assert_eq!(end_texts(&mut world), vec!["Merge".to_string()]);
+    }
+
+    #[test]
+    fn branch_leaves_are_end_nodes() {
+        let builder = TalkBuilder::default().say("Hello").choose(vec![
+            ("Left", TalkBuilder::default().say("L")),
+            ("Right", TalkBuilder::default().say("R")),
+        ]);
+        let mut world = build(builder);
+        assert_eq!(
+            end_texts(&mut world),
+            vec!["L".to_string(), "R".to_string()]
+        );
+    }
+
+    #[test]
+    fn deeply_nested_choices_do_not_overflow() {
+        const DEPTH: usize = 10_000;
+        let mut builder = TalkBuilder::default().say("The bottom");
+        for _ in 0..DEPTH {
+            builder = TalkBuilder::default().choose(vec![("Deeper", builder)]);
+        }
+        let mut world = build(builder);
+        assert_eq!(count::<&ChoiceNode>(&mut world), DEPTH);
+        assert_eq!(end_texts(&mut world), vec!["The bottom".to_string()]);
+    }
 }
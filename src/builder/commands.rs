@@ -1,10 +1,24 @@
 //! Commands for talks
 
-use bevy::ecs::system::{Commands, EntityCommands};
+use bevy::{
+    asset::Handle,
+    ecs::{
+        bundle::Bundle,
+        entity::Entity,
+        system::{Commands, EntityCommands},
+        world::World,
+    },
+};
 
-use crate::prelude::Talk;
+use crate::pool::{release_talk, SpawnPooledTalk};
+use crate::prelude::{
+    PooledTalk, PreloadTalkAssets, Talk, TalkData, WaitForTalkAsset, WaitForTalkAssets,
+};
 
-use super::{build_command::BuildTalkCommand, TalkBuilder};
+use super::{
+    build_command::{BuildTalkCommand, InterjectCommand},
+    TalkBuilder,
+};
 
 /// Extension trait for [`Commands`] to spawn a talk.
 pub trait TalkCommandsExt<'w, 's> {
@@ -21,6 +35,120 @@ pub trait TalkCommandsExt<'w, 's> {
     ///     commands.spawn_talk(talk_builder);
     /// }
     fn spawn_talk(&mut self, builder: TalkBuilder) -> EntityCommands<'w, 's, '_>;
+
+    /// Like [`spawn_talk`](TalkCommandsExt::spawn_talk), but the parent entity is spawned with the input bundle
+    /// too (tags, transforms, `Name`...), so it never exists without it.
+    /// Returns a handle of the parent entity.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::*;
+    /// use bevy::prelude::*;
+    ///
+    /// #[derive(Component)]
+    /// struct Cutscene;
+    ///
+    /// fn setup(mut commands: Commands) {
+    ///     let talk_builder = TalkBuilder::default().say("Hello world!");
+    ///     commands.spawn_talk_with(talk_builder, (Cutscene, Name::new("Intro")));
+    /// }
+    /// ```
+    fn spawn_talk_with(
+        &mut self,
+        builder: TalkBuilder,
+        bundle: impl Bundle,
+    ) -> EntityCommands<'w, 's, '_>;
+
+    /// Interjects the nodes of the builder in an active talk, right after its current node.
+    /// The next request moves into them, then the talk resumes its original flow.
+    /// See [`InterjectCommand`] for the details.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::*;
+    /// use bevy::prelude::*;
+    ///
+    /// fn hint(mut commands: Commands, talks: Query<Entity, With<Talk>>) {
+    ///     for talk in &talks {
+    ///         commands.interject(talk, TalkBuilder::default().say("Wait!"));
+    ///     }
+    /// }
+    /// ```
+    fn interject(&mut self, talk: Entity, builder: TalkBuilder);
+
+    /// Loads the assets referenced by the actors of a talk asset (portraits and voice blips)
+    /// and sends a [`TalkAssetsReady`](crate::prelude::TalkAssetsReady) event once they are all loaded,
+    /// so the first line can be shown without pop-in. The assets are kept loaded from then on.
+    ///
+    /// Preloading an already ready talk sends the event again.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::*;
+    /// use bevy::prelude::*;
+    ///
+    /// fn preload(mut commands: Commands, server: Res<AssetServer>) {
+    ///     commands.preload_talk_assets(server.load("talks/simple.talk.ron"));
+    /// }
+    /// ```
+    fn preload_talk_assets(&mut self, handle: Handle<TalkData>);
+
+    /// Spawns a parent entity with a [`Talk`] component and preloads the assets of the talk asset
+    /// (see [`preload_talk_assets`](TalkCommandsExt::preload_talk_assets)). The dialogue graph is built
+    /// once they are ready. Returns a handle of the parent entity.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::*;
+    /// use bevy::prelude::*;
+    ///
+    /// fn setup(mut commands: Commands, server: Res<AssetServer>) {
+    ///     commands.spawn_talk_when_ready(server.load("talks/simple.talk.ron"));
+    /// }
+    /// ```
+    fn spawn_talk_when_ready(&mut self, handle: Handle<TalkData>) -> EntityCommands<'w, 's, '_>;
+
+    /// Spawns a parent entity with a [`Talk`] component and builds the dialogue graph of the talk asset
+    /// as soon as it is loaded, sending a [`TalkSpawned`](crate::prelude::TalkSpawned) event.
+    /// Unlike [`spawn_talk_when_ready`](TalkCommandsExt::spawn_talk_when_ready), it doesn't wait for
+    /// the assets of the actors. Returns a handle of the parent entity.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::*;
+    /// use bevy::prelude::*;
+    ///
+    /// fn setup(mut commands: Commands, server: Res<AssetServer>) {
+    ///     commands.spawn_talk_from_handle(server.load("talks/simple.talk.ron"));
+    /// }
+    /// ```
+    fn spawn_talk_from_handle(&mut self, handle: Handle<TalkData>) -> EntityCommands<'w, 's, '_>;
+
+    /// Spawns a parent entity with a [`Talk`] component and the dialogue graph of the talk asset,
+    /// reusing an idle graph of the same asset from the pool if there is one (reset to its start node).
+    /// Returns a handle of the parent entity. The asset must be loaded.
+    ///
+    /// Release the talk with [`release_talk`](TalkCommandsExt::release_talk) (or end it with `OnEnd::Despawn`)
+    /// to put its graph back in the pool. The runtime edits of the graph are kept in the pool,
+    /// see [`TalkPoolPolicy`](crate::prelude::TalkPoolPolicy) for how many idle graphs are kept.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::*;
+    /// use bevy::prelude::*;
+    ///
+    /// #[derive(Resource)]
+    /// struct Bark(Handle<TalkData>);
+    ///
+    /// fn bark(mut commands: Commands, bark: Res<Bark>) {
+    ///     commands.spawn_talk_pooled(bark.0.clone()).insert(OnEnd::Despawn);
+    /// }
+    /// ```
+    fn spawn_talk_pooled(&mut self, handle: Handle<TalkData>) -> EntityCommands<'w, 's, '_>;
+
+    /// Despawns a talk, moving its graph back to the pool if it was spawned with
+    /// [`spawn_talk_pooled`](TalkCommandsExt::spawn_talk_pooled). The entity must not be used afterwards.
+    fn release_talk(&mut self, talk: Entity);
 }
 
 impl<'w, 's> TalkCommandsExt<'w, 's> for Commands<'w, 's> {
@@ -29,4 +157,73 @@ impl<'w, 's> TalkCommandsExt<'w, 's> for Commands<'w, 's> {
         self.add(BuildTalkCommand::new(parent, builder));
         self.entity(parent)
     }
+
+    fn spawn_talk_with(
+        &mut self,
+        builder: TalkBuilder,
+        bundle: impl Bundle,
+    ) -> EntityCommands<'w, 's, '_> {
+        let parent = self.spawn((Talk::default(), bundle)).id();
+        self.add(BuildTalkCommand::new(parent, builder));
+        self.entity(parent)
+    }
+
+    fn interject(&mut self, talk: Entity, builder: TalkBuilder) {
+        self.add(InterjectCommand::new(talk, builder));
+    }
+
+    fn preload_talk_assets(&mut self, handle: Handle<TalkData>) {
+        self.add(PreloadTalkAssets(handle));
+    }
+
+    fn spawn_talk_when_ready(&mut self, handle: Handle<TalkData>) -> EntityCommands<'w, 's, '_> {
+        let parent = self
+            .spawn((Talk::default(), WaitForTalkAssets(handle.clone())))
+            .id();
+        self.add(PreloadTalkAssets(handle));
+        self.entity(parent)
+    }
+
+    fn spawn_talk_from_handle(&mut self, handle: Handle<TalkData>) -> EntityCommands<'w, 's, '_> {
+        self.spawn((Talk::default(), WaitForTalkAsset(handle)))
+    }
+
+    fn spawn_talk_pooled(&mut self, handle: Handle<TalkData>) -> EntityCommands<'w, 's, '_> {
+        let talk = self
+            .spawn((Talk::default(), PooledTalk(handle.clone())))
+            .id();
+        self.add(SpawnPooledTalk { talk, handle });
+        self.entity(talk)
+    }
+
+    fn release_talk(&mut self, talk: Entity) {
+        self.add(move |world: &mut World| release_talk(world, talk));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::system::CommandQueue, prelude::*};
+
+    use crate::{prelude::*, tests::talks_minimal_app};
+
+    /// A tag for the talk parent entity.
+    #[derive(Component)]
+    struct Tag;
+
+    #[test]
+    fn spawn_talk_with_bundle() {
+        let mut app = talks_minimal_app();
+        let mut queue = CommandQueue::default();
+        let talk = Commands::new(&mut queue, &app.world)
+            .spawn_talk_with(TalkBuilder::default().say("Hi"), (Tag, Name::new("talk")))
+            .id();
+        queue.apply(&mut app.world);
+
+        let talk = app.world.entity(talk);
+        assert!(talk.contains::<Talk>());
+        assert!(talk.contains::<Tag>());
+        assert_eq!(talk.get::<Name>().unwrap().as_str(), "talk");
+        assert_eq!(talk.get::<Children>().unwrap().len(), 2);
+    }
 }
@@ -0,0 +1,286 @@
+//! Lazy building of the choice branches, see [`TalkBuilder::lazy_branches`].
+//!
+//! The deferred branches are spawned as placeholder nodes with a [`LazyBranch`] component.
+//! When a talk gets within two nodes of a placeholder, the branch is built and the placeholder entity
+//! becomes its first node, so the choices (and the events) pointing to it stay valid.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use aery::{prelude::*, tuple_traits::RelationEntries};
+use bevy::{
+    prelude::*,
+    utils::{hashbrown::HashMap, HashSet},
+};
+
+use crate::prelude::{CurrentNodes, EndNode, FollowedBy, LazyBranch};
+
+use super::{all_build_nodes, build_command::build_graph, BuildNode, BuildNodeId, TalkBuilder};
+
+/// Replaces the branches of the choice nodes with at least `depth` choice nodes above them
+/// with placeholder nodes holding the branch. The branches nested in a deferred one are deferred too.
+pub(crate) fn defer_branches(builder: &mut TalkBuilder, depth: usize) {
+    let mut targets = HashMap::new();
+    count_targets(builder, &mut targets);
+    defer_queue(&mut builder.queue, depth, 0, &targets);
+}
+
+/// Recursively defers the branches of the queue. `level` is the number of choice nodes above the queue.
+fn defer_queue(
+    queue: &mut VecDeque<BuildNode>,
+    depth: usize,
+    level: usize,
+    targets: &HashMap<BuildNodeId, usize>,
+) {
+    for node in queue.iter_mut() {
+        for branch in node.choices.iter_mut().map(|c| &mut c.branch) {
+            defer_queue(&mut branch.queue, depth, level + 1, targets);
+            if level < depth || !is_self_contained(branch, targets) {
+                continue;
+            }
+            let deferred = std::mem::take(branch);
+            branch.queue.push_back(BuildNode {
+                id: BuildNodeId::new(),
+                chapter: deferred.queue.front().and_then(|n| n.chapter.clone()),
+                lazy: Some(Arc::new(deferred)),
+                ..default()
+            });
+        }
+    }
+}
+
+/// Returns whether the branch can be deferred: it is not empty, it only connects to its own nodes
+/// and its nodes are not connected to from the outside (`targets` are the connection counts of the whole talk).
+fn is_self_contained(branch: &TalkBuilder, targets: &HashMap<BuildNodeId, usize>) -> bool {
+    if branch.queue.is_empty() || branch.connect_parent.is_some() {
+        return false;
+    }
+    let mut ids = HashSet::new();
+    collect_ids(branch, &mut ids);
+    let mut inner_targets = HashMap::new();
+    count_targets(branch, &mut inner_targets);
+
+    inner_targets.keys().all(|id| ids.contains(id))
+        && ids
+            .iter()
+            .all(|id| inner_targets.get(id) == targets.get(id))
+}
+
+/// Counts the connections (`connect_to`) to each node id of the builder, branches included.
+fn count_targets(builder: &TalkBuilder, counts: &mut HashMap<BuildNodeId, usize>) {
+    let connections = builder
+        .queue
+        .iter()
+        .flat_map(|n| n.manual_connections.iter());
+    for id in builder.connect_parent.iter().chain(connections) {
+        *counts.entry(*id).or_default() += 1;
+    }
+    for node in builder.queue.iter() {
+        for branch in node.choices.iter().map(|c| &c.branch) {
+            count_targets(branch, counts);
+        }
+        if let Some(deferred) = &node.lazy {
+            count_targets(deferred, counts);
+        }
+    }
+}
+
+/// Collects the node ids of the builder, branches included.
+fn collect_ids(builder: &TalkBuilder, ids: &mut HashSet<BuildNodeId>) {
+    for node in builder.queue.iter() {
+        ids.insert(node.id);
+        for branch in node.choices.iter().map(|c| &c.branch) {
+            collect_ids(branch, ids);
+        }
+        if let Some(deferred) = &node.lazy {
+            collect_ids(deferred, ids);
+        }
+    }
+}
+
+/// Adds the [`LazyBranch`] component with the deferred branch to the spawned placeholder nodes.
+pub(crate) fn insert_lazy_branches(
+    build_nodes: &VecDeque<BuildNode>,
+    node_entities: &HashMap<BuildNodeId, Entity>,
+    world: &mut World,
+) {
+    for build_node in all_build_nodes(build_nodes) {
+        if let Some(deferred) = &build_node.lazy {
+            world
+                .entity_mut(node_entities[&build_node.id])
+                .insert(LazyBranch(deferred.clone()));
+        }
+    }
+}
+
+/// Expands the lazy branch placeholders within two nodes of the current node of the talks.
+pub(crate) fn expand_lazy_branches(
+    mut cmd: Commands,
+    current_nodes: Res<CurrentNodes>,
+    nodes: Query<Relations<FollowedBy>>,
+    placeholders: Query<(), With<LazyBranch>>,
+) {
+    if placeholders.is_empty() {
+        return;
+    }
+    let followers = |node: Entity| {
+        nodes
+            .get(node)
+            .map(|edges| edges.targets(FollowedBy).to_vec())
+            .unwrap_or_default()
+    };
+
+    let mut to_expand = HashSet::new();
+    for (talk, current) in current_nodes.0.iter() {
+        for next in followers(*current) {
+            for node in std::iter::once(next).chain(followers(next)) {
+                if placeholders.contains(node) {
+                    to_expand.insert((*talk, node));
+                }
+            }
+        }
+    }
+    for (talk, placeholder) in to_expand {
+        cmd.add(move |world: &mut World| expand_lazy_branch(world, talk, placeholder));
+    }
+}
+
+/// Builds the deferred branch of the placeholder, which becomes the first node of the branch.
+/// The branch leads to the nodes the placeholder was followed by.
+fn expand_lazy_branch(world: &mut World, talk: Entity, placeholder: Entity) {
+    let Some(mut entity) = world.get_entity_mut(placeholder) else {
+        return;
+    };
+    let Some(LazyBranch(branch)) = entity.take::<LazyBranch>() else {
+        return;
+    };
+    entity.remove::<EndNode>();
+
+    let resume = world
+        .query::<Relations<FollowedBy>>()
+        .get(world, placeholder)
+        .map(|edges| edges.targets(FollowedBy).to_vec())
+        .unwrap_or_default();
+    for next in resume.iter() {
+        world.entity_mut(placeholder).unset::<FollowedBy>(*next);
+    }
+
+    // build from a temporary root, then detach it (unset first, the recursive cleanup would despawn the branch)
+    let root = world.spawn_empty().id();
+    build_graph(talk, root, &branch, &resume, Some(placeholder), world);
+    world.entity_mut(root).unset::<FollowedBy>(placeholder);
+    world.despawn(root);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        prelude::*,
+        tests::{count, single, talks_minimal_app},
+    };
+
+    use super::*;
+
+    /// Returns the texts of the `TextNodeEvent`s.
+    fn texts(app: &App) -> Vec<String> {
+        let evs = app.world.resource::<Events<TextNodeEvent>>();
+        evs.get_reader().read(evs).map(|t| t.text.clone()).collect()
+    }
+
+    /// Builds the talk and sends the first `NextNodeRequest`.
+    fn build_and_next(app: &mut App, builder: TalkBuilder) -> Entity {
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        talk
+    }
+
+    #[test]
+    fn branches_are_expanded_when_close() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default()
+            .lazy_branches(0)
+            .say("Hi")
+            .choose(vec![
+                ("A", TalkBuilder::default().say("a1").say("a2")),
+                ("B", TalkBuilder::default().say("b")),
+            ]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        assert_eq!(count::<&TextNode>(&mut app.world), 1);
+        assert_eq!(count::<&LazyBranch>(&mut app.world), 2);
+
+        // on "Hi" the branches are two nodes away
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(count::<&TextNode>(&mut app.world), 4);
+        assert_eq!(count::<&LazyBranch>(&mut app.world), 0);
+
+        // the choices still point to the (expanded) placeholders
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let choice = single::<&ChoiceNode>(&mut app.world).0[0].clone();
+        app.world
+            .send_event(ChooseNodeRequest::new(talk, choice.next));
+        app.update();
+        assert_eq!(texts(&app), vec!["a1"]);
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(texts(&app), vec!["a1", "a2"]);
+        let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        assert!(app.world.get::<EndNode>(current).is_some());
+    }
+
+    #[test]
+    fn expanded_branch_resumes_after_the_choice() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default()
+            .lazy_branches(0)
+            .choose(vec![("A", TalkBuilder::default().say("a"))])
+            .say("After");
+        let talk = build_and_next(&mut app, builder);
+
+        let choice = single::<&ChoiceNode>(&mut app.world).0[0].clone();
+        app.world
+            .send_event(ChooseNodeRequest::new(talk, choice.next));
+        app.update();
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(texts(&app), vec!["a", "After"]);
+    }
+
+    #[test]
+    fn only_deep_branches_are_lazy() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default().lazy_branches(1).choose(vec![(
+            "A",
+            TalkBuilder::default()
+                .say("a")
+                .choose(vec![("B", TalkBuilder::default().say("b"))]),
+        )]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        assert_eq!(count::<&TextNode>(&mut app.world), 1);
+        assert_eq!(count::<&ChoiceNode>(&mut app.world), 2);
+        assert_eq!(count::<&LazyBranch>(&mut app.world), 1);
+    }
+
+    #[test]
+    fn connected_branches_are_eager() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default().say("Hi");
+        let hi = builder.last_node_id();
+        let builder = builder.lazy_branches(0).choose(vec![
+            ("Again", TalkBuilder::default().say("again").connect_to(hi)),
+            ("Go", TalkBuilder::default().say("bye")),
+        ]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        assert_eq!(count::<&TextNode>(&mut app.world), 2);
+        assert_eq!(count::<&LazyBranch>(&mut app.world), 1);
+    }
+}
@@ -1,14 +1,25 @@
 //! Programmatically build Talks
 
-use bevy::prelude::*;
-use bevy::utils::Uuid;
+use bevy::{prelude::*, utils::HashMap};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
-use crate::prelude::{Actor, ActorSlug, TalkData};
+use crate::localization::TalkLocalization;
+use crate::prelude::{
+    Actor, ActorLines, ActorSlug, AnimateActor, CustomNode, EmitterSpec, Emphasis, Icon,
+    NodeEffect, ResumeNode, TalkData, TalkMetadata, TextPages, Thought,
+};
 use crate::{JoinNode, LeaveNode, TextNode};
 
 pub mod build_command;
 pub mod commands;
+pub(crate) mod lazy;
+pub mod passes;
 
 /// An implementation of the builder pattern for the dialogue graph.
 /// You can define dialogue graphs programmatically using this builder and
@@ -43,12 +54,92 @@ pub struct TalkBuilder {
     /// It is set when `connect_to` is called on an empty builder.
     /// It signals the Command to connect the last node of the parent builder (in a choice node).
     pub(crate) connect_parent: Option<BuildNodeId>,
+    /// The chapter assigned to the nodes added from now on. Set via `chapter`.
+    pub(crate) chapter: Option<String>,
+    /// The metadata copied onto the `Talk` component when the graph is built. Set via `metadata`.
+    pub(crate) metadata: Option<TalkMetadata>,
+    /// The choice depth from which the branches are built lazily. Set via `lazy_branches`.
+    pub(crate) lazy_depth: Option<usize>,
+    /// The stable id of the talk, added as `TalkId` when the graph is built. Set via `talk_id`.
+    pub(crate) talk_id: Option<String>,
+    /// The localized texts of the talk asset the builder was filled with (locale => texts).
+    pub(crate) locales: Arc<HashMap<String, TalkLocalization>>,
+    /// The actor aliases of the talk (alias => actor slug). Set via `alias`.
+    pub(crate) aliases: HashMap<String, ActorSlug>,
 }
 
 /// The ID of the nodes in the builder. It is used to identify the dialogue graph nodes before
 /// they are actually spawned in the world.
 /// It is useful to connect manually the nodes at build time with the `connect_to` method.
-pub type BuildNodeId = String;
+///
+/// It is a lightweight `Copy` id, unique in the whole app. It can also be created from a string
+/// (e.g. the old string ids), in which case the same string always gives the same id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BuildNodeId(u64);
+
+/// The bit set in the ids created from strings, so they never collide with the generated ones.
+const STRING_ID_BIT: u64 = 1 << 63;
+
+impl BuildNodeId {
+    /// Generates a new unique id.
+    pub fn new() -> Self {
+        /// The counter of the generated ids.
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT_ID.fetch_add(1, Ordering::Relaxed) & !STRING_ID_BIT)
+    }
+}
+
+impl Default for BuildNodeId {
+    /// Generates a new unique id, as [`BuildNodeId::new`].
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for BuildNodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl From<&str> for BuildNodeId {
+    fn from(value: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        Self(hasher.finish() | STRING_ID_BIT)
+    }
+}
+
+impl From<String> for BuildNodeId {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+/// Where a node of the builder comes from, to point the authors to it in the build errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeLocation {
+    /// The node was added by the n-th node method call (`say`, `choose`, ...) on its builder, starting from 0.
+    /// The builders of the choice branches count their own nodes.
+    Builder(usize),
+    /// The node was created from the action with this id of a talk asset.
+    Action(usize),
+}
+
+impl Default for NodeLocation {
+    fn default() -> Self {
+        Self::Builder(0)
+    }
+}
+
+impl fmt::Display for NodeLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Builder(index) => write!(f, "builder node #{index}"),
+            Self::Action(id) => write!(f, "action {id}"),
+        }
+    }
+}
 
 /// A struct with the data to build a node.
 #[derive(Default)]
@@ -59,14 +150,136 @@ pub(crate) struct BuildNode {
     /// NOTE: due to the limitation of current entity relationship system (with aery) we need to store the choices
     /// until the entities are spawned cause edges cannot hold any data, so we can't already create the
     /// choice node components.
-    pub(crate) choices: Vec<(String, TalkBuilder)>,
+    pub(crate) choices: Vec<BuildChoice>,
     /// The ids to add extra connections.
     pub(crate) manual_connections: Vec<BuildNodeId>,
     /// The actors slugs that are performing the node action.
     pub(crate) actors: Vec<ActorSlug>,
+    /// The aliases the actors perform the node under (actor slug => alias), set when the aliases are resolved.
+    pub(crate) actor_aliases: HashMap<ActorSlug, String>,
     /// The components to add to the node entity. These will be `TextNode`, JoinNode`, `LeaveNode` + custom components.
     /// `ChoiceNode` components are added later when the entities are spawned.
     pub(crate) components: Vec<Box<dyn Reflect>>,
+    /// The components to construct via the type registry when building, added to `components`.
+    pub(crate) emitters: Vec<EmitterSpec>,
+    /// The chapter the node belongs to, if any.
+    pub(crate) chapter: Option<String>,
+    /// The explicit end semantics of the node: `Some(true)` for an end node, `Some(false)` to never
+    /// make it an end node and `None` to let the build infer it.
+    pub(crate) end: Option<bool>,
+    /// The deferred branch if the node is a lazy branch placeholder (see `lazy_branches`).
+    pub(crate) lazy: Option<Arc<TalkBuilder>>,
+    /// The effect if the node is a logic node (see `do_`).
+    pub(crate) effect: Option<NodeEffect>,
+    /// The kind and data if the node is a custom node (see `custom_node`).
+    pub(crate) custom: Option<CustomNode>,
+    /// The variable to route on if the node is a switch node (see `switch`).
+    /// The branches are the cases in `choices`, the last one being the default.
+    pub(crate) switch: Option<String>,
+    /// The weights of the branches if the node is a random node (see `random`).
+    /// The branches are in `choices`, in the same order.
+    pub(crate) random: Option<Vec<u32>>,
+    /// Where the node comes from, for the build errors.
+    pub(crate) location: NodeLocation,
+}
+
+/// A choice of a node to build, with its branch. The switch cases and the random branches are stored as choices too.
+#[derive(Default)]
+pub(crate) struct BuildChoice {
+    /// The text of the choice (the value of the case for a switch node).
+    pub(crate) text: String,
+    /// The builder of the branch.
+    pub(crate) branch: TalkBuilder,
+    /// The optional description of the choice.
+    pub(crate) description: Option<String>,
+    /// The optional sound cue of the choice. Set via `choice_sound`.
+    pub(crate) sound: Option<String>,
+    /// The optional hotkey of the choice. Set via `hotkey`.
+    pub(crate) hotkey: Option<KeyCode>,
+}
+
+impl BuildChoice {
+    /// Creates a choice with the given text and branch.
+    pub(crate) fn new(text: impl Into<String>, branch: TalkBuilder) -> Self {
+        Self {
+            text: text.into(),
+            branch,
+            ..default()
+        }
+    }
+}
+
+/// Returns the nodes of the queue and of the branches of its choice nodes, in the builder order
+/// (each node before its branches).
+///
+/// The branches are walked with a worklist instead of recursion, so deeply nested choices
+/// (e.g. generated content) can't overflow the stack.
+pub(crate) fn all_build_nodes(queue: &VecDeque<BuildNode>) -> Vec<&BuildNode> {
+    let mut nodes = Vec::with_capacity(queue.len());
+    let mut stack = vec![queue.iter()];
+    while let Some(queue_nodes) = stack.last_mut() {
+        let Some(node) = queue_nodes.next() else {
+            stack.pop();
+            continue;
+        };
+        nodes.push(node);
+        stack.extend(node.choices.iter().rev().map(|c| c.branch.queue.iter()));
+    }
+    nodes
+}
+
+/// Calls `f` on the nodes of the queue and of the branches of its choice nodes, in the builder order,
+/// stopping at the first error. Like [`all_build_nodes`], it doesn't recurse.
+pub(crate) fn try_for_each_build_node_mut<E>(
+    queue: &mut VecDeque<BuildNode>,
+    mut f: impl FnMut(&mut BuildNode) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut stack = vec![queue.iter_mut()];
+    while let Some(queue_nodes) = stack.last_mut() {
+        let Some(node) = queue_nodes.next() else {
+            stack.pop();
+            continue;
+        };
+        f(node)?;
+        stack.extend(
+            node.choices
+                .iter_mut()
+                .rev()
+                .map(|c| c.branch.queue.iter_mut()),
+        );
+    }
+    Ok(())
+}
+
+/// Calls `f` on the nodes of the queue and of the branches of its choice nodes, in the builder order.
+/// Like [`all_build_nodes`], it doesn't recurse.
+pub(crate) fn for_each_build_node_mut(
+    queue: &mut VecDeque<BuildNode>,
+    mut f: impl FnMut(&mut BuildNode),
+) {
+    try_for_each_build_node_mut(queue, |node| {
+        f(node);
+        Ok::<(), Infallible>(())
+    })
+    .unwrap_or_else(|never| match never {});
+}
+
+impl Drop for TalkBuilder {
+    /// Drops the branches one by one, the default drop recurses into them and deeply nested choices
+    /// could overflow the stack.
+    fn drop(&mut self) {
+        let mut branches = Vec::new();
+        let mut queue = std::mem::take(&mut self.queue);
+        loop {
+            for node in queue.iter_mut() {
+                branches.extend(node.choices.drain(..).map(|c| c.branch));
+            }
+            let Some(mut branch) = branches.pop() else {
+                break;
+            };
+            queue = std::mem::take(&mut branch.queue);
+        }
+    }
 }
 
 impl TalkBuilder {
@@ -105,14 +318,46 @@ impl TalkBuilder {
     /// ```
     pub fn say(mut self, text: impl Into<String>) -> Self {
         let talk_node = BuildNode {
-            id: Uuid::new_v4().to_string(),
+            id: BuildNodeId::new(),
             components: vec![Box::new(TextNode(text.into()))],
             ..default()
         };
-        self.queue.push_back(talk_node);
+        self.push_node(talk_node);
         self
     }
 
+    /// Add a text node split in pages (see [`TextPages`](crate::prelude::TextPages)): each `NextNodeRequest`
+    /// shows the next page, and the talk moves on from the last one. The `TextNode` has all the pages, one per line.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// TalkBuilder::default().say_pages(["It was a dark night.", "The wind howled."]).say("Then...");
+    /// ```
+    pub fn say_pages(self, pages: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        let pages = TextPages::new(pages);
+        self.say(pages.pages.join("\n")).with_component(pages)
+    }
+
+    /// Add a simple text node for each text of the iterator, in order.
+    /// An empty iterator adds nothing.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// let items = ["A rusty sword.", "A wooden shield."];
+    /// TalkBuilder::default().say_each(items.iter().map(|i| format!("You found: {i}")));
+    /// ```
+    pub fn say_each(self, texts: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        texts
+            .into_iter()
+            .fold(self, |builder, text| builder.say(text))
+    }
+
     /// Add a choice node that branches the conversation in different paths.
     /// It will spawn a `ChoiceNode` entity.
     ///
@@ -147,45 +392,267 @@ impl TalkBuilder {
     ///     ("Choice 2", TalkBuilder::default().say("World!")),
     /// ]).say("Hi");
     /// ```
-    pub fn choose(mut self, choices: Vec<(impl Into<String>, Self)>) -> Self {
+    pub fn choose(self, choices: Vec<(impl Into<String>, Self)>) -> Self {
+        self.choose_with_descriptions(
+            choices
+                .into_iter()
+                .map(|(text, builder)| (text, None, builder))
+                .collect(),
+        )
+    }
+
+    /// Add a choice node performed by an actor (e.g. the actor asking the question), so the actor is
+    /// connected to the node and passed in the `ChoiceNodeEvent`. It works like [`choose`](TalkBuilder::choose) otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use bevy_talks::prelude::{Actor, TalkBuilder};
+    ///
+    /// TalkBuilder::default().add_actor(Actor::new("bob", "Bob")).actor_choose("bob", vec![
+    ///     ("Yes", TalkBuilder::default().say("Great!")),
+    ///     ("No", TalkBuilder::default().say("Too bad.")),
+    /// ]);
+    /// ```
+    pub fn actor_choose(
+        self,
+        actor_slug: impl Into<String>,
+        choices: Vec<(impl Into<String>, Self)>,
+    ) -> Self {
+        let mut builder = self.choose(choices);
+        if let Some(node) = builder.queue.back_mut() {
+            node.actors = vec![actor_slug.into()];
+        }
+        builder
+    }
+
+    /// Add a choice node to the dialogue graph, with an optional description for each choice
+    /// (e.g. skill check odds or flavor text). The descriptions are passed along in the `Choice`s
+    /// of the `ChoiceNodeEvent`. It works like [`choose`](TalkBuilder::choose) otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// TalkBuilder::default().choose_with_descriptions(vec![
+    ///     ("Persuade", Some("Charisma check: 40%".to_string()), TalkBuilder::default().say("Fine...")),
+    ///     ("Leave", None, TalkBuilder::default().say("Bye!")),
+    /// ]);
+    /// ```
+    pub fn choose_with_descriptions(
+        mut self,
+        choices: Vec<(impl Into<String>, Option<String>, Self)>,
+    ) -> Self {
         assert!(!choices.is_empty(), "You can't choose node without choices");
 
         let choices = choices
             .into_iter()
-            .map(|(t, b)| (t.into(), b))
-            .collect::<Vec<(String, TalkBuilder)>>();
+            .map(|(text, description, mut branch)| {
+                branch.inherit_chapter(&self.chapter);
+                BuildChoice {
+                    description,
+                    ..BuildChoice::new(text, branch)
+                }
+            })
+            .collect();
 
         let choice_node = BuildNode {
-            id: Uuid::new_v4().to_string(),
+            id: BuildNodeId::new(),
+            choices,
+            ..default()
+        };
+
+        self.push_node(choice_node);
+        self
+    }
+
+    /// Add a choice node with a choice for each (text, branch builder) pair of the iterator.
+    /// It works like [`choose`](TalkBuilder::choose), but an empty iterator adds nothing instead of panicking.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// let quests = ["Find the cat", "Slay the dragon"];
+    /// TalkBuilder::default().choose_from_iter(
+    ///     quests.iter().map(|q| (*q, TalkBuilder::default().say(format!("{q}? Good luck!")))),
+    /// );
+    /// ```
+    pub fn choose_from_iter(
+        self,
+        choices: impl IntoIterator<Item = (impl Into<String>, Self)>,
+    ) -> Self {
+        let choices = choices.into_iter().collect::<Vec<_>>();
+        if choices.is_empty() {
+            return self;
+        }
+        self.choose(choices)
+    }
+
+    /// Add a logic node that runs the effect on the world when the talk reaches it, then moves past it right away
+    /// (the effect doesn't consume a `NextNodeRequest`). It will spawn an entity with a [`LogicNode`](crate::prelude::LogicNode),
+    /// as the graph edges connect the node entities, but the talk never stops on it: no `CurrentNodeChanged`,
+    /// chapter, start or end events are sent for it.
+    ///
+    /// Use it to set variables and flags, or to emit events (components added with `with_component` emit their events too).
+    /// The effects run in order with the other commands of the traversal.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy::prelude::*;
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// #[derive(Resource)]
+    /// struct MetBob(bool);
+    ///
+    /// TalkBuilder::default()
+    ///     .say("Hi, I'm Bob.")
+    ///     .do_(|world: &mut World| world.insert_resource(MetBob(true)))
+    ///     .say("Nice to meet you!");
+    /// ```
+    pub fn do_(mut self, effect: impl Fn(&mut World) + Send + Sync + 'static) -> Self {
+        let logic_node = BuildNode {
+            id: BuildNodeId::new(),
+            effect: Some(Arc::new(effect)),
+            ..default()
+        };
+        self.push_node(logic_node);
+        self
+    }
+
+    /// Add a switch node that routes the talk silently on the value of a [`TalkVariables`](crate::prelude::TalkVariables)
+    /// variable: to the branch of the matching case, or to the default branch if no case matches.
+    /// It will spawn an entity with a [`SwitchNode`](crate::prelude::SwitchNode).
+    ///
+    /// The branches work like the choice branches, and an empty branch goes on with the node after the switch.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// TalkBuilder::default()
+    ///     .switch(
+    ///         "mood",
+    ///         vec![
+    ///             ("angry", TalkBuilder::default().say("Go away!")),
+    ///             ("happy", TalkBuilder::default().say("What a day!")),
+    ///         ],
+    ///         TalkBuilder::default(),
+    ///     )
+    ///     .say("Anyway...");
+    /// ```
+    pub fn switch(
+        mut self,
+        variable: impl Into<String>,
+        cases: Vec<(impl Into<String>, Self)>,
+        default_branch: Self,
+    ) -> Self {
+        let choices = cases
+            .into_iter()
+            .map(|(value, b)| (value.into(), b))
+            .chain(std::iter::once((String::new(), default_branch)))
+            .map(|(value, mut b)| {
+                b.inherit_chapter(&self.chapter);
+                BuildChoice::new(value, b)
+            })
+            .collect();
+
+        let switch_node = BuildNode {
+            id: BuildNodeId::new(),
             choices,
+            switch: Some(variable.into()),
             ..default()
         };
+        self.push_node(switch_node);
+        self
+    }
+
+    /// Add a random node that routes the talk silently to one of the branches, picked at random by weight
+    /// with the [`TalkRng`](crate::prelude::TalkRng) of the talk (seed it for the same branches every time).
+    /// It will spawn an entity with a [`RandomNode`](crate::prelude::RandomNode).
+    ///
+    /// The branches work like the choice branches, and an empty branch goes on with the node after the random node.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// TalkBuilder::default()
+    ///     .random(vec![
+    ///         (3, TalkBuilder::default().say("Nice weather today.")),
+    ///         (1, TalkBuilder::default().say("Looks like rain.")),
+    ///     ])
+    ///     .say("Anyway...");
+    /// ```
+    pub fn random(mut self, branches: Vec<(u32, Self)>) -> Self {
+        let (weights, choices) = branches
+            .into_iter()
+            .map(|(weight, mut b)| {
+                b.inherit_chapter(&self.chapter);
+                (weight, BuildChoice::new(String::new(), b))
+            })
+            .unzip();
 
-        self.queue.push_back(choice_node);
+        let random_node = BuildNode {
+            id: BuildNodeId::new(),
+            choices,
+            random: Some(weights),
+            ..default()
+        };
+        self.push_node(random_node);
+        self
+    }
+
+    /// Add a custom node of the given kind. It will spawn an entity with a [`CustomNode`] component,
+    /// then the factory registered for the kind (see [`AppExt::register_custom_node_kind`](crate::prelude::AppExt::register_custom_node_kind))
+    /// adds its components. If no factory is registered for the kind, a warning is logged.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// TalkBuilder::default().say("Welcome!").custom_node("shop", "blacksmith");
+    /// ```
+    pub fn custom_node(mut self, kind: impl Into<String>, data: impl Into<String>) -> Self {
+        let custom_node = BuildNode {
+            id: BuildNodeId::new(),
+            custom: Some(CustomNode {
+                kind: kind.into(),
+                data: data.into(),
+            }),
+            ..default()
+        };
+        self.push_node(custom_node);
         self
     }
 
     /// Add a Join node to the dialogue graph.
     pub fn join(mut self, actor_slugs: &[ActorSlug]) -> Self {
         let join_node = BuildNode {
-            id: Uuid::new_v4().to_string(),
+            id: BuildNodeId::new(),
             actors: actor_slugs.to_vec(),
             components: vec![Box::new(JoinNode)],
             ..default()
         };
-        self.queue.push_back(join_node);
+        self.push_node(join_node);
         self
     }
 
     /// Add a Leave node to the dialogue graph.
     pub fn leave(mut self, actor_slugs: &[ActorSlug]) -> Self {
         let leave_node = BuildNode {
-            id: Uuid::new_v4().to_string(),
+            id: BuildNodeId::new(),
             actors: actor_slugs.to_vec(),
             components: vec![Box::new(LeaveNode)],
             ..default()
         };
-        self.queue.push_back(leave_node);
+        self.push_node(leave_node);
         self
     }
 
@@ -223,7 +690,8 @@ impl TalkBuilder {
     /// builder = builder.say("how are you?");
     /// builder = builder.connect_to(hello_id);
     /// ```
-    pub fn connect_to(mut self, node_id: BuildNodeId) -> Self {
+    pub fn connect_to(mut self, node_id: impl Into<BuildNodeId>) -> Self {
+        let node_id = node_id.into();
         match self.queue.back_mut() {
             None => self.connect_parent = Some(node_id),
             Some(node) => node.manual_connections.push(node_id),
@@ -232,7 +700,7 @@ impl TalkBuilder {
         self
     }
 
-    /// Get a unique id (uuids v4) for the latest node added to the builder.
+    /// Get the unique id of the latest node added to the builder.
     /// You can use the returned id with `connect_to` to manually pair nodes.
     ///
     /// # Panics
@@ -249,10 +717,36 @@ impl TalkBuilder {
     pub fn last_node_id(&self) -> BuildNodeId {
         match self.queue.back() {
             None => panic!("You can't get the last node id of an empty builder"),
-            Some(node) => node.id.clone(),
+            Some(node) => node.id,
         }
     }
 
+    /// Set the id of the latest node added to the builder, so it can be targeted with `connect_to`
+    /// before it is added (e.g. jumping forward to a later node).
+    ///
+    /// Call it right after adding the node, the connections already made to its old id are not updated.
+    /// On an empty builder it does nothing.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::{BuildNodeId, TalkBuilder};
+    ///
+    /// let skip = BuildNodeId::new();
+    /// let builder = TalkBuilder::default()
+    ///     .choose(vec![
+    ///         ("Tell me more", TalkBuilder::default().say("It's a long story...")),
+    ///         ("Skip", TalkBuilder::default().connect_to(skip)),
+    ///     ])
+    ///     .say("Anyway, see you!")
+    ///     .node_id(skip);
+    /// ```
+    pub fn node_id(mut self, id: impl Into<BuildNodeId>) -> Self {
+        if let Some(node) = self.queue.back_mut() {
+            node.id = id.into();
+        }
+        self
+    }
+
     /// Add an actor to the builder to be spawned (if not already present in the world, checked with the slug identifier).
     /// # Note
     /// Adding actors to nested builders (when branching) has no effect. Add them to the root builder instead.
@@ -269,28 +763,139 @@ impl TalkBuilder {
         self
     }
 
+    /// Add an alias of an actor: the nodes performed by the alias slug are performed by the actor,
+    /// but their events have the alias as the actor name until it is revealed (see [`ActorAliases`](crate::prelude::ActorAliases)).
+    /// # Note
+    /// Adding aliases to nested builders (when branching) has no effect. Add them to the root builder instead.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::*;
+    ///
+    /// TalkBuilder::default()
+    ///     .add_actor(Actor::new("stranger", "Geralt"))
+    ///     .alias("???", "stranger")
+    ///     .actor_say("???", "I'm just passing through.");
+    /// ```
+    pub fn alias(mut self, alias: impl Into<String>, slug: impl Into<ActorSlug>) -> Self {
+        self.aliases.insert(alias.into(), slug.into());
+        self
+    }
+
+    /// Set the metadata of the talk. It is copied onto the [`Talk`](crate::prelude::Talk) component when the graph is built.
+    /// # Note
+    /// Setting the metadata of nested builders (when branching) has no effect. Set it on the root builder instead.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy_talks::prelude::*;
+    ///
+    /// let metadata = TalkMetadata { title: Some("Greetings".to_string()), ..Default::default() };
+    /// TalkBuilder::default().metadata(metadata).say("Hello");
+    /// ```
+    pub fn metadata(mut self, metadata: TalkMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Build lazily the choice branches from the given depth, to reduce the initial entity count of huge talks.
+    ///
+    /// The branches of the choice nodes with at least `depth` choice nodes above them are not spawned with the talk:
+    /// each one is replaced by a placeholder node (with a [`LazyBranch`](crate::prelude::LazyBranch) component)
+    /// that is expanded into the real branch when the talk gets within two nodes of it.
+    /// With `0`, all the branches are lazy. Setting it on nested builders (when branching) has no effect.
+    ///
+    /// # Note
+    /// Branches that are targets of `connect_to` from outside (or that connect to nodes outside of them)
+    /// are always built eagerly, since their edges must be in place from the start.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// let builder = TalkBuilder::default().lazy_branches(1).choose(vec![
+    ///     ("Built with the talk", TalkBuilder::default().choose(vec![
+    ///         ("Built when the talk gets close", TalkBuilder::default().say("Hi")),
+    ///     ])),
+    /// ]);
+    /// ```
+    pub fn lazy_branches(mut self, depth: usize) -> Self {
+        self.lazy_depth = Some(depth);
+        self
+    }
+
+    /// Set the stable id of the talk. It is added as a [`TalkId`](crate::prelude::TalkId) component
+    /// to the talk when the graph is built, so its state is kept across respawns (see [`TalkStates`](crate::prelude::TalkStates)).
+    /// The talks filled from assets already have the asset path as id. Setting it on nested builders has no effect.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// TalkBuilder::default().talk_id("village/bob").say("Hello");
+    /// ```
+    pub fn talk_id(mut self, id: impl Into<String>) -> Self {
+        self.talk_id = Some(id.into());
+        self
+    }
+
     /// Add a talk node with an actor. It will spawn an entity with `TalkText` connected with the actor entity identified by the slug.
     pub fn actor_say(mut self, actor_slug: impl Into<String>, text: impl Into<String>) -> Self {
         let talk_node = BuildNode {
-            id: Uuid::new_v4().to_string(),
+            id: BuildNodeId::new(),
             actors: vec![actor_slug.into()],
             components: vec![Box::new(TextNode(text.into()))],
             ..default()
         };
-        self.queue.push_back(talk_node);
+        self.push_node(talk_node);
         self
     }
 
     /// Add a talk node with multiple actors.
     /// It will spawn an entity with `TalkText` connected with the actor entities identified by the slugs.
+    /// The actors are passed to the events in the order of the slugs.
     pub fn actors_say(mut self, actor_slugs: &[ActorSlug], text: impl Into<String>) -> Self {
         let talk_node = BuildNode {
-            id: Uuid::new_v4().to_string(),
+            id: BuildNodeId::new(),
             components: vec![Box::new(TextNode(text.into()))],
             actors: actor_slugs.to_vec(),
             ..default()
         };
-        self.queue.push_back(talk_node);
+        self.push_node(talk_node);
+        self
+    }
+
+    /// Add a talk node with multiple actors, each one with its own line.
+    /// The actors speak in the given order: the `TextNodeEvent` has the actors and their `lines` in this order,
+    /// and its text is all the lines joined with a new line.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::{Actor, TalkBuilder};
+    ///
+    /// let builder = TalkBuilder::default()
+    ///     .add_actors(vec![Actor::new("bob", "Bob"), Actor::new("alice", "Alice")])
+    ///     .actors_say_lines(vec![("alice", "Happy birthday"), ("bob", "to you!")]);
+    /// ```
+    pub fn actors_say_lines(
+        mut self,
+        lines: Vec<(impl Into<ActorSlug>, impl Into<String>)>,
+    ) -> Self {
+        let (actors, lines): (Vec<ActorSlug>, Vec<String>) = lines
+            .into_iter()
+            .map(|(slug, line)| (slug.into(), line.into()))
+            .unzip();
+        let talk_node = BuildNode {
+            id: BuildNodeId::new(),
+            components: vec![
+                Box::new(TextNode(lines.join("\n"))),
+                Box::new(ActorLines(lines)),
+            ],
+            actors,
+            ..default()
+        };
+        self.push_node(talk_node);
         self
     }
 
@@ -310,10 +915,218 @@ impl TalkBuilder {
     /// ```
     pub fn empty_node(mut self) -> Self {
         let talk_node = BuildNode {
-            id: Uuid::new_v4().to_string(),
+            id: BuildNodeId::new(),
             ..default()
         };
-        self.queue.push_back(talk_node);
+        self.push_node(talk_node);
+        self
+    }
+
+    /// Start a new chapter. All the nodes added after this call (branches included) will be tagged with
+    /// a [`Chapter`](crate::prelude::Chapter) component with the given name, until another chapter is set.
+    ///
+    /// When the traversal moves between nodes of different chapters, a `ChapterEndedEvent` for the old chapter
+    /// and a `ChapterStartedEvent` for the new one are sent.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// let builder = TalkBuilder::default()
+    ///     .chapter("Act 1")
+    ///     .say("Once upon a time...")
+    ///     .chapter("Act 2")
+    ///     .say("...and they lived happily ever after.");
+    /// ```
+    pub fn chapter(mut self, name: impl Into<String>) -> Self {
+        self.chapter = Some(name.into());
+        self
+    }
+
+    /// Set the emphasis intensity of the latest added node. It adds an [`Emphasis`] component to the node
+    /// and its value is passed in the `TextNodeEvent`.
+    ///
+    /// # Panics
+    /// If you call this method on an empty builder it will panic.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// let builder = TalkBuilder::default().say("BOOM!").emphasis(2.0);
+    /// ```
+    pub fn emphasis(self, intensity: f32) -> Self {
+        self.with_component(Emphasis(intensity))
+    }
+
+    /// Set the icon (an emoji or emote id) of the latest added node. It adds an [`Icon`] component to the node
+    /// and its value is passed in the `TextNodeEvent` or `ChoiceNodeEvent`.
+    ///
+    /// # Panics
+    /// If you call this method on an empty builder it will panic.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// let builder = TalkBuilder::default().say("I'm so angry!").icon("anger");
+    /// ```
+    pub fn icon(self, icon: impl Into<String>) -> Self {
+        self.with_component(Icon(icon.into()))
+    }
+
+    /// Mark the latest added node as the node to resume from when the talk is started again after it ended,
+    /// instead of starting over from the first node. It adds a [`ResumeNode`] component to the node.
+    ///
+    /// # Panics
+    /// If you call this method on an empty builder it will panic.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// let builder = TalkBuilder::default()
+    ///     .say("Welcome to my shop, traveler!")
+    ///     .say("What else?")
+    ///     .resume_here();
+    /// ```
+    pub fn resume_here(self) -> Self {
+        self.with_component(ResumeNode)
+    }
+
+    /// Set the thought (e.g. the inner monologue of the speaker) of the latest added node, shown alongside its text.
+    /// It adds a [`Thought`] component to the node and its value is passed in the `TextNodeEvent`.
+    ///
+    /// # Panics
+    /// If you call this method on an empty builder it will panic.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// let builder = TalkBuilder::default().say("Lovely weather.").thought("I hate rain.");
+    /// ```
+    pub fn thought(self, thought: impl Into<String>) -> Self {
+        self.with_component(Thought(thought.into()))
+    }
+
+    /// Set the sound cue (e.g. a confirm sound or a themed stinger) of the choice with the given index
+    /// of the latest added node. It is passed in the [`Choice`](crate::prelude::Choice) of the
+    /// `ChoiceSelectedEvent` sent when the choice is selected.
+    ///
+    /// # Panics
+    /// If the latest added node is not a choice node or it doesn't have a choice with the given index.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// let builder = TalkBuilder::default()
+    ///     .choose(vec![
+    ///         ("Accept the quest", TalkBuilder::default().say("Great!")),
+    ///         ("Refuse", TalkBuilder::default().say("Too bad.")),
+    ///     ])
+    ///     .choice_sound(0, "sfx/quest_accepted.ogg");
+    /// ```
+    pub fn choice_sound(mut self, choice: usize, sound: impl Into<String>) -> Self {
+        self.latest_choice_mut(choice).sound = Some(sound.into());
+        self
+    }
+
+    /// Set the keyboard hotkey of the choice with the given index of the latest added node, which must be a choice node.
+    /// The hotkey is carried in the `Choice`s of the `ChoiceNodeEvent`, and the [`ChoiceHotkeysPlugin`](crate::prelude::ChoiceHotkeysPlugin)
+    /// chooses the choice when it is pressed.
+    ///
+    /// # Panics
+    /// If the latest added node is not a choice node or it doesn't have a choice with the given index.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy::prelude::KeyCode;
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// let builder = TalkBuilder::default()
+    ///     .choose(vec![
+    ///         ("Yes", TalkBuilder::default().say("Great!")),
+    ///         ("No", TalkBuilder::default().say("Too bad.")),
+    ///     ])
+    ///     .hotkey(0, KeyCode::Key1)
+    ///     .hotkey(1, KeyCode::Key2);
+    /// ```
+    pub fn hotkey(mut self, choice: usize, key: KeyCode) -> Self {
+        self.latest_choice_mut(choice).hotkey = Some(key);
+        self
+    }
+
+    /// Returns the choice with the given index of the latest added node.
+    ///
+    /// # Panics
+    /// If the latest added node is not a choice node (a switch or random node has choices too)
+    /// or it doesn't have a choice with the given index.
+    fn latest_choice_mut(&mut self, choice: usize) -> &mut BuildChoice {
+        let node = self
+            .queue
+            .back_mut()
+            .expect("You can't set a choice property on an empty builder");
+        assert!(
+            node.switch.is_none() && node.random.is_none() && !node.choices.is_empty(),
+            "The latest node is not a choice node"
+        );
+        node.choices
+            .get_mut(choice)
+            .unwrap_or_else(|| panic!("The latest node has no choice {choice}"))
+    }
+
+    /// Make the latest added node trigger the animation `clip` on the actor with the given slug.
+    /// It adds an [`AnimateActor`] component to the node, so an `AnimateActorEvent` is sent when it is reached.
+    ///
+    /// # Panics
+    /// If you call this method on an empty builder it will panic.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::{Actor, TalkBuilder};
+    ///
+    /// let builder = TalkBuilder::default()
+    ///     .add_actor(Actor::new("bob", "Bob"))
+    ///     .actor_say("bob", "Hi!")
+    ///     .animate("bob", "wave");
+    /// ```
+    pub fn animate(self, actor_slug: impl Into<ActorSlug>, clip: impl Into<String>) -> Self {
+        self.with_component(AnimateActor::new(actor_slug, clip))
+    }
+
+    /// Mark the latest added node as an end node. It will get the [`EndNode`](crate::prelude::EndNode) component
+    /// even if it is followed by other nodes.
+    ///
+    /// By default, the nodes without any following node are the end nodes.
+    ///
+    /// # Panics
+    /// If you call this method on an empty builder it will panic.
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::TalkBuilder;
+    ///
+    /// let builder = TalkBuilder::default().say("Bye!").end().say("Still here?");
+    /// ```
+    pub fn end(mut self) -> Self {
+        match self.queue.back_mut() {
+            None => panic!("You can't mark as end a node of an empty builder"),
+            Some(node) => node.end = Some(true),
+        };
+        self
+    }
+
+    /// Prevent the latest added node from being inferred as an end node, even if no node follows it.
+    ///
+    /// # Panics
+    /// If you call this method on an empty builder it will panic.
+    pub fn no_end(mut self) -> Self {
+        match self.queue.back_mut() {
+            None => panic!("You can't unmark as end a node of an empty builder"),
+            Some(node) => node.end = Some(false),
+        };
         self
     }
 
@@ -333,6 +1146,49 @@ impl TalkBuilder {
         };
         self
     }
+
+    /// Add a component described by its type name and field values to the latest added node,
+    /// like the `emit` field of the RON actions. It is constructed via the type registry when the talk is built,
+    /// and the build fails with [`BuildError::InvalidEmitter`](crate::prelude::BuildError::InvalidEmitter) if it can't be.
+    ///
+    /// # Panics
+    /// If you call this method on an empty builder it will panic.
+    pub fn emit(mut self, spec: EmitterSpec) -> Self {
+        match self.queue.back_mut() {
+            None => panic!("You can't add an emitter to an empty builder"),
+            Some(node) => node.emitters.push(spec),
+        };
+        self
+    }
+}
+
+impl TalkBuilder {
+    /// Push a node in the queue, tagging it with the current chapter and its location in the builder.
+    fn push_node(&mut self, mut node: BuildNode) {
+        node.chapter = self.chapter.clone();
+        node.location = NodeLocation::Builder(self.queue.len());
+        self.queue.push_back(node);
+    }
+
+    /// Sets the location of the latest added node (e.g. the asset action it was created from).
+    pub(crate) fn set_last_location(&mut self, location: NodeLocation) {
+        if let Some(node) = self.queue.back_mut() {
+            node.location = location;
+        }
+    }
+
+    /// Assign the given chapter to the nodes (and branches) without a chapter.
+    /// Used to make the branches of a choice node inherit the chapter of the parent builder.
+    fn inherit_chapter(&mut self, chapter: &Option<String>) {
+        if chapter.is_none() {
+            return;
+        }
+        for_each_build_node_mut(&mut self.queue, |node| {
+            if node.chapter.is_none() {
+                node.chapter = chapter.clone();
+            }
+        });
+    }
 }
 
 #[cfg(test)]
@@ -389,7 +1245,43 @@ mod tests {
     fn connect_to_in_empty_builder_sets_connect_parent(talk_builder: TalkBuilder) {
         let id = "some id".to_string();
         let builder = talk_builder.connect_to(id.clone());
-        assert_eq!(builder.connect_parent, Some(id));
+        assert_eq!(builder.connect_parent, Some(BuildNodeId::from(id)));
+    }
+
+    #[rstest]
+    fn node_id_sets_the_last_node_id(talk_builder: TalkBuilder) {
+        let id = BuildNodeId::new();
+        let builder = talk_builder.say("hello").say("world").node_id(id);
+        assert_eq!(builder.last_node_id(), id);
+        assert_ne!(builder.queue[0].id, id);
+    }
+
+    #[rstest]
+    fn say_each_adds_text_nodes(talk_builder: TalkBuilder) {
+        let builder = talk_builder.say_each((1..=3).map(|i| format!("Line {i}")));
+        assert_eq!(builder.queue.len(), 3);
+
+        let builder = builder.say_each(Vec::<String>::new());
+        assert_eq!(builder.queue.len(), 3);
+    }
+
+    #[rstest]
+    fn choose_from_iter_adds_choice_node(talk_builder: TalkBuilder) {
+        let builder =
+            talk_builder.choose_from_iter(["A", "B"].map(|t| (t, TalkBuilder::default().say(t))));
+        assert_eq!(builder.queue.len(), 1);
+        assert_eq!(builder.queue[0].choices.len(), 2);
+
+        let builder = builder.choose_from_iter(Vec::<(String, TalkBuilder)>::new());
+        assert_eq!(builder.queue.len(), 1);
+    }
+
+    #[test]
+    fn build_node_ids() {
+        assert_ne!(BuildNodeId::new(), BuildNodeId::new());
+        assert_eq!(BuildNodeId::from("a"), BuildNodeId::from("a".to_string()));
+        assert_ne!(BuildNodeId::from("a"), BuildNodeId::from("b"));
+        assert_eq!(BuildNodeId::from("a").to_string().len(), 16);
     }
 
     #[test]
@@ -423,10 +1315,7 @@ mod tests {
 
     #[rstest]
     fn test_add_actor(talk_builder: TalkBuilder) {
-        let actor = Actor {
-            slug: "slug".to_string(),
-            name: "Actor".to_string(),
-        };
+        let actor = Actor::new("slug", "Actor");
         let builder = talk_builder.add_actor(actor.clone());
         assert_eq!(builder.actors.len(), 1);
         assert_eq!(builder.actors[0], actor);
@@ -434,15 +1323,30 @@ mod tests {
 
     #[rstest]
     fn test_actor_say_success(talk_builder: TalkBuilder) {
-        let builder = talk_builder.add_actor(Actor {
-            slug: "slug".to_string(),
-            name: "Actor".to_string(),
-        });
+        let builder = talk_builder.add_actor(Actor::new("slug", "Actor"));
         let builder = builder.actor_say("slug", "hello");
         assert_eq!(builder.queue.len(), 1);
         assert_eq!(builder.queue[0].actors[0], "slug");
     }
 
+    #[rstest]
+    fn chapter_tags_following_nodes(talk_builder: TalkBuilder) {
+        let builder = talk_builder
+            .say("no chapter")
+            .chapter("Act 1")
+            .say("hello")
+            .choose(vec![("Choice", TalkBuilder::default().say("branch"))])
+            .chapter("Act 2")
+            .say("bye");
+
+        assert_eq!(builder.queue[0].chapter, None);
+        assert_eq!(builder.queue[1].chapter, Some("Act 1".to_string()));
+        assert_eq!(builder.queue[2].chapter, Some("Act 1".to_string()));
+        let branch = &builder.queue[2].choices[0].branch;
+        assert_eq!(branch.queue[0].chapter, Some("Act 1".to_string()));
+        assert_eq!(builder.queue[3].chapter, Some("Act 2".to_string()));
+    }
+
     #[derive(Component, Reflect)]
     struct MyComp;
 
@@ -458,4 +1362,40 @@ mod tests {
     fn add_component_on_empty_panics(talk_builder: TalkBuilder) {
         talk_builder.with_component(MyComp);
     }
+
+    #[rstest]
+    fn choice_sound_on_last_choice_node(talk_builder: TalkBuilder) {
+        let builder = talk_builder
+            .choose(vec![
+                ("A", TalkBuilder::default().say("a")),
+                ("B", TalkBuilder::default().say("b")),
+            ])
+            .choice_sound(1, "stinger.ogg");
+        let sounds: Vec<_> = builder.queue[0]
+            .choices
+            .iter()
+            .map(|c| c.sound.clone())
+            .collect();
+        assert_eq!(sounds, vec![None, Some("stinger.ogg".to_string())]);
+    }
+
+    #[rstest]
+    #[should_panic]
+    fn hotkey_on_missing_choice_panics(talk_builder: TalkBuilder) {
+        talk_builder
+            .choose(vec![("A", TalkBuilder::default().say("a"))])
+            .hotkey(1, KeyCode::Key2);
+    }
+
+    #[rstest]
+    #[should_panic(expected = "not a choice node")]
+    fn choice_sound_on_switch_panics(talk_builder: TalkBuilder) {
+        talk_builder
+            .switch(
+                "mood",
+                vec![("angry", TalkBuilder::default())],
+                TalkBuilder::default(),
+            )
+            .choice_sound(0, "stinger.ogg");
+    }
 }
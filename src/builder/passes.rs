@@ -0,0 +1,149 @@
+//! The passes of the building of the dialogue graphs, with the registration point for your own passes.
+//!
+//! The [`BuildTalkCommand`](super::build_command::BuildTalkCommand) (and the interjections) build the graphs
+//! running the [`BuildPass`]es in order. The passes registered with
+//! [`AppExt::add_build_pass`](crate::prelude::AppExt::add_build_pass) run right after the built-in pass
+//! they are registered for, in registration order.
+
+use bevy::{prelude::*, utils::HashMap};
+use std::sync::Arc;
+
+use crate::prelude::ActorSlug;
+
+/// The built-in passes of the building of a dialogue graph, in the order they run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BuildPass {
+    /// Spawns the node entities (numbered with their `OrderIndex` and added as children of the talk) and the actors.
+    SpawnNodes,
+    /// Inserts the components of the nodes.
+    InsertComponents,
+    /// Connects the nodes with the `FollowedBy` relations, adds the `ChoiceNode`s and marks the end nodes.
+    FormGraph,
+    /// Connects the nodes with the `PerformedBy` relations to their actors.
+    ConnectActors,
+    /// Finishes the special nodes: the deferred branches, the logic nodes and the custom nodes.
+    SpecialNodes,
+}
+
+impl BuildPass {
+    /// All the built-in passes, in the order they run.
+    pub(crate) const ORDER: [BuildPass; 5] = [
+        BuildPass::SpawnNodes,
+        BuildPass::InsertComponents,
+        BuildPass::FormGraph,
+        BuildPass::ConnectActors,
+        BuildPass::SpecialNodes,
+    ];
+}
+
+/// What a [`BuildTalkPass`] gets to know about the graph being built.
+#[derive(Debug)]
+pub struct BuildContext<'a> {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// The spawned node entities, in the builder order (each node before its branches).
+    /// The start node of the talk is not included.
+    pub nodes: &'a [Entity],
+    /// The actor entities that can perform the nodes (actor slug => entity).
+    pub actors: &'a HashMap<ActorSlug, Entity>,
+}
+
+/// Trait to implement to run your own pass while the dialogue graphs are built (e.g. to attach colliders
+/// to the nodes or assign voices to the actors).
+///
+/// It is implemented for every `Fn(&mut World, &BuildContext)` closure, so you can
+/// register closures directly with [`AppExt::add_build_pass`](crate::prelude::AppExt::add_build_pass).
+///
+/// # Example
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// #[derive(Component)]
+/// struct Voiced;
+///
+/// let mut app = App::new();
+/// app.add_build_pass(BuildPass::ConnectActors, |world: &mut World, ctx: &BuildContext| {
+///     for actor in ctx.actors.values() {
+///         world.entity_mut(*actor).insert(Voiced);
+///     }
+/// });
+/// ```
+pub trait BuildTalkPass: Send + Sync + 'static {
+    /// Runs the pass on the graph being built.
+    fn run(&self, world: &mut World, ctx: &BuildContext);
+}
+
+impl<F> BuildTalkPass for F
+where
+    F: Fn(&mut World, &BuildContext) + Send + Sync + 'static,
+{
+    fn run(&self, world: &mut World, ctx: &BuildContext) {
+        self(world, ctx)
+    }
+}
+
+/// The registered build passes, with the built-in pass they run after.
+#[derive(Resource, Default, Clone)]
+pub struct BuildPasses(pub(crate) Vec<(BuildPass, Arc<dyn BuildTalkPass>)>);
+
+impl BuildPasses {
+    /// Returns the passes registered to run after the given built-in pass, in registration order.
+    pub(crate) fn after(&self, pass: BuildPass) -> impl Iterator<Item = &Arc<dyn BuildTalkPass>> {
+        self.0
+            .iter()
+            .filter(move |(after, _)| *after == pass)
+            .map(|(_, p)| p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::Command;
+
+    use crate::{
+        prelude::*,
+        tests::{count, talks_minimal_app},
+    };
+
+    use super::*;
+
+    /// Marker added by the test passes.
+    #[derive(Component)]
+    struct Collider;
+
+    #[test]
+    fn user_passes_run_after_their_pass() {
+        let mut app = talks_minimal_app();
+        app.add_build_pass(
+            BuildPass::SpawnNodes,
+            |world: &mut World, ctx: &BuildContext| {
+                // the components are not inserted yet
+                assert!(ctx
+                    .nodes
+                    .iter()
+                    .all(|n| world.get::<TextNode>(*n).is_none()));
+                assert_eq!(ctx.nodes.len(), 2);
+            },
+        )
+        .add_build_pass(
+            BuildPass::ConnectActors,
+            |world: &mut World, ctx: &BuildContext| {
+                for node in ctx.nodes {
+                    if world.get::<TextNode>(*node).is_some() {
+                        world.entity_mut(*node).insert(Collider);
+                    }
+                }
+                assert!(ctx.actors.contains_key("bob"));
+            },
+        );
+        let builder = TalkBuilder::default()
+            .add_actor(Actor::new("bob", "Bob"))
+            .actor_say("bob", "Hello")
+            .say("Bye");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        assert_eq!(count::<(&TextNode, &Collider)>(&mut app.world), 2);
+    }
+}
@@ -0,0 +1,185 @@
+//! Normalized caption events for the subtitle and closed-caption systems, whatever the node kind.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{events::ReflectEvent, text_filter::apply_text_filters};
+
+/// Event sent for every spoken node, with what is said and by whom, decoupled from the UI.
+///
+/// It is sent after the node events for the text nodes (with the text of their `TextNodeEvent`, so without the markup
+/// and with the text filters applied) and for the nodes of any kind with a [`Caption`] component.
+/// It can be turned off with the [`CaptionSettings`].
+///
+/// # Example
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// fn show_subtitles(mut captions: EventReader<CaptionEvent>) {
+///     for caption in captions.read() {
+///         let speaker = caption.speaker.as_deref().unwrap_or("Narrator");
+///         println!("[{speaker}] {} ({:?})", caption.text, caption.duration_hint);
+///     }
+/// }
+/// ```
+#[derive(Event, Reflect, Debug, Clone, PartialEq)]
+#[reflect(Event)]
+pub struct CaptionEvent {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// The node entity.
+    pub node: Entity,
+    /// The name of the first actor of the node, `None` for the narrated nodes.
+    pub speaker: Option<String>,
+    /// The text of the caption.
+    pub text: String,
+    /// How long the caption should stay on screen, from its length and the [`CaptionSettings`].
+    pub duration_hint: Duration,
+}
+
+/// Component to give a node its caption, e.g. for the custom nodes with their own events (a voice line, a sound).
+/// On a text node, it replaces the text in the [`CaptionEvent`].
+///
+/// # Example
+/// ```rust
+/// use bevy_talks::prelude::*;
+///
+/// let builder = TalkBuilder::default()
+///     .say("...")
+///     .with_component(Caption("[door creaks]".to_string()));
+/// ```
+#[derive(Component, Reflect, Default, Debug, Clone, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct Caption(pub String);
+
+/// Resource to configure the [`CaptionEvent`]s.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct CaptionSettings {
+    /// Whether the caption events are sent.
+    pub enabled: bool,
+    /// The reading speed used for the duration hints, in characters per second.
+    pub chars_per_second: f32,
+    /// The minimum duration hint.
+    pub min_duration: Duration,
+}
+
+impl Default for CaptionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            chars_per_second: 15.0,
+            min_duration: Duration::from_millis(1500),
+        }
+    }
+}
+
+impl CaptionSettings {
+    /// Returns how long a caption with the text should stay on screen.
+    pub fn duration_hint(&self, text: &str) -> Duration {
+        let reading = text.chars().count() as f32 / self.chars_per_second.max(f32::EPSILON);
+        Duration::from_secs_f32(reading).max(self.min_duration)
+    }
+}
+
+/// Sends the [`CaptionEvent`] of the node, if the captions are enabled.
+pub(crate) fn send_caption(world: &mut World, node: Entity, speaker: Option<String>, text: String) {
+    let Some(settings) = world.get_resource::<CaptionSettings>() else {
+        return;
+    };
+    let Some(talk) = world.get::<Parent>(node).map(|p| p.get()) else {
+        return;
+    };
+    if !settings.enabled || text.is_empty() {
+        return;
+    }
+    let duration_hint = settings.duration_hint(&text);
+    world.send_event(CaptionEvent {
+        talk,
+        node,
+        speaker,
+        text,
+        duration_hint,
+    });
+}
+
+/// Sends the [`CaptionEvent`] of the node from its [`Caption`] component, if it has one.
+pub(crate) fn send_component_caption(world: &mut World, node: Entity, speaker: Option<String>) {
+    let Some(Caption(text)) = world.get::<Caption>(node) else {
+        return;
+    };
+    let mut text = text.clone();
+    apply_text_filters(world, node, &mut text);
+    send_caption(world, node, speaker, text);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::Command;
+
+    use crate::{prelude::*, tests::talks_minimal_app};
+
+    use super::*;
+
+    /// Returns the caption events sent since the last call.
+    fn captions(app: &mut App) -> Vec<CaptionEvent> {
+        let mut evs = app.world.resource_mut::<Events<CaptionEvent>>();
+        evs.drain().collect()
+    }
+
+    #[test]
+    fn captions_for_text_and_captioned_nodes() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default()
+            .add_actor(Actor::new("bob", "Bob"))
+            .actor_say("bob", "Hello {pause=1}there")
+            .say("...")
+            .with_component(Caption("[door creaks]".to_string()))
+            .join(&["bob".to_string()]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let sent = captions(&mut app);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].talk, talk);
+        assert_eq!(sent[0].speaker.as_deref(), Some("Bob"));
+        assert_eq!(sent[0].text, "Hello there");
+        assert_eq!(sent[0].duration_hint, Duration::from_millis(1500));
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let sent = captions(&mut app);
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].speaker, None);
+        assert_eq!(sent[0].text, "[door creaks]");
+
+        // no caption for the join node
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert!(captions(&mut app).is_empty());
+    }
+
+    #[test]
+    fn captions_can_be_disabled() {
+        let mut app = talks_minimal_app();
+        app.world.resource_mut::<CaptionSettings>().enabled = false;
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, TalkBuilder::default().say("Hi")).apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert!(captions(&mut app).is_empty());
+    }
+
+    #[test]
+    fn duration_hint_from_the_length() {
+        let settings = CaptionSettings::default();
+        assert_eq!(settings.duration_hint("Hi"), Duration::from_millis(1500));
+        assert_eq!(
+            settings.duration_hint(&"a".repeat(45)),
+            Duration::from_secs(3)
+        );
+    }
+}
@@ -0,0 +1,384 @@
+//! Hooks to filter the choices of a choice node before the `ChoiceNodeEvent` is emitted.
+
+use bevy::prelude::*;
+
+use crate::prelude::{Choice, ChoiceNode, ChooseNodeRequest};
+
+/// Trait to implement to filter (or modify) the choices of a choice node right before
+/// the [`ChoiceNodeEvent`](crate::prelude::ChoiceNodeEvent) is emitted.
+///
+/// It is implemented for every `Fn(&World, Entity, &mut Vec<Choice>)` closure, so you can
+/// register closures directly with [`AppExt::add_choice_filter`](crate::prelude::AppExt::add_choice_filter).
+///
+/// # Example
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// #[derive(Resource)]
+/// struct QuestCompleted(bool);
+///
+/// let mut app = App::new();
+/// app.add_choice_filter(|world: &World, _talk: Entity, choices: &mut Vec<Choice>| {
+///     if world.get_resource::<QuestCompleted>().is_some_and(|q| q.0) {
+///         choices.retain(|c| c.text != "Accept the quest");
+///     }
+/// });
+/// ```
+pub trait ChoiceFilter: Send + Sync + 'static {
+    /// Filters the choices of a choice node of the given talk. Remove or change them in place.
+    fn filter(&self, world: &World, talk: Entity, choices: &mut Vec<Choice>);
+
+    /// The name of the filter in the diagnostics (see [`explain_choice_availability`]). Defaults to its type name.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+}
+
+impl<F> ChoiceFilter for F
+where
+    F: Fn(&World, Entity, &mut Vec<Choice>) + Send + Sync + 'static,
+{
+    fn filter(&self, world: &World, talk: Entity, choices: &mut Vec<Choice>) {
+        self(world, talk, choices)
+    }
+}
+
+/// The registered choice filters. They are applied in registration order.
+#[derive(Resource, Default)]
+pub struct ChoiceFilters(pub(crate) Vec<Box<dyn ChoiceFilter>>);
+
+/// The choices of a choice node kept by the filters when its `ChoiceNodeEvent` was last emitted
+/// (their next node entities), so a `ChooseNodeRequest` for a hidden choice can be rejected.
+#[derive(Component, Debug, Clone)]
+pub(crate) struct ShownChoices(pub(crate) Vec<Entity>);
+
+impl ShownChoices {
+    /// Returns whether the choice leading to the given node was shown.
+    pub(crate) fn contains(&self, next: Entity) -> bool {
+        self.0.contains(&next)
+    }
+}
+
+/// The request to choose the single choice of a choice node skipped by the [`SingleChoicePolicy::AutoSkip`].
+///
+/// It is handled like a `ChooseNodeRequest`, but it is sent by the crate, so it is not locked out.
+#[derive(Event, Debug, Clone, Copy)]
+pub(crate) struct SkippedChoice(pub(crate) ChooseNodeRequest);
+
+/// What to do with a choice node that has exactly one choice left after the filters are applied.
+///
+/// Insert it as a resource to set the global policy, or add it to a choice node
+/// (e.g. with [`TalkBuilder::with_component`](crate::prelude::TalkBuilder::with_component)) to override it for that node.
+///
+/// # Example
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// let mut app = App::new();
+/// app.insert_resource(SingleChoicePolicy::AutoSkip);
+///
+/// // or just for a node
+/// let builder = TalkBuilder::default()
+///     .choose(vec![("Continue", TalkBuilder::default().say("..."))])
+///     .with_component(SingleChoicePolicy::AutoSkip);
+/// ```
+#[derive(Resource, Component, Reflect, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub enum SingleChoicePolicy {
+    /// Emit the `ChoiceNodeEvent` with the single choice as usual.
+    #[default]
+    Show,
+    /// Don't emit the `ChoiceNodeEvent`, choose the single choice instead, so the talk advances as if it was chosen
+    /// (the next node events are emitted in the next update). The [`ChoiceLockout`](crate::prelude::ChoiceLockout)
+    /// does not apply, since the choice is not made by the player.
+    AutoSkip,
+}
+
+/// Returns the single choice policy of the node: its own component if it has one, or the global resource.
+pub(crate) fn single_choice_policy(world: &World, node: Entity) -> SingleChoicePolicy {
+    world
+        .get::<SingleChoicePolicy>(node)
+        .or_else(|| world.get_resource::<SingleChoicePolicy>())
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Applies all the registered filters to the choices of the given choice node.
+pub(crate) fn apply_choice_filters(world: &World, node: Entity, choices: &mut Vec<Choice>) {
+    let Some(filters) = world.get_resource::<ChoiceFilters>() else {
+        return;
+    };
+    let Some(talk) = world.get::<Parent>(node).map(|p| p.get()) else {
+        return;
+    };
+    for filter in filters.0.iter() {
+        filter.filter(world, talk, choices);
+    }
+}
+
+/// Explains which choices of a choice node are shown and which are hidden by the registered filters,
+/// to debug why an option is missing. Returns each choice (in the authored order) with whether it is available
+/// and the reason: the first filter removing it, by registration index and [`name`](ChoiceFilter::name).
+///
+/// The choices are matched before and after each filter by their `next` entity and `order`, so the returned
+/// choices have the changes of the filters (e.g. the texts) up to the one removing them.
+/// Returns an empty vec if the node is not a choice node.
+///
+/// # Example
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// fn debug_choices(world: &World, current: Query<Entity, (With<ChoiceNode>, With<CurrentNode>)>) {
+///     for node in &current {
+///         for (choice, available, reason) in explain_choice_availability(world, node) {
+///             info!("{} available: {available} ({reason})", choice.text);
+///         }
+///     }
+/// }
+/// ```
+pub fn explain_choice_availability(world: &World, node: Entity) -> Vec<(Choice, bool, String)> {
+    let Some(ChoiceNode(choices)) = world.get::<ChoiceNode>(node) else {
+        return vec![];
+    };
+    let mut choices = choices.clone();
+    choices.sort_by_key(|c| c.order);
+    let talk = world.get::<Parent>(node).map(|p| p.get());
+    let filters = world.get_resource::<ChoiceFilters>();
+    let (Some(talk), Some(filters)) = (talk, filters) else {
+        let reason = "no choice filters applied";
+        return choices
+            .into_iter()
+            .map(|c| (c, true, reason.to_string()))
+            .collect();
+    };
+
+    let mut explained = Vec::with_capacity(choices.len());
+    for (i, filter) in filters.0.iter().enumerate() {
+        let mut filtered = choices.clone();
+        filter.filter(world, talk, &mut filtered);
+        for choice in std::mem::take(&mut choices) {
+            let kept = filtered
+                .iter()
+                .position(|c| c.next == choice.next && c.order == choice.order);
+            match kept {
+                Some(kept) => choices.push(filtered.swap_remove(kept)),
+                None => {
+                    let reason = format!("removed by choice filter {i} ({})", filter.name());
+                    explained.push((choice, false, reason));
+                }
+            }
+        }
+    }
+    let reason = format!("kept by all the {} choice filters", filters.0.len());
+    explained.extend(choices.into_iter().map(|c| (c, true, reason.clone())));
+    explained.sort_by_key(|(c, _, _)| c.order);
+    explained
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::Command;
+    use rstest::rstest;
+
+    use crate::{prelude::*, tests::talks_minimal_app};
+
+    use super::*;
+
+    #[test]
+    fn filters_remove_choices_from_event() {
+        let mut app = talks_minimal_app();
+        app.add_choice_filter(|_: &World, _: Entity, choices: &mut Vec<Choice>| {
+            choices.retain(|c| c.text != "Hidden");
+        });
+
+        let builder = TalkBuilder::default().choose(vec![
+            ("Visible", TalkBuilder::default().say("a")),
+            ("Hidden", TalkBuilder::default().say("b")),
+        ]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+
+        let evs = app.world.resource::<Events<ChoiceNodeEvent>>();
+        let choices = &evs.get_reader().read(evs).next().unwrap().choices;
+        assert_eq!(choices.len(), 1);
+        assert_eq!(choices[0].text, "Visible");
+
+        // the component is untouched
+        let choice_node = app.world.query::<&ChoiceNode>().single(&app.world);
+        assert_eq!(choice_node.0.len(), 2);
+    }
+
+    #[test]
+    fn hidden_choice_rejected() {
+        use bevy::ecs::system::RunSystemOnce;
+
+        use crate::traverse::choice_handler;
+
+        let mut app = talks_minimal_app();
+        app.add_choice_filter(|_: &World, _: Entity, choices: &mut Vec<Choice>| {
+            choices.retain(|c| c.text != "Hidden");
+        });
+        let builder = TalkBuilder::default().choose(vec![
+            ("Visible", TalkBuilder::default().say("a")),
+            ("Hidden", TalkBuilder::default().say("b")),
+        ]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let (node, choices) = app
+            .world
+            .query::<(Entity, &ChoiceNode)>()
+            .single(&app.world);
+        let (visible, hidden) = (choices.0[0].next, choices.0[1].next);
+
+        app.world.send_event(ChooseNodeRequest::new(talk, hidden));
+        let errors = app.world.run_system_once(choice_handler);
+        assert_eq!(
+            errors,
+            [NextActionError::HiddenChoice {
+                talk,
+                node,
+                chosen: hidden
+            }]
+        );
+        assert_eq!(app.world.resource::<CurrentNodes>().get(talk), Some(node));
+
+        app.world
+            .resource_mut::<Events<ChooseNodeRequest>>()
+            .clear();
+        app.world.send_event(ChooseNodeRequest::new(talk, visible));
+        assert!(app.world.run_system_once(choice_handler).is_empty());
+        assert_eq!(
+            app.world.resource::<CurrentNodes>().get(talk),
+            Some(visible)
+        );
+    }
+
+    /// A filter hiding the locked choices, with its own name.
+    struct LockedChoices;
+
+    impl ChoiceFilter for LockedChoices {
+        fn filter(&self, _: &World, _: Entity, choices: &mut Vec<Choice>) {
+            choices.retain(|c| c.text != "LOCKED");
+        }
+
+        fn name(&self) -> &str {
+            "locked choices"
+        }
+    }
+
+    #[test]
+    fn explain_filtered_choices() {
+        let mut app = talks_minimal_app();
+        app.add_choice_filter(|_: &World, _: Entity, choices: &mut Vec<Choice>| {
+            choices.retain(|c| c.text != "Hidden");
+            choices
+                .iter_mut()
+                .for_each(|c| c.text = c.text.to_uppercase());
+        });
+        app.add_choice_filter(LockedChoices);
+        let builder = TalkBuilder::default().choose(vec![
+            ("Hidden", TalkBuilder::default().say("a")),
+            ("Visible", TalkBuilder::default().say("b")),
+            ("Locked", TalkBuilder::default().say("c")),
+        ]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        let node = app
+            .world
+            .query::<(Entity, &ChoiceNode)>()
+            .single(&app.world)
+            .0;
+
+        let explained = explain_choice_availability(&app.world, node);
+        let summary = explained
+            .iter()
+            .map(|(c, available, reason)| (c.text.as_str(), *available, reason.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(summary[0].0, "Hidden");
+        assert!(!summary[0].1);
+        assert!(summary[0].2.starts_with("removed by choice filter 0"));
+        assert_eq!(
+            summary[1],
+            ("VISIBLE", true, "kept by all the 2 choice filters")
+        );
+        // with the text changed by the first filter
+        assert_eq!(
+            summary[2],
+            (
+                "LOCKED",
+                false,
+                "removed by choice filter 1 (locked choices)"
+            )
+        );
+
+        let text = app
+            .world
+            .query::<(Entity, &TextNode)>()
+            .iter(&app.world)
+            .next()
+            .unwrap()
+            .0;
+        assert!(explain_choice_availability(&app.world, text).is_empty());
+    }
+
+    /// Sets up a talk with a choice node with the "Hidden" choice filtered out, and reaches the choice node.
+    fn single_choice_left(app: &mut App, builder_policy: Option<SingleChoicePolicy>) -> Entity {
+        app.add_choice_filter(|_: &World, _: Entity, choices: &mut Vec<Choice>| {
+            choices.retain(|c| c.text != "Hidden");
+        });
+        let mut builder = TalkBuilder::default().choose(vec![
+            ("Visible", TalkBuilder::default().say("a")),
+            ("Hidden", TalkBuilder::default().say("b")),
+        ]);
+        if let Some(policy) = builder_policy {
+            builder = builder.with_component(policy);
+        }
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        app.update();
+        talk
+    }
+
+    #[rstest]
+    #[case(None, None)]
+    #[case(Some(SingleChoicePolicy::AutoSkip), None)]
+    #[case(None, Some(SingleChoicePolicy::AutoSkip))]
+    #[case(Some(SingleChoicePolicy::AutoSkip), Some(SingleChoicePolicy::Show))]
+    fn single_choice_policies(
+        #[case] global: Option<SingleChoicePolicy>,
+        #[case] node: Option<SingleChoicePolicy>,
+    ) {
+        let mut app = talks_minimal_app();
+        if let Some(policy) = global {
+            app.insert_resource(policy);
+        }
+        let skip = node.or(global) == Some(SingleChoicePolicy::AutoSkip);
+
+        single_choice_left(&mut app, node);
+
+        let choice_evs = app.world.resource::<Events<ChoiceNodeEvent>>();
+        assert_eq!(choice_evs.is_empty(), skip);
+        let text_evs = app.world.resource::<Events<TextNodeEvent>>();
+        let texts = text_evs
+            .get_reader()
+            .read(text_evs)
+            .map(|t| t.text.clone())
+            .collect::<Vec<_>>();
+        if skip {
+            assert_eq!(texts, vec!["a".to_string()]);
+        } else {
+            assert!(texts.is_empty());
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Timed choices, with their remaining time exposed for the countdown UIs.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{
+    events::ReflectEvent,
+    min_display::NodeEnteredTimes,
+    prelude::{ChoiceNode, ChooseNodeRequest, CurrentNodes},
+};
+
+/// Component to add to a choice node to give the player a limited (real) time to choose.
+///
+/// While the node is the current node, the talk has a [`ChoiceCountdown`] with the remaining time
+/// and a [`ChoiceTimerTick`] is sent in every update. When the time is up, a [`ChoiceTimedOutEvent`] is sent
+/// and the `default` choice is chosen, if there is one.
+///
+/// Without the `Time<Real>` resource (no `TimePlugin`) the choices are not timed.
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+/// use bevy_talks::prelude::*;
+///
+/// let builder = TalkBuilder::default()
+///     .choose(vec![
+///         ("Cut the red wire", TalkBuilder::default().say("Phew.")),
+///         ("Cut the blue wire", TalkBuilder::default().say("BOOM!")),
+///     ])
+///     .with_component(ChoiceTimeout::new(Duration::from_secs(5)).with_default(1));
+/// ```
+#[derive(Component, Reflect, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct ChoiceTimeout {
+    /// The time the player has to choose.
+    pub limit: Duration,
+    /// The index of the choice (in the node) chosen when the time is up. With `None` the talk waits,
+    /// handle the [`ChoiceTimedOutEvent`] to go on.
+    pub default: Option<usize>,
+}
+
+impl ChoiceTimeout {
+    /// Creates a timeout with the given limit and no default choice.
+    pub fn new(limit: Duration) -> Self {
+        Self {
+            limit,
+            default: None,
+        }
+    }
+
+    /// Sets the index of the choice chosen when the time is up.
+    pub fn with_default(mut self, choice: usize) -> Self {
+        self.default = Some(choice);
+        self
+    }
+}
+
+/// Component on the talks whose current node is a timed choice, with the time left to choose.
+/// Query it to render a countdown bar, it is removed when the talk moves on.
+#[derive(Component, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct ChoiceCountdown {
+    /// The timed choice node.
+    pub node: Entity,
+    /// The time the player has to choose.
+    pub limit: Duration,
+    /// The time left to choose.
+    pub remaining: Duration,
+}
+
+impl ChoiceCountdown {
+    /// Returns the fraction of the time left, from `1.0` (just shown) to `0.0` (time is up).
+    pub fn fraction(&self) -> f32 {
+        if self.limit.is_zero() {
+            return 0.0;
+        }
+        self.remaining.as_secs_f32() / self.limit.as_secs_f32()
+    }
+}
+
+/// Event sent in every update while the current node of a talk is a timed choice, with the time left to choose.
+///
+/// # Example
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// fn update_countdown_bar(mut ticks: EventReader<ChoiceTimerTick>) {
+///     for tick in ticks.read() {
+///         println!("{:.1}s left to choose", tick.remaining.as_secs_f32());
+///     }
+/// }
+/// ```
+#[derive(Event, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Event)]
+pub struct ChoiceTimerTick {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// The timed choice node.
+    pub node: Entity,
+    /// The time the player has to choose.
+    pub limit: Duration,
+    /// The time left to choose.
+    pub remaining: Duration,
+}
+
+/// Event sent when the time to choose of a timed choice is up, before its default choice is chosen.
+#[derive(Event, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Event)]
+pub struct ChoiceTimedOutEvent {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// The timed choice node.
+    pub node: Entity,
+}
+
+/// Updates the [`ChoiceCountdown`]s of the talks and sends the [`ChoiceTimerTick`]s.
+/// When the time is up, sends the [`ChoiceTimedOutEvent`] and chooses the default choice. It runs after the request handlers.
+pub(crate) fn tick_choice_timers(
+    mut cmd: Commands,
+    current_nodes: Res<CurrentNodes>,
+    timed: Query<(&ChoiceTimeout, &ChoiceNode)>,
+    countdowns: Query<(Entity, &ChoiceCountdown)>,
+    mut entered: ResMut<NodeEnteredTimes>,
+    time: Option<Res<Time<Real>>>,
+    mut tick_writer: EventWriter<ChoiceTimerTick>,
+    mut timed_out_writer: EventWriter<ChoiceTimedOutEvent>,
+    mut choose_writer: EventWriter<ChooseNodeRequest>,
+) {
+    // the talks that moved on from their timed choice
+    for (talk, countdown) in countdowns.iter() {
+        if current_nodes.get(talk) != Some(countdown.node) {
+            cmd.entity(talk).remove::<ChoiceCountdown>();
+        }
+    }
+
+    let Some(time) = time else {
+        return;
+    };
+    let now = time.elapsed();
+    for (talk, node) in current_nodes.0.iter() {
+        let Ok((timeout, ChoiceNode(choices))) = timed.get(*node) else {
+            continue;
+        };
+        // already timed out, waiting for the default choice (or the game) to move the talk
+        let timed_out = countdowns
+            .get(*talk)
+            .is_ok_and(|(_, c)| c.node == *node && c.remaining.is_zero());
+        if timed_out {
+            continue;
+        }
+
+        let shown = now.saturating_sub(entered.entered(*talk, *node, now));
+        let remaining = timeout.limit.saturating_sub(shown);
+        cmd.entity(*talk).insert(ChoiceCountdown {
+            node: *node,
+            limit: timeout.limit,
+            remaining,
+        });
+        tick_writer.send(ChoiceTimerTick {
+            talk: *talk,
+            node: *node,
+            limit: timeout.limit,
+            remaining,
+        });
+        if remaining.is_zero() {
+            timed_out_writer.send(ChoiceTimedOutEvent {
+                talk: *talk,
+                node: *node,
+            });
+            if let Some(choice) = timeout.default.and_then(|i| choices.get(i)) {
+                choose_writer.send(ChooseNodeRequest::new(*talk, choice.next));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use bevy::ecs::system::Command;
+
+    use crate::{prelude::*, tests::talks_minimal_app};
+
+    use super::*;
+
+    #[test]
+    fn countdown_then_default_choice() {
+        let mut app = talks_minimal_app();
+        let start = Instant::now();
+        let mut time = Time::<Real>::new(start);
+        time.update_with_instant(start);
+        app.insert_resource(time);
+        let builder = TalkBuilder::default()
+            .choose(vec![
+                ("Red", TalkBuilder::default().say("Phew.")),
+                ("Blue", TalkBuilder::default().say("BOOM!")),
+            ])
+            .with_component(ChoiceTimeout::new(Duration::from_secs(2)).with_default(1));
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let countdown = *app.world.get::<ChoiceCountdown>(talk).unwrap();
+        assert_eq!(countdown.remaining, Duration::from_secs(2));
+        assert_eq!(countdown.fraction(), 1.0);
+
+        app.world
+            .resource_mut::<Time<Real>>()
+            .update_with_instant(start + Duration::from_millis(1500));
+        app.update();
+        let evs = app.world.resource::<Events<ChoiceTimerTick>>();
+        let tick = *evs.get_reader().read(evs).last().unwrap();
+        assert_eq!(tick.talk, talk);
+        assert_eq!(tick.remaining, Duration::from_millis(500));
+        assert_eq!(tick.limit, Duration::from_secs(2));
+
+        app.world
+            .resource_mut::<Time<Real>>()
+            .update_with_instant(start + Duration::from_secs(2));
+        app.update();
+        let evs = app.world.resource::<Events<ChoiceTimedOutEvent>>();
+        assert_eq!(evs.get_reader().read(evs).count(), 1);
+
+        // the default choice is chosen in the next update
+        app.update();
+        let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        assert_eq!(app.world.get::<TextNode>(current).unwrap().0, "BOOM!");
+        assert!(app.world.get::<ChoiceCountdown>(talk).is_none());
+    }
+}
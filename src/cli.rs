@@ -0,0 +1,144 @@
+//! Validation of the talk assets outside of the game, for CI pipelines. Available with the `cli` feature.
+//!
+//! The `validate_talks` binary runs [`validate_talks`] on the given directories and exits with an error
+//! if any talk is invalid:
+//! ```sh
+//! cargo run --features cli --bin validate_talks -- assets/dialogues
+//! ```
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::ron_loader::loader::{check_ron_talk, RonLoaderError};
+
+/// An error found in a talk file.
+#[derive(Debug)]
+pub struct TalkDiagnostic {
+    /// The path of the talk file.
+    pub path: PathBuf,
+    /// The error found in the file.
+    pub error: RonLoaderError,
+}
+
+impl fmt::Display for TalkDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.error)
+    }
+}
+
+/// The result of the validation of the talk files of a directory.
+#[derive(Debug, Default)]
+pub struct TalkValidationReport {
+    /// The checked talk files, sorted.
+    pub checked: Vec<PathBuf>,
+    /// The errors found in the talk files. A file can have several errors (e.g. a pack with two invalid talks).
+    pub diagnostics: Vec<TalkDiagnostic>,
+}
+
+impl TalkValidationReport {
+    /// Returns true if no error was found.
+    pub fn is_ok(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+/// Parses and validates all the `.talk.ron` files under the directory (recursively), with the same checks as the loader:
+/// the RON syntax, the talk graph (ids, nexts, actors), the named talks of the packs and the locale sidecar files.
+///
+/// All the files are checked, the errors don't stop the validation. It fails only if a directory can't be read.
+///
+/// # Example
+/// ```rust,no_run
+/// use bevy_talks::cli::validate_talks;
+///
+/// let report = validate_talks("assets/dialogues").unwrap();
+/// for diagnostic in &report.diagnostics {
+///     eprintln!("{diagnostic}");
+/// }
+/// assert!(report.is_ok());
+/// ```
+pub fn validate_talks(dir: impl AsRef<Path>) -> io::Result<TalkValidationReport> {
+    let mut checked = vec![];
+    collect_talk_files(dir.as_ref(), &mut checked)?;
+    checked.sort();
+
+    let mut diagnostics = vec![];
+    for path in &checked {
+        let errors = match fs::read(path) {
+            Ok(bytes) => check_ron_talk(&bytes, path),
+            Err(err) => vec![err.into()],
+        };
+        diagnostics.extend(errors.into_iter().map(|error| TalkDiagnostic {
+            path: path.clone(),
+            error,
+        }));
+    }
+    Ok(TalkValidationReport {
+        checked,
+        diagnostics,
+    })
+}
+
+/// Collects the `.talk.ron` files under the directory, recursively.
+fn collect_talk_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_talk_files(&path, files)?;
+        } else if path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().ends_with(".talk.ron"))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repo_assets_are_valid() {
+        let report = validate_talks("assets/talks").unwrap();
+        assert!(report.checked.len() > 1);
+        assert!(report.is_ok(), "{:?}", report.diagnostics);
+    }
+
+    #[test]
+    fn all_errors_are_reported() {
+        let dir = std::env::temp_dir().join(format!("bevy_talks_cli_{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(
+            dir.join("ok.talk.ron"),
+            r#"(script: [(id: 1, text: Some("Hi"))])"#,
+        )
+        .unwrap();
+        fs::write(dir.join("nested/broken.talk.ron"), "(script: [").unwrap();
+        fs::write(
+            dir.join("bad_next.talk.ron"),
+            r#"(script: [(id: 1, text: Some("Hi"), next: Some(2))])"#,
+        )
+        .unwrap();
+        // not a talk file
+        fs::write(dir.join("notes.ron"), "(").unwrap();
+
+        let report = validate_talks(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(report.checked.len(), 3);
+        let failed = report
+            .diagnostics
+            .iter()
+            .map(|d| d.path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        assert_eq!(failed, ["bad_next.talk.ron", "broken.talk.ron"]);
+        assert!(matches!(
+            report.diagnostics[1].error,
+            RonLoaderError::RonError(_)
+        ));
+    }
+}
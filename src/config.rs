@@ -0,0 +1,102 @@
+//! Configuration of the plugin logs.
+
+use bevy::{log::Level, prelude::*};
+
+/// The categories of the logs emitted by the plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogCategory {
+    /// Logs about the registration of the node events.
+    Registration,
+    /// Logs from the building of the dialogue graphs.
+    Build,
+    /// Logs from the traversal of the dialogue graphs (errors from the requests included).
+    Traversal,
+}
+
+/// Resource to configure the logs emitted by the plugin, so shipping builds aren't spammed.
+///
+/// By default only warnings and errors are logged, in all the categories.
+/// Insert it before adding the [`TalksPlugin`](crate::TalksPlugin) to also affect the registration logs.
+///
+/// # Example
+/// ```rust
+/// use bevy::{log::Level, prelude::*};
+/// use bevy_talks::prelude::*;
+///
+/// let mut app = App::new();
+/// app.insert_resource(TalksConfig {
+///     log_level: Level::DEBUG,
+///     log_traversal: false,
+///     ..default()
+/// });
+/// ```
+#[derive(Resource, Debug, Clone)]
+pub struct TalksConfig {
+    /// The most verbose level logged by the plugin.
+    pub log_level: Level,
+    /// Whether to log the registration of the node events.
+    pub log_registration: bool,
+    /// Whether to log the building of the dialogue graphs.
+    pub log_build: bool,
+    /// Whether to log the traversal of the dialogue graphs.
+    pub log_traversal: bool,
+}
+
+impl Default for TalksConfig {
+    fn default() -> Self {
+        Self {
+            log_level: Level::WARN,
+            log_registration: true,
+            log_build: true,
+            log_traversal: true,
+        }
+    }
+}
+
+impl TalksConfig {
+    /// Returns whether a log of the given category and level should be emitted.
+    pub fn should_log(&self, category: LogCategory, level: Level) -> bool {
+        let enabled = match category {
+            LogCategory::Registration => self.log_registration,
+            LogCategory::Build => self.log_build,
+            LogCategory::Traversal => self.log_traversal,
+        };
+        enabled && level <= self.log_level
+    }
+}
+
+/// Logs a message if the given `Option<&TalksConfig>` allows the category and level.
+/// Without a config the default one is used.
+macro_rules! talks_log {
+    ($config:expr, $category:expr, $level:expr, $($arg:tt)+) => {
+        if $config.map_or_else(
+            || $crate::config::TalksConfig::default().should_log($category, $level),
+            |c: &$crate::config::TalksConfig| c.should_log($category, $level),
+        ) {
+            bevy::utils::tracing::event!($level, $($arg)+);
+        }
+    };
+}
+
+pub(crate) use talks_log;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_log_filters_level_and_category() {
+        let config = TalksConfig::default();
+        assert!(config.should_log(LogCategory::Build, Level::ERROR));
+        assert!(config.should_log(LogCategory::Build, Level::WARN));
+        assert!(!config.should_log(LogCategory::Build, Level::INFO));
+
+        let config = TalksConfig {
+            log_level: Level::TRACE,
+            log_build: false,
+            ..default()
+        };
+        assert!(!config.should_log(LogCategory::Build, Level::ERROR));
+        assert!(config.should_log(LogCategory::Traversal, Level::TRACE));
+    }
+}
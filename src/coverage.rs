@@ -0,0 +1,189 @@
+//! Branch coverage of the talks for QA builds: which edges of the dialogue graphs were traversed in a play session.
+//! Available with the `coverage` feature.
+
+use std::collections::BTreeMap;
+
+use aery::{prelude::*, tuple_traits::RelationEntries};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::{CurrentNodeChanged, FollowedBy, OrderIndex, TalkBuiltEvent, TalkId};
+
+/// Plugin that tracks the traversed edges of the talks in the [`TalkCoverage`] resource.
+///
+/// Only the talks with a [`TalkId`] are tracked (the talks from assets have one by default),
+/// so the coverage of the respawned talks adds up.
+///
+/// # Example
+/// ```rust,no_run
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// App::new().add_plugins((DefaultPlugins, TalksPlugin, TalkCoveragePlugin));
+/// ```
+pub struct TalkCoveragePlugin;
+
+impl Plugin for TalkCoveragePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TalkCoverage>().add_systems(
+            Update,
+            (register_talk_edges, record_traversed_edges).chain(),
+        );
+    }
+}
+
+/// An edge of a dialogue graph, from a node to one of its followers.
+/// The nodes are identified by their [`OrderIndex`], which is the same across sessions.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EdgeCoverage {
+    /// The order index of the node the edge starts from.
+    pub from: u32,
+    /// The order index of the following node.
+    pub to: u32,
+    /// How many times the edge was traversed.
+    pub traversed: u32,
+}
+
+/// Resource with the edges of the tracked talks (talk id => edges sorted by their nodes)
+/// and how many times they were traversed.
+///
+/// It is serializable, export it with [`to_json`](TalkCoverage::to_json) at the end of a play session
+/// to check which dialogue paths were never exercised.
+#[derive(Resource, Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TalkCoverage(pub BTreeMap<String, Vec<EdgeCoverage>>);
+
+impl TalkCoverage {
+    /// Returns the ratio of traversed edges of all the tracked talks, from `0.0` to `1.0` (`1.0` if there are no edges).
+    pub fn ratio(&self) -> f32 {
+        let edges = self.0.values().flatten();
+        let total = edges.clone().count();
+        if total == 0 {
+            return 1.0;
+        }
+        edges.filter(|e| e.traversed > 0).count() as f32 / total as f32
+    }
+
+    /// Returns the edges never traversed, with the id of their talk.
+    pub fn untraversed(&self) -> impl Iterator<Item = (&str, &EdgeCoverage)> {
+        self.0.iter().flat_map(|(talk, edges)| {
+            edges
+                .iter()
+                .filter(|e| e.traversed == 0)
+                .map(move |e| (talk.as_str(), e))
+        })
+    }
+
+    /// Serializes the coverage to JSON.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Returns the edge of the talk, adding it if it was not tracked yet.
+    fn edge_mut(&mut self, talk: &str, from: u32, to: u32) -> &mut EdgeCoverage {
+        let edges = self.0.entry(talk.to_string()).or_default();
+        let index = match edges.binary_search_by_key(&(from, to), |e| (e.from, e.to)) {
+            Ok(index) => index,
+            Err(index) => {
+                let edge = EdgeCoverage {
+                    from,
+                    to,
+                    traversed: 0,
+                };
+                edges.insert(index, edge);
+                index
+            }
+        };
+        &mut edges[index]
+    }
+}
+
+/// Adds the edges of the built talks to the coverage, so the never traversed ones are reported too.
+fn register_talk_edges(
+    mut built: EventReader<TalkBuiltEvent>,
+    talks: Query<(&TalkId, &Children)>,
+    nodes: Query<(&OrderIndex, Relations<FollowedBy>)>,
+    indexes: Query<&OrderIndex>,
+    mut coverage: ResMut<TalkCoverage>,
+) {
+    for event in built.read() {
+        let Ok((TalkId(id), children)) = talks.get(event.talk) else {
+            continue;
+        };
+        for (from, edges) in nodes.iter_many(children.iter()) {
+            for to in edges.targets(FollowedBy) {
+                if let Ok(to) = indexes.get(*to) {
+                    coverage.edge_mut(id, from.0, to.0);
+                }
+            }
+        }
+    }
+}
+
+/// Counts the traversed edges of the tracked talks. The jumps that are not edges (e.g. restarting on end) are ignored.
+fn record_traversed_edges(
+    mut changes: EventReader<CurrentNodeChanged>,
+    talks: Query<&TalkId>,
+    nodes: Query<(&OrderIndex, Relations<FollowedBy>)>,
+    indexes: Query<&OrderIndex>,
+    mut coverage: ResMut<TalkCoverage>,
+) {
+    for change in changes.read() {
+        let Ok(TalkId(id)) = talks.get(change.talk) else {
+            continue;
+        };
+        let Ok((from, edges)) = nodes.get(change.previous) else {
+            continue;
+        };
+        if !edges.targets(FollowedBy).contains(&change.current) {
+            continue;
+        }
+        if let Ok(to) = indexes.get(change.current) {
+            coverage.edge_mut(id, from.0, to.0).traversed += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::Command;
+
+    use crate::{prelude::*, tests::talks_minimal_app};
+
+    use super::*;
+
+    #[test]
+    fn traversed_edges_are_counted() {
+        let mut app = talks_minimal_app();
+        app.add_plugins(TalkCoveragePlugin);
+        let builder = TalkBuilder::default().talk_id("bob").say("Hi").choose(vec![
+            ("Yes", TalkBuilder::default().say("Great")),
+            ("No", TalkBuilder::default().say("Too bad")),
+        ]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        app.update();
+
+        let coverage = app.world.resource::<TalkCoverage>();
+        assert_eq!(coverage.0["bob"].len(), 4);
+        assert_eq!(coverage.ratio(), 0.0);
+
+        for _ in 0..2 {
+            app.world.send_event(NextNodeRequest::new(talk));
+            app.update();
+        }
+        let evs = app.world.resource::<Events<ChoiceNodeEvent>>();
+        let choice = evs.get_reader().read(evs).next().unwrap().choices[0].next;
+        app.world.send_event(ChooseNodeRequest::new(talk, choice));
+        app.update();
+
+        let coverage = app.world.resource::<TalkCoverage>();
+        assert_eq!(coverage.ratio(), 0.75);
+        let untraversed: Vec<_> = coverage.untraversed().collect();
+        assert_eq!(untraversed.len(), 1);
+        assert_eq!(untraversed[0].1.from, 2);
+
+        let json = coverage.to_json().unwrap();
+        let parsed: TalkCoverage = serde_json::from_str(&json).unwrap();
+        assert_eq!(&parsed, coverage);
+    }
+}
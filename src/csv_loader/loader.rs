@@ -0,0 +1,292 @@
+//! The CSV Asset Loader.
+//!
+//! A `.talk.csv` file is a spreadsheet with a header row and a row for each node of the talk.
+//! The columns are identified by the header name (case insensitive, in any order):
+//!
+//! | Column               | Required | Content                                                                  |
+//! |----------------------|----------|--------------------------------------------------------------------------|
+//! | `id`                 | yes      | The unique number of the node. The first row is the start of the talk.   |
+//! | `kind`               | no       | `Talk`, `Choice`, `Join` or `Leave`. Defaults to `Talk` (or `Choice`).   |
+//! | `actor` / `actors`   | no       | The actors performing the node, separated by `;`.                        |
+//! | `text`               | no       | The text of the node.                                                    |
+//! | `next`               | no       | The id of the next node. If empty the talk ends.                         |
+//! | `choice` / `choices` | no       | The choices, separated by `\|`, each one as `Choice text->next id`.      |
+//!
+//! The actors are created from the values found in the actors column, using them as both slug and name.
+//!
+//! ```csv
+//! id,actor,text,next,choices
+//! 1,bob,Hello!,2,
+//! 2,,,,Hi Bob->3|Bye->4
+//! 3,bob,:),,
+//! 4,bob,:(,,
+//! ```
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    utils::BoxedFuture,
+};
+use thiserror::Error;
+
+use crate::prelude::{Actor, IrChoice, IrError, IrNode, NodeKind, TalkData, TalkIr};
+
+/// Load Talks from csv assets.
+pub struct CsvLoader;
+
+/// The error type for the CSV Talks loader.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum CsvLoaderError {
+    /// An [IO Error](std::io::Error)
+    #[error("Could not read the file: {0}")]
+    Io(#[from] std::io::Error),
+    /// The file is not valid UTF-8
+    #[error("Could not read the file as UTF-8: {0}")]
+    Utf8(#[from] std::string::FromUtf8Error),
+    /// A required column is missing from the header
+    #[error("the required column {0} is missing")]
+    MissingColumn(String),
+    /// A value that should be a number is not
+    #[error("row {0}: {1} is not a valid id")]
+    InvalidId(usize, String),
+    /// The node kind is not known
+    #[error("row {0}: {1} is not a valid node kind")]
+    InvalidKind(usize, String),
+    /// A choice is not in the `text->id` format
+    #[error("row {0}: the choice {1} is not in the 'text->id' format")]
+    InvalidChoice(usize, String),
+    /// The talk is not well formed
+    #[error("Invalid talk: {0}")]
+    Invalid(#[from] IrError),
+}
+
+impl AssetLoader for CsvLoader {
+    type Asset = TalkData;
+    type Settings = ();
+    type Error = CsvLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let talk_ir = parse_talk_csv(&String::from_utf8(bytes)?)?;
+            Ok(TalkData::try_from(talk_ir)?.with_asset_id(load_context.path(), None))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["talk.csv"]
+    }
+}
+
+/// The indexes of the known columns in a row.
+struct Columns {
+    /// The `id` column.
+    id: usize,
+    /// The `kind` column.
+    kind: Option<usize>,
+    /// The `actor` column.
+    actors: Option<usize>,
+    /// The `text` column.
+    text: Option<usize>,
+    /// The `next` column.
+    next: Option<usize>,
+    /// The `choice` column.
+    choices: Option<usize>,
+}
+
+impl Columns {
+    /// Finds the columns in the header row.
+    fn from_header(header: &[String]) -> Result<Self, CsvLoaderError> {
+        let find = |names: &[&str]| {
+            header
+                .iter()
+                .position(|h| names.iter().any(|n| h.trim().eq_ignore_ascii_case(n)))
+        };
+        Ok(Columns {
+            id: find(&["id"]).ok_or_else(|| CsvLoaderError::MissingColumn("id".to_string()))?,
+            kind: find(&["kind"]),
+            actors: find(&["actor", "actors"]),
+            text: find(&["text"]),
+            next: find(&["next"]),
+            choices: find(&["choice", "choices"]),
+        })
+    }
+}
+
+/// Parses the csv talk into the IR.
+pub(crate) fn parse_talk_csv(source: &str) -> Result<TalkIr, CsvLoaderError> {
+    let mut rows = parse_csv(source).into_iter();
+    let Some(header) = rows.next() else {
+        return Err(CsvLoaderError::MissingColumn("id".to_string()));
+    };
+    let columns = Columns::from_header(&header)?;
+
+    let mut ir = TalkIr::default();
+    // the header is row 1
+    for (row_number, row) in rows.enumerate().map(|(i, r)| (i + 2, r)) {
+        let cell = |col: Option<usize>| {
+            col.and_then(|c| row.get(c))
+                .map(|v| v.trim())
+                .unwrap_or_default()
+        };
+        if row.iter().all(|v| v.trim().is_empty()) {
+            continue; // skip empty lines
+        }
+
+        let parse_id = |value: &str| {
+            value
+                .parse::<usize>()
+                .map_err(|_| CsvLoaderError::InvalidId(row_number, value.to_string()))
+        };
+
+        let mut node = IrNode {
+            id: parse_id(cell(Some(columns.id)))?,
+            text: cell(columns.text).to_string(),
+            ..Default::default()
+        };
+
+        let next = cell(columns.next);
+        if !next.is_empty() {
+            node.next = Some(parse_id(next)?);
+        }
+
+        for slug in cell(columns.actors).split(';').map(str::trim) {
+            if slug.is_empty() {
+                continue;
+            }
+            node.actors.push(slug.to_string());
+            if !ir.actors.iter().any(|a| a.slug == slug) {
+                ir.actors.push(Actor::new(slug, slug));
+            }
+        }
+
+        for choice in cell(columns.choices).split('|').map(str::trim) {
+            if choice.is_empty() {
+                continue;
+            }
+            let Some((text, next)) = choice.rsplit_once("->") else {
+                return Err(CsvLoaderError::InvalidChoice(
+                    row_number,
+                    choice.to_string(),
+                ));
+            };
+            node.choices.push(IrChoice {
+                text: text.trim().to_string(),
+                next: parse_id(next.trim())?,
+                description: None,
+                sound: None,
+                key: None,
+            });
+        }
+
+        node.kind = match cell(columns.kind).to_lowercase().as_str() {
+            "" | "talk" if !node.choices.is_empty() => NodeKind::Choice,
+            "" | "talk" => NodeKind::Talk,
+            "choice" => NodeKind::Choice,
+            "join" => NodeKind::Join,
+            "leave" => NodeKind::Leave,
+            _ => {
+                return Err(CsvLoaderError::InvalidKind(
+                    row_number,
+                    cell(columns.kind).to_string(),
+                ))
+            }
+        };
+
+        ir.nodes.push(node);
+    }
+    Ok(ir)
+}
+
+/// Splits a csv source in rows of fields.
+/// Fields can be quoted with `"` to contain commas and new lines, and `""` is an escaped quote.
+fn parse_csv(source: &str) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if field.is_empty() => in_quotes = true,
+            (',', false) => row.push(std::mem::take(&mut field)),
+            ('\r', false) => (),
+            ('\n', false) => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_talk_csv() {
+        let csv = "id,actor,text,next,choices\n\
+                   1,bob,\"Hello, you!\",2,\n\
+                   2,,,,Hi Bob->3|Bye->4\n\
+                   3,bob;alice,\"He said \"\"hi\"\"\",,\n\
+                   \n\
+                   4,,Bye,,\n";
+        let ir = parse_talk_csv(csv).unwrap();
+        assert!(ir.validate().is_ok());
+
+        assert_eq!(ir.actors.len(), 2);
+        assert_eq!(ir.nodes.len(), 4);
+        assert_eq!(ir.nodes[0].text, "Hello, you!");
+        assert_eq!(ir.nodes[0].next, Some(2));
+        assert_eq!(ir.nodes[1].kind, NodeKind::Choice);
+        assert_eq!(ir.nodes[1].choices[1].text, "Bye");
+        assert_eq!(ir.nodes[1].choices[1].next, 4);
+        assert_eq!(ir.nodes[2].text, "He said \"hi\"");
+        assert_eq!(ir.nodes[2].actors, vec!["bob", "alice"]);
+    }
+
+    #[test]
+    fn kind_column() {
+        let ir = parse_talk_csv("ID,Kind,Actors,Next\n1,join,bob,2\n2,Leave,bob,\n").unwrap();
+        assert_eq!(ir.nodes[0].kind, NodeKind::Join);
+        assert_eq!(ir.nodes[1].kind, NodeKind::Leave);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(
+            parse_talk_csv("text\nhello\n"),
+            Err(CsvLoaderError::MissingColumn(_))
+        ));
+        assert!(matches!(
+            parse_talk_csv("id,next\none,\n"),
+            Err(CsvLoaderError::InvalidId(2, _))
+        ));
+        assert!(matches!(
+            parse_talk_csv("id,kind\n1,dance\n"),
+            Err(CsvLoaderError::InvalidKind(2, _))
+        ));
+        assert!(matches!(
+            parse_talk_csv("id,choices\n1,nowhere\n"),
+            Err(CsvLoaderError::InvalidChoice(2, _))
+        ));
+    }
+}
@@ -0,0 +1,3 @@
+//! Asset loader for Talks from "talk.csv" spreadsheet files.
+
+pub(crate) mod loader;
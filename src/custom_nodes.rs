@@ -0,0 +1,119 @@
+//! Custom node kinds, to extend the asset formats with your own node types.
+//!
+//! A RON action with `action: Custom("shop")` (or a [`TalkBuilder::custom_node`](crate::prelude::TalkBuilder::custom_node))
+//! spawns a node with a [`CustomNode`] component, then the factory registered for the `"shop"` kind adds its components.
+
+use bevy::{log::Level, prelude::*, utils::HashMap};
+use std::sync::Arc;
+
+use crate::config::{talks_log, LogCategory, TalksConfig};
+
+/// Component of the custom nodes, with their kind and data.
+#[derive(Component, Reflect, Default, Debug, Clone, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct CustomNode {
+    /// The custom kind of the node, the name its factory was registered with.
+    pub kind: String,
+    /// The data of the node, the `text` of the action in the assets.
+    pub data: String,
+}
+
+/// Trait to implement to build the nodes of a custom kind: add the components of the kind to the node entity.
+///
+/// It is implemented for every `Fn(&mut EntityWorldMut, &CustomNode)` closure, so you can
+/// register closures directly with [`AppExt::register_custom_node_kind`](crate::prelude::AppExt::register_custom_node_kind).
+///
+/// # Example
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// #[derive(Component)]
+/// struct Shop(String);
+///
+/// let mut app = App::new();
+/// app.register_custom_node_kind("shop", |node: &mut EntityWorldMut, custom: &CustomNode| {
+///     node.insert(Shop(custom.data.clone()));
+/// });
+/// ```
+pub trait CustomNodeFactory: Send + Sync + 'static {
+    /// Adds the components of the custom kind to the node entity.
+    fn build(&self, node: &mut EntityWorldMut, custom: &CustomNode);
+}
+
+impl<F> CustomNodeFactory for F
+where
+    F: Fn(&mut EntityWorldMut, &CustomNode) + Send + Sync + 'static,
+{
+    fn build(&self, node: &mut EntityWorldMut, custom: &CustomNode) {
+        self(node, custom)
+    }
+}
+
+/// The registered custom node kinds (kind => factory).
+#[derive(Resource, Default)]
+pub struct CustomNodeKinds(pub(crate) HashMap<String, Arc<dyn CustomNodeFactory>>);
+
+/// Inserts the [`CustomNode`] component in the node and runs the factory of its kind.
+/// The nodes of unknown kinds only get the `CustomNode` component and a warning is logged.
+pub(crate) fn build_custom_node(world: &mut World, node: Entity, custom: CustomNode) {
+    let factory = world
+        .get_resource::<CustomNodeKinds>()
+        .and_then(|kinds| kinds.0.get(&custom.kind))
+        .cloned();
+    let Some(factory) = factory else {
+        talks_log!(
+            world.get_resource::<TalksConfig>(),
+            LogCategory::Build,
+            Level::WARN,
+            "No factory registered for the custom node kind {}, only the CustomNode component is added.",
+            custom.kind
+        );
+        world.entity_mut(node).insert(custom);
+        return;
+    };
+    let mut entity = world.entity_mut(node);
+    factory.build(&mut entity, &custom);
+    entity.insert(custom);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::Command;
+
+    use crate::{
+        prelude::*,
+        tests::{single, talks_minimal_app},
+    };
+
+    use super::*;
+
+    #[derive(Component)]
+    struct Shop(String);
+
+    #[test]
+    fn custom_node_built_by_factory() {
+        let mut app = talks_minimal_app();
+        app.register_custom_node_kind("shop", |node: &mut EntityWorldMut, custom: &CustomNode| {
+            node.insert(Shop(custom.data.clone()));
+        });
+        let talk = app.world.spawn(Talk::default()).id();
+        let builder = TalkBuilder::default().custom_node("shop", "blacksmith");
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        let (custom, shop) = single::<(&CustomNode, &Shop)>(&mut app.world);
+        assert_eq!(custom.kind, "shop");
+        assert_eq!(shop.0, "blacksmith");
+    }
+
+    #[test]
+    fn unknown_custom_kind() {
+        let mut app = talks_minimal_app();
+        let talk = app.world.spawn(Talk::default()).id();
+        let builder = TalkBuilder::default().custom_node("shop", "blacksmith");
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        let custom = single::<&CustomNode>(&mut app.world);
+        assert_eq!(custom.data, "blacksmith");
+    }
+}
@@ -0,0 +1,194 @@
+//! The display backends (bevy_ui, egui, terminal...) the node events are forwarded to.
+
+use bevy::{ecs::event::ManualEventReader, prelude::*};
+
+use crate::prelude::{
+    ChoiceNodeEvent, EndEvent, JoinNodeEvent, LeaveNodeEvent, StartEvent, TextNodeEvent,
+};
+
+/// Trait to implement to show the talks with your own display backend, without writing the event reader systems.
+///
+/// Register it with [`AppExt::add_talk_display`](crate::prelude::AppExt::add_talk_display) and the node events
+/// of all the talks are forwarded to its methods, right after the traversal, in the order:
+/// start, join, text, leave, choices and end. All the methods do nothing by default.
+///
+/// # Example
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// struct Terminal;
+///
+/// impl TalkDisplay for Terminal {
+///     fn text(&mut self, _world: &mut World, event: &TextNodeEvent) {
+///         println!("{}: {}", event.actors.join(", "), event.text);
+///     }
+///
+///     fn choices(&mut self, _world: &mut World, event: &ChoiceNodeEvent) {
+///         for (i, choice) in event.choices.iter().enumerate() {
+///             println!("{}. {}", i + 1, choice.text);
+///         }
+///     }
+/// }
+///
+/// let mut app = App::new();
+/// app.add_talk_display(Terminal);
+/// ```
+pub trait TalkDisplay: Send + Sync + 'static {
+    /// A talk left its start node.
+    fn started(&mut self, _world: &mut World, _talk: Entity) {}
+
+    /// A text node was reached.
+    fn text(&mut self, _world: &mut World, _event: &TextNodeEvent) {}
+
+    /// A choice node was reached.
+    fn choices(&mut self, _world: &mut World, _event: &ChoiceNodeEvent) {}
+
+    /// A join node was reached.
+    fn join(&mut self, _world: &mut World, _event: &JoinNodeEvent) {}
+
+    /// A leave node was reached.
+    fn leave(&mut self, _world: &mut World, _event: &LeaveNodeEvent) {}
+
+    /// A talk reached an end node.
+    fn ended(&mut self, _world: &mut World, _talk: Entity) {}
+}
+
+/// The registered display backends, with the readers of the events forwarded to them.
+#[derive(Resource, Default)]
+pub struct TalkDisplays {
+    /// The display backends, in registration order.
+    pub(crate) displays: Vec<Box<dyn TalkDisplay>>,
+    /// The reader of the start events.
+    starts: ManualEventReader<StartEvent>,
+    /// The reader of the text events.
+    texts: ManualEventReader<TextNodeEvent>,
+    /// The reader of the choice events.
+    choices: ManualEventReader<ChoiceNodeEvent>,
+    /// The reader of the join events.
+    joins: ManualEventReader<JoinNodeEvent>,
+    /// The reader of the leave events.
+    leaves: ManualEventReader<LeaveNodeEvent>,
+    /// The reader of the end events.
+    ends: ManualEventReader<EndEvent>,
+}
+
+/// Returns the events of the type not read yet by the reader.
+fn unread<E: Event, T>(
+    world: &World,
+    reader: &mut ManualEventReader<E>,
+    map: impl Fn(&E) -> T,
+) -> Vec<T> {
+    world
+        .get_resource::<Events<E>>()
+        .map(|events| reader.read(events).map(map).collect())
+        .unwrap_or_default()
+}
+
+/// Forwards the node events sent since the last run to all the registered [`TalkDisplay`]s.
+pub(crate) fn forward_to_displays(world: &mut World) {
+    if !world
+        .get_resource::<TalkDisplays>()
+        .is_some_and(|d| !d.displays.is_empty())
+    {
+        return;
+    }
+    world.resource_scope(|world, mut displays: Mut<TalkDisplays>| {
+        let displays = &mut *displays;
+        let starts = unread(world, &mut displays.starts, |e| e.0);
+        let joins = unread(world, &mut displays.joins, JoinNodeEvent::clone);
+        let texts = unread(world, &mut displays.texts, TextNodeEvent::clone);
+        let leaves = unread(world, &mut displays.leaves, LeaveNodeEvent::clone);
+        let choices = unread(world, &mut displays.choices, ChoiceNodeEvent::clone);
+        let ends = unread(world, &mut displays.ends, |e| e.0);
+
+        for display in displays.displays.iter_mut() {
+            for talk in &starts {
+                display.started(world, *talk);
+            }
+            for event in &joins {
+                display.join(world, event);
+            }
+            for event in &texts {
+                display.text(world, event);
+            }
+            for event in &leaves {
+                display.leave(world, event);
+            }
+            for event in &choices {
+                display.choices(world, event);
+            }
+            for talk in &ends {
+                display.ended(world, *talk);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::Command;
+
+    use crate::{prelude::*, tests::talks_minimal_app};
+
+    use super::*;
+
+    /// What the test display showed.
+    #[derive(Resource, Default)]
+    struct Shown(Vec<String>);
+
+    /// A display writing what it shows in the `Shown` resource.
+    struct TestDisplay;
+
+    impl TalkDisplay for TestDisplay {
+        fn started(&mut self, world: &mut World, _talk: Entity) {
+            world.resource_mut::<Shown>().0.push("start".to_string());
+        }
+
+        fn text(&mut self, world: &mut World, event: &TextNodeEvent) {
+            world.resource_mut::<Shown>().0.push(event.text.clone());
+        }
+
+        fn choices(&mut self, world: &mut World, event: &ChoiceNodeEvent) {
+            let texts = event.choices.iter().map(|c| c.text.as_str());
+            let shown = texts.collect::<Vec<_>>().join("|");
+            world.resource_mut::<Shown>().0.push(shown);
+        }
+
+        fn ended(&mut self, world: &mut World, _talk: Entity) {
+            world.resource_mut::<Shown>().0.push("end".to_string());
+        }
+    }
+
+    #[test]
+    fn node_events_forwarded_to_displays() {
+        let mut app = talks_minimal_app();
+        app.init_resource::<Shown>().add_talk_display(TestDisplay);
+        let builder = TalkBuilder::default().say("Hello").choose(vec![
+            ("Bye", TalkBuilder::default().say("Bye")),
+            ("Stay", TalkBuilder::default().say("Ok")),
+        ]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(
+            app.world.resource::<Shown>().0,
+            ["start", "Hello", "Bye|Stay"]
+        );
+
+        let choices = app.world.resource::<Events<ChoiceNodeEvent>>();
+        let bye = choices.get_reader().read(choices).last().unwrap().choices[0].next;
+        app.world.send_event(ChooseNodeRequest::new(talk, bye));
+        app.update();
+        // each event is forwarded once
+        app.update();
+        assert_eq!(
+            app.world.resource::<Shown>().0,
+            ["start", "Hello", "Bye|Stay", "Bye", "end"]
+        );
+    }
+}
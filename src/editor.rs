@@ -0,0 +1,418 @@
+//! Runtime editing of the spawned dialogue nodes, for in-game editors and debug consoles.
+//!
+//! Prefer these mutators to changing the node components directly: they validate the edit,
+//! keep the related components in sync (e.g. the `ActorLines` of a multi-actor node) and send a
+//! [`NodeEditedEvent`] so the UIs can refresh.
+
+use aery::prelude::*;
+use bevy::{
+    ecs::system::Commands,
+    log::Level,
+    prelude::{Entity, Events, Parent, World},
+};
+
+use crate::{
+    builder::build_command::{build_graph, validate_builder},
+    config::{talks_log, LogCategory, TalksConfig},
+    prelude::{
+        ActorLines, Choice, ChoiceNode, EditError, FollowedBy, NodeEditedEvent, TalkBuilder,
+        TextNode,
+    },
+};
+
+/// Sets the text of a text node.
+///
+/// Fails if the node is not a text node, or if it has the lines of multiple actors (use [`set_actor_line`]).
+pub fn set_node_text(
+    world: &mut World,
+    node: Entity,
+    text: impl Into<String>,
+) -> Result<(), EditError> {
+    if world.get::<ActorLines>(node).is_some() {
+        return Err(EditError::HasActorLines(node));
+    }
+    let mut text_node = world
+        .get_mut::<TextNode>(node)
+        .ok_or(EditError::NotATextNode(node))?;
+    text_node.0 = text.into();
+    send_edited(world, node);
+    Ok(())
+}
+
+/// Sets the line at `index` of a multi-actor text node, updating the node text too.
+pub fn set_actor_line(
+    world: &mut World,
+    node: Entity,
+    index: usize,
+    line: impl Into<String>,
+) -> Result<(), EditError> {
+    if world.get::<TextNode>(node).is_none() {
+        return Err(EditError::NotATextNode(node));
+    }
+    let Some(mut lines) = world.get_mut::<ActorLines>(node) else {
+        return Err(EditError::OutOfBounds {
+            node,
+            index,
+            len: 0,
+        });
+    };
+    let len = lines.0.len();
+    let Some(old) = lines.0.get_mut(index) else {
+        return Err(EditError::OutOfBounds { node, index, len });
+    };
+    *old = line.into();
+    let text = lines.0.join("\n");
+
+    if let Some(mut text_node) = world.get_mut::<TextNode>(node) {
+        text_node.0 = text;
+    }
+    send_edited(world, node);
+    Ok(())
+}
+
+/// Sets the text of the choice at `index` of a choice node.
+pub fn set_choice_text(
+    world: &mut World,
+    node: Entity,
+    index: usize,
+    text: impl Into<String>,
+) -> Result<(), EditError> {
+    let mut choice_node = world
+        .get_mut::<ChoiceNode>(node)
+        .ok_or(EditError::NotAChoiceNode(node))?;
+    let len = choice_node.0.len();
+    let choice = choice_node
+        .0
+        .get_mut(index)
+        .ok_or(EditError::OutOfBounds { node, index, len })?;
+    choice.text = text.into();
+    send_edited(world, node);
+    Ok(())
+}
+
+/// Where a choice added with [`add_choice`] leads to.
+pub enum ChoiceTarget {
+    /// An existing node of the same talk.
+    Node(Entity),
+    /// A new branch, built in the talk of the choice node.
+    Branch(TalkBuilder),
+}
+
+impl From<Entity> for ChoiceTarget {
+    fn from(node: Entity) -> Self {
+        Self::Node(node)
+    }
+}
+
+impl From<TalkBuilder> for ChoiceTarget {
+    fn from(builder: TalkBuilder) -> Self {
+        Self::Branch(builder)
+    }
+}
+
+/// Adds a choice to a choice node, connecting it to the target node (or to the first node of the built branch).
+/// Returns the entity the choice leads to.
+///
+/// The new branches are built like the ones of the builder, so their last nodes are end nodes unless they connect
+/// somewhere else. If the choice node is the current node, refire it to show the new choice.
+pub fn add_choice(
+    world: &mut World,
+    node: Entity,
+    text: impl Into<String>,
+    target: impl Into<ChoiceTarget>,
+) -> Result<Entity, EditError> {
+    if world.get::<ChoiceNode>(node).is_none() {
+        return Err(EditError::NotAChoiceNode(node));
+    }
+    let talk = world.get::<Parent>(node).map(|p| p.get());
+    let next = match target.into() {
+        ChoiceTarget::Node(target) => {
+            if talk.is_none() || world.get::<Parent>(target).map(|p| p.get()) != talk {
+                return Err(EditError::NotInTalk { node, target });
+            }
+            world.entity_mut(node).set::<FollowedBy>(target);
+            target
+        }
+        ChoiceTarget::Branch(mut builder) => {
+            let Some(talk) = talk else {
+                return Err(EditError::NotAChoiceNode(node));
+            };
+            if builder.queue.is_empty() {
+                return Err(EditError::EmptyBranch(node));
+            }
+            validate_builder(&mut builder, world).map_err(EditError::InvalidBranch)?;
+            build_graph(talk, node, &builder, &[], None, world)[0]
+        }
+    };
+    if let Some(mut choice_node) = world.get_mut::<ChoiceNode>(node) {
+        // after the other choices, whatever the order of the vec
+        let order = choice_node.0.iter().map(|c| c.order + 1).max().unwrap_or(0);
+        choice_node
+            .0
+            .push(Choice::new(text, next).with_order(order));
+    }
+    send_edited(world, node);
+    Ok(next)
+}
+
+/// Removes the choice at `index` of a choice node and returns it.
+///
+/// The edge to the node the choice leads to is removed too, unless another choice of the node leads there.
+/// The nodes of the branch are not despawned, they can still be reached from other nodes.
+pub fn remove_choice(world: &mut World, node: Entity, index: usize) -> Result<Choice, EditError> {
+    let mut choice_node = world
+        .get_mut::<ChoiceNode>(node)
+        .ok_or(EditError::NotAChoiceNode(node))?;
+    let len = choice_node.0.len();
+    if index >= len {
+        return Err(EditError::OutOfBounds { node, index, len });
+    }
+    let removed = choice_node.0.remove(index);
+    if !choice_node.0.iter().any(|c| c.next == removed.next) {
+        world.entity_mut(node).unset::<FollowedBy>(removed.next);
+    }
+    send_edited(world, node);
+    Ok(removed)
+}
+
+/// Sends the [`NodeEditedEvent`] for the node, if the events are registered.
+fn send_edited(world: &mut World, node: Entity) {
+    if let Some(mut edited_events) = world.get_resource_mut::<Events<NodeEditedEvent>>() {
+        edited_events.send(NodeEditedEvent { node });
+    }
+}
+
+/// Logs the error of an edit applied via [`TalkEditCommandsExt`].
+fn log_edit_error(world: &World, result: Result<(), EditError>) {
+    if let Err(err) = result {
+        talks_log!(
+            world.get_resource::<TalksConfig>(),
+            LogCategory::Build,
+            Level::WARN,
+            "Cannot edit node: {}",
+            err
+        );
+    }
+}
+
+/// Extension trait for [`Commands`] to edit the dialogue nodes.
+/// The edits that fail are logged as warnings.
+///
+/// # Example
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// fn fix_typo(mut commands: Commands, nodes: Query<(Entity, &TextNode)>) {
+///     for (node, text) in &nodes {
+///         if text.0 == "Helo" {
+///             commands.set_node_text(node, "Hello");
+///         }
+///     }
+/// }
+/// ```
+pub trait TalkEditCommandsExt {
+    /// Sets the text of a text node. See [`set_node_text`].
+    fn set_node_text(&mut self, node: Entity, text: impl Into<String>);
+    /// Sets a line of a multi-actor text node. See [`set_actor_line`].
+    fn set_actor_line(&mut self, node: Entity, index: usize, line: impl Into<String>);
+    /// Sets the text of a choice of a choice node. See [`set_choice_text`].
+    fn set_choice_text(&mut self, node: Entity, index: usize, text: impl Into<String>);
+    /// Adds a choice to a choice node, leading to a node or a new branch. See [`add_choice`].
+    fn add_choice(
+        &mut self,
+        node: Entity,
+        text: impl Into<String>,
+        target: impl Into<ChoiceTarget>,
+    );
+    /// Removes a choice of a choice node. See [`remove_choice`].
+    fn remove_choice(&mut self, node: Entity, index: usize);
+}
+
+impl TalkEditCommandsExt for Commands<'_, '_> {
+    fn set_node_text(&mut self, node: Entity, text: impl Into<String>) {
+        let text = text.into();
+        self.add(move |world: &mut World| {
+            let result = set_node_text(world, node, text);
+            log_edit_error(world, result);
+        });
+    }
+
+    fn set_actor_line(&mut self, node: Entity, index: usize, line: impl Into<String>) {
+        let line = line.into();
+        self.add(move |world: &mut World| {
+            let result = set_actor_line(world, node, index, line);
+            log_edit_error(world, result);
+        });
+    }
+
+    fn set_choice_text(&mut self, node: Entity, index: usize, text: impl Into<String>) {
+        let text = text.into();
+        self.add(move |world: &mut World| {
+            let result = set_choice_text(world, node, index, text);
+            log_edit_error(world, result);
+        });
+    }
+
+    fn add_choice(
+        &mut self,
+        node: Entity,
+        text: impl Into<String>,
+        target: impl Into<ChoiceTarget>,
+    ) {
+        let (text, target) = (text.into(), target.into());
+        self.add(move |world: &mut World| {
+            let result = add_choice(world, node, text, target).map(|_| ());
+            log_edit_error(world, result);
+        });
+    }
+
+    fn remove_choice(&mut self, node: Entity, index: usize) {
+        self.add(move |world: &mut World| {
+            let result = remove_choice(world, node, index).map(|_| ());
+            log_edit_error(world, result);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aery::tuple_traits::RelationEntries;
+    use bevy::{
+        ecs::system::{Command, RunSystemOnce},
+        prelude::{App, Component, With},
+    };
+
+    use crate::{
+        prelude::*,
+        tests::{single, talks_minimal_app},
+    };
+
+    use super::*;
+
+    /// Builds the talk and returns the app and the entity of the first node with the component.
+    fn build<C: Component>(builder: TalkBuilder) -> (App, Entity) {
+        let mut app = talks_minimal_app();
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        let node = single::<(Entity, With<C>)>(&mut app.world).0;
+        (app, node)
+    }
+
+    #[test]
+    fn node_text_is_set() {
+        let (mut app, node) = build::<TextNode>(TalkBuilder::default().say("Helo"));
+
+        assert_eq!(set_node_text(&mut app.world, node, "Hello"), Ok(()));
+        assert_eq!(app.world.get::<TextNode>(node).unwrap().0, "Hello");
+        assert_eq!(app.world.resource::<Events<NodeEditedEvent>>().len(), 1);
+    }
+
+    #[test]
+    fn node_text_of_non_text_node() {
+        let (mut app, node) = build::<StartNode>(TalkBuilder::default().say("Hello"));
+
+        let result = set_node_text(&mut app.world, node, "Hello");
+        assert_eq!(result, Err(EditError::NotATextNode(node)));
+        assert!(app.world.resource::<Events<NodeEditedEvent>>().is_empty());
+    }
+
+    #[test]
+    fn actor_lines_are_kept_in_sync() {
+        let builder = TalkBuilder::default()
+            .add_actor(Actor::new("alice", "Alice"))
+            .add_actor(Actor::new("bob", "Bob"))
+            .actors_say_lines(vec![("alice", "Happy birthday"), ("bob", "to yuo!")]);
+        let (mut app, node) = build::<ActorLines>(builder);
+
+        let result = set_node_text(&mut app.world, node, "Happy birthday");
+        assert_eq!(result, Err(EditError::HasActorLines(node)));
+
+        assert_eq!(set_actor_line(&mut app.world, node, 1, "to you!"), Ok(()));
+        assert_eq!(app.world.get::<ActorLines>(node).unwrap().0[1], "to you!");
+        assert_eq!(
+            app.world.get::<TextNode>(node).unwrap().0,
+            "Happy birthday\nto you!"
+        );
+
+        let result = set_actor_line(&mut app.world, node, 2, "!");
+        let err = EditError::OutOfBounds {
+            node,
+            index: 2,
+            len: 2,
+        };
+        assert_eq!(result, Err(err));
+    }
+
+    #[test]
+    fn choice_text_is_set() {
+        let builder = TalkBuilder::default().choose(vec![
+            ("Yes", TalkBuilder::default().say("Ok")),
+            ("Noo", TalkBuilder::default().say("Bye")),
+        ]);
+        let (mut app, node) = build::<ChoiceNode>(builder);
+
+        assert_eq!(set_choice_text(&mut app.world, node, 1, "No"), Ok(()));
+        assert_eq!(app.world.get::<ChoiceNode>(node).unwrap().0[1].text, "No");
+    }
+
+    #[test]
+    fn edits_via_commands() {
+        let (mut app, node) = build::<TextNode>(TalkBuilder::default().say("Helo"));
+
+        app.world.run_system_once(move |mut commands: Commands| {
+            commands.set_node_text(node, "Hello");
+            commands.set_choice_text(node, 0, "Nope");
+        });
+
+        assert_eq!(app.world.get::<TextNode>(node).unwrap().0, "Hello");
+        assert_eq!(app.world.resource::<Events<NodeEditedEvent>>().len(), 1);
+    }
+
+    /// Returns the followers of the node.
+    fn followers(app: &mut App, node: Entity) -> Vec<Entity> {
+        app.world
+            .query::<Relations<FollowedBy>>()
+            .get(&app.world, node)
+            .unwrap()
+            .targets(FollowedBy)
+            .to_vec()
+    }
+
+    #[test]
+    fn choices_added_and_removed() {
+        let builder = TalkBuilder::default().say("Hi").choose(vec![
+            ("Yes", TalkBuilder::default().say("Ok")),
+            ("No", TalkBuilder::default().say("Bye")),
+        ]);
+        let (mut app, node) = build::<ChoiceNode>(builder);
+        let bye = app.world.get::<ChoiceNode>(node).unwrap().0[1].next;
+
+        let topic = TalkBuilder::default().say("The dragon?").say("ROAR");
+        let dragon = add_choice(&mut app.world, node, "The dragon", topic).unwrap();
+        assert_eq!(app.world.get::<TextNode>(dragon).unwrap().0, "The dragon?");
+        assert!(followers(&mut app, node).contains(&dragon));
+
+        assert_eq!(add_choice(&mut app.world, node, "Bye?", bye), Ok(bye));
+        let choices = &app.world.get::<ChoiceNode>(node).unwrap().0;
+        assert_eq!(choices.len(), 4);
+        assert_eq!(choices[2].text, "The dragon");
+
+        // another choice still leads to bye
+        assert_eq!(remove_choice(&mut app.world, node, 1).unwrap().text, "No");
+        assert!(followers(&mut app, node).contains(&bye));
+        assert_eq!(remove_choice(&mut app.world, node, 2).unwrap().next, bye);
+        assert!(!followers(&mut app, node).contains(&bye));
+        assert_eq!(followers(&mut app, node).len(), 2);
+
+        let outsider = app.world.spawn(TextNode("Hey".to_string())).id();
+        let result = add_choice(&mut app.world, node, "Out", outsider);
+        let err = EditError::NotInTalk {
+            node,
+            target: outsider,
+        };
+        assert_eq!(result, Err(err));
+        let result = remove_choice(&mut app.world, node, 5);
+        assert!(matches!(result, Err(EditError::OutOfBounds { len: 2, .. })));
+    }
+}
@@ -0,0 +1,103 @@
+//! A debug overlay window (made with [egui](https://crates.io/crates/bevy_egui)) to inspect and drive
+//! the talks during development. Available with the `egui` feature.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+use crate::prelude::{
+    ChoiceNode, ChooseNodeRequest, CurrentNode, CurrentNodes, HasEnded, HasStarted,
+    NextNodeRequest, RefireNodeRequest, StartNode, Talk, TextNode,
+};
+
+/// Plugin that adds a debug window listing the active talks, their current node,
+/// the queued requests and buttons to advance, choose, refire and reset the talks.
+///
+/// It adds the `EguiPlugin` if it's not already in the app.
+///
+/// # Example
+/// ```rust,no_run
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// App::new()
+///     .add_plugins((DefaultPlugins, TalksPlugin, TalksDebugOverlayPlugin))
+///     .run();
+/// ```
+pub struct TalksDebugOverlayPlugin;
+
+impl Plugin for TalksDebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.add_systems(Update, talks_overlay);
+    }
+}
+
+/// Draws the talks debug window.
+fn talks_overlay(
+    mut contexts: EguiContexts,
+    mut cmd: Commands,
+    mut current_nodes: ResMut<CurrentNodes>,
+    talks: Query<(Entity, Has<HasStarted>, Option<&Name>, &Children), With<Talk>>,
+    starts: Query<(), With<StartNode>>,
+    texts: Query<&TextNode>,
+    choices: Query<&ChoiceNode>,
+    mut next_reqs: ResMut<Events<NextNodeRequest>>,
+    mut choose_reqs: ResMut<Events<ChooseNodeRequest>>,
+    mut refire_reqs: EventWriter<RefireNodeRequest>,
+) {
+    egui::Window::new("Talks").show(contexts.ctx_mut(), |ui| {
+        ui.label(format!(
+            "Queued requests: {} next, {} choose",
+            next_reqs.len(),
+            choose_reqs.len()
+        ));
+
+        for (talk_ent, has_started, name, children) in &talks {
+            ui.separator();
+            ui.heading(name.map_or_else(|| format!("{talk_ent:?}"), |n| n.to_string()));
+            ui.label(format!("Started: {has_started}"));
+
+            let current = current_nodes.get(talk_ent);
+            match current {
+                None => {
+                    ui.label("No current node");
+                }
+                Some(node) => {
+                    ui.label(format!("Current node: {node:?}"));
+                    if let Ok(text) = texts.get(node) {
+                        ui.label(format!("Text: {}", text.0));
+                    }
+                    if let Ok(choice_node) = choices.get(node) {
+                        for choice in choice_node.0.iter() {
+                            if ui.button(choice.text.as_str()).clicked() {
+                                choose_reqs.send(ChooseNodeRequest::new(talk_ent, choice.next));
+                            }
+                        }
+                    }
+                }
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Next").clicked() {
+                    next_reqs.send(NextNodeRequest::new(talk_ent));
+                }
+                if ui.button("Refire").clicked() {
+                    refire_reqs.send(RefireNodeRequest::new(talk_ent));
+                }
+                if ui.button("Reset").clicked() {
+                    // move the current node back to the start node
+                    if let Some(start) = children.iter().find(|c| starts.contains(**c)) {
+                        if let Some(current) = current {
+                            cmd.entity(current).remove::<CurrentNode>();
+                        }
+                        cmd.entity(*start).insert(CurrentNode);
+                        current_nodes.0.insert(talk_ent, *start);
+                        cmd.entity(talk_ent).remove::<(HasStarted, HasEnded)>();
+                    }
+                }
+            });
+        }
+    });
+}
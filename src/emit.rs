@@ -0,0 +1,202 @@
+//! Reflected components declared in the talk assets, to attach custom event emitters (or any other component)
+//! to the nodes without code.
+//!
+//! A RON action can list them in its `emit` field, with the type name of the component and its fields:
+//! ```ron
+//! (id: 3, text: Some("Take this."), emit: [(type: "QuestTrigger", fields: {"id": "q1", "stage": 2})]),
+//! ```
+//! The components are constructed via the type registry when the talk is built, so the types must be registered
+//! with `ReflectComponent` (use `app.register_node_event` for `NodeEventEmitter` components).
+
+use std::collections::BTreeMap;
+
+use bevy::{
+    ecs::reflect::ReflectComponent,
+    reflect::{serde::TypedReflectDeserializer, Reflect, ReflectFromReflect, TypeRegistry},
+};
+use serde::{
+    de::{DeserializeOwned, DeserializeSeed},
+    Deserialize, Serialize,
+};
+
+use crate::{
+    builder::{try_for_each_build_node_mut, TalkBuilder},
+    prelude::BuildError,
+};
+
+/// A component to add to a node, described by its type name and field values.
+///
+/// See [`TalkBuilder::emit`] and the `emit` field of the RON actions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EmitterSpec {
+    /// The type path (e.g. `my_game::quests::QuestTrigger`) or short type path (e.g. `QuestTrigger`) of the component.
+    #[serde(rename = "type")]
+    pub type_name: String,
+    /// The values of the fields of the component (field name => value). Set with [`EmitterSpec::with_field`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    fields: BTreeMap<String, serde_ron::Value>,
+}
+
+impl EmitterSpec {
+    /// Creates a spec for the component with the given type name and no fields.
+    pub fn new(type_name: impl Into<String>) -> Self {
+        Self {
+            type_name: type_name.into(),
+            fields: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the value of a field of the component.
+    ///
+    /// # Panics
+    /// If the value can't be represented in RON.
+    pub fn with_field(mut self, name: impl Into<String>, value: impl Serialize) -> Self {
+        let value = serde_ron::to_string(&value)
+            .and_then(|ron| serde_ron::from_str(&ron).map_err(|e| e.code))
+            .unwrap_or_else(|e| panic!("The field value can't be represented in RON: {e}"));
+        self.fields.insert(name.into(), value);
+        self
+    }
+
+    /// Returns the names of the fields with a value, in alphabetical order.
+    pub fn field_names(&self) -> impl Iterator<Item = &str> {
+        self.fields.keys().map(String::as_str)
+    }
+
+    /// Returns the value of the field, if it is set and it can be deserialized as `T`.
+    pub fn field<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        self.fields.get(name)?.clone().into_rust().ok()
+    }
+
+    /// Returns the fields as a RON struct, the format the reflection deserializer expects.
+    fn fields_ron(&self) -> Result<String, serde_ron::Error> {
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, value)| Ok(format!("{name}: {}", serde_ron::to_string(value)?)))
+            .collect::<Result<Vec<_>, serde_ron::Error>>()?;
+        Ok(format!("({})", fields.join(", ")))
+    }
+
+    /// Constructs the component via the type registry.
+    fn construct(&self, registry: &TypeRegistry) -> Result<Box<dyn Reflect>, String> {
+        let registration = registry
+            .get_with_type_path(&self.type_name)
+            .or_else(|| registry.get_with_short_type_path(&self.type_name))
+            .ok_or("the type is not registered")?;
+        if registration.data::<ReflectComponent>().is_none() {
+            return Err("the type is not registered as a component".to_string());
+        }
+        let from_reflect = registration
+            .data::<ReflectFromReflect>()
+            .ok_or("the type does not implement FromReflect")?;
+
+        let ron = self.fields_ron().map_err(|e| e.to_string())?;
+        let mut deserializer =
+            serde_ron::Deserializer::from_str(&ron).map_err(|e| e.to_string())?;
+        let value = TypedReflectDeserializer::new(registration, registry)
+            .deserialize(&mut deserializer)
+            .map_err(|e| e.to_string())?;
+        // the deserializer returns a dynamic value, make it the concrete component
+        from_reflect
+            .from_reflect(&*value)
+            .ok_or_else(|| "the fields do not match the type".to_string())
+    }
+}
+
+/// Constructs the components of the emitter specs of the builder nodes (branches included)
+/// and adds them to the node components, so they are validated and inserted like the other ones.
+pub(crate) fn resolve_builder_emitters(
+    builder: &mut TalkBuilder,
+    registry: &TypeRegistry,
+) -> Result<(), BuildError> {
+    try_for_each_build_node_mut(&mut builder.queue, |node| {
+        for spec in std::mem::take(&mut node.emitters) {
+            let component =
+                spec.construct(registry)
+                    .map_err(|reason| BuildError::InvalidEmitter {
+                        component: spec.type_name.clone(),
+                        reason,
+                        node: node.id,
+                        location: node.location,
+                    })?;
+            node.components.push(component);
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::system::Command, prelude::*};
+
+    use crate::{
+        prelude::*,
+        tests::{single, talks_minimal_app},
+    };
+
+    /// A custom component declared in the assets.
+    #[derive(Component, Reflect, Default, Debug, PartialEq)]
+    #[reflect(Component, Default)]
+    struct QuestTrigger {
+        /// The quest id.
+        id: String,
+        /// The quest stage.
+        stage: u32,
+    }
+
+    /// Builds the RON talk and returns the app.
+    fn build_ron(ron: &str) -> App {
+        let mut app = talks_minimal_app();
+        app.register_type::<QuestTrigger>();
+        let builder = TalkIr::from_ron(ron).unwrap().into_builder().unwrap();
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        app
+    }
+
+    #[test]
+    fn emitter_from_ron() {
+        let mut app = build_ron(
+            r#"(script: [(id: 1, text: Some("Take this."),
+                emit: [(type: "QuestTrigger", fields: {"id": "q1", "stage": 2})])])"#,
+        );
+        let (text, trigger) = single::<(&TextNode, &QuestTrigger)>(&mut app.world);
+        assert_eq!(text.0, "Take this.");
+        assert_eq!(trigger.id, "q1");
+        assert_eq!(trigger.stage, 2);
+    }
+
+    #[test]
+    fn invalid_emitter_fails_the_build() {
+        let mut app =
+            build_ron(r#"(script: [(id: 1, text: Some("Hi"), emit: [(type: "QuestTriger")])])"#);
+        let evs = app.world.resource::<Events<TalkBuildFailedEvent>>();
+        let error = &evs.get_reader().read(evs).next().unwrap().error;
+        assert!(matches!(
+            error,
+            BuildError::InvalidEmitter { component, location: NodeLocation::Action(1), .. }
+                if component == "QuestTriger"
+        ));
+        assert!(app
+            .world
+            .query::<&TextNode>()
+            .iter(&app.world)
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn emitter_ron_round_trip() {
+        let ron = r#"(script: [(id: 1, text: Some("Hi"), emit: [(type: "QuestTrigger", fields: {"stage": 2})])])"#;
+        let ir = TalkIr::from_ron(ron).unwrap();
+        assert_eq!(
+            ir.nodes[0].emit,
+            vec![EmitterSpec::new("QuestTrigger").with_field("stage", 2)]
+        );
+        assert_eq!(ir.nodes[0].emit[0].field::<u32>("stage"), Some(2));
+        assert_eq!(ir.nodes[0].emit[0].field::<String>("stage"), None);
+        assert!(ir.nodes[0].emit[0].field_names().eq(["stage"]));
+        assert_eq!(TalkIr::from_ron(&ir.to_ron().unwrap()).unwrap(), ir);
+    }
+}
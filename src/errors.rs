@@ -1,30 +1,191 @@
 //! Errors that can happen when using `bevy_talks`.
 
+use bevy::ecs::entity::Entity;
 use thiserror::Error;
 
-use crate::prelude::ActorSlug;
+use crate::{
+    prelude::{ActorSlug, BuildNodeId, NodeLocation},
+    validation::did_you_mean,
+};
 
 /// Errors when moving to the next action
 #[derive(Error, Debug, PartialEq, Eq)]
 pub enum NextActionError {
     /// NextActionRequest error.
-    #[error("No next action found.")]
-    NoNextAction,
-    /// NextActionRequest error.
-    #[error("Current node is a Choice. Cannot just advance.")]
-    ChoicesNotHandled,
+    #[error("No next action found in talk {talk:?} from node {node:?}.")]
+    NoNextAction {
+        /// The talk entity from the request.
+        talk: Entity,
+        /// The current node entity of the talk.
+        node: Entity,
+    },
+    /// NextActionRequest error. A [`ChoicesNotHandledEvent`](crate::prelude::ChoicesNotHandledEvent)
+    /// with the available choices is also sent, so the UI can show them.
+    #[error("Current node {node:?} of talk {talk:?} is a Choice with {} choices. Cannot just advance.", .choices.len())]
+    ChoicesNotHandled {
+        /// The talk entity from the request.
+        talk: Entity,
+        /// The current node entity of the talk.
+        node: Entity,
+        /// The next node entities of the current node, one per choice.
+        choices: Vec<Entity>,
+    },
     /// ChooseActionRequest error.
-    #[error("The current node has no choice with the given entity. (If the talk has not started you need to Next first)")]
-    BadChoice,
+    #[error("The current node {node:?} of talk {talk:?} has no choice with entity {chosen:?}. (If the talk has not started you need to Next first)")]
+    BadChoice {
+        /// The talk entity from the request.
+        talk: Entity,
+        /// The current node entity of the talk.
+        node: Entity,
+        /// The chosen entity from the request.
+        chosen: Entity,
+    },
+    /// ChooseActionRequest error, the choice was hidden by the [`ChoiceFilter`](crate::prelude::ChoiceFilter)s
+    /// when the [`ChoiceNodeEvent`](crate::prelude::ChoiceNodeEvent) was emitted.
+    #[error("The choice with entity {chosen:?} of node {node:?} in talk {talk:?} is hidden by the choice filters.")]
+    HiddenChoice {
+        /// The talk entity from the request.
+        talk: Entity,
+        /// The current node entity of the talk.
+        node: Entity,
+        /// The chosen entity from the request.
+        chosen: Entity,
+    },
+    /// ChooseActionRequest error, the chosen node was despawned (e.g. a branch removed at runtime).
+    #[error("The chosen node {target:?} of node {node:?} in talk {talk:?} was despawned.")]
+    TargetMissing {
+        /// The talk entity from the request.
+        talk: Entity,
+        /// The current node entity of the talk.
+        node: Entity,
+        /// The despawned node entity from the request.
+        target: Entity,
+    },
+    /// The node is performed by an actor entity that was despawned.
+    #[error(
+        "Node {node:?} of talk {talk:?} is performed by actor {actor:?}, which was not found."
+    )]
+    MissingActor {
+        /// The talk entity from the request.
+        talk: Entity,
+        /// The node entity performed by the missing actor.
+        node: Entity,
+        /// The missing actor entity.
+        actor: Entity,
+    },
     /// Requests error.
-    #[error("No talk was found with the given entity from the event.")]
-    NoTalk,
+    #[error("No talk was found with the entity {0:?} from the event.")]
+    NoTalk(Entity),
+    /// Requests error, the entity from the event is not a talk.
+    #[error(
+        "The entity {0:?} from the event is not a talk. Did you use the id of another entity?"
+    )]
+    NotATalk(Entity),
 }
 
 /// Errors from the builder
-#[derive(Error, Debug, PartialEq, Eq)]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum BuildError {
+    /// The talk parent entity does not exist
+    #[error("The talk parent entity {0:?} does not exist. Was it despawned before the build?")]
+    MissingParent(Entity),
     /// An action has a non-existent actor
-    #[error("Tried to use non-existent actor {0} in the builder. Did you forget to add it?")]
-    InvalidActor(ActorSlug),
+    #[error("Tried to use non-existent actor {slug} in the builder. Did you forget to add it?{}", did_you_mean(.suggestions))]
+    InvalidActor {
+        /// The unknown actor slug.
+        slug: ActorSlug,
+        /// The known actor slugs that are near-misses of the unknown one, nearest first.
+        suggestions: Vec<ActorSlug>,
+    },
+    /// A node has a component whose type is not registered (with `ReflectComponent`), so it cannot be inserted
+    #[error("Component {component} of {location} (node {node}) is not registered. Register it with `app.register_type`.")]
+    UnregisteredComponent {
+        /// The type path of the component.
+        component: String,
+        /// The id of the node with the component.
+        node: BuildNodeId,
+        /// Where the node comes from: the builder method call or the asset action.
+        location: NodeLocation,
+    },
+    /// A node has an emitter spec that cannot be constructed via the type registry
+    #[error("Cannot construct component {component} of {location} (node {node}): {reason}.")]
+    InvalidEmitter {
+        /// The type name of the component in the spec.
+        component: String,
+        /// Why it cannot be constructed.
+        reason: String,
+        /// The id of the node with the spec.
+        node: BuildNodeId,
+        /// Where the node comes from: the builder method call or the asset action.
+        location: NodeLocation,
+    },
+}
+
+/// Errors from the validation of a [`TalkIr`](crate::prelude::TalkIr)
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum IrError {
+    /// Multiple nodes have same id error
+    #[error("multiple nodes have same id: {0}")]
+    DuplicateNodeId(usize),
+    /// The actor slug is duplicated
+    #[error("the actor slug {0} is duplicated")]
+    DuplicateActorSlug(ActorSlug),
+    /// A node has the next field pointing to a non-existent node
+    #[error("the node {0} is pointing to id {1} which was not found")]
+    InvalidNextNode(usize, usize),
+    /// A node has a non-existent actor
+    #[error("A node is performed by actor {slug}, but it was not defined in the actors.{}", did_you_mean(.suggestions))]
+    InvalidActorSlug {
+        /// The unknown actor slug.
+        slug: ActorSlug,
+        /// The defined actor slugs that are near-misses of the unknown one, nearest first.
+        suggestions: Vec<ActorSlug>,
+    },
+    /// The merged talks define different actors with the same slug
+    #[error("the actor slug {0} is used by different actors in the merged talks, remap it first")]
+    ConflictingActor(ActorSlug),
+    /// The node was not found
+    #[error("the node {0} was not found")]
+    NodeNotFound(usize),
+    /// Choices can only be injected in choice nodes
+    #[error("the node {0} is not a choice node")]
+    NotAChoiceNode(usize),
+}
+
+/// Errors from the runtime editing of the dialogue nodes (see the [`editor`](crate::editor) module)
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum EditError {
+    /// The entity is not a text node
+    #[error("The entity {0:?} is not a text node.")]
+    NotATextNode(Entity),
+    /// The entity is not a choice node
+    #[error("The entity {0:?} is not a choice node.")]
+    NotAChoiceNode(Entity),
+    /// The text node has the lines of multiple actors, the text cannot be set as a whole
+    #[error("The node {0:?} has the lines of multiple actors. Edit them one by one instead.")]
+    HasActorLines(Entity),
+    /// The index is out of the choices or actor lines of the node
+    #[error("The node {node:?} has {len} choices or lines, index {index} is out of bounds.")]
+    OutOfBounds {
+        /// The edited node entity.
+        node: Entity,
+        /// The index from the edit.
+        index: usize,
+        /// The number of choices or lines of the node.
+        len: usize,
+    },
+    /// The target of a new choice is not a node of the same talk
+    #[error("The entity {target:?} is not a node of the talk of {node:?}.")]
+    NotInTalk {
+        /// The edited node entity.
+        node: Entity,
+        /// The target entity of the choice.
+        target: Entity,
+    },
+    /// The branch of a new choice has no nodes
+    #[error("The branch of the new choice of {0:?} has no nodes.")]
+    EmptyBranch(Entity),
+    /// The branch of a new choice cannot be built
+    #[error("The branch of the new choice is invalid: {0}")]
+    InvalidBranch(BuildError),
 }
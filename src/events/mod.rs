@@ -1,10 +1,21 @@
 //! Events to interact with the dialogue graph.
+use std::any::TypeId;
+
+use bevy::ecs::system::Command;
+use bevy::log::Level;
 use bevy::prelude::*;
 use bevy::reflect::{FromType, Reflect};
-use bevy_trait_query::RegisterExt;
 
-use crate::prelude::{Actor, ChoiceNode, JoinNode, LeaveNode, TextNode};
-use crate::TalksSet;
+use crate::config::{talks_log, LogCategory, TalksConfig};
+use crate::prelude::{
+    Actor, AnimateActor, BuildPass, BuildPasses, BuildTalkPass, ChoiceFilter, ChoiceFilters,
+    ChoiceNode, CustomNodeFactory, CustomNodeKinds, JoinNode, LeaveNode, LoadTalkPersistence,
+    TalkDisplay, TalkDisplays, TalkPersistence, TalkPersistenceStore, TextFilter, TextFilters,
+    TextNode,
+};
+use crate::{
+    emit_animate_event, emit_choice_event, emit_join_event, emit_leave_event, emit_text_event,
+};
 
 use self::{node_events::*, requests::*};
 
@@ -21,10 +32,47 @@ impl Plugin for TalksEventsPlugin {
             .add_event::<RefireNodeRequest>()
             .add_event::<StartEvent>()
             .add_event::<EndEvent>()
+            .add_event::<OnEndEvent>()
+            .add_event::<ChapterStartedEvent>()
+            .add_event::<ChapterEndedEvent>()
+            .add_event::<MissingActorEvent>()
+            .add_event::<TalkBuiltEvent>()
+            .add_event::<TalkBuildFailedEvent>()
+            .add_event::<NodeEditedEvent>()
+            .add_event::<CurrentNodeChanged>()
+            .add_event::<ChoiceSelectedEvent>()
+            .add_event::<ChoicesNotHandledEvent>()
+            .add_event::<ActorJoinedEvent>()
+            .add_event::<ActorLeftEvent>()
+            .register_type::<NextNodeRequest>()
+            .register_type::<ChooseNodeRequest>()
+            .register_type::<RefireNodeRequest>()
+            .register_type::<StartEvent>()
+            .register_type::<EndEvent>()
+            .register_type::<OnEndEvent>()
+            .register_type::<ChapterStartedEvent>()
+            .register_type::<ChapterEndedEvent>()
+            .register_type::<MissingActorEvent>()
+            .register_type::<TalkBuiltEvent>()
+            .register_type::<NodeEditedEvent>()
+            .register_type::<CurrentNodeChanged>()
+            .register_type::<ChoiceSelectedEvent>()
+            .register_type::<ChoicesNotHandledEvent>()
+            .register_type::<ActorJoinedEvent>()
+            .register_type::<ActorLeftEvent>()
             .register_node_event::<TextNode, TextNodeEvent>()
             .register_node_event::<ChoiceNode, ChoiceNodeEvent>()
             .register_node_event::<JoinNode, JoinNodeEvent>()
-            .register_node_event::<LeaveNode, LeaveNodeEvent>();
+            .register_node_event::<LeaveNode, LeaveNodeEvent>()
+            .register_node_event::<AnimateActor, AnimateActorEvent>();
+
+        // the built-in events that are changed or followed by other events when they are sent
+        let mut emitters = app.world.resource_mut::<NodeEventEmitters>();
+        emitters.set::<TextNode>(emit_text_event);
+        emitters.set::<ChoiceNode>(emit_choice_event);
+        emitters.set::<JoinNode>(emit_join_event);
+        emitters.set::<LeaveNode>(emit_leave_event);
+        emitters.set::<AnimateActor>(emit_animate_event);
     }
 }
 
@@ -32,30 +80,162 @@ impl Plugin for TalksEventsPlugin {
 pub trait AppExt {
     /// Registers a node event for a component.
     fn register_node_event<
-        C: Component + NodeEventEmitter + bevy::reflect::GetTypeRegistration,
-        T: Event + bevy::reflect::GetTypeRegistration,
+        C: Component + NodeEventEmitter<Event = T> + bevy::reflect::GetTypeRegistration,
+        T: Event + Reflect + bevy::reflect::GetTypeRegistration,
+    >(
+        &mut self,
+    ) -> &mut Self;
+
+    /// Registers a node event for a component implementing [`DynamicNodeEventEmitter`],
+    /// sent through reflection (the event types must be registered with `#[reflect(Event)]`).
+    fn register_dynamic_node_event<
+        C: Component + DynamicNodeEventEmitter + bevy::reflect::GetTypeRegistration,
     >(
         &mut self,
     ) -> &mut Self;
+
+    /// Registers a [`ChoiceFilter`] applied to the choices right before a `ChoiceNodeEvent` is emitted.
+    fn add_choice_filter(&mut self, filter: impl ChoiceFilter) -> &mut Self;
+
+    /// Registers a [`TextFilter`] applied to the node texts (and choice texts) right before their events are emitted.
+    /// The filters run in registration order.
+    fn add_text_filter(&mut self, filter: impl TextFilter) -> &mut Self;
+
+    /// Registers a [`TextFilter`] that only runs when the given locale is the current
+    /// [`TalksLocale`](crate::prelude::TalksLocale).
+    fn add_locale_text_filter(
+        &mut self,
+        locale: impl Into<String>,
+        filter: impl TextFilter,
+    ) -> &mut Self;
+
+    /// Registers the [`CustomNodeFactory`] that builds the custom nodes of the given kind
+    /// (`action: Custom("kind")` in the RON assets). Registering a kind again replaces its factory.
+    fn register_custom_node_kind(
+        &mut self,
+        kind: impl Into<String>,
+        factory: impl CustomNodeFactory,
+    ) -> &mut Self;
+
+    /// Registers a [`BuildTalkPass`] that runs right after the given built-in [`BuildPass`] when the talks are built.
+    /// The passes registered for the same built-in pass run in registration order.
+    fn add_build_pass(&mut self, after: BuildPass, pass: impl BuildTalkPass) -> &mut Self;
+
+    /// Registers a [`TalkDisplay`] backend the node events of all the talks are forwarded to.
+    /// The displays are called in registration order.
+    fn add_talk_display(&mut self, display: impl TalkDisplay) -> &mut Self;
+
+    /// Sets the [`TalkPersistence`] backend the talk variables and history are written to,
+    /// and loads them from it right away.
+    fn set_talk_persistence(&mut self, persistence: impl TalkPersistence) -> &mut Self;
 }
 
 impl AppExt for App {
     fn register_node_event<
-        C: Component + NodeEventEmitter + bevy::reflect::GetTypeRegistration,
-        E: Event + bevy::reflect::GetTypeRegistration,
+        C: Component + NodeEventEmitter<Event = E> + bevy::reflect::GetTypeRegistration,
+        E: Event + Reflect + bevy::reflect::GetTypeRegistration,
     >(
         &mut self,
     ) -> &mut Self {
         if !self.world.contains_resource::<Events<E>>() {
             self.add_event::<E>();
         }
-        self.add_event::<EmissionTrigger<E>>();
-        self.add_systems(PreUpdate, relay_node_event::<E>.after(TalksSet));
+        self.world
+            .get_resource_or_insert_with(NodeEventEmitters::default)
+            .add::<C>(emit_node_event::<C>);
         self.register_type::<C>();
         self.register_type::<E>();
-        self.register_component_as::<dyn NodeEventEmitter, C>();
-        info!("Registered node emitter: {}", std::any::type_name::<C>());
+        talks_log!(
+            self.world.get_resource::<TalksConfig>(),
+            LogCategory::Registration,
+            Level::INFO,
+            "Registered node emitter: {}",
+            std::any::type_name::<C>()
+        );
+
+        self
+    }
+
+    fn register_dynamic_node_event<
+        C: Component + DynamicNodeEventEmitter + bevy::reflect::GetTypeRegistration,
+    >(
+        &mut self,
+    ) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(NodeEventEmitters::default)
+            .add::<C>(emit_dynamic_node_event::<C>);
+        self.register_type::<C>();
+        talks_log!(
+            self.world.get_resource::<TalksConfig>(),
+            LogCategory::Registration,
+            Level::INFO,
+            "Registered dynamic node emitter: {}",
+            std::any::type_name::<C>()
+        );
+
+        self
+    }
+
+    fn add_choice_filter(&mut self, filter: impl ChoiceFilter) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(ChoiceFilters::default)
+            .0
+            .push(Box::new(filter));
+        self
+    }
+
+    fn add_text_filter(&mut self, filter: impl TextFilter) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(TextFilters::default)
+            .0
+            .push((None, Box::new(filter)));
+        self
+    }
+
+    fn add_locale_text_filter(
+        &mut self,
+        locale: impl Into<String>,
+        filter: impl TextFilter,
+    ) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(TextFilters::default)
+            .0
+            .push((Some(locale.into()), Box::new(filter)));
+        self
+    }
+
+    fn register_custom_node_kind(
+        &mut self,
+        kind: impl Into<String>,
+        factory: impl CustomNodeFactory,
+    ) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(CustomNodeKinds::default)
+            .0
+            .insert(kind.into(), std::sync::Arc::new(factory));
+        self
+    }
+
+    fn add_build_pass(&mut self, after: BuildPass, pass: impl BuildTalkPass) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(BuildPasses::default)
+            .0
+            .push((after, std::sync::Arc::new(pass)));
+        self
+    }
+
+    fn add_talk_display(&mut self, display: impl TalkDisplay) -> &mut Self {
+        self.world
+            .get_resource_or_insert_with(TalkDisplays::default)
+            .displays
+            .push(Box::new(display));
+        self
+    }
 
+    fn set_talk_persistence(&mut self, persistence: impl TalkPersistence) -> &mut Self {
+        self.world
+            .insert_resource(TalkPersistenceStore(Box::new(persistence)));
+        LoadTalkPersistence.apply(&mut self.world);
         self
     }
 }
@@ -118,28 +298,131 @@ impl<E: Event + Reflect + Clone> FromType<E> for ReflectEvent {
 }
 
 /// Trait to implement on dialogue node components to make them emit an event when reached.
-#[bevy_trait_query::queryable]
 pub trait NodeEventEmitter {
-    /// Creates an event to be emitted when a node is reached.
+    /// The event emitted by the component.
+    type Event: Event;
+
+    /// Creates the event to be emitted when a node is reached.
+    fn make(&self, actors: &[Actor]) -> Self::Event;
+}
+
+/// Trait to implement on dialogue node components that emit an event whose type is only known at runtime
+/// (e.g. picked from data), the reflection fallback of [`NodeEventEmitter`].
+///
+/// The events are sent with the [`ReflectEvent`] of their type, so it must be registered with `#[reflect(Event)]`.
+/// Register the component with [`AppExt::register_dynamic_node_event`].
+pub trait DynamicNodeEventEmitter {
+    /// Creates the event to be emitted when a node is reached.
     fn make(&self, actors: &[Actor]) -> Box<dyn Reflect>;
 }
 
-/// Internal event used to trigger the emission of a node event.
-#[derive(Event)]
-pub(crate) struct EmissionTrigger<T: Event> {
-    /// The event to be emitted.
-    pub(crate) event: T,
+/// Makes the event of an emitter component of the node, if the node has one, and sends it.
+pub(crate) type NodeEventEmit = fn(&mut World, Entity, &[Actor]);
+
+/// The emitters registered with [`AppExt::register_node_event`] and [`AppExt::register_dynamic_node_event`]
+/// (component type => emit function), in registration order.
+///
+/// The events are made and sent typed, the dynamic ones through reflection. The built-in events that need more
+/// than a `send_event` (e.g. the choice filters) have their own emit function.
+#[derive(Resource, Default)]
+pub(crate) struct NodeEventEmitters(Vec<(TypeId, NodeEventEmit)>);
+
+impl NodeEventEmitters {
+    /// Adds the emit function of the emitter component `C`, unless it has one already
+    /// (so registering a built-in emitter again keeps its own emit function).
+    pub(crate) fn add<C: Component>(&mut self, emit: NodeEventEmit) {
+        let type_id = TypeId::of::<C>();
+        if self.0.iter().all(|(t, _)| *t != type_id) {
+            self.0.push((type_id, emit));
+        }
+    }
+
+    /// Sets the emit function of the emitter component `C`, replacing the previous one.
+    pub(crate) fn set<C: Component>(&mut self, emit: NodeEventEmit) {
+        let type_id = TypeId::of::<C>();
+        match self.0.iter_mut().find(|(t, _)| *t == type_id) {
+            Some(entry) => entry.1 = emit,
+            None => self.0.push((type_id, emit)),
+        }
+    }
+
+    /// Calls the emit functions of all the emitters on the node.
+    pub(crate) fn emit(&self, world: &mut World, node: Entity, actors: &[Actor]) {
+        for (_, emit) in self.0.iter() {
+            emit(world, node, actors);
+        }
+    }
+}
+
+/// Makes the event of the emitter component `C` of the node, if it has one.
+pub(crate) fn make_node_event<C: Component + NodeEventEmitter>(
+    world: &World,
+    node: Entity,
+    actors: &[Actor],
+) -> Option<C::Event> {
+    world.get::<C>(node).map(|emitter| emitter.make(actors))
+}
+
+/// Sends the event of a node (recording it first with the `test` feature).
+pub(crate) fn send_node_event<E: Event + Reflect>(world: &mut World, node: Entity, event: E) {
+    #[cfg(feature = "test")]
+    crate::recorder::record_node_event(world, node, &event);
+    #[cfg(not(feature = "test"))]
+    let _ = node;
+    world.send_event(event);
 }
 
-/// System that relays node events to their respective event channels.
-fn relay_node_event<T: Event>(mut t: ResMut<Events<EmissionTrigger<T>>>, mut w: EventWriter<T>) {
-    t.drain().for_each(|EmissionTrigger { event }| {
-        w.send(event);
-    });
+/// The default emit function: makes and sends the event of the emitter component `C` of the node, if it has one.
+fn emit_node_event<C: Component + NodeEventEmitter>(
+    world: &mut World,
+    node: Entity,
+    actors: &[Actor],
+) where
+    C::Event: Reflect,
+{
+    if let Some(event) = make_node_event::<C>(world, node, actors) {
+        send_node_event(world, node, event);
+    }
+}
+
+/// The reflection fallback emit function: makes the event of the dynamic emitter component `C` of the node,
+/// if it has one, and sends it with the [`ReflectEvent`] registered for its type.
+fn emit_dynamic_node_event<C: Component + DynamicNodeEventEmitter>(
+    world: &mut World,
+    node: Entity,
+    actors: &[Actor],
+) {
+    let Some(event) = world.get::<C>(node).map(|emitter| emitter.make(actors)) else {
+        return;
+    };
+    let reflect_event = world
+        .resource::<AppTypeRegistry>()
+        .read()
+        .get_type_data::<ReflectEvent>(event.as_any().type_id())
+        .cloned();
+    let Some(reflect_event) = reflect_event else {
+        talks_log!(
+            world.get_resource::<TalksConfig>(),
+            LogCategory::Traversal,
+            Level::ERROR,
+            "Node event {} of {} not sent: it has no ReflectEvent, add #[reflect(Event)] and register it.",
+            event.reflect_type_path(),
+            std::any::type_name::<C>()
+        );
+        return;
+    };
+    #[cfg(feature = "test")]
+    crate::recorder::record_node_event(world, node, &*event);
+    #[cfg(not(feature = "test"))]
+    let _ = node;
+    reflect_event.send(&*event, world);
 }
 
 #[cfg(test)]
 mod tests {
+    use bevy::prelude::*;
+
+    use super::ReflectEvent;
     use crate::tests::talks_minimal_app;
 
     #[test]
@@ -151,5 +434,82 @@ mod tests {
         assert!(app.world.contains_resource::<Events<ChoiceNodeEvent>>());
         assert!(app.world.contains_resource::<Events<JoinNodeEvent>>());
         assert!(app.world.contains_resource::<Events<LeaveNodeEvent>>());
+        assert!(app.world.contains_resource::<Events<AnimateActorEvent>>());
+    }
+
+    #[test]
+    fn registering_again_keeps_the_emitter() {
+        use super::*;
+        use crate::prelude::{NextNodeRequest, Talk, TalkCommandsExt};
+        use bevy::ecs::system::CommandQueue;
+
+        let mut app = talks_minimal_app();
+        app.register_node_event::<TextNode, TextNodeEvent>();
+        let mut queue = CommandQueue::default();
+        let talk = Commands::new(&mut queue, &app.world)
+            .spawn_talk(Talk::builder().say("Hello"))
+            .id();
+        queue.apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+
+        let mut events = app.world.resource_mut::<Events<TextNodeEvent>>();
+        assert_eq!(events.drain().count(), 1);
+    }
+
+    /// A node component emitting one of two events, picked at runtime.
+    #[derive(Component, Reflect, Default)]
+    #[reflect(Component)]
+    struct Weather {
+        /// Whether it rains.
+        rain: bool,
+    }
+
+    /// The event of a sunny `Weather` node.
+    #[derive(Event, Reflect, Clone)]
+    #[reflect(Event)]
+    struct SunEvent;
+
+    /// The event of a rainy `Weather` node.
+    #[derive(Event, Reflect, Clone)]
+    #[reflect(Event)]
+    struct RainEvent;
+
+    impl super::DynamicNodeEventEmitter for Weather {
+        fn make(&self, _: &[super::Actor]) -> Box<dyn Reflect> {
+            if self.rain {
+                Box::new(RainEvent)
+            } else {
+                Box::new(SunEvent)
+            }
+        }
+    }
+
+    #[test]
+    fn dynamic_node_events_sent_through_reflection() {
+        use super::*;
+        use crate::prelude::{NextNodeRequest, Talk, TalkCommandsExt};
+        use bevy::ecs::system::CommandQueue;
+
+        let mut app = talks_minimal_app();
+        app.add_event::<SunEvent>()
+            .add_event::<RainEvent>()
+            .register_type::<SunEvent>()
+            .register_type::<RainEvent>()
+            .register_dynamic_node_event::<Weather>();
+        let mut queue = CommandQueue::default();
+        let talk = Commands::new(&mut queue, &app.world)
+            .spawn_talk(
+                Talk::builder()
+                    .say("Hello")
+                    .with_component(Weather { rain: true }),
+            )
+            .id();
+        queue.apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+
+        assert_eq!(app.world.resource::<Events<RainEvent>>().len(), 1);
+        assert!(app.world.resource::<Events<SunEvent>>().is_empty());
     }
 }
@@ -1,37 +1,223 @@
 //! Events the plugin emits.
 use bevy::prelude::*;
 
-use crate::prelude::{Actor, Choice, ChoiceNode, JoinNode, LeaveNode, TextNode};
+use crate::prelude::{
+    Actor, ActorSlug, ActorVoice, AnimateActor, Choice, ChoiceNode, JoinNode, LeaveNode, TextNode,
+};
 
 use super::{NodeEventEmitter, ReflectEvent};
+use crate::markup::{parse_markup, TextTiming};
 
 /// Start event sent when a talk is started.
 /// It happens when a next action request is sent to the talk that is still in the start node.
 /// Contains the talk parent entity.
-#[derive(Event)]
+#[derive(Event, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Event)]
 pub struct StartEvent(pub Entity);
 
 /// End event sent when a talk reaches an end node.
 /// Contains the talk parent entity.
-#[derive(Event)]
+#[derive(Event, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Event)]
 pub struct EndEvent(pub Entity);
 
+/// Event sent when a talk with the [`OnEnd::SendEvent`](crate::prelude::OnEnd::SendEvent) behavior reaches an end node.
+#[derive(Event, Reflect, Debug, Clone, PartialEq, Eq)]
+#[reflect(Event)]
+pub struct OnEndEvent {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// The tag from the `OnEnd` component.
+    pub tag: String,
+}
+
+/// Chapter started event sent when the traversal moves into a node of a new chapter.
+#[derive(Event, Reflect, Debug, Clone)]
+#[reflect(Event)]
+pub struct ChapterStartedEvent {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// The name of the chapter that started.
+    pub chapter: String,
+}
+
+/// Chapter ended event sent when the traversal moves out of the nodes of a chapter.
+#[derive(Event, Reflect, Debug, Clone)]
+#[reflect(Event)]
+pub struct ChapterEndedEvent {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// The name of the chapter that ended.
+    pub chapter: String,
+}
+
+/// Talk built event sent when a `BuildTalkCommand` has spawned and connected all the nodes of a talk,
+/// so the dialogue graph can be queried.
+#[derive(Event, Reflect, Debug, Clone, PartialEq, Eq)]
+#[reflect(Event)]
+pub struct TalkBuiltEvent {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// The number of node entities spawned (start node included).
+    pub nodes: usize,
+}
+
+/// Talk build failed event sent when a `BuildTalkCommand` skips the build of a talk
+/// (e.g. the parent entity was despawned or the builder uses an unknown actor). Nothing is spawned.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct TalkBuildFailedEvent {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// Why the build was skipped.
+    pub error: BuildError,
+}
+
+/// Event sent every time the current node of a talk changes, whatever the request (next, choose, jumps)
+/// or the end behavior (e.g. [`OnEnd::Loop`](crate::prelude::OnEnd::Loop)) that moved it.
+///
+/// It is the single subscription point for the systems that don't care about the node kinds
+/// (autosaves, cameras, analytics). The node events of the new current node are sent as usual.
+#[derive(Event, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Event)]
+pub struct CurrentNodeChanged {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// The node the talk moved from.
+    pub previous: Entity,
+    /// The node the talk moved to, the new current node.
+    pub current: Entity,
+}
+
+/// Event sent when a choice of a choice node is selected with a
+/// [`ChooseNodeRequest`](crate::prelude::ChooseNodeRequest), with the selected choice and its sound cue
+/// (so the audio systems can play it without looking up the choices).
+#[derive(Event, Reflect, Debug, Clone)]
+#[reflect(Event)]
+pub struct ChoiceSelectedEvent {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// The choice node entity.
+    pub node: Entity,
+    /// The index of the selected choice in the choice node.
+    pub index: usize,
+    /// The selected choice.
+    pub choice: Choice,
+}
+
+/// Event sent when a `NextNodeRequest` can't advance because the current node has choices,
+/// with the available choices (sorted and filtered as in the [`ChoiceNodeEvent`]) so the UI can show them right away.
+///
+/// The choices are empty if the node has more than one next node but is not a choice node.
+#[derive(Event, Reflect, Debug, Clone)]
+#[reflect(Event)]
+pub struct ChoicesNotHandledEvent {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// The current node entity of the talk.
+    pub node: Entity,
+    /// The available choices of the node.
+    pub choices: Vec<Choice>,
+}
+
+/// Node edited event sent when the text of a node is changed with the [`editor`](crate::editor) API,
+/// so the UIs showing it can refresh.
+#[derive(Event, Reflect, Debug, Clone, PartialEq, Eq)]
+#[reflect(Event)]
+pub struct NodeEditedEvent {
+    /// The edited node entity.
+    pub node: Entity,
+}
+
+/// Actor joined event sent for each actor entity performing a join node when it is reached (after its [`JoinNodeEvent`]),
+/// so the stage management systems (e.g. spawning the character sprite, playing the walk-in animation)
+/// can bind directly to the actor entities.
+#[derive(Event, Reflect, Debug, Clone, PartialEq, Eq)]
+#[reflect(Event)]
+pub struct ActorJoinedEvent {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// The actor entity.
+    pub actor: Entity,
+    /// The slug of the actor.
+    pub slug: String,
+}
+
+/// Actor left event sent for each actor entity performing a leave node when it is reached (after its [`LeaveNodeEvent`]).
+#[derive(Event, Reflect, Debug, Clone, PartialEq, Eq)]
+#[reflect(Event)]
+pub struct ActorLeftEvent {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// The actor entity.
+    pub actor: Entity,
+    /// The slug of the actor.
+    pub slug: String,
+}
+
+/// Missing actor event sent when a node is performed by an actor entity that was despawned.
+/// What happens to the traversal depends on the [`MissingActorPolicy`](crate::prelude::MissingActorPolicy).
+#[derive(Event, Reflect, Debug, Clone, PartialEq, Eq)]
+#[reflect(Event)]
+pub struct MissingActorEvent {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// The node entity performed by the missing actor.
+    pub node: Entity,
+    /// The missing actor entity.
+    pub actor: Entity,
+}
+
 /// Emitted when a text node is reached.
 #[derive(Event, Reflect, Default, Clone)]
 #[reflect(Event)]
 pub struct TextNodeEvent {
-    /// The text from the node.
+    /// The text from the node, without the markup control codes (see the [`markup`](crate::markup) module).
     pub text: String,
+    /// The timing track (pauses and speed changes) parsed out of the node text, to drive the text reveal.
+    pub timing: TextTiming,
     /// The actor names from the node.
     pub actors: Vec<String>,
+    /// The voice of the first actor in the node that has one, to play the blips while revealing the text.
+    pub voice: Option<ActorVoice>,
+    /// The emphasis intensity of the node, if it has an `Emphasis` component.
+    pub emphasis: Option<f32>,
+    /// The icon (emoji or emote id) of the node, if it has an `Icon` component.
+    pub icon: Option<String>,
+    /// The thought shown alongside the text, if the node has a `Thought` component.
+    pub thought: Option<String>,
+    /// Whether the node is performed by the "player" pseudo-actor (e.g. to align the line to the right).
+    pub is_player: bool,
+    /// The line of each actor in `actors` if the node text is split per actor, empty otherwise.
+    pub lines: Vec<String>,
+    /// The index of the page shown, if the node has [`TextPages`](crate::prelude::TextPages). `0` otherwise.
+    pub page: usize,
+    /// The number of pages of the node, `1` if it has no [`TextPages`](crate::prelude::TextPages).
+    pub pages: usize,
+    /// The portrait of the first actor in the node that has one. Available with the `portraits` feature.
+    #[cfg(feature = "portraits")]
+    pub portrait: Option<Handle<Image>>,
 }
 
 impl NodeEventEmitter for TextNode {
-    fn make(&self, actors: &[Actor]) -> Box<dyn Reflect> {
-        Box::from(TextNodeEvent {
-            text: self.0.clone(),
+    type Event = TextNodeEvent;
+
+    fn make(&self, actors: &[Actor]) -> Self::Event {
+        let (text, timing) = parse_markup(&self.0);
+        TextNodeEvent {
+            text,
+            timing,
             actors: actors.iter().map(|a| a.name.clone()).collect(),
-        })
+            voice: actors.iter().find_map(|a| a.voice.clone()),
+            emphasis: None,
+            icon: None,
+            thought: None,
+            is_player: false,
+            lines: vec![],
+            page: 0,
+            pages: 1,
+            #[cfg(feature = "portraits")]
+            portrait: actors.iter().find_map(|a| a.portrait.clone()),
+        }
     }
 }
 
@@ -41,13 +227,24 @@ impl NodeEventEmitter for TextNode {
 pub struct ChoiceNodeEvent {
     /// The choices from the node.
     pub choices: Vec<Choice>,
+    /// The icon (emoji or emote id) of the node, if it has an `Icon` component.
+    pub icon: Option<String>,
+    /// The actor names from the node (e.g. the actor asking the question), like in `TextNodeEvent`.
+    pub actors: Vec<String>,
 }
 
 impl NodeEventEmitter for ChoiceNode {
-    fn make(&self, _actors: &[Actor]) -> Box<dyn Reflect> {
-        Box::from(ChoiceNodeEvent {
-            choices: self.0.clone(),
-        })
+    type Event = ChoiceNodeEvent;
+
+    fn make(&self, actors: &[Actor]) -> Self::Event {
+        // the authored order, the sort is stable so the choices with the same order keep the vec order
+        let mut choices = self.0.clone();
+        choices.sort_by_key(|c| c.order);
+        ChoiceNodeEvent {
+            choices,
+            actors: actors.iter().map(|a| a.name.clone()).collect(),
+            icon: None,
+        }
     }
 }
 
@@ -60,10 +257,12 @@ pub struct JoinNodeEvent {
 }
 
 impl NodeEventEmitter for JoinNode {
-    fn make(&self, actors: &[Actor]) -> Box<dyn Reflect> {
-        Box::from(JoinNodeEvent {
+    type Event = JoinNodeEvent;
+
+    fn make(&self, actors: &[Actor]) -> Self::Event {
+        JoinNodeEvent {
             actors: actors.iter().map(|a| a.name.clone()).collect(),
-        })
+        }
     }
 }
 
@@ -76,9 +275,35 @@ pub struct LeaveNodeEvent {
 }
 
 impl NodeEventEmitter for LeaveNode {
-    fn make(&self, actors: &[Actor]) -> Box<dyn Reflect> {
-        Box::from(LeaveNodeEvent {
+    type Event = LeaveNodeEvent;
+
+    fn make(&self, actors: &[Actor]) -> Self::Event {
+        LeaveNodeEvent {
             actors: actors.iter().map(|a| a.name.clone()).collect(),
-        })
+        }
+    }
+}
+
+/// Emitted when a node with an [`AnimateActor`] component is reached.
+#[derive(Event, Reflect, Default, Clone)]
+#[reflect(Event)]
+pub struct AnimateActorEvent {
+    /// The slug of the actor to animate.
+    pub slug: ActorSlug,
+    /// The name of the animation clip to play.
+    pub clip: String,
+    /// The actor entity with the slug performing the node (or another node of the talk), if it was found.
+    pub actor: Option<Entity>,
+}
+
+impl NodeEventEmitter for AnimateActor {
+    type Event = AnimateActorEvent;
+
+    fn make(&self, _actors: &[Actor]) -> Self::Event {
+        AnimateActorEvent {
+            slug: self.slug.clone(),
+            clip: self.clip.clone(),
+            actor: None,
+        }
     }
 }
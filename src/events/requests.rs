@@ -2,8 +2,11 @@
 
 use bevy::prelude::*;
 
+use super::ReflectEvent;
+
 /// Event to request the current node to re-send all its events.
-#[derive(Event)]
+#[derive(Event, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Event)]
 pub struct RefireNodeRequest {
     /// The entity with the `Talk` component you want to update.
     pub talk: Entity,
@@ -19,7 +22,8 @@ impl RefireNodeRequest {
 ///
 /// This event is typically used wired to an input from the player, e.g. a mouse click to advance the current dialogue.
 /// It can fail (and logs an error) in case there is no next action or in case the current action is a choice action.
-#[derive(Event)]
+#[derive(Event, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Event)]
 pub struct NextNodeRequest {
     /// The entity with the `Talk` component you want to update.
     pub talk: Entity,
@@ -36,7 +40,8 @@ impl NextNodeRequest {
 ///
 /// It is typically used when you want to go to a target node from a choice node.
 /// The `ActionId` to jump to is the one defined in the next field for the Choice choosen by the player.
-#[derive(Event)]
+#[derive(Event, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Event)]
 pub struct ChooseNodeRequest {
     /// The entity with the `Talk` component you want to update.
     pub talk: Entity,
@@ -0,0 +1,175 @@
+//! An optional [petgraph](https://crates.io/crates/petgraph) mirror of the spawned dialogue graphs,
+//! for algorithmic queries (reachability, shortest paths, cycles) that are bad fits for the entity relations.
+//! Available with the `petgraph` feature.
+
+use aery::{prelude::*, tuple_traits::RelationEntries};
+use bevy::{ecs::system::Command, log::Level, prelude::*, utils::HashMap};
+use petgraph::{
+    algo::{astar, has_path_connecting, is_cyclic_directed},
+    graph::{DiGraph, NodeIndex},
+};
+
+use crate::{
+    config::{talks_log, LogCategory, TalksConfig},
+    prelude::{FollowedBy, StartNode},
+};
+
+/// Component with a petgraph `DiGraph` mirror of the dialogue graph of a talk.
+///
+/// The mirror is built on demand, it is not kept in sync with the entities: add the
+/// [`RefreshTalkGraphCache`] command to (re)build it after the talk graph is spawned or changed.
+///
+/// # Example
+/// ```rust,no_run
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// fn refresh(mut commands: Commands, talks: Query<Entity, Added<Talk>>) {
+///     for talk in &talks {
+///         commands.add(RefreshTalkGraphCache { talk });
+///     }
+/// }
+/// ```
+#[derive(Component, Debug, Default, Clone)]
+pub struct TalkGraphCache {
+    /// The mirror graph. The node weights are the node entities.
+    graph: DiGraph<Entity, ()>,
+    /// The node entity => graph node index mapping.
+    indices: HashMap<Entity, NodeIndex>,
+}
+
+impl TalkGraphCache {
+    /// Builds the mirror of the dialogue graph of the given talk, visiting it from its start node.
+    ///
+    /// Returns `None` if the entity has no start node child.
+    pub fn from_world(world: &mut World, talk: Entity) -> Option<Self> {
+        let start = world
+            .get::<Children>(talk)?
+            .iter()
+            .copied()
+            .find(|e| world.get::<StartNode>(*e).is_some())?;
+
+        let mut followers_query = world.query::<Relations<FollowedBy>>();
+        let mut cache = Self::default();
+        let mut to_visit = vec![start];
+        cache.add_node(start);
+        while let Some(node) = to_visit.pop() {
+            let followers = followers_query
+                .get(world, node)
+                .map(|edges| edges.targets(FollowedBy).to_vec())
+                .unwrap_or_default();
+            for follower in followers {
+                if !cache.indices.contains_key(&follower) {
+                    to_visit.push(follower);
+                }
+                let (from, to) = (cache.add_node(node), cache.add_node(follower));
+                cache.graph.add_edge(from, to, ());
+            }
+        }
+        Some(cache)
+    }
+
+    /// Adds the node entity to the graph (if not already there) and returns its index.
+    fn add_node(&mut self, node: Entity) -> NodeIndex {
+        if let Some(index) = self.indices.get(&node) {
+            return *index;
+        }
+        let index = self.graph.add_node(node);
+        self.indices.insert(node, index);
+        index
+    }
+
+    /// The mirror graph. The node weights are the node entities.
+    pub fn graph(&self) -> &DiGraph<Entity, ()> {
+        &self.graph
+    }
+
+    /// Returns the graph index of the node entity, if it is part of the graph.
+    pub fn node_index(&self, node: Entity) -> Option<NodeIndex> {
+        self.indices.get(&node).copied()
+    }
+
+    /// Returns the node entity of the graph index, if any.
+    pub fn entity(&self, index: NodeIndex) -> Option<Entity> {
+        self.graph.node_weight(index).copied()
+    }
+
+    /// Returns whether the node `to` can be reached from the node `from`.
+    pub fn is_reachable(&self, from: Entity, to: Entity) -> bool {
+        match (self.node_index(from), self.node_index(to)) {
+            (Some(from), Some(to)) => has_path_connecting(&self.graph, from, to, None),
+            _ => false,
+        }
+    }
+
+    /// Returns the shortest path (both ends included) from the node `from` to the node `to`, if any.
+    pub fn shortest_path(&self, from: Entity, to: Entity) -> Option<Vec<Entity>> {
+        let (from, to) = (self.node_index(from)?, self.node_index(to)?);
+        let (_, path) = astar(&self.graph, from, |n| n == to, |_| 1, |_| 0)?;
+        path.into_iter().map(|index| self.entity(index)).collect()
+    }
+
+    /// Returns whether the dialogue graph has cycles (e.g. choices looping back).
+    pub fn has_cycles(&self) -> bool {
+        is_cyclic_directed(&self.graph)
+    }
+}
+
+/// Command to (re)build the [`TalkGraphCache`] of a talk and insert it in the talk parent entity.
+pub struct RefreshTalkGraphCache {
+    /// The talk parent entity.
+    pub talk: Entity,
+}
+
+impl Command for RefreshTalkGraphCache {
+    fn apply(self, world: &mut World) {
+        match TalkGraphCache::from_world(world, self.talk) {
+            Some(cache) => {
+                world.entity_mut(self.talk).insert(cache);
+            }
+            None => talks_log!(
+                world.get_resource::<TalksConfig>(),
+                LogCategory::Build,
+                Level::WARN,
+                "Cannot build the graph cache of {:?}, no talk found.",
+                self.talk
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{prelude::*, tests::talks_minimal_app};
+
+    use super::*;
+
+    #[test]
+    fn cache_mirrors_the_talk() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default().say("Hello");
+        let hello = builder.last_node_id();
+        let builder = builder.say("Again?").choose(vec![
+            ("Yes", TalkBuilder::default().connect_to(hello)),
+            ("No", TalkBuilder::default().say("Bye")),
+        ]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        RefreshTalkGraphCache { talk }.apply(&mut app.world);
+
+        let start = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        let (bye, _) = app
+            .world
+            .query::<(Entity, &TextNode)>()
+            .iter(&app.world)
+            .find(|(_, t)| t.0 == "Bye")
+            .unwrap();
+
+        let cache = app.world.get::<TalkGraphCache>(talk).unwrap();
+        assert_eq!(cache.graph().node_count(), 5);
+        assert!(cache.has_cycles());
+        assert!(cache.is_reachable(start, bye));
+        assert!(!cache.is_reachable(bye, start));
+        assert_eq!(cache.shortest_path(start, bye).unwrap().len(), 5);
+    }
+}
@@ -0,0 +1,167 @@
+//! The keyboard hotkeys of the choices, with the optional plugin choosing them on key press.
+
+use bevy::prelude::*;
+
+use crate::prelude::{explain_choice_availability, ChooseNodeRequest, CurrentNodes};
+
+/// The names of the keys that can be used as choice hotkeys in the assets (e.g. RON `key: "1"`).
+const HOTKEYS: [(&str, KeyCode); 50] = [
+    ("0", KeyCode::Key0),
+    ("1", KeyCode::Key1),
+    ("2", KeyCode::Key2),
+    ("3", KeyCode::Key3),
+    ("4", KeyCode::Key4),
+    ("5", KeyCode::Key5),
+    ("6", KeyCode::Key6),
+    ("7", KeyCode::Key7),
+    ("8", KeyCode::Key8),
+    ("9", KeyCode::Key9),
+    ("A", KeyCode::A),
+    ("B", KeyCode::B),
+    ("C", KeyCode::C),
+    ("D", KeyCode::D),
+    ("E", KeyCode::E),
+    ("F", KeyCode::F),
+    ("G", KeyCode::G),
+    ("H", KeyCode::H),
+    ("I", KeyCode::I),
+    ("J", KeyCode::J),
+    ("K", KeyCode::K),
+    ("L", KeyCode::L),
+    ("M", KeyCode::M),
+    ("N", KeyCode::N),
+    ("O", KeyCode::O),
+    ("P", KeyCode::P),
+    ("Q", KeyCode::Q),
+    ("R", KeyCode::R),
+    ("S", KeyCode::S),
+    ("T", KeyCode::T),
+    ("U", KeyCode::U),
+    ("V", KeyCode::V),
+    ("W", KeyCode::W),
+    ("X", KeyCode::X),
+    ("Y", KeyCode::Y),
+    ("Z", KeyCode::Z),
+    ("F1", KeyCode::F1),
+    ("F2", KeyCode::F2),
+    ("F3", KeyCode::F3),
+    ("F4", KeyCode::F4),
+    ("F5", KeyCode::F5),
+    ("F6", KeyCode::F6),
+    ("F7", KeyCode::F7),
+    ("F8", KeyCode::F8),
+    ("F9", KeyCode::F9),
+    ("F10", KeyCode::F10),
+    ("F11", KeyCode::F11),
+    ("F12", KeyCode::F12),
+    ("Space", KeyCode::Space),
+    ("Enter", KeyCode::Return),
+];
+
+/// Returns the key with the given name: a digit (`"1"`), a letter (`"Y"`), a function key (`"F1"`),
+/// `"Space"` or `"Enter"`. The names are case insensitive.
+pub fn parse_hotkey(name: &str) -> Option<KeyCode> {
+    HOTKEYS
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, key)| *key)
+}
+
+/// Returns the name of the key in the assets, if it can be used as a choice hotkey.
+pub fn hotkey_name(key: KeyCode) -> Option<&'static str> {
+    HOTKEYS
+        .iter()
+        .find(|(_, k)| *k == key)
+        .map(|(name, _)| *name)
+}
+
+/// Plugin that sends a `ChooseNodeRequest` when the hotkey of an available choice is pressed,
+/// for all the talks on a choice node. The choices removed by the choice filters can't be chosen.
+///
+/// It needs the `Input<KeyCode>` resource (added by the `InputPlugin` of the `DefaultPlugins`).
+///
+/// # Example
+/// ```rust,no_run
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// App::new()
+///     .add_plugins((DefaultPlugins, TalksPlugin, ChoiceHotkeysPlugin))
+///     .run();
+/// ```
+pub struct ChoiceHotkeysPlugin;
+
+impl Plugin for ChoiceHotkeysPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, choose_with_hotkeys);
+    }
+}
+
+/// Sends the `ChooseNodeRequest`s for the available choices whose hotkey was just pressed.
+fn choose_with_hotkeys(world: &mut World) {
+    let Some(keys) = world.get_resource::<Input<KeyCode>>() else {
+        return;
+    };
+    if keys.get_just_pressed().next().is_none() {
+        return;
+    }
+    let mut requests = vec![];
+    for (talk, node) in world.resource::<CurrentNodes>().0.iter() {
+        let pressed = explain_choice_availability(world, *node)
+            .into_iter()
+            .find(|(c, available, _)| *available && c.hotkey.is_some_and(|k| keys.just_pressed(k)));
+        if let Some((choice, _, _)) = pressed {
+            requests.push(ChooseNodeRequest::new(*talk, choice.next));
+        }
+    }
+    for request in requests {
+        world.send_event(request);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::Command;
+
+    use crate::{prelude::*, tests::talks_minimal_app};
+
+    use super::*;
+
+    #[test]
+    fn hotkey_names() {
+        assert_eq!(parse_hotkey("1"), Some(KeyCode::Key1));
+        assert_eq!(parse_hotkey("y"), Some(KeyCode::Y));
+        assert_eq!(parse_hotkey("enter"), Some(KeyCode::Return));
+        assert_eq!(parse_hotkey("Hyper"), None);
+        assert_eq!(hotkey_name(KeyCode::F3), Some("F3"));
+        assert_eq!(hotkey_name(KeyCode::Insert), None);
+    }
+
+    #[test]
+    fn hotkey_chooses_the_choice() {
+        let mut app = talks_minimal_app();
+        app.add_plugins(ChoiceHotkeysPlugin)
+            .init_resource::<Input<KeyCode>>();
+        let builder = TalkBuilder::default()
+            .choose(vec![
+                ("Yes", TalkBuilder::default().say("Great")),
+                ("No", TalkBuilder::default().say("Too bad")),
+            ])
+            .hotkey(0, KeyCode::Y)
+            .hotkey(1, KeyCode::N);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+
+        let evs = app.world.resource::<Events<ChoiceNodeEvent>>();
+        let choices = &evs.get_reader().read(evs).last().unwrap().choices;
+        assert_eq!(choices[1].hotkey, Some(KeyCode::N));
+
+        app.world.resource_mut::<Input<KeyCode>>().press(KeyCode::N);
+        app.update();
+        app.update();
+        let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        assert_eq!(app.world.get::<TextNode>(current).unwrap().0, "Too bad");
+    }
+}
@@ -0,0 +1,664 @@
+//! The format-agnostic intermediate representation (IR) of a Talk.
+//!
+//! Importers (RON, and any other format) convert their own data into a [`TalkIr`]
+//! and then use it to validate and assemble the dialogue graph:
+//!
+//! ```text
+//! RON / other formats <--> TalkIr <--> TalkData --> TalkBuilder --> spawned entity graph
+//!                            ^                                            |
+//!                            +--------------------------------------------+
+//! ```
+
+use aery::{prelude::*, tuple_traits::RelationEntries};
+use bevy::{prelude::*, utils::HashMap, utils::HashSet};
+use indexmap::IndexMap;
+
+use std::collections::BTreeMap;
+
+use crate::{
+    actors::AliasedActors,
+    prelude::{
+        hotkey_name, Action, Actor, ActorAliases, ActorSlug, ChoiceData, ChoiceNode, CustomNode,
+        EmitterSpec, Emphasis, EndNode, FollowedBy, Icon, IrError, JoinNode, LeaveNode, NodeKind,
+        PerformedBy, PlayerNode, ResumeNode, SpeakingOrder, StartNode, Talk, TalkBuilder, TalkData,
+        TalkMetadata, TextNode, Thought,
+    },
+    ron_loader::types::RonTalk,
+    validation::suggest_slugs,
+};
+
+/// The identifier of a node in the IR.
+pub type IrNodeId = usize;
+
+/// The intermediate representation of a Talk.
+///
+/// The first node in `nodes` is the entry point of the dialogue graph.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TalkIr {
+    /// The actors that appear in the Talk.
+    pub actors: Vec<Actor>,
+    /// The actor aliases of the Talk (alias => actor slug). The nodes can be performed by an alias,
+    /// see [`ActorAliases`](crate::prelude::ActorAliases).
+    pub aliases: BTreeMap<String, ActorSlug>,
+    /// The id of the node to resume from when the Talk is started again after it ended.
+    pub resume_at: Option<IrNodeId>,
+    /// The nodes of the Talk.
+    pub nodes: Vec<IrNode>,
+    /// The metadata of the Talk.
+    pub metadata: TalkMetadata,
+}
+
+/// A node of the [`TalkIr`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IrNode {
+    /// The unique id of the node.
+    pub id: IrNodeId,
+    /// The kind of node.
+    pub kind: NodeKind,
+    /// The slugs of the actors performing the node.
+    pub actors: Vec<ActorSlug>,
+    /// The choices of a choice node.
+    pub choices: Vec<IrChoice>,
+    /// The text of a talk node.
+    pub text: String,
+    /// The id of the next node. Not used for choice nodes.
+    pub next: Option<IrNodeId>,
+    /// The emphasis intensity of the node.
+    pub emphasis: Option<f32>,
+    /// The icon (emoji or emote id) of the node.
+    pub icon: Option<String>,
+    /// The thought shown alongside the text of a talk node.
+    pub thought: Option<String>,
+    /// Whether the node is explicitly an end node. The nodes without a following node are end nodes anyway.
+    pub end: bool,
+    /// The components to construct and add to the node (see [`EmitterSpec`]).
+    pub emit: Vec<EmitterSpec>,
+}
+
+/// A choice of an [`IrNode`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IrChoice {
+    /// The text of the choice.
+    pub text: String,
+    /// The id of the node to go to if the choice is selected.
+    pub next: IrNodeId,
+    /// The optional description of the choice.
+    pub description: Option<String>,
+    /// The optional sound cue of the choice, played when it is selected.
+    pub sound: Option<String>,
+    /// The optional name of the hotkey of the choice (see [`parse_hotkey`](crate::prelude::parse_hotkey)).
+    pub key: Option<String>,
+}
+
+impl TalkIr {
+    /// Checks that the IR describes a well formed Talk: unique node ids and actor slugs (aliases included),
+    /// `next` fields pointing to existing nodes, aliases of existing actors and nodes performed by existing actors or aliases.
+    pub fn validate(&self) -> Result<(), IrError> {
+        let mut slugs = HashSet::with_capacity(self.actors.len());
+        for actor in self.actors.iter() {
+            if !slugs.insert(actor.slug.clone()) {
+                return Err(IrError::DuplicateActorSlug(actor.slug.clone()));
+            }
+        }
+
+        let mut ids = HashSet::with_capacity(self.nodes.len());
+        for node in self.nodes.iter() {
+            if !ids.insert(node.id) {
+                return Err(IrError::DuplicateNodeId(node.id));
+            }
+        }
+
+        for (alias, slug) in self.aliases.iter() {
+            if slugs.contains(alias) {
+                return Err(IrError::DuplicateActorSlug(alias.clone()));
+            }
+            if !slugs.contains(slug) {
+                return Err(IrError::InvalidActorSlug {
+                    slug: slug.clone(),
+                    suggestions: suggest_slugs(slug, slugs.iter().map(String::as_str)),
+                });
+            }
+        }
+        slugs.extend(self.aliases.keys().cloned());
+
+        if let Some(resume_at) = self.resume_at.filter(|id| !ids.contains(id)) {
+            return Err(IrError::NodeNotFound(resume_at));
+        }
+
+        validate_all_nexts(&self.nodes, &ids)?;
+        validate_actors(&self.nodes, &slugs)
+    }
+
+    /// Parses a RON talk (the `.talk.ron` format) into the IR. It does not validate it.
+    pub fn from_ron(ron: &str) -> Result<Self, serde_ron::error::SpannedError> {
+        Ok(serde_ron::from_str::<RonTalk>(ron)?.into())
+    }
+
+    /// Serializes the IR in the RON talk format (the `.talk.ron` format).
+    pub fn to_ron(&self) -> Result<String, serde_ron::Error> {
+        serde_ron::ser::to_string_pretty(&RonTalk::from(self), default())
+    }
+
+    /// Validates the IR and converts it into a [`TalkBuilder`] ready to spawn the dialogue graph.
+    pub fn into_builder(self) -> Result<TalkBuilder, IrError> {
+        let talk_data = TalkData::try_from(self)?;
+        Ok(TalkBuilder::default().fill_with_talk_data(&talk_data))
+    }
+
+    /// Reads back a spawned dialogue graph into the IR.
+    ///
+    /// The node ids are assigned in breadth-first order starting from the start node.
+    /// Only the built-in node components are read: custom components are not part of the IR
+    /// and nodes without a built-in component become empty talk nodes.
+    ///
+    /// Returns `None` if the entity has no start node child.
+    pub fn from_graph(world: &mut World, talk: Entity) -> Option<Self> {
+        let children: Vec<Entity> = world.get::<Children>(talk)?.iter().copied().collect();
+        let start = children
+            .into_iter()
+            .find(|e| world.get::<StartNode>(*e).is_some())?;
+
+        let mut followers_query = world.query::<Relations<FollowedBy>>();
+
+        // assign the ids visiting the graph from the start node
+        let mut ids = HashMap::new();
+        let mut order = vec![];
+        let mut to_visit =
+            std::collections::VecDeque::from(followers(&mut followers_query, world, start));
+        while let Some(e) = to_visit.pop_front() {
+            if ids.contains_key(&e) {
+                continue;
+            }
+            ids.insert(e, order.len());
+            order.push(e);
+            to_visit.extend(followers(&mut followers_query, world, e));
+        }
+        let resume_at = order
+            .iter()
+            .find(|e| world.get::<ResumeNode>(**e).is_some())
+            .map(|e| ids[e]);
+
+        let mut performers_query = world.query::<Relations<PerformedBy>>();
+        let mut actors = vec![];
+        let mut nodes = Vec::with_capacity(order.len());
+        for e in order {
+            let mut node = IrNode {
+                id: ids[&e],
+                emphasis: world.get::<Emphasis>(e).map(|em| em.0),
+                icon: world.get::<Icon>(e).map(|i| i.0.clone()),
+                thought: world.get::<Thought>(e).map(|t| t.0.clone()),
+                ..default()
+            };
+
+            // the player pseudo-actor has no entity, it is read from the marker
+            if world.get::<PlayerNode>(e).is_some() {
+                node.actors.push(Actor::PLAYER_SLUG.to_string());
+            }
+            if let Ok(edges) = performers_query.get(world, e) {
+                let aliased = world.get::<AliasedActors>(e);
+                for actor_ent in edges.targets(PerformedBy) {
+                    if let Some(actor) = world.get::<Actor>(*actor_ent) {
+                        // the nodes performed under an alias keep it
+                        let slug = aliased
+                            .and_then(|a| a.0.get(&actor.slug))
+                            .unwrap_or(&actor.slug);
+                        if !node.actors.contains(slug) {
+                            node.actors.push(slug.clone());
+                        }
+                        if !actors.contains(actor) {
+                            actors.push(actor.clone());
+                        }
+                    }
+                }
+            }
+
+            if let Some(choice_node) = world.get::<ChoiceNode>(e) {
+                node.kind = NodeKind::Choice;
+                node.choices = choice_node
+                    .0
+                    .iter()
+                    .map(|c| IrChoice {
+                        text: c.text.clone(),
+                        next: ids[&c.next],
+                        description: c.description.clone(),
+                        sound: c.sound.clone(),
+                        key: c.hotkey.and_then(hotkey_name).map(str::to_string),
+                    })
+                    .collect();
+            } else {
+                if let Some(custom) = world.get::<CustomNode>(e) {
+                    node.kind = NodeKind::Custom(custom.kind.clone());
+                    node.text = custom.data.clone();
+                } else if world.get::<JoinNode>(e).is_some() {
+                    node.kind = NodeKind::Join;
+                } else if world.get::<LeaveNode>(e).is_some() {
+                    node.kind = NodeKind::Leave;
+                } else if let Some(text_node) = world.get::<TextNode>(e) {
+                    node.text = text_node.0.clone();
+                }
+                node.next = followers(&mut followers_query, world, e)
+                    .first()
+                    .map(|next| ids[next]);
+            }
+            // only the end nodes with following nodes need to be explicit
+            node.end = world.get::<EndNode>(e).is_some()
+                && !followers(&mut followers_query, world, e).is_empty();
+            if let Some(SpeakingOrder(order)) = world.get::<SpeakingOrder>(e) {
+                node.actors
+                    .sort_by_key(|slug| order.iter().position(|s| s == slug));
+            }
+            nodes.push(node);
+        }
+
+        let metadata = world
+            .get::<Talk>(talk)
+            .map(|t| t.metadata.clone())
+            .unwrap_or_default();
+        let aliases = world
+            .get::<ActorAliases>(talk)
+            .map(|a| {
+                a.iter()
+                    .map(|(alias, slug)| (alias.to_string(), slug.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some(TalkIr {
+            actors,
+            aliases,
+            resume_at,
+            nodes,
+            metadata,
+        })
+    }
+}
+
+impl TryFrom<TalkIr> for TalkData {
+    type Error = IrError;
+
+    fn try_from(ir: TalkIr) -> Result<Self, Self::Error> {
+        ir.validate()?;
+        let script = ir
+            .nodes
+            .into_iter()
+            .map(|node| (node.id, node.into()))
+            .collect::<IndexMap<_, Action>>();
+        let mut talk_data = TalkData::new(script, ir.actors);
+        talk_data.aliases = ir.aliases;
+        talk_data.resume_at = ir.resume_at;
+        talk_data.metadata = ir.metadata;
+        Ok(talk_data)
+    }
+}
+
+impl From<&TalkData> for TalkIr {
+    fn from(talk: &TalkData) -> Self {
+        TalkIr {
+            actors: talk.actors.clone(),
+            aliases: talk.aliases.clone(),
+            resume_at: talk.resume_at,
+            metadata: talk.metadata.clone(),
+            nodes: talk
+                .script
+                .iter()
+                .map(|(id, action)| IrNode {
+                    id: *id,
+                    kind: action.kind.clone(),
+                    actors: action.actors.clone(),
+                    choices: action
+                        .choices
+                        .iter()
+                        .map(|c| IrChoice {
+                            text: c.text.clone(),
+                            next: c.next,
+                            description: c.description.clone(),
+                            sound: c.sound.clone(),
+                            key: c.key.clone(),
+                        })
+                        .collect(),
+                    text: action.text.clone(),
+                    next: action.next,
+                    emphasis: action.emphasis,
+                    icon: action.icon.clone(),
+                    thought: action.thought.clone(),
+                    end: action.end,
+                    emit: action.emit.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<IrNode> for Action {
+    fn from(node: IrNode) -> Self {
+        Action {
+            kind: node.kind,
+            actors: node.actors,
+            choices: node
+                .choices
+                .into_iter()
+                .map(|c| ChoiceData {
+                    text: c.text,
+                    next: c.next,
+                    description: c.description,
+                    sound: c.sound,
+                    key: c.key,
+                })
+                .collect(),
+            text: node.text,
+            next: node.next,
+            emphasis: node.emphasis,
+            icon: node.icon,
+            thought: node.thought,
+            end: node.end,
+            emit: node.emit,
+        }
+    }
+}
+
+/// Returns the nodes following the given one in the graph.
+fn followers(
+    query: &mut QueryState<Relations<FollowedBy>>,
+    world: &World,
+    node: Entity,
+) -> Vec<Entity> {
+    query
+        .get(world, node)
+        .map(|edges| edges.targets(FollowedBy).to_vec())
+        .unwrap_or_default()
+}
+
+/// Check if the nodes use only actors that are defined in the talk.
+fn validate_actors(nodes: &[IrNode], actor_slugs: &HashSet<ActorSlug>) -> Result<(), IrError> {
+    for node in nodes.iter() {
+        for slug in node.actors.iter() {
+            if !actor_slugs.contains(slug) && slug != Actor::PLAYER_SLUG {
+                return Err(IrError::InvalidActorSlug {
+                    slug: slug.clone(),
+                    suggestions: suggest_slugs(slug, actor_slugs.iter().map(String::as_str)),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check if all `next` fields and choice `next` fields point to real nodes.
+/// If the node has choices, the `next` field is not checked.
+fn validate_all_nexts(nodes: &[IrNode], ids: &HashSet<IrNodeId>) -> Result<(), IrError> {
+    for node in nodes {
+        if !node.choices.is_empty() {
+            for choice in node.choices.iter() {
+                if !ids.contains(&choice.next) {
+                    return Err(IrError::InvalidNextNode(node.id, choice.next));
+                }
+            }
+        } else if let Some(next_id) = &node.next {
+            if !ids.contains(next_id) {
+                return Err(IrError::InvalidNextNode(node.id, *next_id));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::Command;
+
+    use crate::{
+        prelude::*,
+        tests::{single, talks_minimal_app},
+    };
+
+    use super::*;
+
+    /// Collects the node ids.
+    fn ids(nodes: &[IrNode]) -> HashSet<IrNodeId> {
+        nodes.iter().map(|n| n.id).collect()
+    }
+
+    #[test]
+    fn error_invalid_next_action() {
+        let nodes = vec![IrNode {
+            next: Some(2),
+            ..default()
+        }];
+        let res = validate_all_nexts(&nodes, &ids(&nodes));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn error_not_found_in_choice() {
+        let nodes = vec![
+            IrNode {
+                choices: vec![IrChoice {
+                    next: 2,
+                    ..default()
+                }],
+                ..default()
+            },
+            IrNode { id: 1, ..default() },
+        ];
+        let res = validate_all_nexts(&nodes, &ids(&nodes));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_validate_actors_valid() {
+        let mut actor_slugs = HashSet::<ActorSlug>::new();
+        actor_slugs.insert("actor1".to_string());
+        actor_slugs.insert("actor2".to_string());
+
+        let nodes = vec![
+            IrNode {
+                actors: vec!["actor1".to_string()],
+                ..default()
+            },
+            IrNode {
+                id: 1,
+                actors: vec!["actor1".to_string(), "actor2".to_string()],
+                ..default()
+            },
+        ];
+        assert!(validate_actors(&nodes, &actor_slugs).is_ok());
+    }
+
+    #[test]
+    fn test_validate_actors_invalid() {
+        let mut actor_slugs = HashSet::<ActorSlug>::new();
+        actor_slugs.insert("actor1".to_string());
+        actor_slugs.insert("actor2".to_string());
+        let nodes = vec![IrNode {
+            actors: vec!["actor3".to_string()],
+            ..default()
+        }];
+        let result = validate_actors(&nodes, &actor_slugs);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_validate_player_actor() {
+        let nodes = vec![IrNode {
+            id: 1,
+            actors: vec![Actor::PLAYER_SLUG.to_string()],
+            ..default()
+        }];
+        assert!(validate_actors(&nodes, &HashSet::new()).is_ok());
+    }
+
+    #[test]
+    fn error_duplicate_ids() {
+        let ir = TalkIr {
+            nodes: vec![IrNode::default(), IrNode::default()],
+            ..default()
+        };
+        assert_eq!(ir.validate(), Err(IrError::DuplicateNodeId(0)));
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let ir = TalkIr {
+            actors: vec![Actor::new("bob", "Bob")],
+            nodes: vec![
+                IrNode {
+                    id: 1,
+                    text: "Hello".to_string(),
+                    actors: vec!["bob".to_string()],
+                    next: Some(2),
+                    icon: Some("wave".to_string()),
+                    thought: Some("Not him again.".to_string()),
+                    end: true,
+                    ..default()
+                },
+                IrNode {
+                    id: 2,
+                    kind: NodeKind::Choice,
+                    choices: vec![IrChoice {
+                        text: "Bye".to_string(),
+                        next: 1,
+                        description: Some("Leave the talk".to_string()),
+                        sound: None,
+                        key: None,
+                    }],
+                    ..default()
+                },
+            ],
+            metadata: TalkMetadata {
+                title: Some("Goodbye".to_string()),
+                tags: vec!["bob".to_string()],
+                custom: [("area".to_string(), "town".to_string())].into(),
+                ..default()
+            },
+            ..default()
+        };
+        let ron = ir.to_ron().unwrap();
+        assert_eq!(TalkIr::from_ron(&ron).unwrap(), ir);
+    }
+
+    #[test]
+    fn graph_round_trip() {
+        let mut app = talks_minimal_app();
+        let ir = TalkIr {
+            actors: vec![Actor::new("bob", "Bob")],
+            nodes: vec![
+                IrNode {
+                    id: 0,
+                    kind: NodeKind::Join,
+                    actors: vec!["bob".to_string()],
+                    next: Some(1),
+                    end: true,
+                    ..default()
+                },
+                IrNode {
+                    id: 1,
+                    kind: NodeKind::Choice,
+                    choices: vec![
+                        IrChoice {
+                            text: "Again".to_string(),
+                            next: 0,
+                            ..default()
+                        },
+                        IrChoice {
+                            text: "Stop".to_string(),
+                            next: 2,
+                            ..default()
+                        },
+                    ],
+                    ..default()
+                },
+                IrNode {
+                    id: 2,
+                    text: "Bye".to_string(),
+                    actors: vec!["bob".to_string()],
+                    ..default()
+                },
+            ],
+            metadata: TalkMetadata {
+                title: Some("Bye Bob".to_string()),
+                ..default()
+            },
+            ..default()
+        };
+
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, ir.clone().into_builder().unwrap()).apply(&mut app.world);
+
+        assert_eq!(TalkIr::from_graph(&mut app.world, talk), Some(ir));
+    }
+
+    #[test]
+    fn aliases_from_ron_and_graph() {
+        let ir = TalkIr::from_ron(
+            r#"(
+                actors: [(slug: "stranger", name: "Geralt")],
+                aliases: {"???": "stranger"},
+                script: [
+                    (id: 0, text: Some("Hmm."), actors: ["???"], next: Some(1)),
+                    (id: 1, text: Some("Of Rivia."), actors: ["stranger"]),
+                ],
+            )"#,
+        )
+        .unwrap();
+        assert_eq!(ir.aliases["???"], "stranger");
+        assert_eq!(ir.validate(), Ok(()));
+
+        let mut app = talks_minimal_app();
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, ir.clone().into_builder().unwrap()).apply(&mut app.world);
+        let graph_ir = TalkIr::from_graph(&mut app.world, talk).unwrap();
+        assert_eq!(graph_ir.aliases, ir.aliases);
+        assert_eq!(graph_ir.nodes[0].actors, vec!["???"]);
+        assert_eq!(graph_ir.nodes[1].actors, vec!["stranger"]);
+    }
+
+    #[test]
+    fn resume_at_from_ron_and_graph() {
+        let ir = TalkIr::from_ron(
+            r#"(
+                resume_at: Some(2),
+                script: [
+                    (id: 1, text: Some("Welcome!"), next: Some(2)),
+                    (id: 2, text: Some("What else?")),
+                ],
+            )"#,
+        )
+        .unwrap();
+        assert_eq!(ir.resume_at, Some(2));
+        assert_eq!(TalkIr::from_ron(&ir.to_ron().unwrap()).unwrap(), ir);
+
+        let mut app = talks_minimal_app();
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, ir.into_builder().unwrap()).apply(&mut app.world);
+        let (resume, _) = single::<(&TextNode, With<ResumeNode>)>(&mut app.world);
+        assert_eq!(resume.0, "What else?");
+        // the graph ids are assigned from 0
+        let graph_ir = TalkIr::from_graph(&mut app.world, talk).unwrap();
+        assert_eq!(graph_ir.resume_at, Some(1));
+
+        let ir = TalkIr {
+            resume_at: Some(7),
+            nodes: vec![IrNode::default()],
+            ..default()
+        };
+        assert_eq!(ir.validate(), Err(IrError::NodeNotFound(7)));
+    }
+
+    #[test]
+    fn invalid_aliases() {
+        let mut ir = TalkIr {
+            actors: vec![Actor::new("bob", "Bob")],
+            ..default()
+        };
+        ir.aliases.insert("bob".to_string(), "bob".to_string());
+        assert_eq!(
+            ir.validate(),
+            Err(IrError::DuplicateActorSlug("bob".to_string()))
+        );
+        ir.aliases.clear();
+        ir.aliases.insert("???".to_string(), "bobb".to_string());
+        assert!(matches!(
+            ir.validate(),
+            Err(IrError::InvalidActorSlug { slug, .. }) if slug == "bobb"
+        ));
+    }
+}
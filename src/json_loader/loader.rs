@@ -0,0 +1,160 @@
+//! The JSON Asset Loader.
+//!
+//! A `.talk.json` file has the same structure as a `.talk.ron` file, with the JSON syntax:
+//!
+//! ```json
+//! {
+//!     "actors": [{ "slug": "bob", "name": "Bob" }],
+//!     "script": [
+//!         { "id": 1, "action": "Join", "actors": ["bob"], "next": 2 },
+//!         { "id": 2, "actors": ["bob"], "text": "Hello!" }
+//!     ]
+//! }
+//! ```
+//!
+//! The optional fields can be omitted or `null`, the enum values with data are objects (`"action": { "Custom": "shop" }`).
+//! The named talks of the packs and the locales are supported too, with the same RON sidecar files.
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    utils::BoxedFuture,
+};
+use thiserror::Error;
+
+use crate::prelude::{RonLoaderError, TalkData};
+use crate::ron_loader::{loader::load_talk_data, types::RonTalk};
+
+/// Load Talks from json assets.
+pub struct JsonTalksLoader;
+
+/// The error type for the JSON Talks loader.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum JsonLoaderError {
+    /// An [IO Error](std::io::Error)
+    #[error("Could not read the file: {0}")]
+    Io(#[from] std::io::Error),
+    /// A [JSON Error](serde_json::Error)
+    #[error("Could not parse JSON: {0}")]
+    JsonError(#[from] serde_json::Error),
+    /// The talk, a named talk of the pack or a locale is not valid, with the same checks as the RON talks
+    #[error(transparent)]
+    Talk(#[from] RonLoaderError),
+}
+
+impl AssetLoader for JsonTalksLoader {
+    type Asset = TalkData;
+    type Settings = ();
+    type Error = JsonLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let json_talk = serde_json::from_slice::<RonTalk>(&bytes)?;
+            Ok(load_talk_data(json_talk, load_context).await?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["talk.json"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_ron::de::from_bytes;
+
+    use bevy::{asset::LoadState, prelude::*};
+
+    use crate::prelude::{IrError, NodeKind, TalkIr};
+    use crate::tests::load_talk_asset;
+
+    use super::*;
+
+    #[test]
+    fn json_talk_same_as_ron() {
+        let json = r#"{
+            "metadata": { "title": "Shop" },
+            "actors": [{ "slug": "bob", "name": "Bob" }],
+            "script": [
+                { "id": 1, "action": "Join", "actors": ["bob"], "next": 2 },
+                { "id": 2, "actors": ["bob"], "text": "Hello!", "next": 3 },
+                { "id": 3, "choices": [{ "text": "Buy", "next": 4 }, { "text": "Bye", "next": 5 }] },
+                { "id": 4, "action": { "Custom": "shop" }, "text": null },
+                { "id": 5, "text": "Bye!" }
+            ]
+        }"#;
+        let ron = r#"(
+            metadata: (title: Some("Shop")),
+            actors: [(slug: "bob", name: "Bob")],
+            script: [
+                (id: 1, action: Join, actors: ["bob"], next: Some(2)),
+                (id: 2, actors: ["bob"], text: Some("Hello!"), next: Some(3)),
+                (id: 3, choices: Some([(text: "Buy", next: 4), (text: "Bye", next: 5)])),
+                (id: 4, action: Custom("shop")),
+                (id: 5, text: Some("Bye!")),
+            ],
+        )"#;
+        let json_talk = serde_json::from_str::<RonTalk>(json).expect("talk should parse");
+        let ron_talk = from_bytes::<RonTalk>(ron.as_bytes()).unwrap();
+        let json_ir = TalkIr::from(json_talk);
+        assert_eq!(json_ir, TalkIr::from(ron_talk));
+        assert_eq!(json_ir.nodes[2].kind, NodeKind::Choice);
+        assert_eq!(json_ir.nodes[3].kind, NodeKind::Custom("shop".to_string()));
+        assert!(TalkData::try_from(json_ir).is_ok());
+    }
+
+    #[test]
+    fn json_talk_validated() {
+        let json = r#"{ "script": [{ "id": 1, "text": "Hi", "next": 2 }] }"#;
+        let json_talk = serde_json::from_str::<RonTalk>(json).unwrap();
+        assert!(matches!(
+            TalkData::try_from(TalkIr::from(json_talk)),
+            Err(IrError::InvalidNextNode(1, 2))
+        ));
+        let err = serde_json::from_str::<RonTalk>(r#"{ "script": [{ "text": "Hi" }] }"#)
+            .map_err(JsonLoaderError::from)
+            .unwrap_err();
+        assert!(err.to_string().starts_with("Could not parse JSON"));
+    }
+
+    #[test]
+    fn json_pack_loaded_with_labels() {
+        let pack = include_str!("../../assets/talks/pack.talk.json");
+        let (app, handle) =
+            load_talk_asset(&[("talks/pack.talk.json", pack)], "talks/pack.talk.json");
+        let server = app.world.resource::<AssetServer>();
+        assert_eq!(server.load_state(&handle), LoadState::Loaded);
+
+        let talks = app.world.resource::<Assets<TalkData>>();
+        let pack_data = talks.get(&handle).unwrap();
+        let mut labels = pack_data.talks.keys().collect::<Vec<_>>();
+        labels.sort();
+        assert_eq!(labels, ["intro", "shop"]);
+        let shop = server.load::<TalkData>("talks/pack.talk.json#shop");
+        assert_eq!(shop, pack_data.talks["shop"]);
+        assert_eq!(talks.get(&shop).unwrap().script.len(), 3);
+    }
+
+    #[test]
+    fn json_pack_validated_when_loaded() {
+        // the intro talk goes to a node that doesn't exist
+        let pack = include_str!("../../assets/talks/pack.talk.json").replacen(
+            r#""next": 2"#,
+            r#""next": 9"#,
+            1,
+        );
+        let (app, handle) = load_talk_asset(
+            &[("talks/pack.talk.json", pack.as_str())],
+            "talks/pack.talk.json",
+        );
+        let server = app.world.resource::<AssetServer>();
+        assert_eq!(server.load_state(&handle), LoadState::Failed);
+    }
+}
@@ -0,0 +1,3 @@
+//! Asset loader for Talks from "talk.json" files. Available with the `json` feature.
+
+pub(crate) mod loader;
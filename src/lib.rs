@@ -1,21 +1,98 @@
 //! `bevy_talks` is a Bevy plugin that provides the basics to build and handle dialogues in games.
 
 use aery::{prelude::*, tuple_traits::RelationEntries};
-use bevy::prelude::*;
-
+use bevy::{
+    ecs::{
+        schedule::ScheduleLabel,
+        system::{Command, SystemParam},
+    },
+    log::Level,
+    prelude::*,
+};
+
+use actors::AliasedActors;
+use builder::lazy::expand_lazy_branches;
+use captions::{send_caption, send_component_caption};
+use choice_filter::{apply_choice_filters, single_choice_policy, ShownChoices, SkippedChoice};
+use choice_timer::tick_choice_timers;
+use config::{talks_log, LogCategory};
+use csv_loader::loader::CsvLoader;
+use display::forward_to_displays;
+use events::{make_node_event, send_node_event, NodeEventEmitters};
+use lockout::{expire_choice_locks, ChoiceLocks};
+use markup::parse_markup;
+use min_display::{forget_entered_times, record_node_entered_times, NodeEnteredTimes};
+use persistence::{record_taken_choices, record_talk_states};
+use pool::TalkPool;
+use preload::{check_talk_preloads, spawn_loaded_talks, spawn_ready_talks, TalkPreloads};
 use prelude::*;
+use refire::{forget_last_refires, LastRefires};
 use ron_loader::loader::TalksLoader;
-use traverse::{choice_handler, next_handler, set_has_started};
+use storage::write_talk_persistence;
+use text_filter::{apply_text_filters, filtered_node_texts, has_text_filters};
+use traverse::{
+    choice_handler, clean_interjections, next_handler, on_end_handler, refire_handler,
+    sync_current_nodes, update_advance_markers,
+};
+use twine_loader::loader::TwineLoader;
 
 pub mod actors;
+pub mod ambient;
+#[cfg(feature = "bubbles")]
+pub mod bubbles;
 pub mod builder;
+pub mod captions;
+pub mod choice_filter;
+pub mod choice_timer;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod config;
+#[cfg(feature = "coverage")]
+pub mod coverage;
+pub mod csv_loader;
+pub mod custom_nodes;
+pub mod display;
+pub mod editor;
+#[cfg(feature = "egui")]
+pub mod egui_overlay;
+pub mod emit;
 pub mod errors;
 pub mod events;
+#[cfg(feature = "petgraph")]
+pub mod graph_cache;
+pub mod hotkeys;
+pub mod ir;
+#[cfg(feature = "json")]
+pub mod json_loader;
+pub mod library;
+pub mod localization;
+pub mod lockout;
+pub mod markers;
+pub mod markup;
+pub mod merge;
+pub mod min_display;
+pub mod persistence;
+pub mod pool;
+pub mod preload;
 pub mod prelude;
+pub mod proximity;
+pub mod random;
+#[cfg(feature = "test")]
+pub mod recorder;
+pub mod refire;
 pub mod ron_loader;
+pub mod screenplay;
+pub mod storage;
+pub mod sync;
 pub mod talk;
 pub mod talk_asset;
+pub mod text_filter;
 mod traverse;
+pub mod twine_loader;
+mod validation;
+pub mod variables;
+#[cfg(feature = "yaml")]
+pub mod yaml_loader;
 
 /// The plugin that provides the basics to build and handle dialogues in games.
 ///
@@ -24,82 +101,215 @@ mod traverse;
 /// This plugin will add Aery if it's not in the app, since it is a unique plugin, having multiple will panic.
 pub struct TalksPlugin;
 
+impl TalksPlugin {
+    /// Returns a [`TalksPlugin`] that runs the traversal (the request handlers) in the given schedule
+    /// instead of `PreUpdate`, e.g. `FixedUpdate` to step the dialogues with a fixed timestep.
+    ///
+    /// The requests are kept until the traversal schedule handles them, even if it does not run every frame
+    /// (or runs several times in one). The node events sent by the traversal are readable
+    /// in the following schedules of the same frame and in the next frame.
+    ///
+    /// ```no_run
+    /// use bevy::prelude::*;
+    /// use bevy_talks::prelude::*;
+    ///
+    /// App::new()
+    ///     .add_plugins((DefaultPlugins, TalksPlugin::in_schedule(FixedUpdate)))
+    ///     .run();
+    /// ```
+    pub fn in_schedule<S: ScheduleLabel + Clone>(schedule: S) -> TalksScheduledPlugin<S> {
+        TalksScheduledPlugin { schedule }
+    }
+}
+
 impl Plugin for TalksPlugin {
     fn build(&self, app: &mut App) {
-        if !app.is_plugin_added::<Aery>() {
-            app.add_plugins(Aery);
-        }
+        build_talks(app, PreUpdate);
+    }
+}
 
-        app.add_plugins(TalksEventsPlugin)
-            .register_asset_loader(TalksLoader)
-            .init_asset::<TalkData>()
-            .configure_sets(PreUpdate, TalksSet)
+/// The [`TalksPlugin`] with the traversal running in a custom schedule, see [`TalksPlugin::in_schedule`].
+pub struct TalksScheduledPlugin<S: ScheduleLabel + Clone> {
+    /// The schedule of the traversal.
+    schedule: S,
+}
+
+impl<S: ScheduleLabel + Clone> Plugin for TalksScheduledPlugin<S> {
+    fn build(&self, app: &mut App) {
+        // The request events are updated by the traversal schedule instead of `First`,
+        // so they are not dropped in the frames the schedule does not run.
+        // They must exist before the events plugin, which skips the events already added.
+        app.init_resource::<Events<NextNodeRequest>>()
+            .init_resource::<Events<ChooseNodeRequest>>()
+            .init_resource::<Events<RefireNodeRequest>>()
+            .init_resource::<Events<SkippedChoice>>()
             .add_systems(
-                PreUpdate,
+                self.schedule.clone(),
                 (
-                    next_handler.pipe(error_logger),
-                    choice_handler.pipe(error_logger),
-                    refire_handler.pipe(error_logger),
-                    set_has_started.after(next_handler),
+                    update_requests::<NextNodeRequest>,
+                    update_requests::<ChooseNodeRequest>,
+                    update_requests::<RefireNodeRequest>,
+                    update_requests::<SkippedChoice>,
                 )
-                    .in_set(TalksSet),
+                    .after(TalksSet),
             );
+        build_talks(app, self.schedule.clone());
     }
 }
 
+/// Adds the talks resources, assets and systems to the app, with the traversal in the given schedule.
+fn build_talks(app: &mut App, schedule: impl ScheduleLabel + Clone) {
+    if !app.is_plugin_added::<Aery>() {
+        app.add_plugins(Aery);
+    }
+
+    // init the config first, so it can also be used while registering the events
+    app.init_resource::<TalksConfig>()
+        .add_plugins(TalksEventsPlugin)
+        .register_asset_loader(TalksLoader)
+        .register_asset_loader(TwineLoader)
+        .register_asset_loader(CsvLoader)
+        .init_asset::<TalkData>()
+        .register_type::<Talk>()
+        .register_type::<TalkMetadata>()
+        .register_type::<Actor>()
+        .register_type::<ActorVoice>()
+        .register_type::<CurrentNode>()
+        .register_type::<HasStarted>()
+        .register_type::<HasEnded>()
+        .register_type::<AwaitingChoice>()
+        .register_type::<AtEnd>()
+        .register_type::<ResumeNode>()
+        .register_type::<StartNode>()
+        .register_type::<EndNode>()
+        .register_type::<TextNode>()
+        .register_type::<ChoiceNode>()
+        .register_type::<Choice>()
+        .register_type::<JoinNode>()
+        .register_type::<LeaveNode>()
+        .register_type::<PlayerNode>()
+        .register_type::<OrderIndex>()
+        .register_type::<Chapter>()
+        .register_type::<Emphasis>()
+        .register_type::<Icon>()
+        .register_type::<Thought>()
+        .register_type::<TextPages>()
+        .register_type::<Caption>()
+        .register_type::<CaptionEvent>()
+        .register_type::<SpeakingOrder>()
+        .register_type::<ActorLines>()
+        .register_type::<OnEnd>()
+        .register_type::<ActorAliases>()
+        .register_type::<SingleChoicePolicy>()
+        .register_type::<RefirePolicy>()
+        .register_type::<CustomNode>()
+        .register_type::<TalkId>()
+        .register_type::<SwitchNode>()
+        .register_type::<RandomNode>()
+        .register_type::<TalkRng>()
+        .register_type::<MinDisplayTime>()
+        .register_type::<AdvanceTooSoonEvent>()
+        .register_type::<ChoiceTimeout>()
+        .register_type::<ChoiceCountdown>()
+        .register_type::<ChoiceTimerTick>()
+        .register_type::<ChoiceTimedOutEvent>()
+        .init_resource::<ChoiceFilters>()
+        .init_resource::<CustomNodeKinds>()
+        .init_resource::<BuildPasses>()
+        .init_resource::<TalkDisplays>()
+        .init_resource::<TextFilters>()
+        .init_resource::<SingleChoicePolicy>()
+        .init_resource::<CurrentNodes>()
+        .init_resource::<MissingActorPolicy>()
+        .init_resource::<ChoiceLockout>()
+        .init_resource::<ChoiceLocks>()
+        .init_resource::<RefirePolicy>()
+        .init_resource::<LastRefires>()
+        .init_resource::<NodeEnteredTimes>()
+        .init_resource::<TalkStates>()
+        .init_resource::<TakenChoices>()
+        .init_resource::<TalkPreloads>()
+        .init_resource::<TalkPool>()
+        .init_resource::<TalkPoolPolicy>()
+        .init_resource::<TalkVariables>()
+        .init_resource::<CaptionSettings>()
+        .init_resource::<TalkPersistenceStore>()
+        .add_event::<TalkAssetsReady>()
+        .add_event::<TalkSpawned>()
+        .add_event::<AdvanceTooSoonEvent>()
+        .add_event::<ChoiceTimerTick>()
+        .add_event::<ChoiceTimedOutEvent>()
+        .add_event::<CaptionEvent>()
+        .add_event::<SkippedChoice>()
+        .add_systems(
+            Update,
+            (
+                (check_talk_preloads, spawn_ready_talks).chain(),
+                spawn_loaded_talks,
+            ),
+        )
+        .configure_sets(schedule.clone(), TalksSet)
+        .add_systems(
+            schedule.clone(),
+            (
+                next_handler.pipe(error_logger).after(choice_handler),
+                choice_handler.pipe(error_logger),
+                refire_handler.pipe(error_logger),
+                on_end_handler
+                    .after(next_handler)
+                    .after(choice_handler)
+                    .after(refire_handler),
+                clean_interjections
+                    .after(next_handler)
+                    .after(choice_handler),
+                sync_current_nodes
+                    .before(next_handler)
+                    .before(choice_handler)
+                    .before(refire_handler),
+                expire_choice_locks
+                    .before(next_handler)
+                    .before(choice_handler),
+                expand_lazy_branches
+                    .after(on_end_handler)
+                    .after(clean_interjections),
+                record_talk_states.after(on_end_handler),
+                record_taken_choices.after(choice_handler),
+                record_node_entered_times.after(on_end_handler),
+                forget_entered_times.after(on_end_handler),
+                forget_last_refires.after(on_end_handler),
+                tick_choice_timers.after(record_node_entered_times),
+                update_advance_markers.after(expand_lazy_branches),
+            )
+                .in_set(TalksSet),
+        )
+        .add_systems(schedule.clone(), write_talk_persistence.after(TalksSet))
+        .add_systems(schedule, forward_to_displays.after(TalksSet));
+
+    #[cfg(feature = "json")]
+    app.register_asset_loader(json_loader::loader::JsonTalksLoader);
+    #[cfg(feature = "yaml")]
+    app.register_asset_loader(yaml_loader::loader::YamlTalksLoader);
+}
+
+/// Updates the request events, in place of the `First` update when the traversal runs in a custom schedule.
+fn update_requests<R: Event>(mut requests: ResMut<Events<R>>) {
+    requests.update();
+}
+
 /// The `SystemSet` for the `TalksPlugin`.
 #[derive(SystemSet, Debug, Default, Clone, PartialEq, Eq, Hash)]
 struct TalksSet;
 
-/// Logs errors from the other systems.
-fn error_logger(In(result): In<Result<(), NextActionError>>) {
-    if let Err(err) = result {
-        error!("Error: {err}");
-    }
-}
-
-/// Handles the `RefireNodeRequest` events. It will emit the events in the current node.
-fn refire_handler(
-    mut cmd: Commands,
-    mut reqs: EventReader<RefireNodeRequest>,
-    current_nodes: Query<(Entity, &Parent), With<CurrentNode>>,
-    start: Query<Entity, With<StartNode>>,
-    end: Query<Entity, With<EndNode>>,
-    all_actors: Query<&Actor>,
-    performers: Query<Relations<PerformedBy>>,
-    emitters: Query<&dyn NodeEventEmitter>,
-    type_registry: Res<AppTypeRegistry>,
-    mut start_ev_writer: EventWriter<StartEvent>,
-    mut end_ev_writer: EventWriter<EndEvent>,
-) -> Result<(), NextActionError> {
-    if let Some(event) = reqs.read().next() {
-        for (current_node, talk_parent) in &current_nodes {
-            let this_talk = talk_parent.get();
-            // if this is the talk we want to advance
-            if this_talk == event.talk {
-                // send start event if we are at the start node
-                maybe_emit_start_event(&start, current_node, &mut start_ev_writer, event.talk);
-
-                // send end event if current node is an end node
-                maybe_emit_end_event(&end, current_node, &mut end_ev_writer, event.talk);
-
-                // grab the actors in the next node
-                let actors_in_node = retrieve_actors(&performers, current_node, &all_actors);
-
-                // emit the events in current node
-                emit_events(
-                    &mut cmd,
-                    &emitters,
-                    current_node,
-                    &type_registry,
-                    actors_in_node,
-                );
-                return Ok(());
-            }
-        }
-        return Err(NextActionError::NoTalk);
+/// Logs the errors of the failed requests from the request handlers.
+fn error_logger(In(errors): In<Vec<NextActionError>>, config: Res<TalksConfig>) {
+    for err in errors {
+        talks_log!(
+            Some(&*config),
+            LogCategory::Traversal,
+            Level::ERROR,
+            "Error: {err}"
+        );
     }
-    Ok(())
 }
 
 /// Emits the start event if the current node is a start node.
@@ -128,51 +338,284 @@ pub(crate) fn maybe_emit_end_event(
     }
 }
 
-/// Retrieves the actors connected to the given node.
+/// Emits the chapter events if the current and next nodes belong to different chapters.
 #[inline]
-pub(crate) fn retrieve_actors(
-    performers: &Query<Relations<PerformedBy>>,
+pub(crate) fn maybe_emit_chapter_events(
+    chapters: &Query<&Chapter>,
+    current_node: Entity,
     next_node: Entity,
-    all_actors: &Query<&Actor>,
-) -> Vec<Actor> {
-    let mut actors_in_node = Vec::<Actor>::new();
-    if let Ok(actor_edges) = &performers.get(next_node) {
-        for actor in actor_edges.targets(PerformedBy) {
-            actors_in_node.push(all_actors.get(*actor).expect("Actor").clone());
+    chapter_start_writer: &mut EventWriter<ChapterStartedEvent>,
+    chapter_end_writer: &mut EventWriter<ChapterEndedEvent>,
+    requested_talk: Entity,
+) {
+    let current_chapter = chapters.get(current_node).ok();
+    let next_chapter = chapters.get(next_node).ok();
+    if current_chapter == next_chapter {
+        return;
+    }
+
+    if let Some(Chapter(chapter)) = current_chapter {
+        chapter_end_writer.send(ChapterEndedEvent {
+            talk: requested_talk,
+            chapter: chapter.clone(),
+        });
+    }
+    if let Some(Chapter(chapter)) = next_chapter {
+        chapter_start_writer.send(ChapterStartedEvent {
+            talk: requested_talk,
+            chapter: chapter.clone(),
+        });
+    }
+}
+
+/// System param to retrieve the actors performing the nodes, taking care of the despawned actors.
+#[derive(SystemParam)]
+pub(crate) struct NodeActors<'w, 's> {
+    /// All the actors, with their entity name to use when the actor name is empty.
+    all_actors: Query<'w, 's, (&'static Actor, Option<&'static Name>)>,
+    /// The nodes with the performers relationships.
+    performers: Query<'w, 's, Relations<PerformedBy>>,
+    /// The speaking order of the multi-actor nodes.
+    orders: Query<'w, 's, &'static SpeakingOrder>,
+    /// The aliases the actors perform the nodes under.
+    aliased: Query<'w, 's, &'static AliasedActors>,
+    /// The actor aliases of the talks, with the revealed ones.
+    talk_aliases: Query<'w, 's, &'static ActorAliases>,
+    /// What to do when an actor is missing.
+    policy: Res<'w, MissingActorPolicy>,
+    /// The writer for the missing actor events.
+    missing_writer: EventWriter<'w, MissingActorEvent>,
+    /// The logs configuration.
+    config: Res<'w, TalksConfig>,
+}
+
+impl NodeActors<'_, '_> {
+    /// Retrieves the actors connected to the given node.
+    ///
+    /// Actors that were despawned are skipped and a `MissingActorEvent` is sent for each of them.
+    /// If the [`MissingActorPolicy`] is `Halt`, an error is returned instead.
+    pub(crate) fn retrieve(
+        &mut self,
+        talk: Entity,
+        node: Entity,
+    ) -> Result<Vec<Actor>, NextActionError> {
+        let mut actors_in_node = Vec::<Actor>::new();
+        if let Ok(actor_edges) = &self.performers.get(node) {
+            for actor in actor_edges.targets(PerformedBy) {
+                match self.all_actors.get(*actor) {
+                    Ok((a, name)) => {
+                        let actor = a.clone().or_entity_name(name);
+                        actors_in_node.push(self.hide_alias(talk, node, actor));
+                    }
+                    Err(_) => {
+                        talks_log!(
+                            Some(&*self.config),
+                            LogCategory::Traversal,
+                            Level::WARN,
+                            "Actor {actor:?} performing node {node:?} of talk {talk:?} not found."
+                        );
+                        self.missing_writer.send(MissingActorEvent {
+                            talk,
+                            node,
+                            actor: *actor,
+                        });
+                        if *self.policy == MissingActorPolicy::Halt {
+                            return Err(NextActionError::MissingActor {
+                                talk,
+                                node,
+                                actor: *actor,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        if let Ok(SpeakingOrder(order)) = self.orders.get(node) {
+            actors_in_node.sort_by_key(|a| order.iter().position(|slug| slug == &a.slug));
         }
+        Ok(actors_in_node)
+    }
+
+    /// Returns the actor with the alias it performs the node under as its name, if the alias is not revealed yet.
+    fn hide_alias(&self, talk: Entity, node: Entity, mut actor: Actor) -> Actor {
+        let alias = self
+            .aliased
+            .get(node)
+            .ok()
+            .and_then(|aliased| aliased.0.get(&actor.slug));
+        if let Some(alias) = alias {
+            let revealed = self
+                .talk_aliases
+                .get(talk)
+                .is_ok_and(|aliases| aliases.is_revealed(alias));
+            if !revealed {
+                actor.name = alias.clone();
+            }
+        }
+        actor
     }
-    actors_in_node
 }
 
-/// Iterates over the `NodeEventEmitter` in the current node and emits the events.
+/// Emits the events of the node with the emitters registered with `register_node_event`, then its caption.
 #[inline]
-pub(crate) fn emit_events(
-    cmd: &mut Commands,
-    emitters: &Query<&dyn NodeEventEmitter>,
-    next_node: Entity,
-    type_registry: &Res<AppTypeRegistry>,
-    actors_in_node: Vec<Actor>,
-) {
-    if let Ok(emitters) = emitters.get(next_node) {
-        let type_registry = type_registry.read();
-
-        for emitter in &emitters {
-            let emitted_event = emitter.make(&actors_in_node);
-
-            let event_type_id = emitted_event.type_id();
-            // The #[reflect] attribute we put on our event trait generated a new `ReflectEvent` struct
-            // that we can use as the event type.
-            let reflect_event = type_registry
-                .get_type_data::<ReflectEvent>(event_type_id)
-                .expect("Event not registered for event type")
-                .clone();
-
-            cmd.add(move |world: &mut World| {
-                reflect_event.send(&*emitted_event, world);
-            });
+pub(crate) fn emit_events(cmd: &mut Commands, node: Entity, actors: Vec<Actor>) {
+    cmd.add(EmitNodeEvents { node, actors });
+}
+
+/// Command to make and send the events of a node, typed, with the actors performing it.
+struct EmitNodeEvents {
+    /// The node entity.
+    node: Entity,
+    /// The actors performing the node.
+    actors: Vec<Actor>,
+}
+
+impl Command for EmitNodeEvents {
+    fn apply(self, world: &mut World) {
+        world.resource_scope(|world, emitters: Mut<NodeEventEmitters>| {
+            emitters.emit(world, self.node, &self.actors);
+        });
+        let speaker = self.actors.first().map(|a| a.name.clone());
+        send_component_caption(world, self.node, speaker);
+    }
+}
+
+/// Emits the [`TextNodeEvent`] of a text node, with the node decorations and the filtered text, and its caption.
+pub(crate) fn emit_text_event(world: &mut World, node: Entity, actors: &[Actor]) {
+    let Some(mut event) = make_node_event::<TextNode>(world, node, actors) else {
+        return;
+    };
+    event.emphasis = world.get::<Emphasis>(node).map(|e| e.0);
+    event.icon = world.get::<Icon>(node).map(|i| i.0.clone());
+    event.thought = world.get::<Thought>(node).map(|t| t.0.clone());
+    if let Some(thought) = event.thought.as_mut() {
+        apply_text_filters(world, node, thought);
+    }
+    event.is_player = world.get::<PlayerNode>(node).is_some();
+    event.lines = world
+        .get::<ActorLines>(node)
+        .map(|l| l.0.clone())
+        .unwrap_or_default();
+    // run the text filters on the raw text, so the markup is parsed from the filtered text
+    if has_text_filters(world) {
+        if let Some((raw, lines)) = filtered_node_texts(world, node) {
+            (event.text, event.timing) = parse_markup(&raw);
+            event.lines = lines;
+        }
+    }
+    // only the page shown of the paged nodes
+    if let Some(pages) = world.get::<TextPages>(node) {
+        let mut raw = pages.pages.get(pages.page).cloned().unwrap_or_default();
+        apply_text_filters(world, node, &mut raw);
+        (event.text, event.timing) = parse_markup(&raw);
+        event.lines = vec![];
+        event.page = pages.page;
+        event.pages = pages.pages.len();
+    }
+
+    // the caption of the text nodes, unless they have their own
+    let caption = world
+        .get::<Caption>(node)
+        .is_none()
+        .then(|| (event.actors.first().cloned(), event.text.clone()));
+    send_node_event(world, node, event);
+    if let Some((speaker, text)) = caption {
+        send_caption(world, node, speaker, text);
+    }
+}
+
+/// Emits the [`ChoiceNodeEvent`] of a choice node with the filtered choices,
+/// or advances with the only choice left if the [`SingleChoicePolicy`] says so.
+pub(crate) fn emit_choice_event(world: &mut World, node: Entity, actors: &[Actor]) {
+    let Some(mut event) = make_node_event::<ChoiceNode>(world, node, actors) else {
+        return;
+    };
+    apply_choice_filters(world, node, &mut event.choices);
+    let shown = ShownChoices(event.choices.iter().map(|c| c.next).collect());
+    world.entity_mut(node).insert(shown);
+    if let [choice] = event.choices.as_slice() {
+        let talk = world.get::<Parent>(node).map(|p| p.get());
+        let policy = single_choice_policy(world, node);
+        if let (Some(talk), SingleChoicePolicy::AutoSkip) = (talk, policy) {
+            world.send_event(SkippedChoice(ChooseNodeRequest::new(talk, choice.next)));
+            return;
         }
     }
+    event.icon = world.get::<Icon>(node).map(|i| i.0.clone());
+    for choice in event.choices.iter_mut() {
+        apply_text_filters(world, node, &mut choice.text);
+    }
+    send_node_event(world, node, event);
+}
+
+/// Emits the [`JoinNodeEvent`] of a join node, followed by an [`ActorJoinedEvent`] for each actor joining.
+pub(crate) fn emit_join_event(world: &mut World, node: Entity, actors: &[Actor]) {
+    if let Some(event) = make_node_event::<JoinNode>(world, node, actors) {
+        send_node_event(world, node, event);
+        send_actor_lifecycle_events(world, node, true);
+    }
+}
+
+/// Emits the [`LeaveNodeEvent`] of a leave node, followed by an [`ActorLeftEvent`] for each actor leaving.
+pub(crate) fn emit_leave_event(world: &mut World, node: Entity, actors: &[Actor]) {
+    if let Some(event) = make_node_event::<LeaveNode>(world, node, actors) {
+        send_node_event(world, node, event);
+        send_actor_lifecycle_events(world, node, false);
+    }
+}
+
+/// Emits the [`AnimateActorEvent`] of a node, with the actor entity to animate resolved from the slug.
+pub(crate) fn emit_animate_event(world: &mut World, node: Entity, actors: &[Actor]) {
+    let Some(mut event) = make_node_event::<AnimateActor>(world, node, actors) else {
+        return;
+    };
+    event.actor = find_talk_actor(world, node, &event.slug);
+    send_node_event(world, node, event);
 }
+
+/// Returns the actor entity with the given slug performing the node or, if none does,
+/// performing another node of its talk.
+fn find_talk_actor(world: &mut World, node: Entity, slug: &str) -> Option<Entity> {
+    let talk_nodes = world
+        .get::<Parent>(node)
+        .and_then(|talk| world.get::<Children>(talk.get()))
+        .map(|nodes| nodes.to_vec())
+        .unwrap_or_default();
+    let mut performers = world.query::<Relations<PerformedBy>>();
+    let world = &*world;
+    std::iter::once(node).chain(talk_nodes).find_map(|n| {
+        let edges = performers.get(world, n).ok()?;
+        edges
+            .targets(PerformedBy)
+            .iter()
+            .copied()
+            .find(|actor| world.get::<Actor>(*actor).is_some_and(|a| a.slug == slug))
+    })
+}
+
+/// Sends an [`ActorJoinedEvent`] (or an [`ActorLeftEvent`] if not `joined`) for each actor entity performing the node.
+fn send_actor_lifecycle_events(world: &mut World, node: Entity, joined: bool) {
+    let Some(talk) = world.get::<Parent>(node).map(|p| p.get()) else {
+        return;
+    };
+    let mut performers = world.query::<Relations<PerformedBy>>();
+    let actors = performers
+        .get(world, node)
+        .map(|edges| edges.targets(PerformedBy).to_vec())
+        .unwrap_or_default();
+    for actor in actors {
+        // the despawned actors are reported by the traversal with a `MissingActorEvent`
+        let Some(slug) = world.get::<Actor>(actor).map(|a| a.slug.clone()) else {
+            continue;
+        };
+        if joined {
+            world.send_event(ActorJoinedEvent { talk, actor, slug });
+        } else {
+            world.send_event(ActorLeftEvent { talk, actor, slug });
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use bevy::ecs::{
@@ -180,6 +623,8 @@ mod tests {
         system::Command,
     };
 
+    use std::any::TypeId;
+
     use indexmap::indexmap;
 
     use super::*;
@@ -230,6 +675,117 @@ mod tests {
         app
     }
 
+    /// Loads the talk asset at `path` through the asset server, from an in-memory asset source with the given files
+    /// (path => content). Returns the app once the asset is loaded or failed, with its handle.
+    #[cfg(any(feature = "json", feature = "yaml"))]
+    #[track_caller]
+    pub fn load_talk_asset(files: &[(&str, &str)], path: &str) -> (App, Handle<TalkData>) {
+        use bevy::asset::{
+            io::{
+                memory::{Dir, MemoryAssetReader},
+                AssetSource, AssetSourceId,
+            },
+            LoadState,
+        };
+
+        let dir = Dir::default();
+        for (file, content) in files {
+            dir.insert_asset_text(std::path::Path::new(file), content);
+        }
+        let mut app = App::new();
+        app.register_asset_source(
+            AssetSourceId::Default,
+            AssetSource::build()
+                .with_reader(move || Box::new(MemoryAssetReader { root: dir.clone() })),
+        )
+        .add_plugins((
+            TaskPoolPlugin::default(),
+            AssetPlugin::default(),
+            TalksPlugin,
+        ));
+
+        let handle = app.world.resource::<AssetServer>().load(path.to_string());
+        for _ in 0..10_000 {
+            app.update();
+            let state = app.world.resource::<AssetServer>().load_state(&handle);
+            if matches!(state, LoadState::Loaded | LoadState::Failed) {
+                return (app, handle);
+            }
+        }
+        panic!("the talk asset {path} is still loading");
+    }
+
+    #[test]
+    fn actor_lifecycle_events() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default()
+            .add_actors(vec![Actor::new("bob", "Bob"), Actor::new("alice", "Alice")])
+            .join(&["bob".to_string(), "alice".to_string()])
+            .leave(&["bob".to_string()]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        let mut actors = app.world.query::<(Entity, &Actor)>();
+        let bob = actors
+            .iter(&app.world)
+            .find(|(_, a)| a.slug == "bob")
+            .unwrap()
+            .0;
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let evs = app.world.resource::<Events<ActorJoinedEvent>>();
+        let joined = evs.get_reader().read(evs).cloned().collect::<Vec<_>>();
+        let slugs = joined.iter().map(|e| e.slug.as_str()).collect::<Vec<_>>();
+        assert_eq!(slugs.len(), 2);
+        assert!(slugs.contains(&"bob") && slugs.contains(&"alice"));
+        assert!(joined.iter().all(|e| e.talk == talk));
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let evs = app.world.resource::<Events<ActorLeftEvent>>();
+        let left = evs.get_reader().read(evs).cloned().collect::<Vec<_>>();
+        assert_eq!(
+            left,
+            [ActorLeftEvent {
+                talk,
+                actor: bob,
+                slug: "bob".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn built_in_types_registered_for_reflection() {
+        let app = talks_minimal_app();
+        let registry = app.world.resource::<AppTypeRegistry>().read();
+        let registration = |type_id| registry.get(type_id).expect("Registered");
+
+        for component in [
+            TypeId::of::<Talk>(),
+            TypeId::of::<Actor>(),
+            TypeId::of::<ChoiceNode>(),
+            TypeId::of::<TextNode>(),
+            TypeId::of::<CurrentNode>(),
+            TypeId::of::<StartNode>(),
+            TypeId::of::<TalkId>(),
+        ] {
+            assert!(registration(component).data::<ReflectComponent>().is_some());
+            assert!(registration(component).data::<ReflectDefault>().is_some());
+        }
+        for event in [
+            TypeId::of::<NextNodeRequest>(),
+            TypeId::of::<ChooseNodeRequest>(),
+            TypeId::of::<StartEvent>(),
+            TypeId::of::<EndEvent>(),
+            TypeId::of::<CurrentNodeChanged>(),
+            TypeId::of::<ChoiceSelectedEvent>(),
+            TypeId::of::<TextNodeEvent>(),
+        ] {
+            assert!(registration(event).data::<ReflectEvent>().is_some());
+        }
+        assert!(registry.get(TypeId::of::<Choice>()).is_some());
+    }
+
     #[test]
     fn refire_request_sends_events() {
         let script = indexmap! {
@@ -246,4 +802,56 @@ mod tests {
         let evs = app.world.resource::<Events<TextNodeEvent>>();
         assert_eq!(evs.get_reader().read(evs).len(), 2);
     }
+
+    #[test]
+    fn fixed_update_traversal_keeps_requests() {
+        let mut app = App::new();
+        app.add_plugins((
+            AssetPlugin::default(),
+            TalksPlugin::in_schedule(FixedUpdate),
+        ));
+        let script = indexmap! {
+            0 => Action { text: "Hello".to_string(), next: Some(1), ..default() },
+            1 => Action { text: "Bye".to_string(), ..default() },
+        };
+        let builder = TalkBuilder::default().fill_with_talk_data(&TalkData::new(script, vec![]));
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        let (start, _) = single::<(Entity, With<StartNode>)>(&mut app.world);
+
+        // the fixed schedule does not run in these frames, the request must not be dropped
+        app.world.send_event(NextNodeRequest::new(talk));
+        for _ in 0..3 {
+            app.update();
+        }
+        let (current, _) = single::<(Entity, With<CurrentNode>)>(&mut app.world);
+        assert_eq!(current, start);
+
+        app.world.run_schedule(FixedUpdate);
+        assert_eq!(
+            get_comp::<TextNode>(current_node(&mut app), &mut app.world).0,
+            "Hello"
+        );
+        let evs = app.world.resource::<Events<TextNodeEvent>>();
+        assert_eq!(evs.get_reader().read(evs).len(), 1);
+
+        // the request is handled only once
+        app.world.run_schedule(FixedUpdate);
+        assert_eq!(
+            get_comp::<TextNode>(current_node(&mut app), &mut app.world).0,
+            "Hello"
+        );
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.world.run_schedule(FixedUpdate);
+        assert_eq!(
+            get_comp::<TextNode>(current_node(&mut app), &mut app.world).0,
+            "Bye"
+        );
+    }
+
+    /// Returns the current node of the single talk of the app.
+    fn current_node(app: &mut App) -> Entity {
+        single::<(Entity, With<CurrentNode>)>(&mut app.world).0
+    }
 }
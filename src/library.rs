@@ -0,0 +1,206 @@
+//! The talk library, to load all the talks of a folder at startup and get their handles by name.
+//!
+//! See [`TalksPlugin::with_folder`].
+
+use std::path::Path;
+
+use bevy::{asset::LoadedFolder, log::Level, prelude::*, utils::HashMap};
+
+use crate::config::{talks_log, LogCategory, TalksConfig};
+use crate::prelude::{CurrentNodes, TalkData, TalksPlugin};
+
+impl TalksPlugin {
+    /// Returns a plugin that loads all the talk files under the given asset folder at startup
+    /// into the [`TalkLibrary`], sending a [`TalkLibraryReady`] event once they are all loaded.
+    ///
+    /// Use it instead of `TalksPlugin`: it adds the `TalksPlugin` if it's not in the app yet.
+    /// Add it once per folder to load several folders into the same library.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use bevy::prelude::*;
+    /// use bevy_talks::prelude::*;
+    ///
+    /// App::new()
+    ///     .add_plugins((DefaultPlugins, TalksPlugin::with_folder("dialogues")))
+    ///     .run();
+    /// ```
+    pub fn with_folder(folder: impl Into<String>) -> TalkFolderPlugin {
+        TalkFolderPlugin {
+            folder: folder.into(),
+        }
+    }
+}
+
+/// Plugin that loads the talks of a folder into the [`TalkLibrary`], see [`TalksPlugin::with_folder`].
+pub struct TalkFolderPlugin {
+    /// The asset folder to load.
+    folder: String,
+}
+
+impl Plugin for TalkFolderPlugin {
+    fn build(&self, app: &mut App) {
+        // the talks plugin may have been added with a custom schedule, check for its resources
+        if !app.world.contains_resource::<CurrentNodes>() {
+            app.add_plugins(TalksPlugin);
+        }
+        if !app.world.contains_resource::<TalkFolders>() {
+            app.init_resource::<TalkFolders>()
+                .init_resource::<TalkLibrary>()
+                .add_event::<TalkLibraryReady>()
+                .add_systems(Startup, load_talk_folders)
+                .add_systems(Update, fill_talk_library);
+        }
+        app.world
+            .resource_mut::<TalkFolders>()
+            .pending
+            .push(self.folder.clone());
+    }
+
+    fn is_unique(&self) -> bool {
+        false
+    }
+}
+
+/// Resource with the talks loaded from the folders (name => handle).
+///
+/// The name of a talk is its path relative to the folder, without the extensions:
+/// `dialogues/town/bob.talk.ron` is `"town/bob"` in the library of the `dialogues` folder.
+#[derive(Resource, Default, Debug)]
+pub struct TalkLibrary {
+    /// The handles of the talks by name.
+    talks: HashMap<String, Handle<TalkData>>,
+}
+
+impl TalkLibrary {
+    /// Returns the handle of the talk with the given name, if any.
+    pub fn get(&self, name: &str) -> Option<&Handle<TalkData>> {
+        self.talks.get(name)
+    }
+
+    /// Returns the names of the talks in the library.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.talks.keys().map(String::as_str)
+    }
+
+    /// Adds a talk to the library, replacing the one with the same name.
+    pub fn insert(&mut self, name: impl Into<String>, handle: Handle<TalkData>) {
+        self.talks.insert(name.into(), handle);
+    }
+
+    /// Returns the number of talks in the library.
+    pub fn len(&self) -> usize {
+        self.talks.len()
+    }
+
+    /// Returns whether the library is empty.
+    pub fn is_empty(&self) -> bool {
+        self.talks.is_empty()
+    }
+}
+
+/// Event sent when the talks of a folder have been loaded and added to the [`TalkLibrary`].
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct TalkLibraryReady {
+    /// The loaded folder.
+    pub folder: String,
+}
+
+/// The folders to load into the library.
+#[derive(Resource, Default)]
+struct TalkFolders {
+    /// The folders to start loading.
+    pending: Vec<String>,
+    /// The folders being loaded, with their handle.
+    loading: Vec<(String, Handle<LoadedFolder>)>,
+}
+
+/// Starts loading the folders added with [`TalksPlugin::with_folder`].
+fn load_talk_folders(mut folders: ResMut<TalkFolders>, server: Res<AssetServer>) {
+    let pending = std::mem::take(&mut folders.pending);
+    for folder in pending {
+        let handle = server.load_folder(folder.clone());
+        folders.loading.push((folder, handle));
+    }
+}
+
+/// Fills the [`TalkLibrary`] with the talks of the loaded folders and sends the [`TalkLibraryReady`] events.
+fn fill_talk_library(
+    mut folders: ResMut<TalkFolders>,
+    mut asset_events: EventReader<AssetEvent<LoadedFolder>>,
+    loaded_folders: Res<Assets<LoadedFolder>>,
+    mut library: ResMut<TalkLibrary>,
+    mut ready_events: EventWriter<TalkLibraryReady>,
+    config: Res<TalksConfig>,
+) {
+    for event in asset_events.read() {
+        let AssetEvent::LoadedWithDependencies { id } = event else {
+            continue;
+        };
+        let Some(index) = folders.loading.iter().position(|(_, h)| h.id() == *id) else {
+            continue;
+        };
+        let (folder, handle) = folders.loading.remove(index);
+        let Some(loaded) = loaded_folders.get(&handle) else {
+            continue;
+        };
+
+        for talk in loaded.handles.iter().cloned() {
+            let Ok(talk) = talk.try_typed::<TalkData>() else {
+                continue;
+            };
+            if let Some(name) = talk.path().and_then(|p| talk_name(&folder, p.path())) {
+                library.insert(name, talk);
+            }
+        }
+        talks_log!(
+            Some(&*config),
+            LogCategory::Build,
+            Level::INFO,
+            "Talk library ready for folder {folder}: {} talks",
+            library.len()
+        );
+        ready_events.send(TalkLibraryReady { folder });
+    }
+}
+
+/// Returns the library name of a talk file: its path relative to the folder, without the extensions.
+fn talk_name(folder: &str, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(folder).unwrap_or(path);
+    let file_name = relative.file_name()?.to_str()?;
+    let stem = file_name.split('.').next()?;
+    let name = relative.with_file_name(stem);
+    Some(name.to_str()?.replace('\\', "/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use crate::tests::talks_minimal_app;
+
+    use super::*;
+
+    #[rstest]
+    #[case("dialogues", "dialogues/bob.talk.ron", "bob")]
+    #[case("dialogues", "dialogues/town/bob.talk.ron", "town/bob")]
+    #[case("dialogues", "dialogues/intro.twee", "intro")]
+    #[case("dialogues/", "dialogues/shop.talk.csv", "shop")]
+    fn talk_names(#[case] folder: &str, #[case] path: &str, #[case] expected: &str) {
+        assert_eq!(talk_name(folder, Path::new(path)).unwrap(), expected);
+    }
+
+    #[test]
+    fn folders_are_queued() {
+        let mut app = talks_minimal_app();
+        app.add_plugins((
+            TalksPlugin::with_folder("dialogues"),
+            TalksPlugin::with_folder("barks"),
+        ));
+
+        assert!(app.world.contains_resource::<TalkLibrary>());
+        assert!(app.world.contains_resource::<Events<TalkLibraryReady>>());
+        let folders = app.world.resource::<TalkFolders>();
+        assert_eq!(folders.pending, vec!["dialogues", "barks"]);
+    }
+}
@@ -0,0 +1,231 @@
+//! Localized texts of the talks, loaded from RON sidecar files and selected with the
+//! [`TalksLocale`](crate::prelude::TalksLocale) resource when the talks are built.
+//!
+//! A RON talk lists its locales and the loader reads the sidecar file of each one, next to the talk file:
+//! `dialogue.talk.ron` with `locales: ["fr"]` reads `dialogue.talk.fr.ron`.
+//! The sidecars only have the texts, by action id, so the structure of the talk stays in the base file:
+//! ```ron
+//! (script: [
+//!     (id: 1, text: Some("Bonjour !")),
+//!     (id: 2, choices: Some(["Oui", "Non"])),
+//! ])
+//! ```
+//! The actions missing from a sidecar keep the texts of the base file.
+
+use bevy::{asset::ReadAssetBytesError, prelude::*, utils::HashMap};
+use indexmap::IndexMap;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    builder::{for_each_build_node_mut, NodeLocation, TalkBuilder},
+    prelude::TalksLocale,
+    talk_asset::{Action, ActionId},
+    TextNode,
+};
+
+/// The error type for the localization sidecar files.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum LocalizationError {
+    /// The sidecar file could not be read
+    #[error("Could not read the file: {0}")]
+    Read(#[from] ReadAssetBytesError),
+    /// A [RON Error](serde_ron::error::SpannedError)
+    #[error("Could not parse RON: {0}")]
+    RonError(#[from] serde_ron::error::SpannedError),
+    /// The sidecar has texts for an action that is not in the talk
+    #[error("The action {0} is not in the talk")]
+    UnknownAction(usize),
+    /// The sidecar has a different number of choices than the choice action
+    #[error("The action {id} has {expected} choices, the localized ones are {found}")]
+    ChoiceCount {
+        /// The id of the choice action.
+        id: usize,
+        /// The number of choices of the action.
+        expected: usize,
+        /// The number of localized choices.
+        found: usize,
+    },
+}
+
+/// The localized texts of an action in a sidecar file.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct LocalizedAction {
+    /// The ID of the action.
+    id: ActionId,
+    /// The localized text of the action.
+    #[serde(default)]
+    text: Option<String>,
+    /// The localized texts of the choices of the action, in order.
+    #[serde(default)]
+    choices: Option<Vec<String>>,
+}
+
+/// The content of a sidecar file.
+#[derive(Debug, Default, Deserialize)]
+struct RonLocalization {
+    /// The localized actions.
+    #[serde(default)]
+    script: Vec<LocalizedAction>,
+}
+
+/// The localized texts of a talk for a locale (action id => texts).
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TalkLocalization(HashMap<ActionId, LocalizedAction>);
+
+impl TalkLocalization {
+    /// Parses a sidecar file and checks it against the script of the talk.
+    pub(crate) fn from_ron(
+        bytes: &[u8],
+        script: &IndexMap<ActionId, Action>,
+    ) -> Result<Self, LocalizationError> {
+        let ron_localization = serde_ron::de::from_bytes::<RonLocalization>(bytes)?;
+        let mut actions = HashMap::with_capacity(ron_localization.script.len());
+        for localized in ron_localization.script {
+            let Some(action) = script.get(&localized.id) else {
+                return Err(LocalizationError::UnknownAction(localized.id));
+            };
+            if let Some(choices) = &localized.choices {
+                if choices.len() != action.choices.len() {
+                    return Err(LocalizationError::ChoiceCount {
+                        id: localized.id,
+                        expected: action.choices.len(),
+                        found: choices.len(),
+                    });
+                }
+            }
+            actions.insert(localized.id, localized);
+        }
+        Ok(Self(actions))
+    }
+
+    /// Replaces the texts of the builder nodes created from the localized actions, branches included.
+    fn apply(&self, builder: &mut TalkBuilder) {
+        for_each_build_node_mut(&mut builder.queue, |node| {
+            let NodeLocation::Action(id) = node.location else {
+                return;
+            };
+            let Some(localized) = self.0.get(&id) else {
+                return;
+            };
+            if let Some(text) = &localized.text {
+                node.components
+                    .iter_mut()
+                    .filter_map(|c| c.downcast_mut::<TextNode>())
+                    .for_each(|t| t.0 = text.clone());
+            }
+            if let Some(choices) = &localized.choices {
+                for (choice, text) in node.choices.iter_mut().zip(choices) {
+                    choice.text = text.clone();
+                }
+            }
+        });
+    }
+}
+
+/// Replaces the texts of the builder nodes with the ones of the current [`TalksLocale`], if the talk has them.
+pub(crate) fn localize_builder(builder: &mut TalkBuilder, world: &World) {
+    let Some(locale) = world.get_resource::<TalksLocale>() else {
+        return;
+    };
+    let locales = builder.locales.clone();
+    if let Some(localization) = locales.get(&locale.0) {
+        localization.apply(builder);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::Command;
+    use indexmap::indexmap;
+
+    use crate::{
+        prelude::*,
+        tests::{single, talks_minimal_app},
+    };
+
+    use super::*;
+
+    /// A talk with a line and a choice, with a french sidecar.
+    fn localized_talk() -> TalkData {
+        let script = indexmap! {
+            1 => Action { text: "Hello!".to_string(), next: Some(2), ..default() },
+            2 => Action {
+                kind: NodeKind::Choice,
+                choices: vec![
+                    ChoiceData { text: "Yes".to_string(), next: 3, ..default() },
+                    ChoiceData { text: "No".to_string(), next: 3, ..default() },
+                ],
+                ..default()
+            },
+            3 => Action { text: "Bye!".to_string(), ..default() },
+        };
+        let sidecar = r#"(script: [(id: 1, text: Some("Bonjour !")), (id: 2, choices: Some(["Oui", "Non"]))])"#;
+        let french = TalkLocalization::from_ron(sidecar.as_bytes(), &script).unwrap();
+        let mut talk_data = TalkData::new(script, vec![]);
+        talk_data.locales = [("fr".to_string(), french)]
+            .into_iter()
+            .collect::<HashMap<_, _>>()
+            .into();
+        talk_data
+    }
+
+    /// Builds the talk and returns the texts of its text nodes and choices.
+    fn build_texts(locale: Option<&str>) -> (Vec<String>, Vec<String>) {
+        let mut app = talks_minimal_app();
+        if let Some(locale) = locale {
+            app.insert_resource(TalksLocale(locale.to_string()));
+        }
+        let builder = TalkBuilder::default().fill_with_talk_data(&localized_talk());
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        let mut texts = app
+            .world
+            .query::<&TextNode>()
+            .iter(&app.world)
+            .map(|t| t.0.clone())
+            .collect::<Vec<_>>();
+        texts.sort();
+        let choices = single::<&ChoiceNode>(&mut app.world)
+            .0
+            .iter()
+            .map(|c| c.text.clone())
+            .collect();
+        (texts, choices)
+    }
+
+    #[test]
+    fn locale_texts_replace_base_ones() {
+        let (texts, choices) = build_texts(Some("fr"));
+        assert_eq!(texts, vec!["Bonjour !", "Bye!"]);
+        assert_eq!(choices, vec!["Oui", "Non"]);
+    }
+
+    #[test]
+    fn base_texts_without_locale() {
+        for locale in [None, Some("de")] {
+            let (texts, choices) = build_texts(locale);
+            assert_eq!(texts, vec!["Bye!", "Hello!"]);
+            assert_eq!(choices, vec!["Yes", "No"]);
+        }
+    }
+
+    #[test]
+    fn invalid_sidecar() {
+        let script = localized_talk().script;
+        let err = TalkLocalization::from_ron(br#"(script: [(id: 7, text: Some("?"))])"#, &script);
+        assert!(matches!(err, Err(LocalizationError::UnknownAction(7))));
+        let err =
+            TalkLocalization::from_ron(br#"(script: [(id: 2, choices: Some(["Oui"]))])"#, &script);
+        assert!(matches!(
+            err,
+            Err(LocalizationError::ChoiceCount {
+                id: 2,
+                expected: 2,
+                found: 1
+            })
+        ));
+    }
+}
@@ -0,0 +1,194 @@
+//! Lockout of the talk requests right after a choice, to prevent accidental double-advances
+//! (e.g. a double click on a choice button skipping the following line).
+
+use std::time::Duration;
+
+use bevy::{ecs::system::SystemParam, log::Level, prelude::*, utils::HashMap};
+
+use crate::config::{talks_log, LogCategory, TalksConfig};
+
+/// Resource to configure the lockout of the talks after a `ChooseNodeRequest` is processed.
+///
+/// While a talk is locked, its next and choose requests are ignored with a warning.
+/// It is `Off` by default.
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// let mut app = App::new();
+/// app.insert_resource(ChoiceLockout::Duration(Duration::from_millis(250)));
+/// ```
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChoiceLockout {
+    /// The requests are never locked out.
+    #[default]
+    Off,
+    /// The requests are ignored until the next update, so the events of the chosen node
+    /// have a frame to be consumed before the talk can move again.
+    Frame,
+    /// The requests are ignored for the given (real) time.
+    /// Without the `Time<Real>` resource (no `TimePlugin`) it works like `Frame`.
+    Duration(Duration),
+}
+
+/// How long a talk stays locked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Lock {
+    /// Until the elapsed real time reaches the given one.
+    Until(Duration),
+    /// For the given number of updates.
+    Updates(u8),
+}
+
+/// The talks currently locked out.
+#[derive(Resource, Debug, Default)]
+pub(crate) struct ChoiceLocks(HashMap<Entity, Lock>);
+
+/// The requests of the user checked against the lockout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LockedRequest {
+    /// A `NextNodeRequest`.
+    Next,
+    /// A `ChooseNodeRequest`.
+    Choose,
+}
+
+/// System param used by the request handlers to check and set the locks.
+#[derive(SystemParam)]
+pub(crate) struct LockoutGuard<'w> {
+    /// The lockout configuration.
+    lockout: Res<'w, ChoiceLockout>,
+    /// The locked talks.
+    locks: ResMut<'w, ChoiceLocks>,
+    /// The real time, for the timed locks.
+    time: Option<Res<'w, Time<Real>>>,
+    /// The config of the logs.
+    config: Res<'w, TalksConfig>,
+}
+
+impl LockoutGuard<'_> {
+    /// Returns whether the talk is locked out, logging a warning about the ignored request if it is.
+    pub(crate) fn is_locked(&self, talk: Entity, request: LockedRequest) -> bool {
+        let locked = self.locks.0.contains_key(&talk);
+        if locked {
+            let request = match request {
+                LockedRequest::Next => "next",
+                LockedRequest::Choose => "choose",
+            };
+            talks_log!(
+                Some(&*self.config),
+                LogCategory::Traversal,
+                Level::WARN,
+                "Ignored {request} request for talk {talk:?}, it is locked out after a choice."
+            );
+        }
+        locked
+    }
+
+    /// Locks the talk out after a choice, according to the [`ChoiceLockout`].
+    pub(crate) fn lock(&mut self, talk: Entity) {
+        let lock = match (*self.lockout, &self.time) {
+            (ChoiceLockout::Off, _) => return,
+            (ChoiceLockout::Duration(duration), Some(time)) => {
+                Lock::Until(time.elapsed() + duration)
+            }
+            (ChoiceLockout::Frame | ChoiceLockout::Duration(_), _) => Lock::Updates(1),
+        };
+        self.locks.0.insert(talk, lock);
+    }
+}
+
+/// Removes the expired locks. It runs before the request handlers.
+pub(crate) fn expire_choice_locks(mut locks: ResMut<ChoiceLocks>, time: Option<Res<Time<Real>>>) {
+    let now = time.map(|t| t.elapsed());
+    locks.0.retain(|_, lock| match lock {
+        Lock::Until(until) => now.is_some_and(|now| now < *until),
+        Lock::Updates(0) => false,
+        Lock::Updates(updates) => {
+            *updates -= 1;
+            true
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::Command;
+
+    use crate::{
+        prelude::*,
+        tests::{get_comp, talks_minimal_app},
+    };
+
+    use super::*;
+
+    /// Returns the text of the current node of the talk.
+    fn current_text(app: &mut App, talk: Entity) -> String {
+        let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        get_comp::<TextNode>(current, &mut app.world).0.clone()
+    }
+
+    #[test]
+    fn requests_locked_for_a_frame_after_a_choice() {
+        let mut app = talks_minimal_app();
+        app.insert_resource(ChoiceLockout::Frame);
+        let builder = TalkBuilder::default()
+            .choose(vec![
+                ("A", TalkBuilder::default().say("a")),
+                ("B", TalkBuilder::default().say("b")),
+            ])
+            .say("end");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+
+        let evs = app.world.resource::<Events<ChoiceNodeEvent>>();
+        let choice = evs.get_reader().read(evs).next().unwrap().choices[0].next;
+        // the double click: the same choice and a next in the same frame
+        app.world.send_event(ChooseNodeRequest::new(talk, choice));
+        app.world.send_event(ChooseNodeRequest::new(talk, choice));
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(current_text(&mut app, talk), "a");
+
+        // still locked in the next update
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(current_text(&mut app, talk), "a");
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(current_text(&mut app, talk), "end");
+    }
+
+    #[test]
+    fn skipped_single_choice_not_locked_out() {
+        let mut app = talks_minimal_app();
+        app.insert_resource(ChoiceLockout::Frame);
+        app.insert_resource(SingleChoicePolicy::AutoSkip);
+        let builder = TalkBuilder::default().choose(vec![
+            (
+                "A",
+                TalkBuilder::default()
+                    .choose(vec![("Continue", TalkBuilder::default().say("skipped"))]),
+            ),
+            ("B", TalkBuilder::default().say("b")),
+        ]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+
+        let evs = app.world.resource::<Events<ChoiceNodeEvent>>();
+        let choice = evs.get_reader().read(evs).next().unwrap().choices[0].next;
+        app.world.send_event(ChooseNodeRequest::new(talk, choice));
+        app.update();
+        // the single choice is skipped while the talk is still locked out after the choice
+        app.update();
+        assert_eq!(current_text(&mut app, talk), "skipped");
+    }
+}
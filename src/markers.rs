@@ -0,0 +1,121 @@
+//! Marker components of the talks and of the dialogue nodes.
+//!
+//! All of them are registered for reflection by the [`TalksPlugin`](crate::TalksPlugin),
+//! so they can be queried, inserted and seen in the inspectors.
+
+use bevy::prelude::*;
+
+use crate::prelude::Choice;
+
+/// Marker component for the current node in a Talk.
+#[derive(Component, Reflect, Default, Debug)]
+#[component(storage = "SparseSet")]
+#[reflect(Component, Default)]
+pub struct CurrentNode;
+
+/// Marker component added to the `Talk` parent entity when the talk has started.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component, Default)]
+pub struct HasStarted;
+
+/// Marker component added to the `Talk` parent entity when the talk has reached an end node.
+/// A talk that ended starts over from its [`ResumeNode`], if it has one.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component, Default)]
+pub struct HasEnded;
+
+/// Marker component kept on the `Talk` parent entity while its current node is a choice node,
+/// so the UIs know to show the choice menu (a `NextNodeRequest` would fail).
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component, Default)]
+pub struct AwaitingChoice;
+
+/// Marker component kept on the `Talk` parent entity while its current node has no next node,
+/// so the UIs know to hide the "continue" indicator (a `NextNodeRequest` would fail).
+///
+/// A talk with neither `AtEnd` nor [`AwaitingChoice`] can advance with a `NextNodeRequest`.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component, Default)]
+pub struct AtEnd;
+
+/// Mark a dialogue node as the node to resume from when a talk that already ended is started again
+/// (e.g. a "what else?" hub instead of the intro). See [`TalkBuilder::resume_here`](crate::prelude::TalkBuilder::resume_here).
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component, Default)]
+pub struct ResumeNode;
+
+/// Mark a dialogue node as a starting node.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component, Default)]
+pub struct StartNode;
+
+/// Mark a dialogue node as an end node.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component, Default)]
+pub struct EndNode;
+
+/// Component to mark a dialogue node as a text node containing some text.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component, Default)]
+pub struct TextNode(pub String);
+
+/// Component to mark a dialogue node as a choice node containing some choices.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component, Default)]
+pub struct ChoiceNode(pub Vec<Choice>);
+
+/// Component to mark a dialogue node as a join node.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component, Default)]
+pub struct JoinNode;
+
+/// Component to mark a dialogue node as a leave node.
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component, Default)]
+pub struct LeaveNode;
+
+/// Mark a dialogue node as performed by the "player" pseudo-actor (see [`Actor::PLAYER_SLUG`](crate::prelude::Actor::PLAYER_SLUG)).
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component, Default)]
+pub struct PlayerNode;
+
+/// The position of a dialogue node in the build order of its talk, to sort the nodes in the inspectors and exports.
+///
+/// The start node is `0`, then the nodes follow the builder order (or the asset script order): each node comes
+/// before the nodes of its choice branches, which come before the next node of the builder.
+/// The nodes spawned later (lazy branches, interjections) are numbered after the existing nodes of the talk.
+#[derive(Component, Reflect, Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[reflect(Component, Default)]
+pub struct OrderIndex(pub u32);
+
+#[cfg(test)]
+mod tests {
+    use std::any::TypeId;
+
+    use super::*;
+    use crate::tests::talks_minimal_app;
+
+    #[test]
+    fn markers_registered() {
+        let app = talks_minimal_app();
+        let registry = app.world.resource::<AppTypeRegistry>().read();
+        for type_id in [
+            TypeId::of::<CurrentNode>(),
+            TypeId::of::<HasStarted>(),
+            TypeId::of::<HasEnded>(),
+            TypeId::of::<ResumeNode>(),
+            TypeId::of::<StartNode>(),
+            TypeId::of::<EndNode>(),
+            TypeId::of::<TextNode>(),
+            TypeId::of::<ChoiceNode>(),
+            TypeId::of::<JoinNode>(),
+            TypeId::of::<LeaveNode>(),
+            TypeId::of::<PlayerNode>(),
+            TypeId::of::<OrderIndex>(),
+        ] {
+            assert!(registry
+                .get_type_data::<ReflectComponent>(type_id)
+                .is_some());
+        }
+    }
+}
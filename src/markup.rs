@@ -0,0 +1,150 @@
+//! Inline markup of the text nodes to control the text reveal (typewriter effect).
+//!
+//! The control codes are written in curly braces inside the text:
+//! - `{pause=0.5}` pauses the reveal for 0.5 seconds at that point,
+//! - `{speed=2}` multiplies the reveal speed by 2 from that point on.
+//!
+//! They are parsed out when the `TextNodeEvent` is emitted: the event has the plain text
+//! and a [`TextTiming`] track with the positions of the codes in the plain text.
+//! Anything in curly braces that is not a valid control code is left in the text.
+
+use bevy::reflect::Reflect;
+
+/// The kind of a timing mark.
+#[derive(Reflect, Debug, Clone, Copy, PartialEq)]
+pub enum TimingKind {
+    /// Pause the reveal for the given seconds.
+    Pause(f32),
+    /// Set the reveal speed multiplier.
+    Speed(f32),
+}
+
+/// A timing mark placed before a character of the plain text.
+#[derive(Reflect, Debug, Clone, Copy, PartialEq)]
+pub struct TimingMark {
+    /// The index (in chars) of the plain text character the mark is placed before.
+    pub index: usize,
+    /// The kind of the mark.
+    pub kind: TimingKind,
+}
+
+/// The timing track parsed out of a text with markup, used to drive the text reveal.
+#[derive(Reflect, Debug, Clone, Default, PartialEq)]
+pub struct TextTiming {
+    /// The timing marks, ordered by index.
+    pub marks: Vec<TimingMark>,
+}
+
+impl TextTiming {
+    /// Returns the total pause (in seconds) to wait before revealing the character at `index`.
+    pub fn pause_before(&self, index: usize) -> f32 {
+        self.marks
+            .iter()
+            .filter(|m| m.index == index)
+            .filter_map(|m| match m.kind {
+                TimingKind::Pause(secs) => Some(secs),
+                TimingKind::Speed(_) => None,
+            })
+            .sum()
+    }
+
+    /// Returns the speed multiplier to use when revealing the character at `index` (1.0 if never set).
+    pub fn speed_at(&self, index: usize) -> f32 {
+        self.marks
+            .iter()
+            .filter(|m| m.index <= index)
+            .filter_map(|m| match m.kind {
+                TimingKind::Speed(speed) => Some(speed),
+                TimingKind::Pause(_) => None,
+            })
+            .last()
+            .unwrap_or(1.0)
+    }
+}
+
+/// Parses the control codes out of the text, returning the plain text and the timing track.
+///
+/// # Example
+/// ```rust
+/// use bevy_talks::markup::{parse_markup, TimingKind};
+///
+/// let (text, timing) = parse_markup("Well...{pause=0.5} {speed=2}I guess so.");
+/// assert_eq!(text, "Well... I guess so.");
+/// assert_eq!(timing.marks[0].index, 7);
+/// assert_eq!(timing.marks[0].kind, TimingKind::Pause(0.5));
+/// assert_eq!(timing.speed_at(10), 2.0);
+/// ```
+pub fn parse_markup(text: &str) -> (String, TextTiming) {
+    let mut plain = String::with_capacity(text.len());
+    let mut plain_len = 0;
+    let mut timing = TextTiming::default();
+
+    let mut rest = text;
+    while let Some(open) = rest.find('{') {
+        let (before, from_open) = rest.split_at(open);
+        plain.push_str(before);
+        plain_len += before.chars().count();
+
+        let code = from_open
+            .find('}')
+            .and_then(|close| Some((parse_code(&from_open[1..close])?, close)));
+        match code {
+            Some((kind, close)) => {
+                timing.marks.push(TimingMark {
+                    index: plain_len,
+                    kind,
+                });
+                rest = &from_open[close + 1..];
+            }
+            None => {
+                // not a control code, keep the brace as text
+                plain.push('{');
+                plain_len += 1;
+                rest = &from_open[1..];
+            }
+        }
+    }
+    plain.push_str(rest);
+
+    (plain, timing)
+}
+
+/// Parses a single control code (without the braces).
+fn parse_code(code: &str) -> Option<TimingKind> {
+    let (name, value) = code.split_once('=')?;
+    let value = value.trim().parse::<f32>().ok()?;
+    match name.trim() {
+        "pause" => Some(TimingKind::Pause(value)),
+        "speed" => Some(TimingKind::Speed(value)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("Hello", "Hello", 0)]
+    #[case("{pause=1}Hello", "Hello", 1)]
+    #[case("He{speed=0.5}llo{pause=2}", "Hello", 2)]
+    #[case("Hello {name}", "Hello {name}", 0)]
+    #[case("{pause=nope}{", "{pause=nope}{", 0)]
+    #[case("Ciaò{pause=1}!", "Ciaò!", 1)]
+    fn markup_is_stripped(#[case] text: &str, #[case] plain: &str, #[case] marks: usize) {
+        let (parsed, timing) = parse_markup(text);
+        assert_eq!(parsed, plain);
+        assert_eq!(timing.marks.len(), marks);
+    }
+
+    #[test]
+    fn timing_track_positions() {
+        let (_, timing) = parse_markup("Ciaò{pause=0.5}{pause=0.25} {speed=3}bello");
+        assert_eq!(timing.pause_before(4), 0.75);
+        assert_eq!(timing.pause_before(5), 0.0);
+        assert_eq!(timing.speed_at(4), 1.0);
+        assert_eq!(timing.speed_at(5), 3.0);
+    }
+}
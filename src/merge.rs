@@ -0,0 +1,285 @@
+//! Graph rewriting utilities to merge talks and remap their actors at runtime.
+//!
+//! They work on the [`TalkIr`], so a mod can extend a talk of the base game with the branches declared
+//! in its own files: convert both [`TalkData`](crate::prelude::TalkData) assets to the IR, merge them,
+//! inject the new choices and add the result back as a new asset.
+//!
+//! ```rust
+//! use bevy::prelude::*;
+//! use bevy_talks::prelude::*;
+//!
+//! fn extend_talk(base: &TalkData, extension: &TalkData) -> Result<TalkData, IrError> {
+//!     let mut ir = TalkIr::from(base);
+//!     let merged = ir.merge(TalkIr::from(extension))?;
+//!     // add a choice to the choice node 3 of the base talk, going to the extension talk
+//!     if let Some(entry) = merged.entry() {
+//!         ir.inject_choice(3, IrChoice { text: "Ask about the dragon".to_string(), next: entry, description: None, sound: None, key: None })?;
+//!     }
+//!     TalkData::try_from(ir)
+//! }
+//! ```
+
+use bevy::utils::HashMap;
+
+use crate::prelude::{ActorSlug, IrChoice, IrError, IrNodeId, NodeKind, TalkIr};
+
+/// How the node ids of a talk merged with [`TalkIr::merge`] were namespaced.
+///
+/// The ids of the merged talk are shifted past the ones of the talk it was merged into,
+/// use [`id`](MergedIds::id) to get the new id of one of its nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergedIds {
+    /// The offset added to the ids of the merged talk.
+    offset: IrNodeId,
+    /// The new id of the entry node of the merged talk, if it had nodes.
+    entry: Option<IrNodeId>,
+}
+
+impl MergedIds {
+    /// Returns the id in the merged IR of the node with the given id in the merged talk.
+    pub fn id(&self, id: IrNodeId) -> IrNodeId {
+        id + self.offset
+    }
+
+    /// Returns the id in the merged IR of the entry node of the merged talk (its first node,
+    /// or the one after it if it is a start node), if it had nodes.
+    pub fn entry(&self) -> Option<IrNodeId> {
+        self.entry
+    }
+}
+
+impl TalkIr {
+    /// Renames the actor slugs (old slug => new slug), both in the actors and in the nodes performed by them.
+    /// The slugs not in the map are kept.
+    ///
+    /// It can be used to point the actors of a talk to the ones of another talk before merging them,
+    /// or to resolve the slugs clashing between them.
+    pub fn remap_actors(&mut self, map: &HashMap<ActorSlug, ActorSlug>) {
+        let remap = |slug: &mut ActorSlug| {
+            if let Some(new_slug) = map.get(slug) {
+                *slug = new_slug.clone();
+            }
+        };
+        self.actors.iter_mut().for_each(|a| remap(&mut a.slug));
+        self.aliases.values_mut().for_each(remap);
+        for node in self.nodes.iter_mut() {
+            node.actors.iter_mut().for_each(remap);
+        }
+    }
+
+    /// Appends the nodes, actors and aliases of the `other` talk, so its nodes can be reached from this talk
+    /// (e.g. with [`inject_choice`](TalkIr::inject_choice)). The metadata of this talk is kept.
+    ///
+    /// The node ids of the other talk are namespaced (shifted past the ids of this talk) to avoid clashes,
+    /// the returned [`MergedIds`] maps them to their new ids. The actors with the same slug in both talks
+    /// must be the same actor, remap them with [`remap_actors`](TalkIr::remap_actors) otherwise.
+    ///
+    /// Both talks are validated before merging. On error this talk is left untouched.
+    pub fn merge(&mut self, other: TalkIr) -> Result<MergedIds, IrError> {
+        self.validate()?;
+        other.validate()?;
+        for actor in other.actors.iter() {
+            if self
+                .actors
+                .iter()
+                .any(|a| a.slug == actor.slug && a != actor)
+            {
+                return Err(IrError::ConflictingActor(actor.slug.clone()));
+            }
+        }
+        for (alias, slug) in other.aliases.iter() {
+            if self.aliases.get(alias).is_some_and(|s| s != slug) {
+                return Err(IrError::ConflictingActor(alias.clone()));
+            }
+        }
+
+        let offset = self
+            .nodes
+            .iter()
+            .map(|n| n.id + 1)
+            .max()
+            .unwrap_or_default();
+        let merged = MergedIds {
+            offset,
+            entry: other.nodes.first().and_then(|first| match first.kind {
+                NodeKind::Start => first.next.map(|next| next + offset),
+                _ => Some(first.id + offset),
+            }),
+        };
+
+        for actor in other.actors {
+            if !self.actors.contains(&actor) {
+                self.actors.push(actor);
+            }
+        }
+        self.aliases.extend(other.aliases);
+        for mut node in other.nodes {
+            node.id = merged.id(node.id);
+            node.next = node.next.map(|next| merged.id(next));
+            for choice in node.choices.iter_mut() {
+                choice.next = merged.id(choice.next);
+            }
+            self.nodes.push(node);
+        }
+        Ok(merged)
+    }
+
+    /// Adds a choice to the choice node with the given id, e.g. to branch into the nodes of a merged talk.
+    ///
+    /// Fails if the node is not a choice node or the choice points to a node that doesn't exist.
+    pub fn inject_choice(&mut self, node: IrNodeId, choice: IrChoice) -> Result<(), IrError> {
+        if !self.nodes.iter().any(|n| n.id == choice.next) {
+            return Err(IrError::InvalidNextNode(node, choice.next));
+        }
+        let Some(choice_node) = self.nodes.iter_mut().find(|n| n.id == node) else {
+            return Err(IrError::NodeNotFound(node));
+        };
+        if choice_node.kind != NodeKind::Choice {
+            return Err(IrError::NotAChoiceNode(node));
+        }
+        choice_node.choices.push(choice);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::*;
+
+    use crate::prelude::*;
+
+    use super::*;
+
+    /// The base talk: a greeting and a choice to leave.
+    fn base() -> TalkIr {
+        TalkIr {
+            actors: vec![Actor::new("bob", "Bob")],
+            nodes: vec![
+                IrNode {
+                    id: 0,
+                    text: "Hello".to_string(),
+                    actors: vec!["bob".to_string()],
+                    next: Some(1),
+                    ..default()
+                },
+                IrNode {
+                    id: 1,
+                    kind: NodeKind::Choice,
+                    choices: vec![IrChoice {
+                        text: "Leave".to_string(),
+                        next: 2,
+                        description: None,
+                        sound: None,
+                        key: None,
+                    }],
+                    ..default()
+                },
+                IrNode {
+                    id: 2,
+                    text: "Bye".to_string(),
+                    ..default()
+                },
+            ],
+            ..default()
+        }
+    }
+
+    /// The extension talk, with the same ids as the base one and its own actor for bob.
+    fn extension() -> TalkIr {
+        TalkIr {
+            actors: vec![Actor::new("robert", "Bob"), Actor::new("dragon", "Dragon")],
+            nodes: vec![
+                IrNode {
+                    id: 0,
+                    text: "The dragon?".to_string(),
+                    actors: vec!["robert".to_string()],
+                    next: Some(1),
+                    ..default()
+                },
+                IrNode {
+                    id: 1,
+                    text: "ROAR".to_string(),
+                    actors: vec!["dragon".to_string()],
+                    ..default()
+                },
+            ],
+            ..default()
+        }
+    }
+
+    #[test]
+    fn merge_namespaces_the_ids() {
+        let mut ir = base();
+        let mut extension = extension();
+        extension.remap_actors(
+            &[("robert".to_string(), "bob".to_string())]
+                .into_iter()
+                .collect(),
+        );
+        let merged = ir.merge(extension).unwrap();
+
+        assert_eq!(merged.entry(), Some(3));
+        assert_eq!(merged.id(1), 4);
+        let ids: Vec<_> = ir.nodes.iter().map(|n| n.id).collect();
+        assert_eq!(ids, vec![0, 1, 2, 3, 4]);
+        assert_eq!(ir.nodes[3].next, Some(4));
+        assert_eq!(ir.nodes[3].actors, vec!["bob"]);
+        let slugs: Vec<_> = ir.actors.iter().map(|a| a.slug.as_str()).collect();
+        assert_eq!(slugs, vec!["bob", "dragon"]);
+
+        ir.inject_choice(
+            1,
+            IrChoice {
+                text: "The dragon".to_string(),
+                next: merged.entry().unwrap(),
+                description: None,
+                sound: None,
+                key: None,
+            },
+        )
+        .unwrap();
+        let data = TalkData::try_from(ir).unwrap();
+        let ir = TalkIr::from(&data);
+        assert_eq!(ir.nodes[1].choices[1].next, 3);
+    }
+
+    #[test]
+    fn conflicting_actors_are_rejected() {
+        let mut ir = base();
+        let mut extension = extension();
+        extension.remap_actors(
+            &[("dragon".to_string(), "bob".to_string())]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(
+            ir.merge(extension),
+            Err(IrError::ConflictingActor("bob".to_string()))
+        );
+        assert_eq!(ir, base());
+    }
+
+    #[test]
+    fn inject_choice_errors() {
+        let mut ir = base();
+        let choice = |next| IrChoice {
+            text: "New".to_string(),
+            next,
+            description: None,
+            sound: None,
+            key: None,
+        };
+        assert_eq!(
+            ir.inject_choice(0, choice(2)),
+            Err(IrError::NotAChoiceNode(0))
+        );
+        assert_eq!(
+            ir.inject_choice(7, choice(2)),
+            Err(IrError::NodeNotFound(7))
+        );
+        assert_eq!(
+            ir.inject_choice(1, choice(9)),
+            Err(IrError::InvalidNextNode(1, 9))
+        );
+    }
+}
@@ -0,0 +1,208 @@
+//! Minimum display time of the nodes, so the players can't skip the critical lines by accident.
+
+use std::time::Duration;
+
+use bevy::{ecs::system::SystemParam, log::Level, prelude::*, utils::HashMap};
+
+use crate::{
+    config::{talks_log, LogCategory, TalksConfig},
+    prelude::{CurrentNodeChanged, ReflectEvent, Talk},
+};
+
+/// Component to add to a node to ignore the `NextNodeRequest`s until it has been the current node
+/// for the given (real) time. An [`AdvanceTooSoonEvent`] is sent for each ignored request.
+///
+/// Without the `Time<Real>` resource (no `TimePlugin`) the requests are not limited.
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+/// use bevy_talks::prelude::*;
+///
+/// let builder = TalkBuilder::default()
+///     .say("The bridge is out, take the north road!")
+///     .with_component(MinDisplayTime(Duration::from_secs(2)))
+///     .say("Good luck.");
+/// ```
+#[derive(Component, Reflect, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct MinDisplayTime(pub Duration);
+
+/// Event sent when a `NextNodeRequest` is ignored because the current node has a [`MinDisplayTime`]
+/// that has not passed yet, e.g. to shake the continue prompt.
+#[derive(Event, Reflect, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Event)]
+pub struct AdvanceTooSoonEvent {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// The current node of the talk.
+    pub node: Entity,
+    /// The time left before the talk can advance.
+    pub remaining: Duration,
+}
+
+/// The current node of each talk, with the real time it became the current node.
+#[derive(Resource, Debug, Default)]
+pub(crate) struct NodeEnteredTimes(HashMap<Entity, (Entity, Duration)>);
+
+impl NodeEnteredTimes {
+    /// Returns when the talk moved to the node. If the move is not recorded yet (the node was entered
+    /// in this update), it is recorded with the given time.
+    pub(crate) fn entered(&mut self, talk: Entity, node: Entity, now: Duration) -> Duration {
+        match self.0.get(&talk) {
+            Some((entered_node, entered)) if *entered_node == node => *entered,
+            _ => {
+                self.0.insert(talk, (node, now));
+                now
+            }
+        }
+    }
+}
+
+/// Records when the talks moved to their current node. It runs after the request handlers.
+pub(crate) fn record_node_entered_times(
+    mut changed_events: EventReader<CurrentNodeChanged>,
+    mut entered: ResMut<NodeEnteredTimes>,
+    time: Option<Res<Time<Real>>>,
+) {
+    let Some(time) = time else {
+        return;
+    };
+    for changed in changed_events.read() {
+        entered
+            .0
+            .insert(changed.talk, (changed.current, time.elapsed()));
+    }
+}
+
+/// Forgets the entered times of the despawned talks. It runs after the request handlers.
+pub(crate) fn forget_entered_times(
+    mut removed: RemovedComponents<Talk>,
+    mut entered: ResMut<NodeEnteredTimes>,
+) {
+    for talk in removed.read() {
+        entered.0.remove(&talk);
+    }
+}
+
+/// System param used by the next handler to apply the [`MinDisplayTime`] of the nodes.
+#[derive(SystemParam)]
+pub(crate) struct DisplayTimeGuard<'w, 's> {
+    /// The nodes with a minimum display time.
+    min_times: Query<'w, 's, &'static MinDisplayTime>,
+    /// When the talks moved to their current node.
+    entered: ResMut<'w, NodeEnteredTimes>,
+    /// The real time.
+    time: Option<Res<'w, Time<Real>>>,
+    /// The writer for the rejected requests.
+    too_soon_writer: EventWriter<'w, AdvanceTooSoonEvent>,
+    /// The config of the logs.
+    config: Res<'w, TalksConfig>,
+}
+
+impl DisplayTimeGuard<'_, '_> {
+    /// Returns whether the talk can move past its current node, sending an [`AdvanceTooSoonEvent`] if it can't.
+    pub(crate) fn allow(&mut self, talk: Entity, node: Entity) -> bool {
+        let (Ok(MinDisplayTime(min_time)), Some(time)) = (self.min_times.get(node), &self.time)
+        else {
+            return true;
+        };
+
+        let now = time.elapsed();
+        // the node may have been entered in this update (e.g. by a previous request), not recorded yet
+        let entered = self.entered.entered(talk, node, now);
+        let shown = now.saturating_sub(entered);
+        if shown >= *min_time {
+            return true;
+        }
+        let remaining = *min_time - shown;
+        talks_log!(
+            Some(&*self.config),
+            LogCategory::Traversal,
+            Level::DEBUG,
+            "Ignored next request for talk {talk:?}, node {node:?} can't be skipped for {remaining:?}."
+        );
+        self.too_soon_writer.send(AdvanceTooSoonEvent {
+            talk,
+            node,
+            remaining,
+        });
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use bevy::ecs::system::Command;
+
+    use crate::{prelude::*, tests::talks_minimal_app};
+
+    use super::*;
+
+    /// Returns the text of the current node of the talk.
+    fn current_text(app: &App, talk: Entity) -> String {
+        let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        app.world.get::<TextNode>(current).unwrap().0.clone()
+    }
+
+    #[test]
+    fn next_ignored_before_min_display_time() {
+        let mut app = talks_minimal_app();
+        let start = Instant::now();
+        let mut time = Time::<Real>::new(start);
+        time.update_with_instant(start);
+        app.insert_resource(time);
+        let builder = TalkBuilder::default()
+            .say("Critical")
+            .with_component(MinDisplayTime(Duration::from_secs(2)))
+            .say("Next");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        // the double press: the second request is ignored right away
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(current_text(&app, talk), "Critical");
+        let evs = app.world.resource::<Events<AdvanceTooSoonEvent>>();
+        let too_soon = evs.get_reader().read(evs).copied().collect::<Vec<_>>();
+        assert_eq!(too_soon.len(), 1);
+        assert_eq!(too_soon[0].talk, talk);
+        assert_eq!(too_soon[0].remaining, Duration::from_secs(2));
+
+        app.world
+            .resource_mut::<Time<Real>>()
+            .update_with_instant(start + Duration::from_secs(1));
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(current_text(&app, talk), "Critical");
+
+        app.world
+            .resource_mut::<Time<Real>>()
+            .update_with_instant(start + Duration::from_secs(2));
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(current_text(&app, talk), "Next");
+    }
+
+    #[test]
+    fn despawned_talks_forgotten() {
+        let mut app = talks_minimal_app();
+        app.insert_resource(Time::<Real>::new(Instant::now()));
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, TalkBuilder::default().say("Hi")).apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert!(app
+            .world
+            .resource::<NodeEnteredTimes>()
+            .0
+            .contains_key(&talk));
+
+        app.world.entity_mut(talk).despawn_recursive();
+        app.update();
+        assert!(app.world.resource::<NodeEnteredTimes>().0.is_empty());
+    }
+}
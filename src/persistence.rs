@@ -0,0 +1,264 @@
+//! Stable talk ids, to keep the state of the talks across respawns (scene reloads, saves).
+//!
+//! The talks with a [`TalkId`] have their current node recorded in the [`TalkStates`] registry.
+//! When a talk with the same id is built again, its saved current node is reapplied.
+//! The choices taken in those talks are recorded in the [`TakenChoices`] registry.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bevy::{ecs::system::Command, log::Level, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{talks_log, LogCategory, TalksConfig};
+use crate::prelude::{
+    ChoiceSelectedEvent, CurrentNode, CurrentNodeChanged, CurrentNodes, OrderIndex, SetHasStarted,
+};
+
+/// Component with the stable id of a talk, the same across respawns.
+///
+/// It is added to the talk parent entity from [`TalkBuilder::talk_id`](crate::prelude::TalkBuilder::talk_id),
+/// or from the asset path for the talks loaded from assets (`"dialogues/bob.talk.ron"`, `"pack.talk.ron#shop"`).
+#[derive(Component, Reflect, Debug, Default, Clone, PartialEq, Eq, Hash)]
+#[reflect(Component, Default)]
+pub struct TalkId(pub String);
+
+/// The saved state of a talk.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TalkState {
+    /// The [`OrderIndex`] of the current node. `0` is the start node.
+    pub node: u32,
+}
+
+/// Registry with the states of the talks with a [`TalkId`] (id => state).
+///
+/// It is kept up to date while the talks are traversed, and the saved state is reapplied
+/// when a talk with the same id is built again. The talk is moved to its saved node without
+/// emitting the node events, send a [`RefireNodeRequest`](crate::prelude::RefireNodeRequest) to get them.
+///
+/// It is serializable, so it can also be stored in the save files and inserted back when loading.
+///
+/// # Note
+/// The states are matched to the nodes by their [`OrderIndex`], so the talk must be built from the same builder (or asset).
+/// The nodes of the expanded lazy branches and of the interjections are not restored, the talk stays at the start.
+#[derive(Resource, Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TalkStates(pub BTreeMap<String, TalkState>);
+
+impl TalkStates {
+    /// Returns the saved state of the talk with the given id, if any.
+    pub fn get(&self, id: &str) -> Option<&TalkState> {
+        self.0.get(id)
+    }
+
+    /// Forgets the saved state of the talk with the given id, so it starts over when built again.
+    pub fn remove(&mut self, id: &str) -> Option<TalkState> {
+        self.0.remove(id)
+    }
+}
+
+/// Registry with the choices taken by the player in the talks with a [`TalkId`]
+/// (id => the taken choices, as pairs of node index and choice index).
+///
+/// The nodes are identified by their [`OrderIndex`] (as in [`TalkState`]) and the choices by their
+/// index in the choice node. It is serializable, so it can be stored in the save files like the [`TalkStates`].
+///
+/// # Example
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// /// The choice 0 of the node 3 of the blacksmith talk is "I'll help you".
+/// fn promised_to_help(taken: Res<TakenChoices>) -> bool {
+///     taken.has_chosen("village/blacksmith", 3, 0)
+/// }
+/// ```
+#[derive(Resource, Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TakenChoices(pub BTreeMap<String, BTreeSet<(u32, usize)>>);
+
+impl TakenChoices {
+    /// Returns whether the choice of the node of the talk with the given id has ever been taken.
+    pub fn has_chosen(&self, talk_id: &str, node: u32, choice: usize) -> bool {
+        self.0
+            .get(talk_id)
+            .is_some_and(|taken| taken.contains(&(node, choice)))
+    }
+
+    /// Forgets the choices taken in the talk with the given id.
+    pub fn remove(&mut self, talk_id: &str) -> Option<BTreeSet<(u32, usize)>> {
+        self.0.remove(talk_id)
+    }
+}
+
+/// Records the choices taken in the talks with a [`TalkId`] in the [`TakenChoices`].
+pub(crate) fn record_taken_choices(
+    mut selected: EventReader<ChoiceSelectedEvent>,
+    talks: Query<&TalkId>,
+    indexes: Query<&OrderIndex>,
+    mut taken: ResMut<TakenChoices>,
+) {
+    for event in selected.read() {
+        let (Ok(id), Ok(OrderIndex(node))) = (talks.get(event.talk), indexes.get(event.node))
+        else {
+            continue;
+        };
+        taken
+            .0
+            .entry(id.0.clone())
+            .or_default()
+            .insert((*node, event.index));
+    }
+}
+
+/// Records the current node of the talks with a [`TalkId`] in the [`TalkStates`].
+pub(crate) fn record_talk_states(
+    mut changes: EventReader<CurrentNodeChanged>,
+    talks: Query<&TalkId>,
+    indexes: Query<&OrderIndex>,
+    mut states: ResMut<TalkStates>,
+) {
+    for change in changes.read() {
+        let (Ok(id), Ok(OrderIndex(node))) = (talks.get(change.talk), indexes.get(change.current))
+        else {
+            continue;
+        };
+        states.0.insert(id.0.clone(), TalkState { node: *node });
+    }
+}
+
+/// Moves the current node of the talk from `from` to `target`, without emitting the node events.
+pub(crate) fn move_current_silently(world: &mut World, talk: Entity, from: Entity, target: Entity) {
+    world.entity_mut(from).remove::<CurrentNode>();
+    world.entity_mut(target).insert(CurrentNode);
+    if let Some(mut current_nodes) = world.get_resource_mut::<CurrentNodes>() {
+        current_nodes.0.insert(talk, target);
+    }
+    SetHasStarted { talk }.apply(world);
+}
+
+/// Moves the just built talk to its saved node, if it has a [`TalkId`] with a saved state.
+/// `nodes` are the built nodes of the talk, besides the `start` node.
+pub(crate) fn restore_talk_state(world: &mut World, talk: Entity, start: Entity, nodes: &[Entity]) {
+    let Some(TalkId(id)) = world.get::<TalkId>(talk).cloned() else {
+        return;
+    };
+    let Some(state) = world
+        .get_resource::<TalkStates>()
+        .and_then(|states| states.get(&id))
+        .copied()
+    else {
+        return;
+    };
+    let Some(&target) = nodes
+        .iter()
+        .find(|n| world.get::<OrderIndex>(**n) == Some(&OrderIndex(state.node)))
+    else {
+        return;
+    };
+
+    move_current_silently(world, talk, start, target);
+    talks_log!(
+        world.get_resource::<TalksConfig>(),
+        LogCategory::Build,
+        Level::DEBUG,
+        "Restored talk {id} ({talk:?}) at node {} ({target:?})",
+        state.node
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        prelude::*,
+        tests::{get_comp, talks_minimal_app},
+    };
+
+    use super::*;
+
+    /// Builds the talk with the id in the app.
+    fn build(app: &mut App) -> Entity {
+        let builder = TalkBuilder::default()
+            .talk_id("bob")
+            .say("Hi")
+            .say("How are you?")
+            .say("Bye");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        talk
+    }
+
+    #[test]
+    fn state_is_recorded_and_restored() {
+        let mut app = talks_minimal_app();
+        let talk = build(&mut app);
+        assert_eq!(get_comp::<TalkId>(talk, &mut app.world).0, "bob");
+        for _ in 0..2 {
+            app.world.send_event(NextNodeRequest::new(talk));
+            app.update();
+        }
+        let states = app.world.resource::<TalkStates>().clone();
+        assert_eq!(states.get("bob"), Some(&TalkState { node: 2 }));
+
+        // respawn the talk in a "reloaded" world with the saved states
+        let mut app = talks_minimal_app();
+        app.insert_resource(states);
+        let talk = build(&mut app);
+
+        let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        assert_eq!(
+            get_comp::<TextNode>(current, &mut app.world).0,
+            "How are you?"
+        );
+        assert!(app.world.get::<CurrentNode>(current).is_some());
+        assert!(app.world.get::<HasStarted>(talk).is_some());
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let evs = app.world.resource::<Events<TextNodeEvent>>();
+        assert_eq!(evs.get_reader().read(evs).next().unwrap().text, "Bye");
+    }
+
+    #[test]
+    fn out_of_range_state_starts_over() {
+        let mut app = talks_minimal_app();
+        app.world
+            .resource_mut::<TalkStates>()
+            .0
+            .insert("bob".to_string(), TalkState { node: 10 });
+        let talk = build(&mut app);
+
+        let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        assert!(app.world.get::<StartNode>(current).is_some());
+        assert!(app.world.get::<HasStarted>(talk).is_none());
+    }
+
+    #[test]
+    fn taken_choices_are_recorded() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default()
+            .talk_id("smith")
+            .say("Help me?")
+            .choose(vec![
+                ("Sure", TalkBuilder::default().say("Thanks")),
+                ("No", TalkBuilder::default().say("Oh")),
+            ]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        for _ in 0..2 {
+            app.world.send_event(NextNodeRequest::new(talk));
+            app.update();
+        }
+        assert!(!app
+            .world
+            .resource::<TakenChoices>()
+            .has_chosen("smith", 2, 0));
+
+        let evs = app.world.resource::<Events<ChoiceNodeEvent>>();
+        let sure = evs.get_reader().read(evs).last().unwrap().choices[0].next;
+        app.world.send_event(ChooseNodeRequest::new(talk, sure));
+        app.update();
+
+        let taken = app.world.resource::<TakenChoices>();
+        assert!(taken.has_chosen("smith", 2, 0));
+        assert!(!taken.has_chosen("smith", 2, 1));
+        assert!(!taken.has_chosen("bob", 2, 0));
+    }
+}
@@ -0,0 +1,258 @@
+//! Pooling of the dialogue graphs of frequently spawned talks (e.g. barks), to reuse the built graphs
+//! instead of rebuilding and respawning all the node entities every time.
+//!
+//! See [`TalkCommandsExt::spawn_talk_pooled`](crate::prelude::TalkCommandsExt::spawn_talk_pooled).
+
+use std::collections::VecDeque;
+
+use bevy::{ecs::system::Command, log::Level, prelude::*};
+
+use crate::config::{talks_log, LogCategory, TalksConfig};
+use crate::prelude::{
+    ActorAliases, BuildTalkCommand, CurrentNode, CurrentNodes, Interjection, StartNode, Talk,
+    TalkBuilder, TalkBuiltEvent, TalkData, TalkId, TalkMetadata,
+};
+
+/// Resource with the eviction policy of the talk pool: how many idle graphs are kept.
+/// When a released graph exceeds a limit, the least recently released graphs are despawned.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TalkPoolPolicy {
+    /// The maximum number of idle graphs of the same talk asset.
+    pub max_idle_per_talk: usize,
+    /// The maximum number of idle graphs of all the talk assets.
+    pub max_idle: usize,
+}
+
+impl Default for TalkPoolPolicy {
+    fn default() -> Self {
+        Self {
+            max_idle_per_talk: 4,
+            max_idle: 32,
+        }
+    }
+}
+
+/// Component of the talks spawned with
+/// [`spawn_talk_pooled`](crate::prelude::TalkCommandsExt::spawn_talk_pooled), with the asset they were built from.
+///
+/// Their graph goes back to the pool when they are released, or when they end with [`OnEnd::Despawn`](crate::prelude::OnEnd::Despawn).
+#[derive(Component, Debug, Clone)]
+pub struct PooledTalk(pub Handle<TalkData>);
+
+/// Marker component of the entities holding the node entities of an idle graph.
+#[derive(Component)]
+struct IdleGraph;
+
+/// An idle graph in the pool.
+struct PooledGraph {
+    /// The asset the graph was built from.
+    asset: AssetId<TalkData>,
+    /// The entity holding the nodes of the graph as children.
+    holder: Entity,
+    /// The metadata of the talk, restored on the talk reusing the graph.
+    metadata: TalkMetadata,
+    /// The id of the talk, if it had one.
+    talk_id: Option<TalkId>,
+    /// The actor aliases of the talk, if it had any.
+    aliases: Option<ActorAliases>,
+}
+
+/// The idle graphs, least recently released first.
+#[derive(Resource, Default)]
+pub(crate) struct TalkPool(VecDeque<PooledGraph>);
+
+/// Command to spawn the graph of a pooled talk, see
+/// [`TalkCommandsExt::spawn_talk_pooled`](crate::prelude::TalkCommandsExt::spawn_talk_pooled).
+pub(crate) struct SpawnPooledTalk {
+    /// The talk parent entity.
+    pub(crate) talk: Entity,
+    /// The talk asset.
+    pub(crate) handle: Handle<TalkData>,
+}
+
+impl Command for SpawnPooledTalk {
+    fn apply(self, world: &mut World) {
+        let asset = self.handle.id();
+        if world.get_entity(self.talk).is_none() {
+            return;
+        }
+        // reuse the most recently released graph, it's the least likely to be evicted next
+        let pool = world.resource::<TalkPool>();
+        let Some(index) = pool.0.iter().rposition(|g| g.asset == asset) else {
+            let Some(talk_data) = world.resource::<Assets<TalkData>>().get(asset) else {
+                talks_log!(
+                    world.get_resource::<TalksConfig>(),
+                    LogCategory::Build,
+                    Level::ERROR,
+                    "Cannot spawn pooled talk {:?}, its asset is not loaded.",
+                    self.talk
+                );
+                return;
+            };
+            let builder = TalkBuilder::default().fill_with_talk_data(talk_data);
+            BuildTalkCommand::new(self.talk, builder).apply(world);
+            return;
+        };
+        let graph = world
+            .resource_mut::<TalkPool>()
+            .0
+            .remove(index)
+            .expect("the index was just found");
+
+        let nodes = world
+            .get::<Children>(graph.holder)
+            .map(|c| c.to_vec())
+            .unwrap_or_default();
+        let start = nodes
+            .iter()
+            .copied()
+            .find(|n| world.get::<StartNode>(*n).is_some());
+        world.entity_mut(self.talk).push_children(&nodes);
+        world.despawn(graph.holder);
+
+        if let Some(mut talk) = world.get_mut::<Talk>(self.talk) {
+            talk.metadata = graph.metadata;
+        }
+        if let Some(talk_id) = graph.talk_id {
+            world.entity_mut(self.talk).insert(talk_id);
+        }
+        if let Some(mut aliases) = graph.aliases {
+            aliases.hide_all();
+            world.entity_mut(self.talk).insert(aliases);
+        }
+        if let (Some(start), Some(mut current_nodes)) =
+            (start, world.get_resource_mut::<CurrentNodes>())
+        {
+            current_nodes.0.insert(self.talk, start);
+        }
+        if let Some(mut built_events) = world.get_resource_mut::<Events<TalkBuiltEvent>>() {
+            built_events.send(TalkBuiltEvent {
+                talk: self.talk,
+                nodes: nodes.len(),
+            });
+        }
+    }
+}
+
+/// Releases a pooled talk: its graph is reset to the start node and moved back to the pool,
+/// and the talk parent entity is despawned. The not pooled talks are just despawned.
+///
+/// The talks with a pending interjection are despawned too, their graph is not reusable as is.
+pub fn release_talk(world: &mut World, talk: Entity) {
+    let Some(PooledTalk(handle)) = world.get::<PooledTalk>(talk).cloned() else {
+        if let Some(talk) = world.get_entity_mut(talk) {
+            talk.despawn_recursive();
+        }
+        return;
+    };
+    if world.get::<Interjection>(talk).is_some() {
+        world.entity_mut(talk).despawn_recursive();
+        return;
+    }
+
+    let nodes = world
+        .get::<Children>(talk)
+        .map(|c| c.to_vec())
+        .unwrap_or_default();
+    let start = nodes
+        .iter()
+        .copied()
+        .find(|n| world.get::<StartNode>(*n).is_some());
+    let current = world
+        .get_resource_mut::<CurrentNodes>()
+        .and_then(|mut c| c.0.remove(&talk));
+    if let (Some(start), Some(current)) = (start, current) {
+        world.entity_mut(current).remove::<CurrentNode>();
+        world.entity_mut(start).insert(CurrentNode);
+    }
+
+    let holder = world.spawn(IdleGraph).id();
+    world.entity_mut(holder).push_children(&nodes);
+    let graph = PooledGraph {
+        asset: handle.id(),
+        holder,
+        metadata: world
+            .get::<Talk>(talk)
+            .map(|t| t.metadata.clone())
+            .unwrap_or_default(),
+        talk_id: world.get::<TalkId>(talk).cloned(),
+        aliases: world.get::<ActorAliases>(talk).cloned(),
+    };
+    world.despawn(talk);
+
+    let policy = world
+        .get_resource::<TalkPoolPolicy>()
+        .copied()
+        .unwrap_or_default();
+    let mut pool = world.resource_mut::<TalkPool>();
+    pool.0.push_back(graph);
+    let mut evicted = vec![];
+    let same_talk = pool.0.iter().filter(|g| g.asset == handle.id()).count();
+    if same_talk > policy.max_idle_per_talk {
+        let oldest = pool.0.iter().position(|g| g.asset == handle.id());
+        evicted.extend(oldest.and_then(|i| pool.0.remove(i)));
+    }
+    while pool.0.len() > policy.max_idle {
+        evicted.extend(pool.0.pop_front());
+    }
+    for graph in evicted {
+        world.entity_mut(graph.holder).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::indexmap;
+
+    use crate::{
+        prelude::*,
+        tests::{count, talks_minimal_app},
+    };
+
+    use super::*;
+
+    /// Spawns a pooled talk of the asset and returns it.
+    fn spawn_pooled(app: &mut App, handle: &Handle<TalkData>) -> Entity {
+        let mut queue = bevy::ecs::system::CommandQueue::default();
+        let talk = Commands::new(&mut queue, &app.world)
+            .spawn_talk_pooled(handle.clone())
+            .id();
+        queue.apply(&mut app.world);
+        talk
+    }
+
+    #[test]
+    fn released_graph_is_reused() {
+        let mut app = talks_minimal_app();
+        app.insert_resource(TalkPoolPolicy {
+            max_idle_per_talk: 1,
+            max_idle: 4,
+        });
+        let script = indexmap! {
+            0 => Action { text: "Hey!".to_string(), ..default() },
+        };
+        let handle = app
+            .world
+            .resource_mut::<Assets<TalkData>>()
+            .add(TalkData::new(script, vec![]));
+
+        let talk = spawn_pooled(&mut app, &handle);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let nodes = app.world.get::<Children>(talk).unwrap().to_vec();
+        release_talk(&mut app.world, talk);
+        assert!(app.world.get_entity(talk).is_none());
+
+        let talk = spawn_pooled(&mut app, &handle);
+        assert_eq!(app.world.get::<Children>(talk).unwrap().to_vec(), nodes);
+        let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        assert!(app.world.get::<StartNode>(current).is_some());
+        assert_eq!(count::<&CurrentNode>(&mut app.world), 1);
+
+        // a second talk is built, then only one idle graph is kept
+        let other = spawn_pooled(&mut app, &handle);
+        release_talk(&mut app.world, talk);
+        release_talk(&mut app.world, other);
+        assert_eq!(count::<&TextNode>(&mut app.world), 1);
+    }
+}
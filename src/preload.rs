@@ -0,0 +1,245 @@
+//! Preloading of the assets referenced by the talks (portraits and voice blips),
+//! so they are available when the first line is shown, and spawning of the talks once their asset is loaded.
+//!
+//! See [`TalkCommandsExt::preload_talk_assets`](crate::prelude::TalkCommandsExt::preload_talk_assets)
+//! and [`TalkCommandsExt::spawn_talk_from_handle`](crate::prelude::TalkCommandsExt::spawn_talk_from_handle).
+
+use bevy::{
+    asset::{LoadState, RecursiveDependencyLoadState, UntypedAssetId},
+    ecs::system::Command,
+    log::Level,
+    prelude::*,
+};
+
+use crate::config::{talks_log, LogCategory, TalksConfig};
+use crate::prelude::{BuildTalkCommand, CurrentNodes, TalkBuilder, TalkData};
+
+/// Event sent when a talk asset and all the assets referenced by its actors are loaded.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct TalkAssetsReady(pub Handle<TalkData>);
+
+/// Component of the talks spawned with
+/// [`spawn_talk_when_ready`](crate::prelude::TalkCommandsExt::spawn_talk_when_ready):
+/// the talk graph is built from the asset once its [`TalkAssetsReady`] is sent, then the component is removed.
+#[derive(Component, Debug, Clone)]
+pub struct WaitForTalkAssets(pub Handle<TalkData>);
+
+/// Component of the talks spawned with
+/// [`spawn_talk_from_handle`](crate::prelude::TalkCommandsExt::spawn_talk_from_handle):
+/// the talk graph is built from the asset as soon as it is loaded, then the component is removed.
+#[derive(Component, Debug, Clone)]
+pub struct WaitForTalkAsset(pub Handle<TalkData>);
+
+/// Event sent when a talk spawned with
+/// [`spawn_talk_from_handle`](crate::prelude::TalkCommandsExt::spawn_talk_from_handle) has its graph built.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct TalkSpawned {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// The handle of the talk asset the graph was built from.
+    pub handle: Handle<TalkData>,
+}
+
+/// The talk assets being preloaded and the assets kept loaded for the ready ones.
+#[derive(Resource, Default)]
+pub(crate) struct TalkPreloads {
+    /// The talks waiting for their assets, with the handles of the assets loaded so far.
+    pending: Vec<(Handle<TalkData>, Vec<UntypedHandle>)>,
+    /// The handles of the assets of the ready talks, kept so they are not unloaded.
+    ready: Vec<(Handle<TalkData>, Vec<UntypedHandle>)>,
+}
+
+/// Command to preload the assets of a talk, see
+/// [`TalkCommandsExt::preload_talk_assets`](crate::prelude::TalkCommandsExt::preload_talk_assets).
+pub struct PreloadTalkAssets(pub Handle<TalkData>);
+
+impl Command for PreloadTalkAssets {
+    fn apply(self, world: &mut World) {
+        let preloads = world.resource::<TalkPreloads>();
+        if preloads.ready.iter().any(|(h, _)| *h == self.0) {
+            // already loaded, just notify again
+            world.send_event(TalkAssetsReady(self.0));
+        } else if !preloads.pending.iter().any(|(h, _)| *h == self.0) {
+            world
+                .resource_mut::<TalkPreloads>()
+                .pending
+                .push((self.0, vec![]));
+        }
+    }
+}
+
+/// Returns whether the asset and its dependencies are done loading (or failed).
+/// The assets not managed by the asset server (e.g. added directly to `Assets`) are always done.
+fn is_done(server: &AssetServer, id: impl Into<UntypedAssetId>) -> bool {
+    !matches!(
+        server.get_recursive_dependency_load_state(id),
+        Some(RecursiveDependencyLoadState::NotLoaded | RecursiveDependencyLoadState::Loading)
+    )
+}
+
+/// Loads the assets of the pending talks and sends their [`TalkAssetsReady`] once they are loaded.
+///
+/// The portraits are dependencies of the talk asset, the voice blips are loaded once the talk is.
+pub(crate) fn check_talk_preloads(
+    mut preloads: ResMut<TalkPreloads>,
+    talks: Res<Assets<TalkData>>,
+    server: Res<AssetServer>,
+    mut ready_events: EventWriter<TalkAssetsReady>,
+    config: Res<TalksConfig>,
+) {
+    let pending = std::mem::take(&mut preloads.pending);
+    for (handle, mut assets) in pending {
+        if server.get_load_state(handle.id()) == Some(LoadState::Failed) {
+            talks_log!(
+                Some(&*config),
+                LogCategory::Build,
+                Level::WARN,
+                "Cannot preload the assets of talk {:?}, it failed to load.",
+                handle.path()
+            );
+            continue;
+        }
+        let Some(talk) = talks.get(&handle) else {
+            preloads.pending.push((handle, assets));
+            continue;
+        };
+        if !is_done(&server, handle.id()) {
+            preloads.pending.push((handle, assets));
+            continue;
+        }
+        if assets.is_empty() {
+            let blips = talk
+                .actors
+                .iter()
+                .filter_map(|a| a.voice.as_ref())
+                .flat_map(|v| v.blips.iter());
+            assets.extend(blips.map(|path| server.load_untyped(path).untyped()));
+        }
+        if !assets.iter().all(|a| is_done(&server, a.id())) {
+            preloads.pending.push((handle, assets));
+            continue;
+        }
+        ready_events.send(TalkAssetsReady(handle.clone()));
+        preloads.ready.push((handle, assets));
+    }
+}
+
+/// Builds the talks waiting for their assets once they are ready.
+pub(crate) fn spawn_ready_talks(
+    mut commands: Commands,
+    mut ready_events: EventReader<TalkAssetsReady>,
+    waiting: Query<(Entity, &WaitForTalkAssets)>,
+    talks: Res<Assets<TalkData>>,
+) {
+    for TalkAssetsReady(handle) in ready_events.read() {
+        let Some(talk_data) = talks.get(handle) else {
+            continue;
+        };
+        for (talk, _) in waiting.iter().filter(|(_, w)| w.0 == *handle) {
+            let builder = TalkBuilder::default().fill_with_talk_data(talk_data);
+            commands.add(BuildTalkCommand::new(talk, builder));
+            commands.entity(talk).remove::<WaitForTalkAssets>();
+        }
+    }
+}
+
+/// Builds the talks waiting for their asset once it is loaded and sends their [`TalkSpawned`].
+/// The talks whose asset failed to load stop waiting, with a warning.
+pub(crate) fn spawn_loaded_talks(
+    mut commands: Commands,
+    waiting: Query<(Entity, &WaitForTalkAsset)>,
+    talks: Res<Assets<TalkData>>,
+    server: Res<AssetServer>,
+    config: Res<TalksConfig>,
+) {
+    for (talk, WaitForTalkAsset(handle)) in waiting.iter() {
+        let Some(talk_data) = talks.get(handle) else {
+            if server.get_load_state(handle.id()) == Some(LoadState::Failed) {
+                talks_log!(
+                    Some(&*config),
+                    LogCategory::Build,
+                    Level::WARN,
+                    "Cannot spawn talk {talk:?}, its asset {:?} failed to load.",
+                    handle.path()
+                );
+                commands.entity(talk).remove::<WaitForTalkAsset>();
+            }
+            continue;
+        };
+        let builder = TalkBuilder::default().fill_with_talk_data(talk_data);
+        commands.add(BuildTalkCommand::new(talk, builder));
+        commands.entity(talk).remove::<WaitForTalkAsset>();
+        let handle = handle.clone();
+        commands.add(move |world: &mut World| {
+            // the build is skipped if it fails (with a `TalkBuildFailedEvent`)
+            if world.resource::<CurrentNodes>().get(talk).is_some() {
+                world.send_event(TalkSpawned { talk, handle });
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indexmap::indexmap;
+
+    use crate::{
+        prelude::*,
+        tests::{count, talks_minimal_app},
+    };
+
+    use super::*;
+
+    #[test]
+    fn waiting_talk_is_built_when_ready() {
+        let mut app = talks_minimal_app();
+        let script = indexmap! {
+            0 => Action { text: "Hi".to_string(), ..default() },
+        };
+        let talk_data = TalkData::new(script, vec![]);
+        let handle = app.world.resource_mut::<Assets<TalkData>>().add(talk_data);
+
+        let mut commands_queue = bevy::ecs::system::CommandQueue::default();
+        let talk = Commands::new(&mut commands_queue, &app.world)
+            .spawn_talk_when_ready(handle.clone())
+            .id();
+        commands_queue.apply(&mut app.world);
+        assert!(app.world.get::<WaitForTalkAssets>(talk).is_some());
+
+        app.update();
+        let evs = app.world.resource::<Events<TalkAssetsReady>>();
+        let ready: Vec<_> = evs.get_reader().read(evs).cloned().collect();
+        assert_eq!(ready, vec![TalkAssetsReady(handle)]);
+        assert!(app.world.get::<WaitForTalkAssets>(talk).is_none());
+        assert_eq!(count::<&TextNode>(&mut app.world), 1);
+    }
+
+    #[test]
+    fn talk_spawned_from_handle_when_loaded() {
+        let mut app = talks_minimal_app();
+        let handle = Handle::<TalkData>::default();
+        let mut commands_queue = bevy::ecs::system::CommandQueue::default();
+        let talk = Commands::new(&mut commands_queue, &app.world)
+            .spawn_talk_from_handle(handle.clone())
+            .id();
+        commands_queue.apply(&mut app.world);
+
+        // not loaded yet
+        app.update();
+        assert!(app.world.get::<WaitForTalkAsset>(talk).is_some());
+        assert!(app.world.resource::<Events<TalkSpawned>>().is_empty());
+
+        let script = indexmap! {
+            0 => Action { text: "Hi".to_string(), ..default() },
+        };
+        app.world
+            .resource_mut::<Assets<TalkData>>()
+            .insert(handle.clone(), TalkData::new(script, vec![]));
+        app.update();
+        let evs = app.world.resource::<Events<TalkSpawned>>();
+        let spawned: Vec<_> = evs.get_reader().read(evs).cloned().collect();
+        assert_eq!(spawned, vec![TalkSpawned { talk, handle }]);
+        assert!(app.world.get::<WaitForTalkAsset>(talk).is_none());
+        assert_eq!(count::<&TextNode>(&mut app.world), 1);
+    }
+}
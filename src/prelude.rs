@@ -1,10 +1,60 @@
 //! Prelude for the `bevy_talks` crate.
-pub use super::TalksPlugin;
+pub use super::{TalksPlugin, TalksScheduledPlugin};
 
 pub use super::actors::*;
-pub use super::builder::{build_command::*, commands::*, *};
+pub use super::ambient::{AmbientTalk, AmbientTalksPlugin};
+#[cfg(feature = "bubbles")]
+pub use super::bubbles::{BubbleOffset, BubbleStyle, SpeechBubble, SpeechBubblesPlugin};
+pub use super::builder::{build_command::*, commands::*, passes::*, *};
+pub use super::captions::{Caption, CaptionEvent, CaptionSettings};
+pub use super::choice_filter::{
+    explain_choice_availability, ChoiceFilter, ChoiceFilters, SingleChoicePolicy,
+};
+pub use super::choice_timer::{
+    ChoiceCountdown, ChoiceTimedOutEvent, ChoiceTimeout, ChoiceTimerTick,
+};
+pub use super::config::{LogCategory, TalksConfig};
+#[cfg(feature = "coverage")]
+pub use super::coverage::{EdgeCoverage, TalkCoverage, TalkCoveragePlugin};
+pub use super::custom_nodes::{CustomNode, CustomNodeFactory, CustomNodeKinds};
+pub use super::display::{TalkDisplay, TalkDisplays};
+pub use super::editor::{ChoiceTarget, TalkEditCommandsExt};
+#[cfg(feature = "egui")]
+pub use super::egui_overlay::TalksDebugOverlayPlugin;
+pub use super::emit::EmitterSpec;
 pub use super::errors::*;
 pub use super::events::{node_events::*, requests::*, *};
+#[cfg(feature = "petgraph")]
+pub use super::graph_cache::{RefreshTalkGraphCache, TalkGraphCache};
+pub use super::hotkeys::{hotkey_name, parse_hotkey, ChoiceHotkeysPlugin};
+pub use super::ir::*;
+#[cfg(feature = "json")]
+pub use super::json_loader::loader::JsonLoaderError;
+pub use super::library::{TalkFolderPlugin, TalkLibrary, TalkLibraryReady};
+pub use super::localization::LocalizationError;
+pub use super::lockout::ChoiceLockout;
+pub use super::markers::*;
+pub use super::merge::MergedIds;
+pub use super::min_display::{AdvanceTooSoonEvent, MinDisplayTime};
+pub use super::persistence::{TakenChoices, TalkId, TalkState, TalkStates};
+pub use super::pool::{PooledTalk, TalkPoolPolicy};
+pub use super::preload::{
+    PreloadTalkAssets, TalkAssetsReady, TalkSpawned, WaitForTalkAsset, WaitForTalkAssets,
+};
+pub use super::proximity::{ProximityTalk, ProximityTalkPlugin};
+pub use super::random::{RandomNode, TalkRng};
+#[cfg(feature = "test")]
+pub use super::recorder::{NodeEventRecorderPlugin, RecordedEvent, RecordedNodeEvents};
+pub use super::refire::RefirePolicy;
+pub use super::ron_loader::loader::RonLoaderError;
+pub use super::storage::{
+    LoadTalkPersistence, MemoryPersistence, TalkPersistence, TalkPersistenceStore,
+};
+pub use super::sync::{NetTalkRequest, SyncTalkState, TalkNetIds};
 pub use super::talk::*;
 pub use super::talk_asset::*;
-pub use bevy_talks_macros::NodeEventEmitter;
+pub use super::text_filter::{TalksLocale, TextFilter, TextFilters};
+pub use super::variables::{SwitchNode, TalkVariables};
+#[cfg(feature = "yaml")]
+pub use super::yaml_loader::loader::YamlLoaderError;
+pub use bevy_talks_macros::{talk, NodeEventEmitter};
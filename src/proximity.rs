@@ -0,0 +1,138 @@
+//! Proximity triggers for the talks (e.g. for NPC barks).
+
+use std::marker::PhantomData;
+
+use bevy::prelude::*;
+
+use crate::prelude::{EndEvent, NextNodeRequest};
+
+/// Plugin that starts/advances the talks of the [`ProximityTalk`] entities when an entity with the
+/// `P` marker component (e.g. your `Player`) enters their radius, and ends them when it leaves.
+///
+/// # Example
+/// ```rust,no_run
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// #[derive(Component)]
+/// struct Player;
+///
+/// App::new()
+///     .add_plugins((DefaultPlugins, TalksPlugin, ProximityTalkPlugin::<Player>::default()))
+///     .run();
+/// ```
+pub struct ProximityTalkPlugin<P: Component> {
+    /// The marker component of the entities triggering the talks.
+    marker: PhantomData<P>,
+}
+
+impl<P: Component> Default for ProximityTalkPlugin<P> {
+    fn default() -> Self {
+        Self {
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<P: Component> Plugin for ProximityTalkPlugin<P> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, proximity_talks::<P>);
+    }
+}
+
+/// Component to place on an entity (e.g. an NPC) to trigger a talk when an entity with the marker
+/// of the [`ProximityTalkPlugin`] gets within `radius`.
+///
+/// When the marked entity enters the radius a `NextNodeRequest` is sent for the talk (so the talk starts
+/// or advances), when it leaves an `EndEvent` is sent so the dialogue UIs can close.
+#[derive(Component, Debug, Clone)]
+pub struct ProximityTalk {
+    /// The talk parent entity.
+    pub talk: Entity,
+    /// The distance from the entity within which the talk is triggered.
+    pub radius: f32,
+    /// Whether a marked entity is currently within the radius.
+    inside: bool,
+}
+
+impl ProximityTalk {
+    /// Creates a new `ProximityTalk` for the given talk and radius.
+    pub fn new(talk: Entity, radius: f32) -> Self {
+        Self {
+            talk,
+            radius,
+            inside: false,
+        }
+    }
+
+    /// Returns whether a marked entity is currently within the radius.
+    pub fn is_inside(&self) -> bool {
+        self.inside
+    }
+}
+
+/// Checks the distance of the marked entities from the [`ProximityTalk`] entities and sends the requests
+/// when they enter or leave the radius.
+fn proximity_talks<P: Component>(
+    triggerers: Query<&GlobalTransform, With<P>>,
+    mut proximity_talks: Query<(&GlobalTransform, &mut ProximityTalk)>,
+    mut next_requests: EventWriter<NextNodeRequest>,
+    mut end_events: EventWriter<EndEvent>,
+) {
+    for (transform, mut proximity) in &mut proximity_talks {
+        let position = transform.translation();
+        let inside = triggerers
+            .iter()
+            .any(|t| t.translation().distance(position) <= proximity.radius);
+
+        if inside == proximity.inside {
+            continue;
+        }
+        proximity.inside = inside;
+        if inside {
+            next_requests.send(NextNodeRequest::new(proximity.talk));
+        } else {
+            end_events.send(EndEvent(proximity.talk));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{prelude::*, tests::talks_minimal_app};
+
+    use super::*;
+
+    #[derive(Component)]
+    struct Player;
+
+    #[test]
+    fn entering_and_leaving_the_radius() {
+        let mut app = talks_minimal_app();
+        app.add_plugins(ProximityTalkPlugin::<Player>::default());
+        let talk = app.world.spawn(Talk::default()).id();
+        app.world
+            .spawn((GlobalTransform::default(), ProximityTalk::new(talk, 2.0)));
+        let player = app
+            .world
+            .spawn((Player, GlobalTransform::from_xyz(5.0, 0.0, 0.0)))
+            .id();
+
+        app.update();
+        assert!(app.world.resource::<Events<NextNodeRequest>>().is_empty());
+
+        app.world
+            .entity_mut(player)
+            .insert(GlobalTransform::from_xyz(1.0, 0.0, 0.0));
+        app.update();
+        assert_eq!(app.world.resource::<Events<NextNodeRequest>>().len(), 1);
+        assert!(single::<&ProximityTalk>(&mut app.world).is_inside());
+
+        app.world
+            .entity_mut(player)
+            .insert(GlobalTransform::from_xyz(3.0, 0.0, 0.0));
+        app.update();
+        assert_eq!(app.world.resource::<Events<EndEvent>>().len(), 1);
+        assert!(!single::<&ProximityTalk>(&mut app.world).is_inside());
+    }
+}
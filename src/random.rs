@@ -0,0 +1,207 @@
+//! The random nodes and the seeded random number generator of the talks.
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hash, Hasher},
+};
+
+use bevy::prelude::*;
+
+use crate::variables::node_after;
+
+/// Component for the nodes that route the talk silently to one of their branches, picked at random by weight.
+///
+/// Once the talk moves to a random node, it moves right away to the target of a branch picked with the
+/// [`TalkRng`] of the talk. Each branch is picked with a probability proportional to its weight,
+/// the branches with a zero weight are never picked (if all the weights are zero, the first branch is taken).
+/// A `None` target (an empty branch) goes on with the node after the random node.
+///
+/// The random nodes are added with [`TalkBuilder::random`](crate::prelude::TalkBuilder::random).
+#[derive(Component, Reflect, Default, Debug, Clone, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct RandomNode {
+    /// The branches (weight => target node).
+    pub branches: Vec<(u32, Option<Entity>)>,
+}
+
+impl RandomNode {
+    /// Returns the target of the branch picked with the given random number generator.
+    /// `None` goes on with the node after the random node.
+    pub fn target(&self, rng: &mut TalkRng) -> Option<Entity> {
+        let total: u64 = self.branches.iter().map(|(w, _)| u64::from(*w)).sum();
+        if total == 0 {
+            return self.branches.first().and_then(|(_, target)| *target);
+        }
+        let mut roll = rng.next_u64() % total;
+        self.branches
+            .iter()
+            .find(|(weight, _)| {
+                let weight = u64::from(*weight);
+                if roll < weight {
+                    return true;
+                }
+                roll -= weight;
+                false
+            })
+            .and_then(|(_, target)| *target)
+    }
+
+    /// Returns whether the given node is the target of a branch.
+    fn is_branch(&self, node: Entity) -> bool {
+        self.branches.iter().any(|(_, t)| *t == Some(node))
+    }
+}
+
+/// Component with the random number generator of a talk, on the talk parent entity.
+/// It picks the branches of the [`RandomNode`]s of the talk.
+///
+/// Insert it with a seed to get the same branches every time (e.g. in the replays and the tests),
+/// or with the [`state`](TalkRng::state) saved in a previous session to go on with the same sequence.
+/// A talk without it gets one with a random seed when it reaches its first random node.
+///
+/// # Example
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// fn seed_new_talks(mut commands: Commands, talks: Query<Entity, Added<Talk>>) {
+///     for talk in talks.iter() {
+///         commands.entity(talk).insert(TalkRng::seeded(42));
+///     }
+/// }
+/// ```
+#[derive(Component, Reflect, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct TalkRng(u64);
+
+impl TalkRng {
+    /// Creates the random number generator with the given seed.
+    pub fn seeded(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// Returns the current state of the random number generator. Save it with your save data and
+    /// insert [`TalkRng::seeded`] with it when loading, to go on with the same sequence.
+    pub fn state(&self) -> u64 {
+        self.0
+    }
+
+    /// Returns the next random number of the sequence (SplitMix64).
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Creates the random number generator of the talk with a random seed.
+    fn unseeded(talk: Entity) -> Self {
+        let mut hasher = RandomState::new().build_hasher();
+        talk.hash(&mut hasher);
+        Self(hasher.finish())
+    }
+}
+
+/// Returns the node the talk is routed to from the given random node, picking a branch with the [`TalkRng`] of the talk.
+///
+/// It runs in the routing command of the traversal, like the switch nodes.
+pub(crate) fn random_target(world: &mut World, talk: Entity, node: Entity) -> Option<Entity> {
+    let random = world.get::<RandomNode>(node)?.clone();
+    let mut rng = world
+        .get::<TalkRng>(talk)
+        .copied()
+        .unwrap_or_else(|| TalkRng::unseeded(talk));
+    let target = random.target(&mut rng);
+    world.entity_mut(talk).insert(rng);
+    target.or_else(|| node_after(world, node, |f| random.is_branch(f)))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::Command;
+
+    use crate::{
+        prelude::*,
+        tests::{single, talks_minimal_app},
+    };
+
+    use super::*;
+
+    /// Builds the talk with a random node and the given seed, returning the talk entity.
+    fn random_talk(app: &mut App, seed: u64) -> Entity {
+        let builder = TalkBuilder::default()
+            .say("Hi")
+            .random(vec![
+                (1, TalkBuilder::default().say("Sunny today.")),
+                (1, TalkBuilder::default().say("Rainy today.")),
+                (0, TalkBuilder::default().say("Snowy today.")),
+                (1, TalkBuilder::default()),
+            ])
+            .say("Bye");
+        let talk = app
+            .world
+            .spawn((Talk::default(), TalkRng::seeded(seed)))
+            .id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        talk
+    }
+
+    /// Sends a next request and updates the app, returning the current text.
+    fn next_text(app: &mut App, talk: Entity) -> String {
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        app.world.get::<TextNode>(current).unwrap().0.clone()
+    }
+
+    #[test]
+    fn same_seed_same_branches() {
+        let mut app = talks_minimal_app();
+        let mut texts = vec![];
+        for seed in 0..50 {
+            let first = random_talk(&mut app, seed);
+            let second = random_talk(&mut app, seed);
+            assert_eq!(next_text(&mut app, first), "Hi");
+            assert_eq!(next_text(&mut app, second), "Hi");
+            let text = next_text(&mut app, first);
+            assert_eq!(next_text(&mut app, second), text);
+            texts.push(text);
+        }
+
+        // the zero weight branch is never picked, the empty branch goes on after the random node
+        assert!(!texts.iter().any(|t| t == "Snowy today."));
+        for text in ["Sunny today.", "Rainy today.", "Bye"] {
+            assert!(texts.iter().any(|t| t == text), "{text} never picked");
+        }
+    }
+
+    #[test]
+    fn random_node_built_with_the_weights() {
+        let mut app = talks_minimal_app();
+        random_talk(&mut app, 0);
+        let random = single::<&RandomNode>(&mut app.world).clone();
+        let weights: Vec<u32> = random.branches.iter().map(|(w, _)| *w).collect();
+        assert_eq!(weights, vec![1, 1, 0, 1]);
+        assert_eq!(random.branches[3].1, None);
+    }
+
+    #[test]
+    fn saved_state_resumes_the_sequence() {
+        let mut rng = TalkRng::seeded(7);
+        rng.next_u64();
+        let mut resumed = TalkRng::seeded(rng.state());
+        assert_eq!(rng.next_u64(), resumed.next_u64());
+        assert_ne!(TalkRng::seeded(7).next_u64(), TalkRng::seeded(8).next_u64());
+    }
+
+    #[test]
+    fn unseeded_talk_gets_a_rng() {
+        let mut app = talks_minimal_app();
+        let talk = random_talk(&mut app, 0);
+        app.world.entity_mut(talk).remove::<TalkRng>();
+        next_text(&mut app, talk);
+        next_text(&mut app, talk);
+        assert!(app.world.get::<TalkRng>(talk).is_some());
+    }
+}
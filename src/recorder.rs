@@ -0,0 +1,134 @@
+//! Recording of the node events emitted by each talk, to inspect them in tests and diagnostics.
+//! Available with the `test` feature.
+
+use bevy::{
+    prelude::*,
+    reflect::{DynamicTypePath, FromReflect, TypePath},
+    utils::HashMap,
+};
+
+/// Plugin that records the node events emitted by each talk during the last update
+/// in the [`RecordedNodeEvents`] resource.
+///
+/// # Example
+/// ```rust
+/// use bevy::{ecs::system::Command, prelude::*};
+/// use bevy_talks::prelude::*;
+///
+/// let mut app = App::new();
+/// app.add_plugins((AssetPlugin::default(), TalksPlugin, NodeEventRecorderPlugin));
+/// let talk = app.world.spawn(Talk::default()).id();
+/// BuildTalkCommand::new(talk, TalkBuilder::default().say("Hello")).apply(&mut app.world);
+///
+/// app.world.send_event(NextNodeRequest::new(talk));
+/// app.update();
+/// assert_eq!(app.world.resource::<RecordedNodeEvents>().names(talk), vec!["TextNodeEvent"]);
+/// ```
+pub struct NodeEventRecorderPlugin;
+
+impl Plugin for NodeEventRecorderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RecordedNodeEvents>()
+            .add_systems(First, clear_recorded_events);
+    }
+}
+
+/// A node event recorded by the [`NodeEventRecorderPlugin`].
+#[derive(Debug)]
+pub struct RecordedEvent {
+    /// The short type path of the event (e.g. `TextNodeEvent`).
+    pub name: String,
+    /// A reflected copy of the event.
+    pub event: Box<dyn Reflect>,
+}
+
+/// Resource with the node events emitted by each talk (talk parent entity => events in emission order)
+/// during the last update. It is cleared at the start of every update.
+///
+/// The events are recorded right before being sent, after the filters ran,
+/// so they are the events the readers get.
+#[derive(Resource, Debug, Default)]
+pub struct RecordedNodeEvents(HashMap<Entity, Vec<RecordedEvent>>);
+
+impl RecordedNodeEvents {
+    /// Returns the events emitted by the talk, in emission order.
+    pub fn get(&self, talk: Entity) -> &[RecordedEvent] {
+        self.0.get(&talk).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Returns the short type paths of the events emitted by the talk, in emission order.
+    pub fn names(&self, talk: Entity) -> Vec<&str> {
+        self.get(talk).iter().map(|r| r.name.as_str()).collect()
+    }
+
+    /// Returns the events of type `E` emitted by the talk, in emission order.
+    pub fn events<E: FromReflect + TypePath>(&self, talk: Entity) -> Vec<E> {
+        self.get(talk)
+            .iter()
+            .filter(|r| r.name == E::short_type_path())
+            .filter_map(|r| E::from_reflect(&*r.event))
+            .collect()
+    }
+}
+
+/// Records a node event emitted by the given node, if the [`NodeEventRecorderPlugin`] was added.
+pub(crate) fn record_node_event(world: &mut World, node: Entity, event: &dyn Reflect) {
+    let Some(talk) = world.get::<Parent>(node).map(|p| p.get()) else {
+        return;
+    };
+    if let Some(mut recorded) = world.get_resource_mut::<RecordedNodeEvents>() {
+        recorded.0.entry(talk).or_default().push(RecordedEvent {
+            name: event.reflect_short_type_path().to_string(),
+            event: event.clone_value(),
+        });
+    }
+}
+
+/// Clears the events recorded in the previous update.
+fn clear_recorded_events(mut recorded: ResMut<RecordedNodeEvents>) {
+    recorded.0.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::Command;
+
+    use crate::{prelude::*, tests::talks_minimal_app};
+
+    use super::*;
+
+    #[test]
+    fn records_the_events_of_the_last_update() {
+        let mut app = talks_minimal_app();
+        app.add_plugins(NodeEventRecorderPlugin);
+        let builder = TalkBuilder::default()
+            .add_actor(Actor::new("bob", "Bob"))
+            .say("Hi")
+            .join(&["bob".to_string()]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let recorded = app.world.resource::<RecordedNodeEvents>();
+        assert_eq!(recorded.names(talk), vec!["TextNodeEvent"]);
+        assert_eq!(recorded.events::<TextNodeEvent>(talk)[0].text, "Hi");
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let recorded = app.world.resource::<RecordedNodeEvents>();
+        assert_eq!(recorded.names(talk), vec!["JoinNodeEvent"]);
+        assert!(recorded.events::<LeaveNodeEvent>(talk).is_empty());
+        assert_eq!(
+            recorded.events::<JoinNodeEvent>(talk)[0].actors,
+            vec!["Bob"]
+        );
+
+        app.update();
+        assert!(app
+            .world
+            .resource::<RecordedNodeEvents>()
+            .get(talk)
+            .is_empty());
+    }
+}
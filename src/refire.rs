@@ -0,0 +1,167 @@
+//! Cooldown of the refire requests, so UIs re-displaying the current node (e.g. on window re-open)
+//! don't spam the event consumers (voice lines restarting etc.) when toggled rapidly.
+
+use std::time::Duration;
+
+use bevy::{ecs::system::SystemParam, log::Level, prelude::*, utils::HashMap};
+
+use crate::{
+    config::{talks_log, LogCategory, TalksConfig},
+    prelude::Talk,
+};
+
+/// Resource and component to limit how often the events of the current node of a talk can be emitted again
+/// with a `RefireNodeRequest`. Add it to a talk parent entity to override the global resource for that talk.
+///
+/// The ignored refires are logged at the debug level.
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// let mut app = App::new();
+/// app.insert_resource(RefirePolicy::MinInterval(Duration::from_millis(500)));
+///
+/// // or just for a talk
+/// fn setup(mut commands: Commands) {
+///     commands.spawn_talk_with(TalkBuilder::default().say("Hi"), RefirePolicy::Always);
+/// }
+/// ```
+#[derive(Resource, Component, Reflect, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub enum RefirePolicy {
+    /// Every refire request emits the events again.
+    #[default]
+    Always,
+    /// The refire requests are ignored until the given (real) time has passed since the last refire of the same node.
+    /// The cooldown starts over when the talk moves to another node.
+    /// Without the `Time<Real>` resource (no `TimePlugin`) the refires are not limited.
+    MinInterval(Duration),
+}
+
+/// The last refired node of each talk, with the real time it was refired at.
+#[derive(Resource, Debug, Default)]
+pub(crate) struct LastRefires(HashMap<Entity, (Entity, Duration)>);
+
+/// Forgets the last refires of the despawned talks. It runs after the request handlers.
+pub(crate) fn forget_last_refires(
+    mut removed: RemovedComponents<Talk>,
+    mut last_refires: ResMut<LastRefires>,
+) {
+    for talk in removed.read() {
+        last_refires.0.remove(&talk);
+    }
+}
+
+/// System param used by the refire handler to apply the [`RefirePolicy`] of the talks.
+#[derive(SystemParam)]
+pub(crate) struct RefireGuard<'w, 's> {
+    /// The global refire policy.
+    policy: Res<'w, RefirePolicy>,
+    /// The talks with their own refire policy.
+    talks: Query<'w, 's, Option<&'static RefirePolicy>, With<Talk>>,
+    /// The last refires.
+    last_refires: ResMut<'w, LastRefires>,
+    /// The real time, for the cooldowns.
+    time: Option<Res<'w, Time<Real>>>,
+    /// The config of the logs.
+    config: Res<'w, TalksConfig>,
+}
+
+impl RefireGuard<'_, '_> {
+    /// Returns whether the current node of the talk can be refired, recording the refire if it can.
+    /// Logs the ignored refires.
+    pub(crate) fn allow(&mut self, talk: Entity, node: Entity) -> bool {
+        let policy = self.talks.get(talk).ok().flatten().unwrap_or(&self.policy);
+        let (RefirePolicy::MinInterval(interval), Some(time)) = (policy, &self.time) else {
+            return true;
+        };
+        let now = time.elapsed();
+        if let Some((last_node, last)) = self.last_refires.0.get(&talk) {
+            if *last_node == node && now < *last + *interval {
+                talks_log!(
+                    Some(&*self.config),
+                    LogCategory::Traversal,
+                    Level::DEBUG,
+                    "Ignored refire request for talk {talk:?}, node {node:?} was refired less than {interval:?} ago."
+                );
+                return false;
+            }
+        }
+        self.last_refires.0.insert(talk, (node, now));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use bevy::ecs::system::Command;
+
+    use crate::{prelude::*, tests::talks_minimal_app};
+
+    use super::*;
+
+    /// Returns the number of `TextNodeEvent` sent in the last update.
+    fn text_events(app: &App) -> usize {
+        let evs = app.world.resource::<Events<TextNodeEvent>>();
+        evs.get_reader().read(evs).count()
+    }
+
+    /// Sends a request and updates the app, returning the number of text events sent.
+    fn request(app: &mut App, event: impl Event) -> usize {
+        app.world.send_event(event);
+        app.update();
+        text_events(app)
+    }
+
+    #[test]
+    fn refires_limited_by_min_interval() {
+        let mut app = talks_minimal_app();
+        let start = Instant::now();
+        let mut time = Time::<Real>::new(start);
+        time.update_with_instant(start);
+        app.insert_resource(time);
+        app.insert_resource(RefirePolicy::MinInterval(Duration::from_secs(1)));
+        let builder = TalkBuilder::default().say("one").say("two");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        assert_eq!(request(&mut app, NextNodeRequest::new(talk)), 1);
+
+        assert_eq!(request(&mut app, RefireNodeRequest::new(talk)), 1);
+        // the window toggled right away
+        assert_eq!(request(&mut app, RefireNodeRequest::new(talk)), 0);
+
+        app.world
+            .resource_mut::<Time<Real>>()
+            .update_with_instant(start + Duration::from_secs(2));
+        assert_eq!(request(&mut app, RefireNodeRequest::new(talk)), 1);
+
+        // another node has its own cooldown
+        assert_eq!(request(&mut app, NextNodeRequest::new(talk)), 1);
+        assert_eq!(request(&mut app, RefireNodeRequest::new(talk)), 1);
+
+        // the talk policy overrides the global one
+        app.world.entity_mut(talk).insert(RefirePolicy::Always);
+        assert_eq!(request(&mut app, RefireNodeRequest::new(talk)), 1);
+    }
+
+    #[test]
+    fn despawned_talks_forgotten() {
+        let mut app = talks_minimal_app();
+        app.insert_resource(Time::<Real>::new(Instant::now()));
+        app.insert_resource(RefirePolicy::MinInterval(Duration::from_secs(1)));
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, TalkBuilder::default().say("Hi")).apply(&mut app.world);
+        request(&mut app, NextNodeRequest::new(talk));
+        request(&mut app, RefireNodeRequest::new(talk));
+        assert!(app.world.resource::<LastRefires>().0.contains_key(&talk));
+
+        app.world.entity_mut(talk).despawn_recursive();
+        app.update();
+        assert!(app.world.resource::<LastRefires>().0.is_empty());
+    }
+}
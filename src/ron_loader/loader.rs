@@ -2,14 +2,17 @@
 
 use bevy::{
     asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
-    log::error,
-    utils::{hashbrown::HashSet, BoxedFuture},
+    utils::{BoxedFuture, HashMap},
 };
-use indexmap::IndexMap;
 use serde_ron::de::from_bytes;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use thiserror::Error;
 
-use crate::prelude::{Action, ActionId, Actor, ActorSlug, TalkData};
+use crate::localization::TalkLocalization;
+use crate::prelude::{IrError, LocalizationError, TalkData, TalkIr};
 
 use super::types::RonTalk;
 
@@ -26,18 +29,25 @@ pub enum RonLoaderError {
     /// A [RON Error](serde_ron::error::SpannedError)
     #[error("Could not parse RON: {0}")]
     RonError(#[from] serde_ron::error::SpannedError),
-    /// Multiple actions have same id error
-    #[error("multiple actions have same id: {0}")]
-    DuplicateActionId(ActionId),
-    /// The actor slug is duplicated
-    #[error("the actor slug {0} is duplicated")]
-    DuplicateActorSlug(ActorSlug),
-    /// An action has the next field pointing to a non-existent action
-    #[error("the action {0} is pointing to id {1} which was not found")]
-    InvalidNextAction(ActionId, ActionId),
-    /// An action has a non-existent actor
-    #[error("An action is performed by actor {0}, but it was not defined in the actors.")]
-    InvalidActorSlug(ActorSlug),
+    /// The talk is not well formed (duplicate ids or slugs, invalid nexts or actors)
+    #[error("Invalid talk: {0}")]
+    Invalid(#[from] IrError),
+    /// A named talk of the pack is not well formed
+    #[error("Invalid talk {label}: {source}")]
+    InvalidLabeled {
+        /// The label of the talk in the pack.
+        label: String,
+        /// The validation error.
+        source: IrError,
+    },
+    /// The sidecar file of a locale could not be loaded or does not match the talk
+    #[error("Invalid {locale} localization: {source}")]
+    InvalidLocale {
+        /// The locale of the sidecar file.
+        locale: String,
+        /// The localization error.
+        source: LocalizationError,
+    },
 }
 
 impl AssetLoader for TalksLoader {
@@ -49,51 +59,13 @@ impl AssetLoader for TalksLoader {
         &'a self,
         reader: &'a mut Reader,
         _settings: &'a Self::Settings,
-        _load_context: &'a mut LoadContext,
+        load_context: &'a mut LoadContext,
     ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
         Box::pin(async move {
             let mut bytes = Vec::new();
             reader.read_to_end(&mut bytes).await?;
             let ron_talk = from_bytes::<RonTalk>(&bytes)?;
-
-            // build a TalkData Asset from the RonTalk
-
-            // 1. Build the actors vec
-            let actors = ron_talk.actors;
-            let mut talk_actors = Vec::<Actor>::with_capacity(actors.len());
-
-            let mut slug_set = HashSet::<ActorSlug>::with_capacity(actors.len());
-
-            // let mut asset_deps = vec![];
-            for actor in actors {
-                let slug = actor.slug.clone();
-
-                if !slug_set.insert(slug.clone()) {
-                    return Err(RonLoaderError::DuplicateActorSlug(slug));
-                }
-                let talk_actor = Actor::new(slug.clone(), actor.name);
-                talk_actors.push(talk_actor)
-            }
-
-            // 2. build the raw_actions vec
-            let mut raw_actions =
-                IndexMap::<ActionId, Action>::with_capacity(ron_talk.script.len());
-            for action in ron_talk.script {
-                let id = action.id;
-                if raw_actions.insert(id, action.into()).is_some() {
-                    return Err(RonLoaderError::DuplicateActionId(id));
-                }
-            }
-
-            validate_all_nexts(&raw_actions)?; // check if all nexts point to real actions
-            validate_actors(slug_set, &raw_actions)?;
-
-            let raw_talk = TalkData {
-                actors: talk_actors,
-                script: raw_actions,
-            };
-
-            Ok(raw_talk)
+            load_talk_data(ron_talk, load_context).await
         })
     }
 
@@ -102,52 +74,306 @@ impl AssetLoader for TalksLoader {
     }
 }
 
-/// Check if the actions use only actors that are defined in the talk.
-fn validate_actors(
-    actor_slugs: HashSet<ActorSlug>,
-    actions: &IndexMap<ActionId, Action>,
-) -> Result<(), RonLoaderError> {
-    for action in actions.values() {
-        for slug in action.actors.iter() {
-            if !actor_slugs.contains(slug) {
-                return Err(RonLoaderError::InvalidActorSlug(slug.clone()));
-            }
+/// Builds the `TalkData` asset from the parsed talk, with its named talks as labeled sub-assets and the localized
+/// texts from the sidecar files of its locales. Shared by the loaders of the talk formats with the same structure.
+pub(crate) async fn load_talk_data(
+    ron_talk: RonTalk,
+    load_context: &mut LoadContext<'_>,
+) -> Result<TalkData, RonLoaderError> {
+    let checked = CheckedTalkFile::new(ron_talk);
+
+    // the named talks of a pack become labeled sub-assets ("file.talk.ron#label")
+    let mut talks = HashMap::new();
+    for (label, sub_talk) in checked.talks {
+        let sub_data = sub_talk?
+            .into_asset(load_context)
+            .with_asset_id(load_context.path(), Some(&label));
+        let handle = load_context.add_labeled_asset(label.clone(), sub_data);
+        talks.insert(label, handle);
+    }
+
+    let mut talk_data = checked
+        .main?
+        .into_asset(load_context)
+        .with_asset_id(load_context.path(), None);
+    talk_data.talks = talks;
+
+    // the localized texts are read from the sidecar files ("file.talk.<locale>.ron")
+    let mut localizations = HashMap::with_capacity(checked.locales.len());
+    for locale in checked.locales {
+        let path = sidecar_path(load_context.path(), &locale);
+        let bytes = load_context
+            .read_asset_bytes(path)
+            .await
+            .map_err(Into::into);
+        let localization = localize(&locale, bytes, &talk_data)?;
+        localizations.insert(locale, localization);
+    }
+    talk_data.locales = Arc::new(localizations);
+    Ok(talk_data)
+}
+
+/// Parses and validates the RON talk at the path, with its named talks and the sidecar files of its locales,
+/// without loading it. All the invalid talks of a pack and all the invalid locales are reported.
+///
+/// It runs the same checks as [`load_talk_data`], reading the sidecar files from the file system.
+#[cfg(feature = "cli")]
+pub(crate) fn check_ron_talk(bytes: &[u8], path: &Path) -> Vec<RonLoaderError> {
+    use bevy::asset::io::AssetReaderError;
+
+    let checked = match from_bytes::<RonTalk>(bytes) {
+        Ok(ron_talk) => CheckedTalkFile::new(ron_talk),
+        Err(err) => return vec![err.into()],
+    };
+    let mut errors: Vec<RonLoaderError> = checked
+        .talks
+        .into_iter()
+        .filter_map(|(_, sub_talk)| sub_talk.err())
+        .collect();
+
+    let talk_data = match checked.main {
+        Ok(main) => main.data,
+        Err(err) => {
+            errors.push(err);
+            return errors;
+        }
+    };
+    for locale in checked.locales {
+        let sidecar = sidecar_path(path, &locale);
+        let bytes = std::fs::read(&sidecar).map_err(|err| {
+            let err = match err.kind() {
+                std::io::ErrorKind::NotFound => AssetReaderError::NotFound(sidecar),
+                _ => AssetReaderError::Io(err),
+            };
+            LocalizationError::Read(err.into())
+        });
+        if let Err(err) = localize(&locale, bytes, &talk_data) {
+            errors.push(err);
         }
     }
-    Ok(())
+    errors
 }
 
-/// Check if all `next` fields and `Choice` `next` fields in a `Vec<RawAction>` point to real actions.
-/// If the action has choices, the `next` field is not checked.
+/// The talks of a parsed talk file, validated through the IR: its named talks, its main talk and its locales.
 ///
-/// Returns a `TalkError::InvalidNextAction` error if any of the `next` fields or `Choice` `next` fields in the `RawAction`s do not point to real actions.
-fn validate_all_nexts(actions: &IndexMap<ActionId, Action>) -> Result<(), RonLoaderError> {
-    let id_set = actions.keys().cloned().collect::<HashSet<_>>();
-    for (id, action) in actions {
-        if !action.choices.is_empty() {
-            for choice in action.choices.iter() {
-                if !id_set.contains(&choice.next) {
-                    return Err(RonLoaderError::InvalidNextAction(*id, choice.next));
+/// It is shared by the asset loaders and the CLI check, so they validate the talk files in the same way.
+struct CheckedTalkFile {
+    /// The named talks of the pack (label => validated talk).
+    talks: Vec<(String, Result<CheckedTalk, RonLoaderError>)>,
+    /// The main talk.
+    main: Result<CheckedTalk, RonLoaderError>,
+    /// The locales with a sidecar file of localized texts.
+    locales: Vec<String>,
+}
+
+impl CheckedTalkFile {
+    /// Validates the named talks and the main talk of the parsed talk file.
+    fn new(mut ron_talk: RonTalk) -> Self {
+        let talks = std::mem::take(&mut ron_talk.talks)
+            .into_iter()
+            .map(|(label, sub_talk)| {
+                let sub_talk =
+                    CheckedTalk::new(sub_talk).map_err(|source| RonLoaderError::InvalidLabeled {
+                        label: label.clone(),
+                        source,
+                    });
+                (label, sub_talk)
+            })
+            .collect();
+        let locales = std::mem::take(&mut ron_talk.locales);
+        let main = CheckedTalk::new(ron_talk).map_err(Into::into);
+        Self {
+            talks,
+            main,
+            locales,
+        }
+    }
+}
+
+/// A talk validated through the IR, with the assets it references.
+struct CheckedTalk {
+    /// The talk data built from the IR.
+    data: TalkData,
+    /// The asset paths of the actor portraits (actor slug => path).
+    #[cfg(feature = "portraits")]
+    portraits: Vec<(String, String)>,
+}
+
+impl CheckedTalk {
+    /// Builds the `TalkData` from the `RonTalk` via the IR, which also validates it.
+    fn new(ron_talk: RonTalk) -> Result<Self, IrError> {
+        #[cfg(feature = "portraits")]
+        let portraits = ron_talk
+            .actors
+            .iter()
+            .filter_map(|a| Some((a.slug.clone(), a.portrait.clone()?)))
+            .collect::<Vec<_>>();
+
+        Ok(Self {
+            data: TalkData::try_from(TalkIr::from(ron_talk))?,
+            #[cfg(feature = "portraits")]
+            portraits,
+        })
+    }
+
+    /// Returns the talk data of the asset.
+    /// With the `portraits` feature, the actor portraits are loaded as dependencies of the asset.
+    fn into_asset(self, load_context: &mut LoadContext) -> TalkData {
+        #[cfg(feature = "portraits")]
+        {
+            let mut talk_data = self.data;
+            for (slug, path) in self.portraits {
+                if let Some(actor) = talk_data.actors.iter_mut().find(|a| a.slug == slug) {
+                    actor.portrait = Some(load_context.load(path));
                 }
             }
-        } else if let Some(next_id) = &action.next {
-            if !id_set.contains(next_id) {
-                return Err(RonLoaderError::InvalidNextAction(*id, *next_id));
-            }
+            talk_data
+        }
+        #[cfg(not(feature = "portraits"))]
+        {
+            let _ = load_context;
+            self.data
         }
     }
-    Ok(())
+}
+
+/// Parses the localized texts of the locale from the bytes of its sidecar file, checking they match the talk.
+fn localize(
+    locale: &str,
+    bytes: Result<Vec<u8>, LocalizationError>,
+    talk_data: &TalkData,
+) -> Result<TalkLocalization, RonLoaderError> {
+    bytes
+        .and_then(|bytes| TalkLocalization::from_ron(&bytes, &talk_data.script))
+        .map_err(|source| RonLoaderError::InvalidLocale {
+            locale: locale.to_string(),
+            source,
+        })
+}
+
+/// Returns the path of the sidecar file with the localized texts of the talk for the locale,
+/// next to the talk file (`dialogue.talk.ron` => `dialogue.talk.fr.ron`).
+/// The sidecar files are RON files whatever the format of the talk (`dialogue.talk.json` => `dialogue.talk.fr.ron`).
+fn sidecar_path(path: &Path, locale: &str) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let base = file_name
+        .rsplit_once('.')
+        .map_or(&*file_name, |(base, _)| base);
+    path.with_file_name(format!("{base}.{locale}.ron"))
 }
 
 #[cfg(test)]
 mod tests {
-    use indexmap::indexmap;
+    use crate::prelude::NodeKind;
 
-    use bevy::prelude::*;
+    use super::*;
 
-    use crate::prelude::*;
+    #[test]
+    fn pack_has_named_talks() {
+        let ron_talk = from_bytes::<RonTalk>(include_bytes!("../../assets/talks/pack.talk.ron"))
+            .expect("pack should parse");
+        assert!(ron_talk.script.is_empty());
+        assert_eq!(
+            ron_talk.talks.keys().collect::<Vec<_>>(),
+            vec!["intro", "shop"]
+        );
+        for (_, sub_talk) in ron_talk.talks {
+            let talk_data = TalkData::try_from(TalkIr::from(sub_talk));
+            assert!(talk_data.is_ok());
+        }
+    }
 
-    use super::*;
+    #[test]
+    fn checked_pack_reports_each_invalid_talk() {
+        let ron = r#"(
+            script: [(id: 1, text: Some("Hi"))],
+            talks: {
+                "bad": (script: [(id: 1, text: Some("Hi"), next: Some(2))]),
+                "good": (script: [(id: 1, text: Some("Hi"))]),
+            },
+            locales: ["fr"],
+        )"#;
+        let checked = CheckedTalkFile::new(from_bytes::<RonTalk>(ron.as_bytes()).unwrap());
+        assert!(checked.main.is_ok());
+        assert_eq!(checked.locales, vec!["fr"]);
+        assert_eq!(checked.talks.len(), 2);
+        assert!(matches!(
+            &checked.talks[0],
+            (label, Err(RonLoaderError::InvalidLabeled { label: err_label, .. }))
+                if label == "bad" && err_label == "bad"
+        ));
+        assert!(checked.talks[1].1.is_ok());
+    }
+
+    #[test]
+    fn actor_portrait_path() {
+        let ron =
+            r#"(actors: [(slug: "bob", name: "Bob", portrait: Some("bob.png"))], script: [])"#;
+        let ron_talk = from_bytes::<RonTalk>(ron.as_bytes()).expect("talk should parse");
+        assert_eq!(ron_talk.actors[0].portrait.as_deref(), Some("bob.png"));
+    }
+
+    #[test]
+    fn talk_metadata() {
+        let ron = r#"(
+            metadata: (title: Some("Shop"), tags: ["shop"], custom: { "area": "town" }),
+            script: [(id: 1, text: Some("Hi"))],
+        )"#;
+        let ron_talk = from_bytes::<RonTalk>(ron.as_bytes()).expect("talk should parse");
+        let talk_data = TalkData::try_from(TalkIr::from(ron_talk)).unwrap();
+        let metadata = talk_data.metadata();
+        assert_eq!(metadata.title.as_deref(), Some("Shop"));
+        assert_eq!(metadata.description, None);
+        assert!(metadata.has_tag("shop"));
+        assert_eq!(metadata.custom["area"], "town");
+    }
+
+    #[test]
+    fn choice_key() {
+        let ron = r#"(script: [
+            (id: 1, choices: Some([(text: "Yes", next: 2, key: "1"), (text: "No", next: 2)])),
+            (id: 2, text: Some("Ok")),
+        ])"#;
+        let ron_talk = from_bytes::<RonTalk>(ron.as_bytes()).expect("talk should parse");
+        let ir = TalkIr::from(ron_talk);
+        assert_eq!(ir.nodes[0].choices[0].key.as_deref(), Some("1"));
+        assert_eq!(ir.nodes[0].choices[1].key, None);
+        assert!(serde_ron::to_string(&RonTalk::from(&ir))
+            .unwrap()
+            .contains(r#"key:"1""#));
+    }
+
+    #[test]
+    fn choice_sound() {
+        let ron = r#"(script: [
+            (id: 1, choices: Some([(text: "Yes", next: 2, sound: Some("confirm.ogg")), (text: "No", next: 2)])),
+            (id: 2, text: Some("Ok")),
+        ])"#;
+        let ron_talk = from_bytes::<RonTalk>(ron.as_bytes()).expect("talk should parse");
+        let ir = TalkIr::from(ron_talk);
+        assert_eq!(ir.nodes[0].choices[0].sound.as_deref(), Some("confirm.ogg"));
+        assert_eq!(ir.nodes[0].choices[1].sound, None);
+    }
+
+    #[test]
+    fn locale_sidecar_path() {
+        assert_eq!(
+            sidecar_path(Path::new("talks/shop.talk.ron"), "fr"),
+            PathBuf::from("talks/shop.talk.fr.ron")
+        );
+        let ron = r#"(locales: ["fr", "de"], script: [(id: 1, text: Some("Hi"))])"#;
+        let ron_talk = from_bytes::<RonTalk>(ron.as_bytes()).expect("talk should parse");
+        assert_eq!(ron_talk.locales, vec!["fr", "de"]);
+    }
+
+    #[test]
+    fn custom_node_kind() {
+        let ron = r#"(script: [(id: 1, action: Custom("shop"), text: Some("blacksmith"))])"#;
+        let ron_talk = from_bytes::<RonTalk>(ron.as_bytes()).expect("talk should parse");
+        let ir = TalkIr::from(ron_talk);
+        assert_eq!(ir.nodes[0].kind, NodeKind::Custom("shop".to_string()));
+        assert_eq!(ir.nodes[0].text, "blacksmith");
+    }
 
     // TODO: test for the RonLoaderErrors
 
@@ -173,69 +399,4 @@ mod tests {
     //     assert_eq!(talk.actors.len(), 2);
     //     assert_eq!(talk.script.len(), 13);
     // }
-
-    #[test]
-    fn error_invalid_next_action() {
-        let talk = TalkData {
-            script: indexmap! {0 => Action {
-                next: Some(2),
-                ..default()
-            }},
-            ..default()
-        };
-        let res = validate_all_nexts(&talk.script);
-        assert!(res.is_err());
-    }
-
-    #[test]
-    fn error_not_found_in_choice() {
-        let talk = TalkData {
-            actors: default(),
-            script: indexmap! {
-                0 => Action {
-                    choices: vec![ChoiceData { next: 2, ..default()}],
-                    ..default()
-                },
-                1 => Action {
-                    ..default()
-                },
-            },
-        };
-        let res = validate_all_nexts(&talk.script);
-        assert!(res.is_err());
-    }
-
-    #[test]
-    fn test_validate_actors_valid() {
-        let mut actor_slugs = HashSet::<ActorSlug>::new();
-        actor_slugs.insert("actor1".to_string());
-        actor_slugs.insert("actor2".to_string());
-
-        let actions = indexmap! {
-            0 => Action {
-                actors: vec!["actor1".to_string()],
-                ..default()
-            },
-            1 => Action {
-                actors: vec!["actor1".to_string(), "actor2".to_string()],
-                ..default()
-            },
-        };
-        assert!(validate_actors(actor_slugs, &actions).is_ok());
-    }
-
-    #[test]
-    fn test_validate_actors_invalid() {
-        let mut actor_slugs = HashSet::<ActorSlug>::new();
-        actor_slugs.insert("actor1".to_string());
-        actor_slugs.insert("actor2".to_string());
-        let actions = indexmap! {
-            0 => Action {
-                actors: vec!["actor3".to_string()],
-                ..default()
-            },
-        };
-        let result = validate_actors(actor_slugs, &actions);
-        assert!(result.is_err());
-    }
 }
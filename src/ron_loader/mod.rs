@@ -1,4 +1,4 @@
 //! Asset loader for Talks from "talks.ron" files.
 
 pub(crate) mod loader;
-mod types;
+pub(crate) mod types;
@@ -1,18 +1,67 @@
 //! Types used by the ron loader.
 
-use serde::Deserialize;
+use std::collections::BTreeMap;
 
-use crate::prelude::{Action, ActionId, ActorSlug, ChoiceData, NodeKind};
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::{
+    ActionId, Actor, ActorSlug, ActorVoice, EmitterSpec, IrChoice, IrNode, NodeKind, TalkIr,
+    TalkMetadata,
+};
 
 /// The ron talk asset type.
 ///
 /// It contains a list of actors that appear in the Talk, and a list of actions that make up the Talk.
-#[derive(Deserialize, Debug)]
+/// It can also contain other named talks, loaded as labeled sub-assets (asset packs).
+#[derive(Deserialize, Serialize, Debug, Default)]
 pub(crate) struct RonTalk {
+    /// The optional metadata of the Talk (title, description, tags and custom data).
+    #[serde(default, skip_serializing_if = "TalkMetadata::is_empty")]
+    pub(crate) metadata: TalkMetadata,
     /// The list of actors that appear in the Talk.
+    #[serde(default)]
     pub(crate) actors: Vec<RonActor>,
+    /// The actor aliases of the Talk (alias => actor slug), e.g. `aliases: {"???": "stranger"}`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) aliases: BTreeMap<String, ActorSlug>,
+    /// The id of the action to resume from when the Talk is started again after it ended.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) resume_at: Option<ActionId>,
     /// The list of actions that make up the Talk.
+    #[serde(default)]
     pub(crate) script: Vec<RonAction>,
+    /// The named talks of the pack (label => talk).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) talks: BTreeMap<String, RonTalk>,
+    /// The locales of the sidecar files with the localized texts of the script (e.g. `["fr", "de"]`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) locales: Vec<String>,
+}
+
+impl From<RonTalk> for TalkIr {
+    fn from(val: RonTalk) -> Self {
+        TalkIr {
+            actors: val.actors.into_iter().map(|a| a.into()).collect(),
+            aliases: val.aliases,
+            resume_at: val.resume_at,
+            nodes: val.script.into_iter().map(|a| a.into()).collect(),
+            metadata: val.metadata,
+        }
+    }
+}
+
+impl From<&TalkIr> for RonTalk {
+    fn from(val: &TalkIr) -> Self {
+        RonTalk {
+            metadata: val.metadata.clone(),
+            actors: val.actors.iter().map(|a| a.into()).collect(),
+            aliases: val.aliases.clone(),
+            resume_at: val.resume_at,
+            script: val.nodes.iter().map(|n| n.into()).collect(),
+            talks: BTreeMap::new(),
+            locales: vec![],
+        }
+    }
 }
 
 /// A struct that represents an action in a Talk.
@@ -21,7 +70,7 @@ pub(crate) struct RonTalk {
 /// kind of action, the actors involved in the action, any choices that the user can make during
 /// the action, the text of the action, the ID of the next action to perform, whether the action is
 /// the start of the Talk, and any sound effect associated with the action.
-#[derive(Debug, Default, Deserialize, Clone)]
+#[derive(Debug, Default, Deserialize, Serialize, Clone)]
 pub(crate) struct RonAction {
     /// The ID of the action.
     pub(crate) id: ActionId,
@@ -37,15 +86,31 @@ pub(crate) struct RonAction {
     pub(crate) text: Option<String>,
     /// The ID of the next action to perform.
     pub(crate) next: Option<ActionId>,
+    /// The emphasis intensity of the action.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) emphasis: Option<f32>,
+    /// The icon (emoji or emote id) of the action.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) icon: Option<String>,
+    /// The thought shown alongside the text of the action.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) thought: Option<String>,
+    /// Whether the action is explicitly an end of the Talk.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) end: bool,
+    /// The components to construct and add to the node.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) emit: Vec<EmitterSpec>,
 }
 
-impl From<RonAction> for Action {
+impl From<RonAction> for IrNode {
     fn from(val: RonAction) -> Self {
         let mut action_kind = val.action;
         if action_kind == NodeKind::Talk && val.choices.is_some() {
             action_kind = NodeKind::Choice;
         }
-        Action {
+        IrNode {
+            id: val.id,
             kind: action_kind,
             actors: val.actors,
             choices: val
@@ -53,6 +118,30 @@ impl From<RonAction> for Action {
                 .map_or(vec![], |c| c.into_iter().map(|c| c.into()).collect()),
             text: val.text.unwrap_or_default(),
             next: val.next,
+            emphasis: val.emphasis,
+            icon: val.icon,
+            thought: val.thought,
+            end: val.end,
+            emit: val.emit,
+        }
+    }
+}
+
+impl From<&IrNode> for RonAction {
+    fn from(val: &IrNode) -> Self {
+        RonAction {
+            id: val.id,
+            action: val.kind.clone(),
+            actors: val.actors.clone(),
+            choices: (!val.choices.is_empty())
+                .then(|| val.choices.iter().map(|c| c.into()).collect()),
+            text: (!val.text.is_empty()).then(|| val.text.clone()),
+            next: val.next,
+            emphasis: val.emphasis,
+            icon: val.icon.clone(),
+            thought: val.thought.clone(),
+            end: val.end,
+            emit: val.emit.clone(),
         }
     }
 }
@@ -62,32 +151,113 @@ impl From<RonAction> for Action {
 /// This struct is used to define an actor in a Talk. It contains the ID of the actor, the
 /// name of the character that the actor plays, and an optional asset that represents the actor's
 /// appearance or voice.
-#[derive(Debug, Deserialize, Clone, Default)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub(crate) struct RonActor {
     /// A string identifying uniquely the actor.
     pub(crate) slug: ActorSlug,
     /// The name of the character that the actor plays.
     pub(crate) name: String,
-    // An optional asset that represents the actor's appearance or voice.
-    // pub(crate) asset: Option<String>,
+    /// The optional voice bank of the actor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) voice: Option<RonVoice>,
+    /// The optional asset path of the portrait image of the actor.
+    /// It is loaded by the loader with the `portraits` feature, ignored otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) portrait: Option<String>,
 }
+
+impl From<RonActor> for Actor {
+    fn from(val: RonActor) -> Self {
+        let actor = Actor::new(val.slug, val.name);
+        match val.voice {
+            Some(voice) => actor.with_voice(voice.into()),
+            None => actor,
+        }
+    }
+}
+
+impl From<&Actor> for RonActor {
+    fn from(val: &Actor) -> Self {
+        RonActor {
+            slug: val.slug.clone(),
+            name: val.name.clone(),
+            voice: val.voice.as_ref().map(|v| RonVoice {
+                blips: v.blips.clone(),
+                chars_per_blip: Some(v.chars_per_blip),
+            }),
+            #[cfg(feature = "portraits")]
+            portrait: val
+                .portrait
+                .as_ref()
+                .and_then(|p| p.path())
+                .map(|p| p.to_string()),
+            #[cfg(not(feature = "portraits"))]
+            portrait: None,
+        }
+    }
+}
+
+/// A struct that represents the voice bank of an actor.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub(crate) struct RonVoice {
+    /// The asset paths of the blip sounds.
+    pub(crate) blips: Vec<String>,
+    /// Play a blip every `chars_per_blip` revealed characters.
+    #[serde(default)]
+    pub(crate) chars_per_blip: Option<usize>,
+}
+
+impl From<RonVoice> for ActorVoice {
+    fn from(val: RonVoice) -> Self {
+        let voice = ActorVoice::new(val.blips);
+        match val.chars_per_blip {
+            Some(n) => voice.with_chars_per_blip(n),
+            None => voice,
+        }
+    }
+}
+
 /// A struct that represents a choice in a Talk.
 ///
 /// This struct is used to define a choice in a Talk. It contains the text of the choice and
 /// the ID of the next action to perform if the choice is selected.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub(crate) struct RonChoice {
     /// The text of the choice.
     pub(crate) text: String,
     /// The ID of the next action to perform if the choice is selected.
     pub(crate) next: ActionId,
+    /// The optional description of the choice.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) description: Option<String>,
+    /// The optional sound cue of the choice (e.g. an asset path), played when it is selected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) sound: Option<String>,
+    /// The optional name of the hotkey of the choice (e.g. `"1"`), empty for no hotkey.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub(crate) key: String,
 }
 
-impl From<RonChoice> for ChoiceData {
+impl From<RonChoice> for IrChoice {
     fn from(val: RonChoice) -> Self {
-        ChoiceData {
+        IrChoice {
             text: val.text,
             next: val.next,
+            description: val.description,
+            sound: val.sound,
+            key: Some(val.key).filter(|k| !k.is_empty()),
+        }
+    }
+}
+
+impl From<&IrChoice> for RonChoice {
+    fn from(val: &IrChoice) -> Self {
+        RonChoice {
+            text: val.text.clone(),
+            next: val.next,
+            description: val.description.clone(),
+            sound: val.sound.clone(),
+            key: val.key.clone().unwrap_or_default(),
         }
     }
 }
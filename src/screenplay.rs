@@ -0,0 +1,199 @@
+//! Export talks as human-readable screenplays, for proofreading and voice-over recording scripts.
+
+use std::fmt::Write;
+
+use bevy::utils::{HashMap, HashSet};
+
+use crate::prelude::{IrNode, IrNodeId, NodeKind, TalkIr};
+
+impl TalkIr {
+    /// Writes the talk as a screenplay: a line for each node with the actor names and text,
+    /// the choices indented under their choice node and explicit jumps where the dialogue doesn't flow
+    /// to the following line.
+    ///
+    /// The nodes are written following the dialogue from the entry node, the branches of the choices
+    /// after each other. Nodes that cannot be reached are written at the end.
+    ///
+    /// To export a `TalkData` asset or a spawned graph convert them to the IR first,
+    /// with `TalkIr::from(&talk_data)` or [`TalkIr::from_graph`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use bevy_talks::prelude::*;
+    ///
+    /// let ir = TalkIr {
+    ///     actors: vec![Actor::new("bob", "Bob")],
+    ///     nodes: vec![IrNode { id: 1, text: "Hello!".to_string(), actors: vec!["bob".to_string()], ..Default::default() }],
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(ir.to_screenplay(), "[1] BOB: Hello!\n    (END)\n");
+    /// ```
+    pub fn to_screenplay(&self) -> String {
+        let nodes: HashMap<IrNodeId, &IrNode> = self.nodes.iter().map(|n| (n.id, n)).collect();
+        let mut writer = ScreenplayWriter {
+            ir: self,
+            nodes,
+            visited: HashSet::new(),
+            out: String::new(),
+        };
+
+        for node in self.nodes.iter() {
+            if !writer.visited.contains(&node.id) {
+                writer.visit(node.id);
+            }
+        }
+        writer.out
+    }
+}
+
+/// Helper to write the screenplay visiting the nodes.
+struct ScreenplayWriter<'a> {
+    /// The talk to write.
+    ir: &'a TalkIr,
+    /// The nodes by id.
+    nodes: HashMap<IrNodeId, &'a IrNode>,
+    /// The nodes already written.
+    visited: HashSet<IrNodeId>,
+    /// The screenplay.
+    out: String,
+}
+
+impl ScreenplayWriter<'_> {
+    /// Writes the node and the following ones, if not already written.
+    fn visit(&mut self, id: IrNodeId) {
+        let mut next = Some(id);
+        while let Some(id) = next.take() {
+            if !self.visited.insert(id) {
+                // already written, just write the jump
+                let _ = writeln!(self.out, "    (GO TO [{id}])");
+                return;
+            }
+            let Some(node) = self.nodes.get(&id).copied() else {
+                return;
+            };
+            let names = self.actor_names(node);
+
+            let _ = match &node.kind {
+                NodeKind::Start => writeln!(self.out, "[{id}] (START)"),
+                NodeKind::Talk if names.is_empty() => writeln!(self.out, "[{id}] {}", node.text),
+                NodeKind::Talk => {
+                    writeln!(self.out, "[{id}] {}: {}", names.to_uppercase(), node.text)
+                }
+                NodeKind::Join => writeln!(self.out, "[{id}] ({names} joins)"),
+                NodeKind::Leave => writeln!(self.out, "[{id}] ({names} leaves)"),
+                NodeKind::Choice => writeln!(self.out, "[{id}] CHOICE:"),
+                NodeKind::Custom(kind) => writeln!(self.out, "[{id}] ({kind}: {})", node.text),
+            };
+
+            if node.kind == NodeKind::Choice {
+                for (i, choice) in node.choices.iter().enumerate() {
+                    let _ = writeln!(
+                        self.out,
+                        "    {}. {} -> [{}]",
+                        i + 1,
+                        choice.text,
+                        choice.next
+                    );
+                }
+                for choice in node.choices.iter() {
+                    if !self.visited.contains(&choice.next) {
+                        self.visit(choice.next);
+                    }
+                }
+                return;
+            }
+
+            match node.next {
+                None => {
+                    let _ = writeln!(self.out, "    (END)");
+                }
+                Some(next_id) => next = Some(next_id),
+            }
+        }
+    }
+
+    /// The names of the actors of the node, joined with `&`.
+    fn actor_names(&self, node: &IrNode) -> String {
+        node.actors
+            .iter()
+            .map(|slug| {
+                self.ir
+                    .actors
+                    .iter()
+                    .find(|a| &a.slug == slug)
+                    .map_or(slug.as_str(), |a| a.name.as_str())
+            })
+            .collect::<Vec<_>>()
+            .join(" & ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[test]
+    fn screenplay_with_choices_and_jumps() {
+        let ir = TalkIr {
+            actors: vec![Actor::new("bob", "Bob"), Actor::new("alice", "Alice")],
+            nodes: vec![
+                IrNode {
+                    id: 1,
+                    kind: NodeKind::Join,
+                    actors: vec!["bob".to_string(), "alice".to_string()],
+                    next: Some(2),
+                    ..Default::default()
+                },
+                IrNode {
+                    id: 2,
+                    text: "Hi!".to_string(),
+                    actors: vec!["bob".to_string()],
+                    next: Some(3),
+                    ..Default::default()
+                },
+                IrNode {
+                    id: 3,
+                    kind: NodeKind::Choice,
+                    choices: vec![
+                        IrChoice {
+                            text: "Again".to_string(),
+                            next: 2,
+                            ..Default::default()
+                        },
+                        IrChoice {
+                            text: "Leave".to_string(),
+                            next: 4,
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+                IrNode {
+                    id: 4,
+                    kind: NodeKind::Leave,
+                    actors: vec!["alice".to_string()],
+                    next: Some(5),
+                    ..Default::default()
+                },
+                IrNode {
+                    id: 5,
+                    text: "The end.".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let expected = "\
+[1] (Bob & Alice joins)
+[2] BOB: Hi!
+[3] CHOICE:
+    1. Again -> [2]
+    2. Leave -> [4]
+[4] (Alice leaves)
+[5] The end.
+    (END)
+";
+        assert_eq!(ir.to_screenplay(), expected);
+    }
+}
@@ -0,0 +1,277 @@
+//! The pluggable persistence backend of the talk data (variables and history), to back it with your save system.
+
+use std::collections::BTreeMap;
+
+use bevy::{ecs::system::Command, log::Level, prelude::*};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::config::{talks_log, LogCategory, TalksConfig};
+use crate::prelude::{TakenChoices, TalkStates, TalkVariables};
+
+/// The name of the blob with the [`TalkStates`].
+pub const TALK_STATES_BLOB: &str = "talk_states";
+/// The name of the blob with the [`TakenChoices`].
+pub const TAKEN_CHOICES_BLOB: &str = "taken_choices";
+
+/// Trait to implement to store the persistent talk data in your own save system.
+///
+/// The [`TalkVariables`] are stored as key-values (variable name => value), the history of the talks
+/// ([`TalkStates`] and [`TakenChoices`]) as RON blobs ([`TALK_STATES_BLOB`] and [`TAKEN_CHOICES_BLOB`]).
+/// They are written to the storage right after the traversal every time they change, and read back
+/// when the storage is set with [`AppExt::set_talk_persistence`](crate::prelude::AppExt::set_talk_persistence)
+/// or with a [`LoadTalkPersistence`] command.
+///
+/// The default storage is a [`MemoryPersistence`].
+///
+/// # Example
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// /// Writes the talk data in the save slot of the game.
+/// #[derive(Default)]
+/// struct SaveSlot {
+///     memory: MemoryPersistence,
+/// }
+///
+/// impl TalkPersistence for SaveSlot {
+///     fn set_value(&mut self, key: &str, value: &str) {
+///         self.memory.set_value(key, value);
+///         // mark the slot as dirty...
+///     }
+///     fn value(&self, key: &str) -> Option<String> {
+///         self.memory.value(key)
+///     }
+///     fn remove_value(&mut self, key: &str) {
+///         self.memory.remove_value(key);
+///     }
+///     fn keys(&self) -> Vec<String> {
+///         self.memory.keys()
+///     }
+///     fn save_blob(&mut self, name: &str, blob: &[u8]) {
+///         self.memory.save_blob(name, blob);
+///     }
+///     fn load_blob(&self, name: &str) -> Option<Vec<u8>> {
+///         self.memory.load_blob(name)
+///     }
+/// }
+///
+/// let mut app = App::new();
+/// app.set_talk_persistence(SaveSlot::default());
+/// ```
+pub trait TalkPersistence: Send + Sync + 'static {
+    /// Stores the value of the key.
+    fn set_value(&mut self, key: &str, value: &str);
+
+    /// Returns the stored value of the key.
+    fn value(&self, key: &str) -> Option<String>;
+
+    /// Removes the value of the key.
+    fn remove_value(&mut self, key: &str);
+
+    /// Returns the keys of the stored values.
+    fn keys(&self) -> Vec<String>;
+
+    /// Stores the blob with the given name, replacing the previous one.
+    fn save_blob(&mut self, name: &str, blob: &[u8]);
+
+    /// Returns the stored blob with the given name.
+    fn load_blob(&self, name: &str) -> Option<Vec<u8>>;
+}
+
+/// The default [`TalkPersistence`], keeping the data in memory.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MemoryPersistence {
+    /// The stored values.
+    pub values: BTreeMap<String, String>,
+    /// The stored blobs.
+    pub blobs: BTreeMap<String, Vec<u8>>,
+}
+
+impl TalkPersistence for MemoryPersistence {
+    fn set_value(&mut self, key: &str, value: &str) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    fn value(&self, key: &str) -> Option<String> {
+        self.values.get(key).cloned()
+    }
+
+    fn remove_value(&mut self, key: &str) {
+        self.values.remove(key);
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.values.keys().cloned().collect()
+    }
+
+    fn save_blob(&mut self, name: &str, blob: &[u8]) {
+        self.blobs.insert(name.to_string(), blob.to_vec());
+    }
+
+    fn load_blob(&self, name: &str) -> Option<Vec<u8>> {
+        self.blobs.get(name).cloned()
+    }
+}
+
+/// Resource with the [`TalkPersistence`] the persistent talk data is written to.
+#[derive(Resource)]
+pub struct TalkPersistenceStore(pub Box<dyn TalkPersistence>);
+
+impl Default for TalkPersistenceStore {
+    fn default() -> Self {
+        Self(Box::<MemoryPersistence>::default())
+    }
+}
+
+/// Command to read the persistent talk data back from the [`TalkPersistence`], e.g. after your save file was loaded.
+/// The [`TalkVariables`], [`TalkStates`] and [`TakenChoices`] are replaced with the stored ones.
+pub struct LoadTalkPersistence;
+
+impl Command for LoadTalkPersistence {
+    fn apply(self, world: &mut World) {
+        let Some(TalkPersistenceStore(storage)) = world.get_resource::<TalkPersistenceStore>()
+        else {
+            return;
+        };
+        let mut variables = TalkVariables::default();
+        for key in storage.keys() {
+            if let Some(value) = storage.value(&key) {
+                variables.set(key, value);
+            }
+        }
+        let states = load_blob::<TalkStates>(&**storage, TALK_STATES_BLOB);
+        let taken = load_blob::<TakenChoices>(&**storage, TAKEN_CHOICES_BLOB);
+
+        world.insert_resource(variables);
+        match (states, taken) {
+            (Ok(states), Ok(taken)) => {
+                world.insert_resource(states);
+                world.insert_resource(taken);
+            }
+            (Err(err), _) | (_, Err(err)) => talks_log!(
+                world.get_resource::<TalksConfig>(),
+                LogCategory::Build,
+                Level::ERROR,
+                "Could not load the talk history from the persistence backend: {err}"
+            ),
+        }
+    }
+}
+
+/// Returns the value of the stored RON blob, or the default value if there is no blob.
+fn load_blob<T: DeserializeOwned + Default>(
+    storage: &dyn TalkPersistence,
+    name: &str,
+) -> Result<T, String> {
+    match storage.load_blob(name) {
+        Some(blob) => serde_ron::de::from_bytes(&blob).map_err(|err| format!("{name}: {err}")),
+        None => Ok(T::default()),
+    }
+}
+
+/// Stores the value as a RON blob.
+fn save_blob<T: Serialize>(storage: &mut dyn TalkPersistence, name: &str, value: &T) {
+    match serde_ron::to_string(value) {
+        Ok(ron) => storage.save_blob(name, ron.as_bytes()),
+        Err(err) => error!("Could not save the talk persistence blob {name}: {err}"),
+    }
+}
+
+/// Writes the changed persistent talk data to the [`TalkPersistence`]. It runs after the traversal.
+pub(crate) fn write_talk_persistence(
+    variables: Res<TalkVariables>,
+    states: Res<TalkStates>,
+    taken: Res<TakenChoices>,
+    mut backend: ResMut<TalkPersistenceStore>,
+) {
+    let storage = &mut *backend.0;
+    if variables.is_changed() {
+        for key in storage.keys() {
+            if variables.get(&key).is_none() {
+                storage.remove_value(&key);
+            }
+        }
+        for (variable, value) in variables.iter() {
+            if storage.value(variable).as_deref() != Some(value) {
+                storage.set_value(variable, value);
+            }
+        }
+    }
+    if states.is_changed() {
+        save_blob(storage, TALK_STATES_BLOB, &*states);
+    }
+    if taken.is_changed() {
+        save_blob(storage, TAKEN_CHOICES_BLOB, &*taken);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{prelude::*, tests::talks_minimal_app};
+
+    use super::*;
+
+    /// A storage shared with the test, to look into it.
+    #[derive(Clone, Default)]
+    struct SharedPersistence(std::sync::Arc<std::sync::Mutex<MemoryPersistence>>);
+
+    impl TalkPersistence for SharedPersistence {
+        fn set_value(&mut self, key: &str, value: &str) {
+            self.0.lock().unwrap().set_value(key, value);
+        }
+        fn value(&self, key: &str) -> Option<String> {
+            self.0.lock().unwrap().value(key)
+        }
+        fn remove_value(&mut self, key: &str) {
+            self.0.lock().unwrap().remove_value(key);
+        }
+        fn keys(&self) -> Vec<String> {
+            self.0.lock().unwrap().keys()
+        }
+        fn save_blob(&mut self, name: &str, blob: &[u8]) {
+            self.0.lock().unwrap().save_blob(name, blob);
+        }
+        fn load_blob(&self, name: &str) -> Option<Vec<u8>> {
+            self.0.lock().unwrap().load_blob(name)
+        }
+    }
+
+    #[test]
+    fn data_written_to_and_read_from_the_storage() {
+        let storage = SharedPersistence::default();
+        let mut app = talks_minimal_app();
+        app.set_talk_persistence(storage.clone());
+        let builder = TalkBuilder::default().talk_id("bob").say("Hi").say("Bye");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        app.world
+            .resource_mut::<TalkVariables>()
+            .set("mood", "angry");
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(storage.value("mood").as_deref(), Some("angry"));
+        assert!(storage.load_blob(TALK_STATES_BLOB).is_some());
+
+        app.world.resource_mut::<TalkVariables>().remove("mood");
+        app.update();
+        assert_eq!(storage.value("mood"), None);
+        app.world
+            .resource_mut::<TalkVariables>()
+            .set("quest", "done");
+        app.update();
+
+        // a new session reads the stored data
+        let mut app = talks_minimal_app();
+        app.set_talk_persistence(storage.clone());
+        assert_eq!(
+            app.world.resource::<TalkVariables>().get("quest"),
+            Some("done")
+        );
+        assert_eq!(
+            app.world.resource::<TalkStates>().get("bob"),
+            Some(&TalkState { node: 1 })
+        );
+    }
+}
@@ -0,0 +1,331 @@
+//! Serializable talk requests and states, to synchronize the talks over the network.
+//!
+//! The entities are different on each peer, so the talks are identified by their [`TalkId`] and the nodes
+//! by their [`OrderIndex`] (as in [`TalkState`]).
+//! The peer driving the talk exports its requests with [`TalkNetIds`] and the other peers apply them as commands.
+
+use bevy::{
+    ecs::system::{Command, SystemParam, SystemState},
+    log::Level,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{talks_log, LogCategory, TalksConfig};
+use crate::persistence::{move_current_silently, TalkId, TalkState};
+use crate::prelude::{
+    ChooseNodeRequest, CurrentNodes, NextNodeRequest, OrderIndex, RefireNodeRequest,
+};
+
+/// A talk request with the stable ids of the talk and of the nodes, to send to the other peers.
+///
+/// Apply it as a command on the receiving peer to send the matching request event for the local talk
+/// with the same [`TalkId`]. The requests for unknown talks or nodes are ignored.
+///
+/// # Example
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// fn on_message(mut commands: Commands, message: &str) {
+///     if let Ok(request) = serde_ron::from_str::<NetTalkRequest>(message) {
+///         commands.add(request);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NetTalkRequest {
+    /// A [`NextNodeRequest`] for the talk with the id.
+    Next {
+        /// The id of the talk.
+        talk: String,
+    },
+    /// A [`ChooseNodeRequest`] for the talk with the id.
+    Choose {
+        /// The id of the talk.
+        talk: String,
+        /// The [`OrderIndex`] of the chosen node.
+        next: u32,
+    },
+    /// A [`RefireNodeRequest`] for the talk with the id.
+    Refire {
+        /// The id of the talk.
+        talk: String,
+    },
+}
+
+impl NetTalkRequest {
+    /// Returns the id of the talk of the request.
+    pub fn talk(&self) -> &str {
+        match self {
+            Self::Next { talk } | Self::Choose { talk, .. } | Self::Refire { talk } => talk,
+        }
+    }
+}
+
+impl Command for NetTalkRequest {
+    fn apply(self, world: &mut World) {
+        let mut state = SystemState::<TalkNetIds>::new(world);
+        let ids = state.get(world);
+        let Some(talk) = ids.talk(self.talk()) else {
+            talks_log!(
+                world.get_resource::<TalksConfig>(),
+                LogCategory::Traversal,
+                Level::WARN,
+                "Ignored network request for unknown talk {}",
+                self.talk()
+            );
+            return;
+        };
+        let next = match &self {
+            Self::Choose { next, .. } => match ids.node(talk, *next) {
+                Some(next) => Some(next),
+                None => {
+                    talks_log!(
+                        world.get_resource::<TalksConfig>(),
+                        LogCategory::Traversal,
+                        Level::WARN,
+                        "Ignored network choice for talk {}: no node {next}",
+                        self.talk()
+                    );
+                    return;
+                }
+            },
+            _ => None,
+        };
+        match (self, next) {
+            (Self::Next { .. }, _) => {
+                world.send_event(NextNodeRequest::new(talk));
+            }
+            (Self::Refire { .. }, _) => {
+                world.send_event(RefireNodeRequest::new(talk));
+            }
+            (Self::Choose { .. }, Some(next)) => {
+                world.send_event(ChooseNodeRequest::new(talk, next));
+            }
+            (Self::Choose { .. }, None) => {}
+        }
+    }
+}
+
+/// The state of a talk with its stable id, to send to the other peers (e.g. to a peer joining mid-talk).
+///
+/// Apply it as a command on the receiving peer to move the local talk with the same [`TalkId`]
+/// to the node, without emitting the node events. Send a [`RefireNodeRequest`] to get them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncTalkState {
+    /// The id of the talk.
+    pub talk: String,
+    /// The state of the talk.
+    pub state: TalkState,
+}
+
+impl Command for SyncTalkState {
+    fn apply(self, world: &mut World) {
+        let mut state = SystemState::<TalkNetIds>::new(world);
+        let ids = state.get(world);
+        let Some(talk) = ids.talk(&self.talk) else {
+            return;
+        };
+        let (Some(target), Some(current)) = (
+            ids.node(talk, self.state.node),
+            world.resource::<CurrentNodes>().get(talk),
+        ) else {
+            talks_log!(
+                world.get_resource::<TalksConfig>(),
+                LogCategory::Traversal,
+                Level::WARN,
+                "Ignored state for talk {}: no node {}",
+                self.talk,
+                self.state.node
+            );
+            return;
+        };
+        if target != current {
+            move_current_silently(world, talk, current, target);
+        }
+    }
+}
+
+/// System param to map the talk and node entities to their stable ids and back.
+///
+/// # Example
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// /// Sends the requests of the local player to the other peers.
+/// fn export_requests(mut requests: EventReader<NextNodeRequest>, ids: TalkNetIds) {
+///     for request in requests.read() {
+///         if let Some(request) = ids.export_next(request) {
+///             let message = serde_ron::to_string(&request).unwrap();
+///             // send the message over your network layer
+///         }
+///     }
+/// }
+/// ```
+#[derive(SystemParam)]
+pub struct TalkNetIds<'w, 's> {
+    /// The talks with a stable id, with their nodes.
+    talks: Query<'w, 's, (Entity, &'static TalkId, &'static Children)>,
+    /// The order indexes of the nodes.
+    indexes: Query<'w, 's, &'static OrderIndex>,
+    /// The current node of the talks.
+    current_nodes: Res<'w, CurrentNodes>,
+}
+
+impl TalkNetIds<'_, '_> {
+    /// Returns the talk with the given id.
+    pub fn talk(&self, id: &str) -> Option<Entity> {
+        self.talks
+            .iter()
+            .find(|(_, talk_id, _)| talk_id.0 == id)
+            .map(|(talk, _, _)| talk)
+    }
+
+    /// Returns the id of the talk.
+    pub fn talk_id(&self, talk: Entity) -> Option<&str> {
+        self.talks.get(talk).ok().map(|(_, id, _)| id.0.as_str())
+    }
+
+    /// Returns the [`OrderIndex`] of the node of the talk. `0` is the start node.
+    pub fn node_index(&self, talk: Entity, node: Entity) -> Option<u32> {
+        let (_, _, children) = self.talks.get(talk).ok()?;
+        if !children.contains(&node) {
+            return None;
+        }
+        self.indexes.get(node).ok().map(|index| index.0)
+    }
+
+    /// Returns the node of the talk with the [`OrderIndex`].
+    pub fn node(&self, talk: Entity, index: u32) -> Option<Entity> {
+        let (_, _, children) = self.talks.get(talk).ok()?;
+        children
+            .iter()
+            .copied()
+            .find(|c| self.indexes.get(*c).is_ok_and(|i| i.0 == index))
+    }
+
+    /// Returns the request with the stable ids, if the talk has a [`TalkId`].
+    pub fn export_next(&self, request: &NextNodeRequest) -> Option<NetTalkRequest> {
+        let talk = self.talk_id(request.talk)?.to_owned();
+        Some(NetTalkRequest::Next { talk })
+    }
+
+    /// Returns the request with the stable ids, if the talk has a [`TalkId`] and the node is one of its nodes.
+    pub fn export_choose(&self, request: &ChooseNodeRequest) -> Option<NetTalkRequest> {
+        let talk = self.talk_id(request.talk)?.to_owned();
+        let next = self.node_index(request.talk, request.next)?;
+        Some(NetTalkRequest::Choose { talk, next })
+    }
+
+    /// Returns the request with the stable ids, if the talk has a [`TalkId`].
+    pub fn export_refire(&self, request: &RefireNodeRequest) -> Option<NetTalkRequest> {
+        let talk = self.talk_id(request.talk)?.to_owned();
+        Some(NetTalkRequest::Refire { talk })
+    }
+
+    /// Returns the current state of the talk with the stable ids, if it has a [`TalkId`].
+    pub fn export_state(&self, talk: Entity) -> Option<SyncTalkState> {
+        let id = self.talk_id(talk)?.to_owned();
+        let node = self.node_index(talk, self.current_nodes.get(talk)?)?;
+        Some(SyncTalkState {
+            talk: id,
+            state: TalkState { node },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{prelude::*, tests::talks_minimal_app};
+
+    use super::*;
+
+    /// Builds the talk with the id in the app.
+    fn build(app: &mut App) -> Entity {
+        let builder = TalkBuilder::default().talk_id("bob").say("Hi").choose(vec![
+            ("Bye", TalkBuilder::default().say("Bye")),
+            ("Stay", TalkBuilder::default().say("Ok")),
+        ]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        talk
+    }
+
+    /// Returns the text of the current node of the talk.
+    fn current_text(app: &App, talk: Entity) -> String {
+        let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        app.world.get::<TextNode>(current).unwrap().0.clone()
+    }
+
+    /// Sends the request over the "network".
+    fn roundtrip(request: &NetTalkRequest) -> NetTalkRequest {
+        serde_ron::from_str(&serde_ron::to_string(request).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn replica_follows_the_driver() {
+        let mut driver = talks_minimal_app();
+        let mut replica = talks_minimal_app();
+        let driver_talk = build(&mut driver);
+        // different entities on the replica
+        replica.world.spawn_empty();
+        let replica_talk = build(&mut replica);
+
+        let mut ids = SystemState::<TalkNetIds>::new(&mut driver.world);
+        let request = NextNodeRequest::new(driver_talk);
+        let exported = ids.get(&driver.world).export_next(&request).unwrap();
+        assert_eq!(
+            exported,
+            NetTalkRequest::Next {
+                talk: "bob".to_string()
+            }
+        );
+        roundtrip(&exported).apply(&mut replica.world);
+        replica.update();
+        assert_eq!(current_text(&replica, replica_talk), "Hi");
+
+        for _ in 0..2 {
+            driver.world.send_event(NextNodeRequest::new(driver_talk));
+            driver.update();
+        }
+        roundtrip(&exported).apply(&mut replica.world);
+        replica.update();
+
+        let choices = driver.world.resource::<Events<ChoiceNodeEvent>>();
+        let stay = choices.get_reader().read(choices).last().unwrap().choices[1].next;
+        let request = ChooseNodeRequest::new(driver_talk, stay);
+        let exported = ids.get(&driver.world).export_choose(&request).unwrap();
+        driver.world.send_event(request);
+        driver.update();
+        roundtrip(&exported).apply(&mut replica.world);
+        replica.update();
+
+        assert_eq!(current_text(&driver, driver_talk), "Ok");
+        assert_eq!(current_text(&replica, replica_talk), "Ok");
+    }
+
+    #[test]
+    fn late_joiner_gets_the_state() {
+        let mut driver = talks_minimal_app();
+        let mut replica = talks_minimal_app();
+        let driver_talk = build(&mut driver);
+        let replica_talk = build(&mut replica);
+        driver.world.send_event(NextNodeRequest::new(driver_talk));
+        driver.update();
+
+        let mut ids = SystemState::<TalkNetIds>::new(&mut driver.world);
+        let state = ids.get(&driver.world).export_state(driver_talk).unwrap();
+        assert_eq!(state.state, TalkState { node: 1 });
+        state.apply(&mut replica.world);
+
+        assert_eq!(current_text(&replica, replica_talk), "Hi");
+        assert!(replica.world.get::<HasStarted>(replica_talk).is_some());
+        // unknown talks are ignored
+        NetTalkRequest::Next {
+            talk: "alice".to_string(),
+        }
+        .apply(&mut replica.world);
+    }
+}
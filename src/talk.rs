@@ -1,9 +1,16 @@
 //! The main types for a Talk.
 
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
 use aery::prelude::*;
-use bevy::prelude::*;
+use bevy::{ecs::system::Command, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
 
-use crate::builder::TalkBuilder;
+use crate::{
+    builder::TalkBuilder,
+    prelude::{ActorSlug, ChoiceNode, HasStarted, TextNode},
+};
 
 /// The relationship of the dialogue nodes.
 /// It needs to be Poly because the choice nodes can have multiple branches.
@@ -18,12 +25,15 @@ pub struct FollowedBy;
 pub struct PerformedBy;
 
 /// Market component used to identify the parent entity of dialogue entity graphs.
+/// When the talk starts (the traversal leaves the start node, whatever the request), the
+/// [`HasStarted`](crate::markers::HasStarted) marker is added to the entity (see [`SetHasStarted`]).
+/// Query it (e.g. with `Has<HasStarted>`) to know if the talk has started.
 /// Build entities with Talk components via the [`TalkBuilder`] to correctly setup the dialogue graph.
-#[derive(Component, Default, Debug)]
+#[derive(Component, Reflect, Default, Debug)]
+#[reflect(Component, Default)]
 pub struct Talk {
-    /// Helper field to know if the talk has started.
-    /// You can also check if the child `CurrentNode` has the `StartNode` component.
-    pub has_started: bool,
+    /// The metadata of the talk, copied from the builder (or the asset) when the graph is built.
+    pub metadata: TalkMetadata,
 }
 
 impl Talk {
@@ -33,38 +43,246 @@ impl Talk {
     }
 }
 
-/// Marker component for the current node in a Talk.
-#[derive(Component)]
-#[component(storage = "SparseSet")]
-pub struct CurrentNode;
+/// Optional descriptive data of a talk, for dialogue browsers, debug overlays and analytics.
+/// It has no effect on the traversal.
+///
+/// In RON assets it is the `metadata` block:
+/// ```ron
+/// metadata: ( title: Some("Bob's Shop"), tags: ["shop", "bob"], custom: { "area": "town" } ),
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Reflect)]
+#[serde(default)]
+#[reflect(Default)]
+pub struct TalkMetadata {
+    /// The title of the talk.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// A description of the talk.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Free-form tags to group and filter the talks.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Custom key-value data. It is not reflected.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    #[reflect(ignore)]
+    pub custom: BTreeMap<String, String>,
+}
 
-/// Mark a dialogue node as a starting node.
-#[derive(Component, Default, Debug)]
-pub struct StartNode;
+impl TalkMetadata {
+    /// Returns true if no metadata is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
 
-/// Mark a dialogue node as an end node.
-#[derive(Component, Default, Debug)]
-pub struct EndNode;
+    /// Returns true if the talk has the given tag.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
 
-/// Component to mark a dialogue node as a text node containing some text.
-#[derive(Component, Reflect, Default, Debug)]
-#[reflect(Component)]
-pub struct TextNode(pub String);
+/// Command to mark a talk as started: it adds the [`HasStarted`] marker to the talk.
+///
+/// The traversal adds it every time a talk leaves its start node, so all the requests (next, choose or jumps)
+/// mark the talk in the same way. It is idempotent: an already started talk is left untouched
+/// (no change detection is triggered), and it does nothing if the entity is not a talk.
+///
+/// # Example
+/// ```rust
+/// use bevy::{ecs::system::Command, prelude::*};
+/// use bevy_talks::prelude::*;
+///
+/// let mut world = World::new();
+/// let talk = world.spawn(Talk::default()).id();
+/// SetHasStarted { talk }.apply(&mut world);
+/// assert!(world.get::<HasStarted>(talk).is_some());
+/// ```
+pub struct SetHasStarted {
+    /// The talk parent entity.
+    pub talk: Entity,
+}
 
-/// Component to mark a dialogue node as a choice node containing some choices.
-#[derive(Component, Reflect, Default, Debug)]
-#[reflect(Component)]
-pub struct ChoiceNode(pub Vec<Choice>);
+impl Command for SetHasStarted {
+    fn apply(self, world: &mut World) {
+        let Some(mut talk_ent) = world.get_entity_mut(self.talk) else {
+            return;
+        };
+        if talk_ent.contains::<Talk>() && !talk_ent.contains::<HasStarted>() {
+            talk_ent.insert(HasStarted);
+        }
+    }
+}
 
-/// Component to mark a dialogue node as a join node.
-#[derive(Component, Reflect, Default, Debug)]
-#[reflect(Component)]
-pub struct JoinNode;
+/// Component added to a talk while it has a pending interjection (see [`InterjectCommand`](crate::prelude::InterjectCommand)).
+/// It is removed, and the original graph restored, once the talk moves past the interjected nodes.
+#[derive(Component, Debug, Clone)]
+pub struct Interjection {
+    /// The node the interjection was added after.
+    pub(crate) node: Entity,
+    /// The nodes that were following `node`, where the talk resumes after the interjection.
+    pub(crate) resume: Vec<Entity>,
+    /// The interjected nodes.
+    pub(crate) nodes: Vec<Entity>,
+}
 
-/// Component to mark a dialogue node as a leave node.
-#[derive(Component, Reflect, Default, Debug)]
-#[reflect(Component)]
-pub struct LeaveNode;
+impl Interjection {
+    /// Returns the interjected node entities.
+    pub fn nodes(&self) -> &[Entity] {
+        &self.nodes
+    }
+}
+
+/// Component of the placeholder nodes of the lazily built branches (see [`TalkBuilder::lazy_branches`]).
+///
+/// The placeholder is expanded into its branch when a talk gets within two nodes of it, then the component is removed.
+/// The placeholder entity becomes the first node of the branch, so the choices pointing to it stay valid.
+#[derive(Component, Clone)]
+pub struct LazyBranch(pub(crate) Arc<TalkBuilder>);
+
+/// The effect of a [`LogicNode`], run on the world when the traversal reaches the node.
+pub type NodeEffect = Arc<dyn Fn(&mut World) + Send + Sync>;
+
+/// Component of the logic nodes added with [`TalkBuilder::do_`]: pass-through nodes that run their effect
+/// and are moved past right away, without waiting for a `NextNodeRequest`.
+///
+/// The logic nodes are spawned as entities like the other nodes, but the traversal doesn't stop on them:
+/// the talk moves from the node before to the node after in a single move, with a single `CurrentNodeChanged`,
+/// and no chapter, start or end events for the logic nodes. Their node events are still emitted.
+///
+/// The traversal stops on a logic node only if it has no single follower (e.g. it is the last node),
+/// or if moving on would loop through the same logic nodes again.
+#[derive(Component, Clone)]
+pub struct LogicNode(pub(crate) NodeEffect);
+
+/// Resource indexing the current node entity of each talk (talk parent entity => current node entity).
+///
+/// It is kept up to date by the traversal systems, so the requests can be dispatched
+/// without iterating over all the `CurrentNode`s. The `CurrentNode` markers moved by other means
+/// (e.g. a loaded scene) are synced into it before the requests of the next update are handled.
+#[derive(Resource, Default, Debug)]
+pub struct CurrentNodes(pub(crate) HashMap<Entity, Entity>);
+
+impl CurrentNodes {
+    /// Returns the current node entity of the given talk, if any.
+    pub fn get(&self, talk: Entity) -> Option<Entity> {
+        self.0.get(&talk).copied()
+    }
+}
+
+/// Component to set what happens when a talk reaches an end node. Add it to the talk parent entity.
+///
+/// Without it, the talk just stays on the end node (and the next requests error out).
+#[derive(Component, Reflect, Default, Debug, Clone, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub enum OnEnd {
+    /// Move back to the start node, so the next `NextNodeRequest` starts the talk again.
+    Loop,
+    /// Stay on the end node and silently ignore the next requests.
+    #[default]
+    Freeze,
+    /// Despawn the talk parent entity and its dialogue graph.
+    Despawn,
+    /// Start (or advance) another talk, sending it a `NextNodeRequest`.
+    StartTalk(Entity),
+    /// Send an [`OnEndEvent`](crate::prelude::OnEndEvent) with the given tag.
+    SendEvent(String),
+}
+
+/// Component to give a dialogue node an emphasis intensity (e.g. for screen-shake or other UI/camera juice).
+/// It is passed along in the `TextNodeEvent` when the node is reached.
+#[derive(Component, Reflect, Default, Debug, Clone, Copy, PartialEq)]
+#[reflect(Component, Default)]
+pub struct Emphasis(pub f32);
+
+/// Component to make a dialogue node trigger an animation clip on an actor.
+/// When the node is reached an `AnimateActorEvent` is sent with the actor entity resolved from the slug
+/// (among the actors performing the node, or else the other nodes of its talk), so the animation systems can drive the character rigs directly from the dialogue scripts.
+#[derive(Component, Reflect, Default, Debug, Clone, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct AnimateActor {
+    /// The slug of the actor to animate.
+    pub slug: ActorSlug,
+    /// The name of the animation clip to play.
+    pub clip: String,
+}
+
+impl AnimateActor {
+    /// Creates a new `AnimateActor` for the actor with the given slug and clip name.
+    pub fn new(slug: impl Into<ActorSlug>, clip: impl Into<String>) -> Self {
+        Self {
+            slug: slug.into(),
+            clip: clip.into(),
+        }
+    }
+}
+
+/// Component to give a dialogue node an icon (an emoji or emote id) to show in its speech bubble.
+/// It is passed along in the `TextNodeEvent` and `ChoiceNodeEvent` when the node is reached.
+#[derive(Component, Reflect, Default, Debug, Clone, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct Icon(pub String);
+
+/// Component to give a text node a thought (e.g. the inner monologue of the speaker), shown alongside the spoken text.
+/// It is passed along in the `TextNodeEvent` when the node is reached.
+#[derive(Component, Reflect, Default, Debug, Clone, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct Thought(pub String);
+
+/// Component to split the text of a text node in pages. Each `NextNodeRequest` shows the next page
+/// (a new `TextNodeEvent` with its `page`), and the talk moves to the next node only from the last page.
+///
+/// The page is reset to the first one every time the node is entered. The `TextNode` keeps the whole text
+/// (e.g. for the exports), the text filters are applied to each page.
+///
+/// # Example
+/// ```rust
+/// use bevy_talks::prelude::*;
+///
+/// let builder = TalkBuilder::default().say_pages(["Long ago, ...", "... and that's how it began."]);
+/// ```
+#[derive(Component, Reflect, Default, Debug, Clone, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct TextPages {
+    /// The text of each page, with its markup.
+    pub pages: Vec<String>,
+    /// The index of the page shown.
+    pub page: usize,
+}
+
+impl TextPages {
+    /// Creates the pages with the given texts, on the first page.
+    pub fn new(pages: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            pages: pages.into_iter().map(Into::into).collect(),
+            page: 0,
+        }
+    }
+
+    /// Returns whether the page shown is the last one.
+    pub fn is_last_page(&self) -> bool {
+        self.page + 1 >= self.pages.len()
+    }
+}
+
+/// Component with the speaking order of the actors of a multi-actor node (as actor slugs).
+///
+/// The builder adds it to the nodes with more than one actor, so the actors are passed to the events
+/// in a deterministic order, which the `PerformedBy` relations alone don't guarantee.
+#[derive(Component, Reflect, Default, Debug, Clone, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct SpeakingOrder(pub Vec<ActorSlug>);
+
+/// Component with the line of each actor of a multi-actor text node, in speaking order.
+/// It is passed along in the `TextNodeEvent` when the node is reached.
+#[derive(Component, Reflect, Default, Debug, Clone, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct ActorLines(pub Vec<String>);
+
+/// Component to tag a dialogue node as part of a chapter.
+/// Traversal emits the chapter events when moving between nodes of different chapters.
+#[derive(Component, Reflect, Default, Debug, Clone, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct Chapter(pub String);
 
 /// The text and next entity of a choice.
 #[derive(Debug, Reflect, Clone)]
@@ -73,6 +291,16 @@ pub struct Choice {
     pub text: String,
     /// The next entity to go to if the choice is selected.
     pub next: Entity,
+    /// The optional description of the choice (e.g. skill check odds or flavor text to show under it).
+    pub description: Option<String>,
+    /// The optional sound cue of the choice (e.g. a confirm sound or a themed stinger),
+    /// passed along in the [`ChoiceSelectedEvent`](crate::prelude::ChoiceSelectedEvent) when it is selected.
+    pub sound: Option<String>,
+    /// The optional keyboard hotkey of the choice, honored by the [`ChoiceHotkeysPlugin`](crate::prelude::ChoiceHotkeysPlugin).
+    pub hotkey: Option<KeyCode>,
+    /// The authored position of the choice in its node. The choices of the [`ChoiceNodeEvent`](crate::prelude::ChoiceNodeEvent)
+    /// are sorted by it, so they are in the authored order whatever the order of the `ChoiceNode` vec.
+    pub order: usize,
 }
 
 impl Choice {
@@ -94,6 +322,69 @@ impl Choice {
         Self {
             text: text.into(),
             next,
+            description: None,
+            sound: None,
+            hotkey: None,
+            order: 0,
         }
     }
+
+    /// Sets the description of the choice.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Sets the sound cue of the choice.
+    pub fn with_sound(mut self, sound: impl Into<String>) -> Self {
+        self.sound = Some(sound.into());
+        self
+    }
+
+    /// Sets the keyboard hotkey of the choice.
+    pub fn with_hotkey(mut self, hotkey: KeyCode) -> Self {
+        self.hotkey = Some(hotkey);
+        self
+    }
+
+    /// Sets the authored position of the choice in its node.
+    pub fn with_order(mut self, order: usize) -> Self {
+        self.order = order;
+        self
+    }
+}
+
+/// Returns the choices of a choice node with the text of the first node of their branch,
+/// if it is a text node (e.g. to show the first line of each branch in a richer menu).
+///
+/// It only walks one step into the branches: the branches starting with another kind of node
+/// (or not expanded yet, see `lazy_branches`) have no preview. Returns an empty vec if the node is not a choice node.
+///
+/// # Example
+/// ```rust
+/// use bevy_talks::prelude::*;
+/// use bevy::prelude::*;
+///
+/// let mut world = World::default();
+/// let hello = world.spawn(TextNode("Hello there!".to_string())).id();
+/// let join = world.spawn(JoinNode).id();
+/// let choice_node = world
+///     .spawn(ChoiceNode(vec![Choice::new("Greet", hello), Choice::new("Wait", join)]))
+///     .id();
+///
+/// let previews = choice_previews(&world, choice_node);
+/// assert_eq!(previews[0], ("Greet".to_string(), Some("Hello there!".to_string())));
+/// assert_eq!(previews[1], ("Wait".to_string(), None));
+/// ```
+pub fn choice_previews(world: &World, choice_node: Entity) -> Vec<(String, Option<String>)> {
+    let Some(ChoiceNode(choices)) = world.get::<ChoiceNode>(choice_node) else {
+        return vec![];
+    };
+    choices
+        .iter()
+        .map(|choice| {
+            let first_text = world.get::<TextNode>(choice.next).map(|t| t.0.clone());
+            (choice.text.clone(), first_text)
+        })
+        .collect()
 }
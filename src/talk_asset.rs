@@ -1,11 +1,13 @@
 //! Talk Asset structs and types.
 
 use crate::{
-    builder::{BuildNodeId, TalkBuilder},
-    prelude::{Actor, ActorSlug},
+    builder::{for_each_build_node_mut, BuildNodeId, NodeLocation, TalkBuilder},
+    localization::TalkLocalization,
+    prelude::{parse_hotkey, Actor, ActorSlug, EmitterSpec, ResumeNode, TalkMetadata},
 };
 use bevy::{prelude::*, reflect::TypePath, utils::HashMap};
 use indexmap::IndexMap;
+use std::{collections::BTreeMap, path::Path, sync::Arc};
 
 /// A unique identifier for an action in a Talk.
 ///
@@ -15,7 +17,7 @@ use indexmap::IndexMap;
 pub(crate) type ActionId = usize;
 
 /// An enumeration of the different kinds of actions that can be performed in a Talk.
-#[derive(Debug, Default, Clone, Hash, Eq, PartialEq, serde::Deserialize)]
+#[derive(Debug, Default, Clone, Hash, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum NodeKind {
     /// An entry point of the dialogue graph
     Start,
@@ -28,6 +30,10 @@ pub enum NodeKind {
     Join,
     /// An exit action, where a character exits a scene.
     Leave,
+    /// A custom node, built by the factory registered for the kind
+    /// (see [`AppExt::register_custom_node_kind`](crate::prelude::AppExt::register_custom_node_kind)).
+    /// The text of the action is passed to the factory as the node data.
+    Custom(String),
 }
 
 /// A struct that represents an action in a Talk.
@@ -36,7 +42,7 @@ pub enum NodeKind {
 /// kind of action, the actors involved in the action, any choices that the user can make during
 /// the action, the text of the action, the ID of the next action to perform, whether the action is
 /// the start of the Talk, and any sound effect associated with the action.
-#[derive(Debug, Default, Clone, Eq, Hash, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub(crate) struct Action {
     /// The kind of action.
     pub(crate) kind: NodeKind,
@@ -48,6 +54,16 @@ pub(crate) struct Action {
     pub(crate) text: String,
     /// The ID of the next action to perform.
     pub(crate) next: Option<ActionId>,
+    /// The emphasis intensity of the action.
+    pub(crate) emphasis: Option<f32>,
+    /// The icon (emoji or emote id) of the action.
+    pub(crate) icon: Option<String>,
+    /// The thought shown alongside the text of the action.
+    pub(crate) thought: Option<String>,
+    /// Whether the action is explicitly an end of the Talk.
+    pub(crate) end: bool,
+    /// The components to construct and add to the node.
+    pub(crate) emit: Vec<EmitterSpec>,
 }
 /// A struct that represents a choice in a Talk.
 ///
@@ -59,29 +75,107 @@ pub(crate) struct ChoiceData {
     pub(crate) text: String,
     /// The ID of the next action to perform if the choice is selected.
     pub(crate) next: ActionId,
+    /// The optional description of the choice.
+    pub(crate) description: Option<String>,
+    /// The optional sound cue of the choice.
+    pub(crate) sound: Option<String>,
+    /// The optional name of the hotkey of the choice (see [`parse_hotkey`](crate::prelude::parse_hotkey)).
+    pub(crate) key: Option<String>,
 }
 
 /// The asset representation of a Talk. It is assumed to represent a well formed Talk,
 /// because the loader should have already validated it while loading.
 ///
+/// A RON file can also be a pack of named talks (`talks: { "intro": (...), "shop": (...) }`),
+/// each loaded as a labeled sub-asset: load one directly with `asset_server.load("file.talk.ron#shop")`
+/// or get its handle from the pack with [`TalkData::talk`].
 #[derive(Asset, Debug, Default, Clone, TypePath)]
 pub struct TalkData {
     /// The list of actions that make up the Talk.
     pub(crate) script: IndexMap<ActionId, Action>,
     /// The list of actors that appear in the Talk.
     pub(crate) actors: Vec<Actor>,
+    /// The actor aliases of the Talk (alias => actor slug).
+    pub(crate) aliases: BTreeMap<String, ActorSlug>,
+    /// The action to resume from when the Talk is started again after it ended.
+    pub(crate) resume_at: Option<ActionId>,
+    /// The handles of the named talks of the pack (label => labeled sub-asset).
+    pub(crate) talks: HashMap<String, Handle<TalkData>>,
+    /// The metadata of the Talk.
+    pub(crate) metadata: TalkMetadata,
+    /// The stable id of the Talk, from the asset path.
+    pub(crate) id: Option<String>,
+    /// The localized texts of the Talk, from the sidecar files (locale => texts).
+    pub(crate) locales: Arc<HashMap<String, TalkLocalization>>,
 }
 
 impl TalkData {
     /// Creates a new `TalkData` with the given script and actors.
     #[allow(dead_code)]
     pub(crate) fn new(script: IndexMap<ActionId, Action>, actors: Vec<Actor>) -> Self {
-        Self { script, actors }
+        Self {
+            script,
+            actors,
+            aliases: BTreeMap::new(),
+            resume_at: None,
+            talks: HashMap::new(),
+            metadata: TalkMetadata::default(),
+            id: None,
+            locales: Arc::default(),
+        }
+    }
+
+    /// Sets the id of the Talk from its asset path (and label for the talks of a pack).
+    pub(crate) fn with_asset_id(mut self, path: &Path, label: Option<&str>) -> Self {
+        let path = path.to_string_lossy().replace('\\', "/");
+        self.id = Some(match label {
+            Some(label) => format!("{path}#{label}"),
+            None => path,
+        });
+        self
+    }
+
+    /// Returns the stable id of the Talk, its asset path. It is the [`TalkId`](crate::prelude::TalkId) of the spawned talks.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Returns the metadata of the Talk. It is copied onto the [`Talk`](crate::prelude::Talk) component at spawn.
+    pub fn metadata(&self) -> &TalkMetadata {
+        &self.metadata
+    }
+
+    /// Returns the handle of the named talk of the pack with the given label, if any.
+    pub fn talk(&self, label: &str) -> Option<&Handle<TalkData>> {
+        self.talks.get(label)
+    }
+
+    /// Returns the locales of the localized texts of the Talk, from its sidecar files.
+    /// The [`TalksLocale`](crate::prelude::TalksLocale) selects the texts used when the talk is built.
+    pub fn locales(&self) -> impl Iterator<Item = &str> {
+        self.locales.keys().map(String::as_str)
+    }
+
+    /// Returns the labels of the named talks of the pack.
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.talks.keys().map(String::as_str)
     }
 
     /// Take a builder and fill it with the talk actions
     pub(crate) fn fill_builder(&self, mut builder: TalkBuilder) -> TalkBuilder {
         builder = builder.add_actors(self.actors.clone());
+        for (alias, slug) in self.aliases.iter() {
+            builder = builder.alias(alias, slug);
+        }
+        if !self.locales.is_empty() {
+            builder.locales = Arc::clone(&self.locales);
+        }
+        if let (None, Some(id)) = (&builder.talk_id, &self.id) {
+            builder = builder.talk_id(id);
+        }
+        if !self.metadata.is_empty() {
+            builder = builder.metadata(self.metadata.clone());
+        }
 
         if self.script.is_empty() {
             return builder;
@@ -89,7 +183,15 @@ impl TalkData {
 
         let mut visited = HashMap::with_capacity(self.script.len());
         let start_id = self.script.keys().next().unwrap();
-        prepare_builder(*start_id, &self.script, builder, &mut visited)
+        let mut builder = prepare_builder(*start_id, &self.script, builder, &mut visited);
+        if let Some(resume_at) = self.resume_at {
+            for_each_build_node_mut(&mut builder.queue, |node| {
+                if node.location == NodeLocation::Action(resume_at) {
+                    node.components.push(Box::new(ResumeNode));
+                }
+            });
+        }
+        builder
     }
 }
 
@@ -106,13 +208,22 @@ fn prepare_builder(
 
     let mut done = false;
     while !done {
-        match the_action.kind {
+        match &the_action.kind {
             NodeKind::Start => (), // nothing to do for this as of now
             NodeKind::Talk => {
                 builder = match the_action.actors.len() {
                     0 => builder.say(&the_action.text),
                     1 => builder.actor_say(&the_action.actors[0], &the_action.text),
                     2.. => builder.actors_say(&the_action.actors, &the_action.text),
+                };
+                if let Some(intensity) = the_action.emphasis {
+                    builder = builder.emphasis(intensity);
+                }
+                if let Some(icon) = &the_action.icon {
+                    builder = builder.icon(icon);
+                }
+                if let Some(thought) = &the_action.thought {
+                    builder = builder.thought(thought);
                 }
             }
             NodeKind::Choice => {
@@ -125,26 +236,58 @@ fn prepare_builder(
 
                     // if already visited, just connect to it instead of recursively building
                     if visited.get(&next).is_some() {
-                        inner_builder = inner_builder.connect_to(visited[&next].clone());
+                        inner_builder = inner_builder.connect_to(visited[&next]);
                     } else {
                         inner_builder = prepare_builder(next, actions, inner_builder, visited);
                     }
-                    choice_vec.push((text, inner_builder));
+                    choice_vec.push((text, c.description.clone(), inner_builder));
                 }
 
-                builder = builder.choose(choice_vec);
+                builder = builder.choose_with_descriptions(choice_vec);
+                builder.set_last_location(NodeLocation::Action(the_id));
+                if let Some(node) = builder.queue.back_mut() {
+                    node.actors = the_action.actors.clone();
+                    for (choice, c) in node.choices.iter_mut().zip(the_action.choices.iter()) {
+                        choice.sound = c.sound.clone();
+                        choice.hotkey = c.key.as_deref().and_then(parse_hotkey);
+                    }
+                }
+                if let Some(icon) = &the_action.icon {
+                    builder = builder.icon(icon);
+                }
+                if the_action.end {
+                    builder = builder.end();
+                }
+                for spec in the_action.emit.iter() {
+                    builder = builder.emit(spec.clone());
+                }
                 visited.insert(the_id, builder.last_node_id());
                 break; // no other nodes to visit from a choice (nexts are not used in this case)
             }
+            NodeKind::Custom(kind) => {
+                builder = builder.custom_node(kind, &the_action.text);
+                if let Some(node) = builder.queue.back_mut() {
+                    node.actors = the_action.actors.clone();
+                }
+            }
             NodeKind::Join => builder = builder.join(&the_action.actors),
             NodeKind::Leave => builder = builder.leave(&the_action.actors),
         }
+        if the_action.kind != NodeKind::Start {
+            builder.set_last_location(NodeLocation::Action(the_id));
+            for spec in the_action.emit.iter() {
+                builder = builder.emit(spec.clone());
+            }
+        }
+        if the_action.end && the_action.kind != NodeKind::Start {
+            builder = builder.end();
+        }
 
         visited.insert(the_id, builder.last_node_id());
         if let Some(next) = the_action.next {
             // just connect if already processed
             if visited.get(&next).is_some() {
-                builder = builder.connect_to(visited[&next].clone());
+                builder = builder.connect_to(visited[&next]);
                 done = true; // no need to continue
             }
             // move to the next action
@@ -239,8 +382,8 @@ mod tests {
             0 =>
             Action {
                 choices: vec![
-                    ChoiceData { text: "Choice 1".to_string(), next: 1, },
-                    ChoiceData { text: "Choice 2".to_string(), next: 2, },
+                    ChoiceData { text: "Choice 1".to_string(), next: 1, ..default() },
+                    ChoiceData { text: "Choice 2".to_string(), next: 2, ..default() },
                 ],
                 kind: NodeKind::Choice,
                 ..default()
@@ -259,6 +402,25 @@ mod tests {
         assert_on_choice_nodes(&mut world, map);
     }
 
+    #[test]
+    fn nodes_located_at_their_actions() {
+        let script = indexmap! {
+            4 =>
+            Action {
+                choices: vec![ChoiceData { text: "Go".to_string(), next: 7, ..default() }],
+                kind: NodeKind::Choice,
+                ..default()
+            },
+            7 => Action { text: "Hello".to_string(), ..default() },
+        };
+
+        let builder = TalkData::new(script, vec![]).fill_builder(TalkBuilder::default());
+        let choice = builder.queue.back().unwrap();
+        assert_eq!(choice.location, NodeLocation::Action(4));
+        let hello = choice.choices[0].branch.queue.back().unwrap();
+        assert_eq!(hello.location, NodeLocation::Action(7));
+    }
+
     #[test]
     fn connect_back_from_branch_book_example() {
         // From the Branching and Manual Connections builder section
@@ -268,8 +430,8 @@ mod tests {
             2 =>
             Action {
                 choices: vec![
-                    ChoiceData { text: "Choice 1".to_string(), next: 3, },
-                    ChoiceData { text: "Choice 2".to_string(), next: 4, },
+                    ChoiceData { text: "Choice 1".to_string(), next: 3, ..default() },
+                    ChoiceData { text: "Choice 2".to_string(), next: 4, ..default() },
                 ],
                 kind: NodeKind::Choice,
                 ..default()
@@ -302,8 +464,8 @@ mod tests {
             0 => // entity: 2
             Action {
                 choices: vec![
-                    ChoiceData { text: "First Choice 1".to_string(), next: 1, },
-                    ChoiceData { text: "First Choice 2".to_string(), next: 2, },
+                    ChoiceData { text: "First Choice 1".to_string(), next: 1, ..default() },
+                    ChoiceData { text: "First Choice 2".to_string(), next: 2, ..default() },
                 ],
                 kind: NodeKind::Choice,
                 ..default()
@@ -313,8 +475,8 @@ mod tests {
             3 =>
             Action {
                 choices: vec![
-                    ChoiceData { text: "Second Choice 1".to_string(), next: 2, },
-                    ChoiceData { text: "Second Choice 2".to_string(), next: 4, },
+                    ChoiceData { text: "Second Choice 1".to_string(), next: 2, ..default() },
+                    ChoiceData { text: "Second Choice 2".to_string(), next: 4, ..default() },
                 ],
                 kind: NodeKind::Choice,
                 ..default()
@@ -0,0 +1,145 @@
+//! Pipeline of text filters applied to the node texts before the events are emitted
+//! (profanity filters, name replacements, content toggles...).
+
+use bevy::prelude::*;
+
+use crate::prelude::{ActorLines, TextNode};
+
+/// Trait to implement to transform the texts of the nodes right before their events are emitted.
+///
+/// The filters get the raw text of the text nodes (markup included, which is parsed after all the filters ran),
+/// the actor lines of the multi-actor nodes and the choice texts.
+///
+/// It is implemented for every `Fn(&World, Entity, &mut String)` closure, so you can
+/// register closures directly with [`AppExt::add_text_filter`](crate::prelude::AppExt::add_text_filter).
+///
+/// # Example
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// let mut app = App::new();
+/// app.add_text_filter(|_world: &World, _talk: Entity, text: &mut String| {
+///     *text = text.replace("darn", "****");
+/// });
+/// ```
+pub trait TextFilter: Send + Sync + 'static {
+    /// Transforms a text of a node of the given talk in place.
+    fn filter(&self, world: &World, talk: Entity, text: &mut String);
+}
+
+impl<F> TextFilter for F
+where
+    F: Fn(&World, Entity, &mut String) + Send + Sync + 'static,
+{
+    fn filter(&self, world: &World, talk: Entity, text: &mut String) {
+        self(world, talk, text)
+    }
+}
+
+/// The registered text filters, each with the locale it is restricted to (if any).
+/// They are applied in registration order.
+#[derive(Resource, Default)]
+pub struct TextFilters(pub(crate) Vec<(Option<String>, Box<dyn TextFilter>)>);
+
+/// Resource with the current locale of the talks (e.g. `"en"`, `"de"`).
+///
+/// The text filters registered for a locale with
+/// [`AppExt::add_locale_text_filter`](crate::prelude::AppExt::add_locale_text_filter)
+/// only run when it is the current locale.
+#[derive(Resource, Debug, Default, Clone, PartialEq, Eq)]
+pub struct TalksLocale(pub String);
+
+/// Returns whether there are text filters to apply.
+pub(crate) fn has_text_filters(world: &World) -> bool {
+    world
+        .get_resource::<TextFilters>()
+        .is_some_and(|filters| !filters.0.is_empty())
+}
+
+/// Applies the text filters of the current locale to a text of the given node, in registration order.
+pub(crate) fn apply_text_filters(world: &World, node: Entity, text: &mut String) {
+    let Some(filters) = world.get_resource::<TextFilters>() else {
+        return;
+    };
+    let Some(talk) = world.get::<Parent>(node).map(|p| p.get()) else {
+        return;
+    };
+    let locale = world.get_resource::<TalksLocale>().map(|l| l.0.as_str());
+    for (filter_locale, filter) in filters.0.iter() {
+        if filter_locale.is_none() || filter_locale.as_deref() == locale {
+            filter.filter(world, talk, text);
+        }
+    }
+}
+
+/// Returns the filtered raw text and actor lines of the text node, to build its event from.
+pub(crate) fn filtered_node_texts(world: &World, node: Entity) -> Option<(String, Vec<String>)> {
+    let mut text = world.get::<TextNode>(node)?.0.clone();
+    apply_text_filters(world, node, &mut text);
+    let mut lines = world
+        .get::<ActorLines>(node)
+        .map(|l| l.0.clone())
+        .unwrap_or_default();
+    for line in lines.iter_mut() {
+        apply_text_filters(world, node, line);
+    }
+    Some((text, lines))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::Command;
+
+    use crate::{prelude::*, tests::talks_minimal_app};
+
+    use super::*;
+
+    /// Builds the talk and reaches its first node.
+    fn build_and_next(app: &mut App, builder: TalkBuilder) {
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+    }
+
+    #[test]
+    fn filters_applied_in_order() {
+        let mut app = talks_minimal_app();
+        app.add_text_filter(|_: &World, _: Entity, text: &mut String| {
+            *text = text.replace("darn", "****");
+        })
+        .add_text_filter(|_: &World, _: Entity, text: &mut String| {
+            *text = text.replace("****", "[censored]");
+        });
+        build_and_next(&mut app, TalkBuilder::default().say("Oh darn{pause=1}!"));
+
+        let evs = app.world.resource::<Events<TextNodeEvent>>();
+        let event = evs.get_reader().read(evs).next().unwrap().clone();
+        assert_eq!(event.text, "Oh [censored]!");
+        assert_eq!(event.timing.marks[0].index, 13);
+        // the node is untouched
+        let node = app.world.query::<&TextNode>().single(&app.world);
+        assert_eq!(node.0, "Oh darn{pause=1}!");
+    }
+
+    #[test]
+    fn locale_filters() {
+        let mut app = talks_minimal_app();
+        app.insert_resource(TalksLocale("de".to_string()))
+            .add_locale_text_filter("en", |_: &World, _: Entity, text: &mut String| {
+                text.push_str(" (en)");
+            })
+            .add_locale_text_filter("de", |_: &World, _: Entity, text: &mut String| {
+                text.push_str(" (de)");
+            });
+        build_and_next(
+            &mut app,
+            TalkBuilder::default().choose(vec![("Hallo", TalkBuilder::default().say("a"))]),
+        );
+
+        let evs = app.world.resource::<Events<ChoiceNodeEvent>>();
+        let choices = &evs.get_reader().read(evs).next().unwrap().choices;
+        assert_eq!(choices[0].text, "Hallo (de)");
+    }
+}
@@ -1,151 +1,787 @@
 //! Dialogue graph traversal systems.
 
 use crate::{
-    emit_events, maybe_emit_end_event, maybe_emit_start_event, prelude::*, retrieve_actors,
+    choice_filter::{ShownChoices, SkippedChoice},
+    config::talks_log,
+    emit_events,
+    lockout::{LockedRequest, LockoutGuard},
+    maybe_emit_chapter_events, maybe_emit_end_event, maybe_emit_start_event,
+    min_display::DisplayTimeGuard,
+    pool::release_talk,
+    prelude::*,
+    random::random_target,
+    refire::RefireGuard,
+    variables::switch_target,
+    NodeActors,
 };
 use aery::{prelude::*, tuple_traits::RelationEntries};
-use bevy::prelude::*;
+use bevy::{
+    ecs::{
+        entity::Entities,
+        system::{Command, SystemParam, SystemState},
+    },
+    log::Level,
+    prelude::*,
+};
+
+/// The shared traversal core used by all the request handlers.
+///
+/// It moves the `CurrentNode` of the talks, keeps the [`CurrentNodes`] index up to date and emits
+/// all the events (start, end, chapters and the node events) in the same way for every handler.
+#[derive(SystemParam)]
+pub(crate) struct Traversal<'w, 's> {
+    /// The commands to move the `CurrentNode` marker and emit the node events.
+    cmd: Commands<'w, 's>,
+    /// The index of the current node of each talk.
+    current_nodes: ResMut<'w, CurrentNodes>,
+    /// The nodes with their followers.
+    nodes: Query<'w, 's, Relations<FollowedBy>>,
+    /// The start nodes.
+    start: Query<'w, 's, Entity, With<StartNode>>,
+    /// The end nodes.
+    end: Query<'w, 's, Entity, With<EndNode>>,
+    /// The actors performing the nodes.
+    node_actors: NodeActors<'w, 's>,
+    /// The writer for the start events.
+    start_ev_writer: EventWriter<'w, StartEvent>,
+    /// The writer for the end events.
+    end_ev_writer: EventWriter<'w, EndEvent>,
+    /// The chapters of the nodes.
+    chapters: Query<'w, 's, &'static Chapter>,
+    /// The writer for the chapter started events.
+    chapter_start_writer: EventWriter<'w, ChapterStartedEvent>,
+    /// The writer for the chapter ended events.
+    chapter_end_writer: EventWriter<'w, ChapterEndedEvent>,
+    /// The end behaviors of the talks.
+    on_ends: Query<'w, 's, &'static OnEnd>,
+    /// The writer for the current node changed events.
+    changed_writer: EventWriter<'w, CurrentNodeChanged>,
+    /// The logic, switch and random nodes, moved past right away.
+    routing: Query<'w, 's, (Option<&'static LogicNode>, Has<SwitchNode>, Has<RandomNode>)>,
+}
+
+/// A [`LogicNode`], [`SwitchNode`] or [`RandomNode`] the traversal moved past, without stopping on it.
+struct PassedNode {
+    /// The node entity.
+    node: Entity,
+    /// The effect of the node, if it is a logic node.
+    effect: Option<NodeEffect>,
+    /// The actors performing the node, for its node events.
+    actors: Vec<Actor>,
+}
+
+/// Command routing a talk from the [`SwitchNode`] or [`RandomNode`] it reached to the target of the matching case
+/// or of the picked branch.
+///
+/// It is queued by the traversal after the effects of the logic nodes passed on the way, so they have
+/// already set the variables, and it moves the talk right away, without stopping on the routing node.
+struct RouteTalk {
+    /// The talk parent entity.
+    talk: Entity,
+    /// The switch or random node the talk is routed from.
+    node: Entity,
+    /// The actors performing the routing node, for its node events.
+    actors: Vec<Actor>,
+    /// The routing nodes the talk was already routed through, to stop instead of routing in a loop.
+    routed: Vec<Entity>,
+}
+
+impl Command for RouteTalk {
+    fn apply(self, world: &mut World) {
+        let target = if world.get::<SwitchNode>(self.node).is_some() {
+            switch_target(world, self.node)
+        } else {
+            random_target(world, self.talk, self.node)
+        };
+        if target.is_none() {
+            talks_log!(
+                world.get_resource::<TalksConfig>(),
+                LogCategory::Traversal,
+                Level::WARN,
+                "Routing node {:?} of talk {:?} has no node after it to go on with.",
+                self.node,
+                self.talk
+            );
+        }
+
+        let mut state = SystemState::<Traversal>::new(world);
+        let mut traversal = state.get_mut(world);
+        let result = match target {
+            Some(target) => {
+                let routing = PassedNode {
+                    node: self.node,
+                    effect: None,
+                    actors: self.actors,
+                };
+                traversal.advance_routed(self.talk, target, vec![routing], self.routed)
+            }
+            // nowhere to go, the talk stops on the routing node
+            None => traversal.step(self.talk, self.node, vec![]),
+        };
+        state.apply(world);
+
+        if let Err(err) = result {
+            talks_log!(
+                world.get_resource::<TalksConfig>(),
+                LogCategory::Traversal,
+                Level::ERROR,
+                "Error: {err}"
+            );
+        }
+    }
+}
+
+impl Traversal<'_, '_> {
+    /// Returns the current node of the given talk.
+    pub(crate) fn current_node(&self, talk: Entity) -> Result<Entity, NextActionError> {
+        self.current_nodes
+            .get(talk)
+            .ok_or(NextActionError::NoTalk(talk))
+    }
+
+    /// Returns whether the talk is frozen: it has the [`OnEnd::Freeze`] behavior and reached an end node.
+    pub(crate) fn is_frozen(&self, talk: Entity) -> bool {
+        matches!(self.on_ends.get(talk), Ok(OnEnd::Freeze))
+            && self
+                .current_node(talk)
+                .is_ok_and(|node| self.end.contains(node))
+    }
+
+    /// Returns whether the given node is a start node.
+    pub(crate) fn is_start(&self, node: Entity) -> bool {
+        self.start.contains(node)
+    }
+
+    /// Returns the nodes following the given node of the talk.
+    pub(crate) fn followers(
+        &self,
+        talk: Entity,
+        node: Entity,
+    ) -> Result<Vec<Entity>, NextActionError> {
+        self.nodes
+            .get(node)
+            .map(|edges| edges.targets(FollowedBy).to_vec())
+            .map_err(|_| NextActionError::NoTalk(talk))
+    }
+
+    /// Moves the current node of the talk to the `target` node, emitting all the events.
+    /// If the target is a [`LogicNode`], its effect is run and the talk moves past it to the following node,
+    /// without stopping on it: the logic nodes moved past emit their node events, but no `CurrentNodeChanged`,
+    /// chapter, start or end events. The talk stops on a logic node only if it has no single follower
+    /// or if moving on would loop through the same logic nodes again.
+    /// If the target is a [`SwitchNode`], the talk moves past it in the same way to the target of its matching case,
+    /// read once the effects of the logic nodes before it are applied, and if it is a [`RandomNode`],
+    /// to the target of a branch picked with the [`TalkRng`] of the talk.
+    ///
+    /// The target is not checked to be a follower of the current node, so it can also be used to jump around the graph.
+    /// If the actors of the target cannot be retrieved (see `MissingActorPolicy`), nothing is changed.
+    pub(crate) fn advance_to(
+        &mut self,
+        talk: Entity,
+        target: Entity,
+    ) -> Result<(), NextActionError> {
+        self.advance_routed(talk, target, vec![], vec![])
+    }
+
+    /// Moves the talk to the `target` node like [`Traversal::advance_to`], after the `passed` nodes.
+    /// The switch and random nodes in `routed` were already routed through on the way: the talk stops on them
+    /// instead of routing in a loop.
+    fn advance_routed(
+        &mut self,
+        talk: Entity,
+        target: Entity,
+        mut passed: Vec<PassedNode>,
+        mut routed: Vec<Entity>,
+    ) -> Result<(), NextActionError> {
+        let mut node = target;
+        while let Some(effect) = self.logic_effect(node) {
+            let followers = self.followers(talk, node)?;
+            let &[next] = followers.as_slice() else {
+                break;
+            };
+            if next == target || passed.iter().any(|p: &PassedNode| p.node == next) {
+                break;
+            }
+            let actors = self.node_actors.retrieve(talk, node)?;
+            passed.push(PassedNode {
+                node,
+                effect: Some(effect),
+                actors,
+            });
+            node = next;
+        }
+
+        // route the switch and random nodes once the effects of the logic nodes before them are applied
+        if matches!(self.routing.get(node), Ok((_, true, _) | (_, _, true)))
+            && !routed.contains(&node)
+        {
+            let actors = self.node_actors.retrieve(talk, node)?;
+            self.pass(passed);
+            routed.push(node);
+            self.cmd.add(RouteTalk {
+                talk,
+                node,
+                actors,
+                routed,
+            });
+            return Ok(());
+        }
+        self.step(talk, node, passed)?;
+
+        // the logic node the talk stopped on runs its effect too
+        if let Some(effect) = self.logic_effect(node) {
+            self.cmd.add(move |world: &mut World| effect(world));
+        }
+        Ok(())
+    }
+
+    /// Returns the effect of the node, if it is a [`LogicNode`].
+    fn logic_effect(&self, node: Entity) -> Option<NodeEffect> {
+        self.routing
+            .get(node)
+            .ok()
+            .and_then(|(logic, _, _)| logic)
+            .map(|logic| logic.0.clone())
+    }
+
+    /// Emits the node events and queues the effects of the nodes moved past, in order.
+    fn pass(&mut self, passed: Vec<PassedNode>) {
+        for PassedNode {
+            node,
+            effect,
+            actors,
+        } in passed
+        {
+            emit_events(&mut self.cmd, node, actors);
+            if let Some(effect) = effect {
+                self.cmd.add(move |world: &mut World| effect(world));
+            }
+        }
+    }
+
+    /// Moves the current node of the talk to the `target` node, emitting all the events of the target,
+    /// after the node events and the effects of the logic nodes moved past to reach it.
+    fn step(
+        &mut self,
+        talk: Entity,
+        target: Entity,
+        passed: Vec<PassedNode>,
+    ) -> Result<(), NextActionError> {
+        let current_node = self.current_node(talk)?;
+
+        // grab the actors in the target node
+        let actors_in_node = self.node_actors.retrieve(talk, target)?;
+
+        self.pass(passed);
+
+        // send start event and mark the talk as started if we are leaving the start node
+        maybe_emit_start_event(&self.start, current_node, &mut self.start_ev_writer, talk);
+        if self.start.contains(current_node) {
+            self.cmd.add(SetHasStarted { talk });
+        }
+
+        // send chapter events if we are crossing a chapter boundary
+        maybe_emit_chapter_events(
+            &self.chapters,
+            current_node,
+            target,
+            &mut self.chapter_start_writer,
+            &mut self.chapter_end_writer,
+            talk,
+        );
+
+        // send end event if the target is an end node, and remember the talk ended
+        maybe_emit_end_event(&self.end, target, &mut self.end_ev_writer, talk);
+        if self.end.contains(target) {
+            self.cmd.add(move |world: &mut World| {
+                if let Some(mut talk) = world.get_entity_mut(talk) {
+                    talk.insert(HasEnded);
+                }
+            });
+        }
+
+        // move CurrentNode component to the target node
+        move_current(
+            &mut self.cmd,
+            &mut self.current_nodes,
+            &mut self.changed_writer,
+            talk,
+            current_node,
+            target,
+        );
+
+        // emit the events in the target node
+        emit_events(&mut self.cmd, target, actors_in_node);
+
+        Ok(())
+    }
+
+    /// Emits the node events of the current node of the talk again, without the start and end events
+    /// (e.g. for the next page of a paged text node).
+    pub(crate) fn emit_current(&mut self, talk: Entity) -> Result<(), NextActionError> {
+        let current_node = self.current_node(talk)?;
+        let actors_in_node = self.node_actors.retrieve(talk, current_node)?;
+        emit_events(&mut self.cmd, current_node, actors_in_node);
+        Ok(())
+    }
+
+    /// Emits again the events of the current node of the talk.
+    pub(crate) fn refire(&mut self, talk: Entity) -> Result<(), NextActionError> {
+        let current_node = self.current_node(talk)?;
 
-/// Sets the `has_started` field of the `Talk` component to true when a `StartEvent` is received.
-pub(crate) fn set_has_started(mut talks: Query<&mut Talk>, mut start_evs: EventReader<StartEvent>) {
-    for event in start_evs.read() {
-        let mut talk = talks.get_mut(event.0).expect("Talk");
-        talk.has_started = true;
+        // grab the actors in the current node
+        let actors_in_node = self.node_actors.retrieve(talk, current_node)?;
+
+        // send start event if we are at the start node
+        maybe_emit_start_event(&self.start, current_node, &mut self.start_ev_writer, talk);
+
+        // send end event if current node is an end node
+        maybe_emit_end_event(&self.end, current_node, &mut self.end_ev_writer, talk);
+
+        // emit the events in current node
+        emit_events(&mut self.cmd, current_node, actors_in_node);
+        Ok(())
     }
 }
 
 /// Handles `NextActionRequest` events by moving the current node of the given `Talk` to the next one
 /// and emitting the events in the next node.
+///
+/// All the requests of the frame are handled in order. The current nodes are read from the [`CurrentNodes`] index,
+/// which is updated right away, so the requests see the moves of the previous ones.
+/// A failing request does not stop the others: the errors of all the failed requests are returned.
+///
+/// A talk that already ended ([`HasEnded`]) moves from its start node to its [`ResumeNode`], if it has one.
+/// On a text node with [`TextPages`], the requests show the next page until the last one.
+/// The requests on a node with a [`MinDisplayTime`] that has not passed yet are ignored.
 pub(crate) fn next_handler(
-    mut cmd: Commands,
     mut reqs: EventReader<NextNodeRequest>,
-    current_nodes: Query<(Entity, &Parent, Relations<FollowedBy>), With<CurrentNode>>,
-    start: Query<Entity, With<StartNode>>,
-    end: Query<Entity, With<EndNode>>,
-    all_actors: Query<&Actor>,
-    performers: Query<Relations<PerformedBy>>,
-    emitters: Query<&dyn NodeEventEmitter>,
-    type_registry: Res<AppTypeRegistry>,
-    mut start_ev_writer: EventWriter<StartEvent>,
-    mut end_ev_writer: EventWriter<EndEvent>,
-) -> Result<(), NextActionError> {
-    if let Some(event) = reqs.read().next() {
-        for (current_node, talk_parent, edges) in &current_nodes {
-            let this_talk = talk_parent.get();
-            // if this is the talk we want to advance
-            if this_talk == event.talk {
-                // send start event if we are at the start node
-                maybe_emit_start_event(&start, current_node, &mut start_ev_writer, event.talk);
-
-                let followings = edges.targets(FollowedBy);
-
-                let next_node = validate_next_node(followings)?;
-
-                // send end event if next node is an end node
-                maybe_emit_end_event(&end, next_node, &mut end_ev_writer, event.talk);
-
-                // grab the actors in the next node
-                let actors_in_node = retrieve_actors(&performers, next_node, &all_actors);
-                // move CurrentNode component to next node
-                move_current(&mut cmd, current_node, next_node);
-                // emit the events in the next node
-                emit_events(
-                    &mut cmd,
-                    &emitters,
-                    next_node,
-                    &type_registry,
-                    actors_in_node,
-                );
+    mut traversal: Traversal,
+    lockout: LockoutGuard,
+    mut display_time: DisplayTimeGuard,
+    talks: Query<Has<Talk>>,
+    ended: Query<(), With<HasEnded>>,
+    resume_nodes: Query<(Entity, &Parent), With<ResumeNode>>,
+    mut pages: Query<&mut TextPages>,
+    mut cmd: Commands,
+) -> Vec<NextActionError> {
+    let mut errors = vec![];
+    for event in reqs.read() {
+        #[cfg(feature = "trace")]
+        let _span = trace_span!("talks_next_node", talk = ?event.talk).entered();
 
+        let mut handle = || -> Result<(), NextActionError> {
+            validate_talk(&talks, event.talk)?;
+
+            if traversal.is_frozen(event.talk) || lockout.is_locked(event.talk, LockedRequest::Next)
+            {
                 return Ok(());
             }
-        }
 
-        return Err(NextActionError::NoTalk);
+            let current_node = traversal.current_node(event.talk)?;
+            if !display_time.allow(event.talk, current_node) {
+                return Ok(());
+            }
+            // show the next page of a paged text node before moving on
+            if let Ok(mut pages) = pages.get_mut(current_node) {
+                if !pages.is_last_page() {
+                    pages.page += 1;
+                    return traversal.emit_current(event.talk);
+                }
+            }
+            let resume_node = resume_nodes
+                .iter()
+                .find(|(_, parent)| parent.get() == event.talk)
+                .map(|(node, _)| node)
+                .filter(|_| traversal.is_start(current_node) && ended.contains(event.talk));
+            let next_node = match resume_node {
+                Some(node) => node,
+                None => {
+                    let followings = traversal.followers(event.talk, current_node)?;
+                    let next = validate_next_node(&followings, event.talk, current_node);
+                    if matches!(next, Err(NextActionError::ChoicesNotHandled { .. })) {
+                        cmd.add(send_choices_not_handled(event.talk, current_node));
+                    }
+                    next?
+                }
+            };
+            traversal.advance_to(event.talk, next_node)
+        };
+        if let Err(err) = handle() {
+            errors.push(err);
+        }
     }
-    Ok(())
+    errors
 }
 
 /// Handles `ChooseActionRequest` events by updating the given Talk graph.
 ///
 /// This function is a Bevy system that listens for `ChooseActionRequest` events.
 /// It will move the current node of the given `Talk` to the one selected in the choose event.
+/// As in [`next_handler`], all the requests of the frame are handled in order, so choice → choice chains
+/// can be traversed (and their events emitted) in a single update, unless a [`ChoiceLockout`] is set,
+/// and a failing request does not stop the others.
+///
+/// The choices hidden by the [`ChoiceFilter`]s when the `ChoiceNodeEvent` of the node was emitted are rejected.
+/// The single choices skipped by the [`SingleChoicePolicy`] are handled after the requests, without the lockout.
 pub(crate) fn choice_handler(
-    mut cmd: Commands,
     mut reqs: EventReader<ChooseNodeRequest>,
-    current_nodes: Query<(Entity, &Parent, Relations<FollowedBy>), With<CurrentNode>>,
-    start: Query<Entity, With<StartNode>>,
-    end: Query<Entity, With<EndNode>>,
-    all_actors: Query<&Actor>,
-    performers: Query<Relations<PerformedBy>>,
-    emitters: Query<&dyn NodeEventEmitter>,
-    type_registry: Res<AppTypeRegistry>,
-    mut start_ev_writer: EventWriter<StartEvent>,
-    mut end_ev_writer: EventWriter<EndEvent>,
-) -> Result<(), NextActionError> {
-    if let Some(event) = reqs.read().next() {
-        for (current_node, talk_parent, edges) in &current_nodes {
-            let this_talk = talk_parent.get();
-            // if this is the talk we want to advance
-            if this_talk == event.talk {
-                // send start event if we are at the start node
-                maybe_emit_start_event(&start, current_node, &mut start_ev_writer, event.talk);
-
-                let followings = edges.targets(FollowedBy);
-
-                let next_node = validate_chosen_node(followings, event.next)?;
-
-                // send end event if next node is an end node
-                maybe_emit_end_event(&end, next_node, &mut end_ev_writer, event.talk);
-
-                // grab the actors in the next node
-                let actors_in_node = retrieve_actors(&performers, next_node, &all_actors);
-                // move CurrentNode component to next node
-                move_current(&mut cmd, current_node, next_node);
-                // emit the events in the next node
-                emit_events(
-                    &mut cmd,
-                    &emitters,
-                    next_node,
-                    &type_registry,
-                    actors_in_node,
-                );
+    mut skipped: EventReader<SkippedChoice>,
+    mut traversal: Traversal,
+    mut lockout: LockoutGuard,
+    choice_nodes: Query<&ChoiceNode>,
+    shown: Query<&ShownChoices>,
+    mut selected_writer: EventWriter<ChoiceSelectedEvent>,
+    talks: Query<Has<Talk>>,
+    entities: &Entities,
+) -> Vec<NextActionError> {
+    let mut errors = vec![];
+    // the skipped choices are not made by the user, so they are not locked out and do not lock the talk
+    let requests = reqs.read().map(|req| (*req, true));
+    let requests = requests.chain(skipped.read().map(|skip| (skip.0, false)));
+    for (event, by_user) in requests {
+        #[cfg(feature = "trace")]
+        let _span = trace_span!("talks_choose_node", talk = ?event.talk).entered();
+
+        let mut handle = || -> Result<(), NextActionError> {
+            validate_talk(&talks, event.talk)?;
+
+            if traversal.is_frozen(event.talk)
+                || (by_user && lockout.is_locked(event.talk, LockedRequest::Choose))
+            {
+                return Ok(());
+            }
+
+            let current_node = traversal.current_node(event.talk)?;
+            let followings = traversal.followers(event.talk, current_node)?;
+            // the despawned branches are still in the choices (and in the relations if not removed with aery)
+            let listed = followings.contains(&event.next)
+                || choice_nodes
+                    .get(current_node)
+                    .is_ok_and(|choices| choices.0.iter().any(|c| c.next == event.next));
+            if listed && !entities.contains(event.next) {
+                return Err(NextActionError::TargetMissing {
+                    talk: event.talk,
+                    node: current_node,
+                    target: event.next,
+                });
+            }
+            let next_node =
+                validate_chosen_node(&followings, event.next, event.talk, current_node)?;
+            if shown
+                .get(current_node)
+                .is_ok_and(|shown| !shown.contains(next_node))
+            {
+                return Err(NextActionError::HiddenChoice {
+                    talk: event.talk,
+                    node: current_node,
+                    chosen: next_node,
+                });
+            }
+            traversal.advance_to(event.talk, next_node)?;
+            if by_user {
+                lockout.lock(event.talk);
+            }
+
+            let selected = choice_nodes.get(current_node).ok().and_then(|choices| {
+                choices
+                    .0
+                    .iter()
+                    .enumerate()
+                    .find(|(_, c)| c.next == next_node)
+            });
+            if let Some((index, choice)) = selected {
+                selected_writer.send(ChoiceSelectedEvent {
+                    talk: event.talk,
+                    node: current_node,
+                    index,
+                    choice: choice.clone(),
+                });
+            }
+            Ok(())
+        };
+        if let Err(err) = handle() {
+            errors.push(err);
+        }
+    }
+    errors
+}
 
+/// Handles the `RefireNodeRequest` events. It will emit the events in the current node,
+/// unless the [`RefirePolicy`] of the talk ignores the request.
+pub(crate) fn refire_handler(
+    mut reqs: EventReader<RefireNodeRequest>,
+    mut traversal: Traversal,
+    talks: Query<Has<Talk>>,
+    mut guard: RefireGuard,
+) -> Vec<NextActionError> {
+    let mut errors = vec![];
+    for event in reqs.read() {
+        #[cfg(feature = "trace")]
+        let _span = trace_span!("talks_refire_node", talk = ?event.talk).entered();
+
+        let mut handle = || -> Result<(), NextActionError> {
+            validate_talk(&talks, event.talk)?;
+            if !guard.allow(event.talk, traversal.current_node(event.talk)?) {
                 return Ok(());
             }
+            traversal.refire(event.talk)
+        };
+        if let Err(err) = handle() {
+            errors.push(err);
         }
+    }
+    errors
+}
 
-        return Err(NextActionError::NoTalk);
+/// Applies the [`OnEnd`] behavior of the talks that reached an end node.
+pub(crate) fn on_end_handler(
+    mut cmd: Commands,
+    mut end_evs: EventReader<EndEvent>,
+    on_ends: Query<&OnEnd>,
+    pooled: Query<(), With<PooledTalk>>,
+    children: Query<&Children>,
+    start: Query<Entity, With<StartNode>>,
+    mut current_nodes: ResMut<CurrentNodes>,
+    mut next_writer: EventWriter<NextNodeRequest>,
+    mut on_end_writer: EventWriter<OnEndEvent>,
+    mut changed_writer: EventWriter<CurrentNodeChanged>,
+) {
+    for EndEvent(talk) in end_evs.read() {
+        let Ok(on_end) = on_ends.get(*talk) else {
+            continue;
+        };
+        match on_end {
+            OnEnd::Loop => {
+                let current_node = current_nodes.get(*talk);
+                let start_node = children
+                    .get(*talk)
+                    .ok()
+                    .and_then(|c| c.iter().copied().find(|c| start.contains(*c)));
+                if let (Some(current_node), Some(start_node)) = (current_node, start_node) {
+                    move_current(
+                        &mut cmd,
+                        &mut current_nodes,
+                        &mut changed_writer,
+                        *talk,
+                        current_node,
+                        start_node,
+                    );
+                }
+            }
+            OnEnd::Freeze => {}
+            OnEnd::Despawn if pooled.contains(*talk) => {
+                let talk = *talk;
+                cmd.add(move |world: &mut World| release_talk(world, talk));
+            }
+            OnEnd::Despawn => cmd.entity(*talk).despawn_recursive(),
+            OnEnd::StartTalk(other) => next_writer.send(NextNodeRequest::new(*other)),
+            OnEnd::SendEvent(tag) => on_end_writer.send(OnEndEvent {
+                talk: *talk,
+                tag: tag.clone(),
+            }),
+        }
+    }
+}
+
+/// Removes the interjections the talks moved past: the interjected nodes are despawned
+/// and the interrupted node is connected back to the nodes that were following it.
+pub(crate) fn clean_interjections(
+    mut cmd: Commands,
+    talks: Query<(Entity, &Interjection)>,
+    current_nodes: Res<CurrentNodes>,
+) {
+    for (talk, interjection) in &talks {
+        let Some(current) = current_nodes.get(talk) else {
+            continue;
+        };
+        if current == interjection.node || interjection.nodes.contains(&current) {
+            continue;
+        }
+
+        let interjection = interjection.clone();
+        cmd.entity(talk).remove::<Interjection>();
+        cmd.add(move |world: &mut World| {
+            // unset all the relations by hand, the recursive cleanup would despawn the followers and actors
+            let mut followers = world.query::<Relations<FollowedBy>>();
+            let mut performers = world.query::<Relations<PerformedBy>>();
+            for host in std::iter::once(interjection.node).chain(interjection.nodes.clone()) {
+                let nexts = followers
+                    .get(world, host)
+                    .map(|edges| edges.targets(FollowedBy).to_vec())
+                    .unwrap_or_default();
+                let actors = performers
+                    .get(world, host)
+                    .map(|edges| edges.targets(PerformedBy).to_vec())
+                    .unwrap_or_default();
+                let mut host = world.entity_mut(host);
+                for next in nexts {
+                    host.unset::<FollowedBy>(next);
+                }
+                for actor in actors {
+                    host.unset::<PerformedBy>(actor);
+                }
+            }
+            for node in interjection.nodes {
+                world.entity_mut(node).despawn_recursive();
+            }
+            for next in interjection.resume {
+                world.entity_mut(interjection.node).set::<FollowedBy>(next);
+            }
+        });
+    }
+}
+
+/// Keeps the [`CurrentNodes`] index in sync with the `CurrentNode` markers moved outside of the traversal
+/// (e.g. by a loaded scene), and removes the despawned talks from it.
+///
+/// The traversal updates the index right away, so this only catches up with the other changes.
+pub(crate) fn sync_current_nodes(
+    mut removed_talks: RemovedComponents<Talk>,
+    mut removed_current: RemovedComponents<CurrentNode>,
+    added_current: Query<(Entity, &Parent), Added<CurrentNode>>,
+    parents: Query<&Parent>,
+    mut current_nodes: ResMut<CurrentNodes>,
+) {
+    for talk in removed_talks.read() {
+        current_nodes.0.remove(&talk);
+    }
+    for node in removed_current.read() {
+        match parents.get(node) {
+            Ok(talk) => {
+                if current_nodes.get(talk.get()) == Some(node) {
+                    current_nodes.0.remove(&talk.get());
+                }
+            }
+            // a node despawned on its own while being the current one
+            Err(_) => current_nodes.0.retain(|_, current| *current != node),
+        }
+    }
+    for (node, talk) in added_current.iter() {
+        current_nodes.0.insert(talk.get(), node);
     }
-    Ok(())
 }
 
-/// Moves the `CurrentNode` component from the current node to the next node.
+/// Keeps the [`AwaitingChoice`] and [`AtEnd`] markers of the talks in sync with their current node,
+/// whatever moved it (requests, jumps, restored states) or changed its followers (edits, lazy branches).
+pub(crate) fn update_advance_markers(
+    mut cmd: Commands,
+    current_nodes: Res<CurrentNodes>,
+    talks: Query<(Has<AwaitingChoice>, Has<AtEnd>)>,
+    nodes: Query<(Relations<FollowedBy>, Has<ChoiceNode>)>,
+) {
+    for (talk, node) in current_nodes.0.iter() {
+        let (Ok((awaiting, at_end)), Ok((edges, is_choice))) = (talks.get(*talk), nodes.get(*node))
+        else {
+            continue;
+        };
+        let is_last = edges.targets(FollowedBy).is_empty();
+        if is_choice && !awaiting {
+            cmd.entity(*talk).insert(AwaitingChoice);
+        } else if !is_choice && awaiting {
+            cmd.entity(*talk).remove::<AwaitingChoice>();
+        }
+        if is_last && !at_end {
+            cmd.entity(*talk).insert(AtEnd);
+        } else if !is_last && at_end {
+            cmd.entity(*talk).remove::<AtEnd>();
+        }
+    }
+}
+
+/// Moves the `CurrentNode` component from the current node to the next node, updates the index
+/// and sends the [`CurrentNodeChanged`] event.
 #[inline]
-fn move_current(cmd: &mut Commands<'_, '_>, current_node: Entity, next_node: Entity) {
+fn move_current(
+    cmd: &mut Commands<'_, '_>,
+    current_nodes: &mut CurrentNodes,
+    changed_writer: &mut EventWriter<CurrentNodeChanged>,
+    talk: Entity,
+    current_node: Entity,
+    next_node: Entity,
+) {
+    #[cfg(feature = "trace")]
+    trace!("Talk {talk:?} moving from node {current_node:?} to {next_node:?}");
     cmd.entity(current_node).remove::<CurrentNode>();
-    cmd.entity(next_node).insert(CurrentNode);
+    cmd.entity(next_node)
+        .insert(CurrentNode)
+        .add(reset_text_page);
+    current_nodes.0.insert(talk, next_node);
+    changed_writer.send(CurrentNodeChanged {
+        talk,
+        previous: current_node,
+        current: next_node,
+    });
+}
+
+/// Shows the first page of the node again, if it has [`TextPages`].
+fn reset_text_page(node: Entity, world: &mut World) {
+    if let Some(mut pages) = world.get_mut::<TextPages>(node) {
+        pages.page = 0;
+    }
+}
+
+/// Validates that the entity of a request is a talk. The despawned talks are left to the handlers,
+/// which fail with [`NextActionError::NoTalk`].
+///
+/// An existing entity without the [`Talk`] component is a mistaken id, so it also fails a debug assertion.
+fn validate_talk(talks: &Query<Has<Talk>>, talk: Entity) -> Result<(), NextActionError> {
+    if talks.get(talk).is_ok_and(|is_talk| !is_talk) {
+        debug_assert!(false, "The request entity {talk:?} is not a talk.");
+        return Err(NextActionError::NotATalk(talk));
+    }
+    Ok(())
 }
 
 /// Validates that there is only one next node.
 #[inline]
-fn validate_next_node(followings: &[Entity]) -> Result<Entity, NextActionError> {
+fn validate_next_node(
+    followings: &[Entity],
+    talk: Entity,
+    node: Entity,
+) -> Result<Entity, NextActionError> {
     if followings.len() > 1 {
-        return Err(NextActionError::ChoicesNotHandled);
+        return Err(NextActionError::ChoicesNotHandled {
+            talk,
+            node,
+            choices: followings.to_vec(),
+        });
     } else if followings.is_empty() {
-        return Err(NextActionError::NoNextAction);
+        return Err(NextActionError::NoNextAction { talk, node });
     }
 
     Ok(followings[0])
 }
 
+/// Returns the command sending the [`ChoicesNotHandledEvent`] with the available choices of the node.
+fn send_choices_not_handled(talk: Entity, node: Entity) -> impl FnOnce(&mut World) {
+    move |world: &mut World| {
+        let choices = explain_choice_availability(world, node)
+            .into_iter()
+            .filter(|(_, available, _)| *available)
+            .map(|(choice, _, _)| choice)
+            .collect();
+        world.send_event(ChoicesNotHandledEvent {
+            talk,
+            node,
+            choices,
+        });
+    }
+}
+
 /// Validates that the chosen next node is connected to the current node.
 fn validate_chosen_node(
     followings: &[Entity],
     chosen_node: Entity,
+    talk: Entity,
+    node: Entity,
 ) -> Result<Entity, NextActionError> {
     if !followings.contains(&chosen_node) {
-        return Err(NextActionError::BadChoice);
+        return Err(NextActionError::BadChoice {
+            talk,
+            node,
+            chosen: chosen_node,
+        });
     }
 
     Ok(chosen_node)
@@ -155,8 +791,9 @@ fn validate_chosen_node(
 mod tests {
     use crate::{
         prelude::Action,
-        tests::{setup_and_next, single},
+        tests::{count, get_comp, setup_and_next, single, talks_minimal_app},
     };
+    use bevy::ecs::system::{Command, RunSystemOnce};
     use indexmap::indexmap;
 
     use super::*;
@@ -235,7 +872,7 @@ mod tests {
     fn choice_event_from_choice_node() {
         let script = indexmap! {
             1 => Action { choices: vec![
-                ChoiceData {text: "Choice 1".to_string(), next: 2},
+                ChoiceData {text: "Choice 1".to_string(), next: 2, ..default() },
                 ], kind: NodeKind::Choice, ..default() },
             2 => Action { text: "test".to_string(), ..default() },
         };
@@ -244,12 +881,62 @@ mod tests {
         assert!(evs.len() > 0);
     }
 
+    #[test]
+    fn choice_event_in_authored_order() {
+        let script = indexmap! {
+            1 => Action { choices: vec![
+                ChoiceData { text: "A".to_string(), next: 4, ..default() },
+                ChoiceData { text: "B".to_string(), next: 3, ..default() },
+                ChoiceData { text: "C".to_string(), next: 2, ..default() },
+                ], kind: NodeKind::Choice, ..default() },
+            2 => Action { text: "c".to_string(), ..default() },
+            3 => Action { text: "b".to_string(), ..default() },
+            4 => Action { text: "a".to_string(), ..default() },
+        };
+        let mut app = setup_and_next(&TalkData::new(script, vec![]));
+        let (t, _) = app.world.query::<(Entity, With<Talk>)>().single(&app.world);
+        let choice_texts = |app: &App| {
+            let evs = app.world.resource::<Events<ChoiceNodeEvent>>();
+            let ev = evs.get_reader().read(evs).last().unwrap().clone();
+            ev.choices.into_iter().map(|c| c.text).collect::<Vec<_>>()
+        };
+        assert_eq!(choice_texts(&app), vec!["A", "B", "C"]);
+
+        // shuffle the choices of the node, the event keeps the authored order
+        let (mut choice_node, _) = app
+            .world
+            .query::<(&mut ChoiceNode, With<CurrentNode>)>()
+            .single_mut(&mut app.world);
+        assert_eq!(
+            choice_node.0.iter().map(|c| c.order).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        choice_node.0.rotate_left(1);
+        app.world.send_event(RefireNodeRequest::new(t));
+        app.update();
+        assert_eq!(choice_texts(&app), vec!["A", "B", "C"]);
+    }
+
+    #[test]
+    fn choice_order_does_not_depend_on_spawn_order() {
+        let mut world = World::default();
+        let second = world.spawn_empty().id();
+        let first = world.spawn_empty().id();
+        let node = ChoiceNode(vec![
+            Choice::new("Second", second).with_order(1),
+            Choice::new("First", first).with_order(0),
+        ]);
+        let event = node.make(&[]).downcast::<ChoiceNodeEvent>().unwrap();
+        let nexts = event.choices.iter().map(|c| c.next).collect::<Vec<_>>();
+        assert_eq!(nexts, vec![first, second]);
+    }
+
     #[test]
     fn test_choice_handler() {
         let script = indexmap! {
             1 => Action {  choices: vec![
-                ChoiceData {text: "Choice 1".to_string(), next: 2},
-                ChoiceData {text: "Choice 2".to_string(), next: 3}
+                ChoiceData {text: "Choice 1".to_string(), next: 2, ..default() },
+                ChoiceData {text: "Choice 2".to_string(), next: 3, ..default() }
                 ], kind: NodeKind::Choice, ..default() },
             2 => Action { kind: NodeKind::Leave, ..default() },
             3 => Action { text: "test".to_string(), ..default() },
@@ -277,6 +964,635 @@ mod tests {
             .is_ok())
     }
 
+    #[test]
+    fn choice_selected_event_with_sound() {
+        let script = indexmap! {
+            1 => Action {  choices: vec![
+                ChoiceData {text: "Choice 1".to_string(), next: 2, ..default() },
+                ChoiceData {text: "Choice 2".to_string(), next: 3, sound: Some("stinger.ogg".to_string()), ..default() }
+                ], kind: NodeKind::Choice, ..default() },
+            2 => Action { text: "one".to_string(), ..default() },
+            3 => Action { text: "two".to_string(), ..default() },
+        };
+        let mut app = setup_and_next(&TalkData::new(script, vec![]));
+        let (t, _) = app.world.query::<(Entity, With<Talk>)>().single(&app.world);
+        let (node, choice_node, _) = app
+            .world
+            .query::<(Entity, &ChoiceNode, With<CurrentNode>)>()
+            .single(&app.world);
+        let next = choice_node.0[1].next;
+
+        app.world.send_event(ChooseNodeRequest::new(t, next));
+        app.update();
+
+        let evs = app.world.resource::<Events<ChoiceSelectedEvent>>();
+        let selected = evs.get_reader().read(evs).cloned().collect::<Vec<_>>();
+        assert_eq!(selected.len(), 1);
+        assert_eq!((selected[0].talk, selected[0].node), (t, node));
+        assert_eq!(selected[0].index, 1);
+        assert_eq!(selected[0].choice.sound.as_deref(), Some("stinger.ogg"));
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic(expected = "is not a talk"))]
+    fn request_for_entity_that_is_not_a_talk() {
+        let mut app = talks_minimal_app();
+        let entity = app.world.spawn(TextNode::default()).id();
+        app.world.send_event(NextNodeRequest::new(entity));
+        let errors = app.world.run_system_once(next_handler);
+        assert_eq!(errors, [NextActionError::NotATalk(entity)]);
+    }
+
+    #[test]
+    fn current_nodes_synced_with_the_markers() {
+        let mut app = talks_minimal_app();
+        // a talk spawned without the builder, like a loaded scene
+        let talk = app.world.spawn(Talk::default()).id();
+        let start = app
+            .world
+            .spawn((StartNode, CurrentNode))
+            .set_parent(talk)
+            .id();
+        let node = app
+            .world
+            .spawn(TextNode("Hello".into()))
+            .set_parent(talk)
+            .id();
+        app.update();
+        assert_eq!(app.world.resource::<CurrentNodes>().get(talk), Some(start));
+
+        app.world.entity_mut(start).remove::<CurrentNode>();
+        app.world.entity_mut(node).insert(CurrentNode);
+        app.update();
+        assert_eq!(app.world.resource::<CurrentNodes>().get(talk), Some(node));
+
+        app.world.entity_mut(node).remove::<CurrentNode>();
+        app.update();
+        assert_eq!(app.world.resource::<CurrentNodes>().get(talk), None);
+
+        app.world.entity_mut(start).insert(CurrentNode);
+        app.update();
+        app.world.entity_mut(start).despawn();
+        app.update();
+        assert_eq!(app.world.resource::<CurrentNodes>().get(talk), None);
+    }
+
+    #[test]
+    fn failing_request_does_not_stop_the_others() {
+        let mut app = talks_minimal_app();
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, TalkBuilder::default().say("Hi")).apply(&mut app.world);
+        let gone = app.world.spawn_empty().id();
+        app.world.despawn(gone);
+
+        app.world.send_event(NextNodeRequest::new(gone));
+        app.world.send_event(NextNodeRequest::new(talk));
+        let errors = app.world.run_system_once(next_handler);
+        assert_eq!(errors, [NextActionError::NoTalk(gone)]);
+        let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        assert_eq!(get_comp::<TextNode>(current, &mut app.world).0, "Hi");
+
+        let builder = TalkBuilder::default().choose(vec![
+            ("Yes", TalkBuilder::default().say("Ok")),
+            ("No", TalkBuilder::default().say("Bye")),
+        ]);
+        let other = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(other, builder).apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(other));
+        app.update();
+        let (_, choices) = single::<(Entity, &ChoiceNode)>(&mut app.world);
+        let yes = choices.0[0].next;
+        app.world.send_event(ChooseNodeRequest::new(gone, yes));
+        app.world.send_event(ChooseNodeRequest::new(other, yes));
+        let errors = app.world.run_system_once(choice_handler);
+        assert_eq!(errors, [NextActionError::NoTalk(gone)]);
+        assert_eq!(app.world.resource::<CurrentNodes>().get(other), Some(yes));
+    }
+
+    #[test]
+    fn advance_markers_follow_the_current_node() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default().say("Hi").choose(vec![
+            ("Bye", TalkBuilder::default().say("Bye")),
+            ("Stay", TalkBuilder::default().say("Ok")),
+        ]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        let markers = |app: &App| {
+            let talk = app.world.entity(talk);
+            (talk.contains::<AwaitingChoice>(), talk.contains::<AtEnd>())
+        };
+
+        app.update();
+        assert_eq!(markers(&app), (false, false));
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(markers(&app), (false, false));
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(markers(&app), (true, false));
+
+        let (_, choices) = single::<(Entity, &ChoiceNode)>(&mut app.world);
+        let bye = choices.0[0].next;
+        app.world.send_event(ChooseNodeRequest::new(talk, bye));
+        app.update();
+        assert_eq!(markers(&app), (false, true));
+    }
+
+    #[test]
+    fn paged_text_node_shows_each_page() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default()
+            .say_pages(["Once upon a time", "the end"])
+            .say("After");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        let mut shown = vec![];
+        for _ in 0..3 {
+            app.world.send_event(NextNodeRequest::new(talk));
+            app.update();
+            let evs = app.world.resource::<Events<TextNodeEvent>>();
+            let event = evs.get_reader().read(evs).last().unwrap();
+            shown.push((event.text.clone(), event.page, event.pages));
+        }
+        assert_eq!(
+            shown,
+            [
+                ("Once upon a time".to_string(), 0, 2),
+                ("the end".to_string(), 1, 2),
+                ("After".to_string(), 0, 1),
+            ]
+        );
+        let (node, pages) = single::<(Entity, &TextPages)>(&mut app.world);
+        assert_eq!(pages.page, 1);
+        assert_eq!(
+            get_comp::<TextNode>(node, &mut app.world).0,
+            "Once upon a time\nthe end"
+        );
+    }
+
+    #[test]
+    fn next_on_choice_node_sends_the_choices() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default().choose(vec![
+            ("Yes", TalkBuilder::default().say("Ok")),
+            ("No", TalkBuilder::default().say("Bye")),
+        ]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let (node, choices) = single::<(Entity, &ChoiceNode)>(&mut app.world);
+        let nexts = choices.0.iter().map(|c| c.next).collect::<Vec<_>>();
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        assert_eq!(
+            app.world.run_system_once(next_handler),
+            [NextActionError::ChoicesNotHandled {
+                talk,
+                node,
+                choices: nexts
+            }]
+        );
+        let evs = app.world.resource::<Events<ChoicesNotHandledEvent>>();
+        let unhandled = evs.get_reader().read(evs).cloned().collect::<Vec<_>>();
+        assert_eq!(unhandled.len(), 1);
+        assert_eq!((unhandled[0].talk, unhandled[0].node), (talk, node));
+        let texts = unhandled[0].choices.iter().map(|c| c.text.as_str());
+        assert_eq!(texts.collect::<Vec<_>>(), ["Yes", "No"]);
+    }
+
+    #[test]
+    fn choosing_a_despawned_branch_fails() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default().choose(vec![
+            ("Yes", TalkBuilder::default().say("Ok")),
+            ("No", TalkBuilder::default().say("Bye")),
+        ]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let (choice_node, choices) = single::<(Entity, &ChoiceNode)>(&mut app.world);
+        let (yes, no) = (choices.0[0].next, choices.0[1].next);
+
+        // with the relations left behind
+        app.world.despawn(yes);
+        app.world.send_event(ChooseNodeRequest::new(talk, yes));
+        assert_eq!(
+            app.world.run_system_once(choice_handler),
+            [NextActionError::TargetMissing {
+                talk,
+                node: choice_node,
+                target: yes
+            }]
+        );
+        app.world
+            .resource_mut::<Events<ChooseNodeRequest>>()
+            .clear();
+        // with the relations cleaned up
+        app.world.entity_mut(choice_node).unset::<FollowedBy>(no);
+        app.world.despawn(no);
+        app.world.send_event(ChooseNodeRequest::new(talk, no));
+        assert_eq!(
+            app.world.run_system_once(choice_handler),
+            [NextActionError::TargetMissing {
+                talk,
+                node: choice_node,
+                target: no
+            }]
+        );
+        app.world
+            .resource_mut::<Events<ChooseNodeRequest>>()
+            .clear();
+
+        // the talk stays on the choice node
+        app.world.send_event(ChooseNodeRequest::new(talk, yes));
+        app.update();
+        assert_eq!(
+            app.world.resource::<CurrentNodes>().get(talk),
+            Some(choice_node)
+        );
+        assert!(app.world.get::<CurrentNode>(choice_node).is_some());
+    }
+
+    #[test]
+    fn advance_to_jumps_to_any_node() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default()
+            .say("Hello")
+            .say("Skipped")
+            .say("Bye");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        let bye = app
+            .world
+            .query::<(Entity, &TextNode)>()
+            .iter(&app.world)
+            .find(|(_, t)| t.0 == "Bye")
+            .map(|(e, _)| e)
+            .unwrap();
+
+        app.world
+            .run_system_once(move |mut traversal: Traversal| traversal.advance_to(talk, bye))
+            .unwrap();
+        app.update();
+
+        assert_eq!(app.world.resource::<CurrentNodes>().get(talk), Some(bye));
+        assert!(app.world.get::<CurrentNode>(bye).is_some());
+        assert_eq!(app.world.resource::<Events<StartEvent>>().len(), 1);
+        assert_eq!(app.world.resource::<Events<EndEvent>>().len(), 1);
+        let evs = app.world.resource::<Events<TextNodeEvent>>();
+        assert_eq!(evs.get_reader().read(evs).next().unwrap().text, "Bye");
+    }
+
+    /// A talk with a choice node leading directly to another choice node.
+    fn choice_chain_talk() -> TalkData {
+        let script = indexmap! {
+            1 => Action { choices: vec![
+                ChoiceData {text: "A".to_string(), next: 2, ..default() },
+                ], kind: NodeKind::Choice, ..default() },
+            2 => Action { choices: vec![
+                ChoiceData {text: "B".to_string(), next: 3, ..default() },
+                ], kind: NodeKind::Choice, ..default() },
+            3 => Action { text: "Done".to_string(), ..default() },
+        };
+        TalkData::new(script, vec![])
+    }
+
+    #[test]
+    fn choice_leading_to_choice_in_single_update() {
+        let mut app = setup_and_next(&choice_chain_talk());
+        let (talk, _) = single::<(Entity, With<Talk>)>(&mut app.world);
+        let (choice_node, _) = single::<(&ChoiceNode, With<CurrentNode>)>(&mut app.world);
+        let second_choice = choice_node.0[0].next;
+
+        app.world
+            .send_event(ChooseNodeRequest::new(talk, second_choice));
+        app.update();
+
+        // the event of the second choice node is emitted in the same update
+        let evs = app.world.resource::<Events<ChoiceNodeEvent>>();
+        let last_choices = &evs.get_reader().read(evs).last().unwrap().choices;
+        assert_eq!(last_choices[0].text, "B");
+        assert_eq!(
+            app.world.resource::<CurrentNodes>().get(talk),
+            Some(second_choice)
+        );
+        assert!(app.world.get::<CurrentNode>(second_choice).is_some());
+    }
+
+    #[test]
+    fn chained_requests_in_single_update() {
+        let mut app = setup_and_next(&choice_chain_talk());
+        let (talk, _) = single::<(Entity, With<Talk>)>(&mut app.world);
+        let (choice_node, _) = single::<(&ChoiceNode, With<CurrentNode>)>(&mut app.world);
+        let second_choice = choice_node.0[0].next;
+        let done = get_comp::<ChoiceNode>(second_choice, &mut app.world).0[0].next;
+
+        app.world
+            .send_event(ChooseNodeRequest::new(talk, second_choice));
+        app.world.send_event(ChooseNodeRequest::new(talk, done));
+        app.update();
+
+        let (text, _) = single::<(&TextNode, With<CurrentNode>)>(&mut app.world);
+        assert_eq!(text.0, "Done");
+        assert_eq!(count::<(Entity, With<CurrentNode>)>(&mut app.world), 1);
+    }
+
+    #[test]
+    fn chapter_events_when_crossing_chapters() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default()
+            .chapter("Act 1")
+            .say("Hello")
+            .say("Still act 1")
+            .chapter("Act 2")
+            .say("Bye");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let evs = app.world.resource::<Events<ChapterStartedEvent>>();
+        assert_eq!(evs.get_reader().read(evs).next().unwrap().chapter, "Act 1");
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(app.world.resource::<Events<ChapterStartedEvent>>().len(), 1);
+        assert!(app.world.resource::<Events<ChapterEndedEvent>>().is_empty());
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let evs = app.world.resource::<Events<ChapterEndedEvent>>();
+        assert_eq!(evs.get_reader().read(evs).next().unwrap().chapter, "Act 1");
+        let evs = app.world.resource::<Events<ChapterStartedEvent>>();
+        assert_eq!(evs.get_reader().read(evs).last().unwrap().chapter, "Act 2");
+    }
+
+    #[test]
+    fn text_event_with_emphasis() {
+        let script = indexmap! {
+            0 => Action { text: "BOOM".to_string(), emphasis: Some(2.0), ..default() },
+        };
+        let app = setup_and_next(&TalkData::new(script, vec![]));
+        let evs = app.world.resource::<Events<TextNodeEvent>>();
+        assert_eq!(
+            evs.get_reader().read(evs).next().unwrap().emphasis,
+            Some(2.0)
+        );
+    }
+
+    #[test]
+    fn choice_event_with_descriptions() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default().choose_with_descriptions(vec![
+            (
+                "Persuade",
+                Some("Charisma check: 40%".to_string()),
+                TalkBuilder::default().say("Fine..."),
+            ),
+            ("Leave", None, TalkBuilder::default().say("Bye!")),
+        ]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+
+        let evs = app.world.resource::<Events<ChoiceNodeEvent>>();
+        let choices = &evs.get_reader().read(evs).next().unwrap().choices;
+        assert_eq!(
+            choices[0].description,
+            Some("Charisma check: 40%".to_string())
+        );
+        assert_eq!(choices[1].description, None);
+    }
+
+    #[test]
+    fn text_and_choice_events_with_icon() {
+        let script = indexmap! {
+            0 => Action { text: "Hmm".to_string(), icon: Some("thinking".to_string()), next: Some(1), ..default() },
+            1 => Action { choices: vec![
+                ChoiceData {text: "Yes".to_string(), next: 2, ..default() },
+            ], kind: NodeKind::Choice, icon: Some("question".to_string()), ..default() },
+            2 => Action { text: "Ok".to_string(), ..default() },
+        };
+        let mut app = setup_and_next(&TalkData::new(script, vec![]));
+        let evs = app.world.resource::<Events<TextNodeEvent>>();
+        let icon = evs.get_reader().read(evs).next().unwrap().icon.clone();
+        assert_eq!(icon, Some("thinking".to_string()));
+
+        let (talk, _) = single::<(Entity, With<Talk>)>(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let evs = app.world.resource::<Events<ChoiceNodeEvent>>();
+        let icon = evs.get_reader().read(evs).next().unwrap().icon.clone();
+        assert_eq!(icon, Some("question".to_string()));
+    }
+
+    #[test]
+    fn text_event_with_thought() {
+        let script = indexmap! {
+            0 => Action { text: "Lovely weather.".to_string(), thought: Some("I hate rain.".to_string()), next: Some(1), ..default() },
+            1 => Action { text: "Bye".to_string(), ..default() },
+        };
+        let mut app = setup_and_next(&TalkData::new(script, vec![]));
+        let evs = app.world.resource::<Events<TextNodeEvent>>();
+        let event = evs.get_reader().read(evs).next().unwrap().clone();
+        assert_eq!(event.text, "Lovely weather.");
+        assert_eq!(event.thought, Some("I hate rain.".to_string()));
+
+        let (talk, _) = single::<(Entity, With<Talk>)>(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let evs = app.world.resource::<Events<TextNodeEvent>>();
+        assert_eq!(evs.get_reader().read(evs).next().unwrap().thought, None);
+    }
+
+    #[test]
+    fn choice_event_with_actors() {
+        let script = indexmap! {
+            1 => Action { kind: NodeKind::Choice, actors: vec!["bob".to_string()], choices: vec![
+                ChoiceData {text: "Yes".to_string(), next: 2, ..default() },
+            ], ..default() },
+            2 => Action { text: "Ok".to_string(), ..default() },
+        };
+        let app = setup_and_next(&TalkData::new(script, vec![Actor::new("bob", "Bob")]));
+        let evs = app.world.resource::<Events<ChoiceNodeEvent>>();
+        assert_eq!(
+            evs.get_reader().read(evs).next().unwrap().actors,
+            vec!["Bob"]
+        );
+
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default()
+            .add_actor(Actor::new("alice", "Alice"))
+            .actor_choose("alice", vec![("Yes", TalkBuilder::default().say("Ok"))]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let evs = app.world.resource::<Events<ChoiceNodeEvent>>();
+        assert_eq!(
+            evs.get_reader().read(evs).next().unwrap().actors,
+            vec!["Alice"]
+        );
+    }
+
+    #[test]
+    fn text_event_from_player_node() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default()
+            .add_actor(Actor::new("bob", "Bob"))
+            .actor_say("bob", "Hello")
+            .actor_say(Actor::PLAYER_SLUG, "Hi Bob");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        assert_eq!(count::<With<PlayerNode>>(&mut app.world), 1);
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+
+        let evs = app.world.resource::<Events<TextNodeEvent>>();
+        let evs = evs.get_reader().read(evs).collect::<Vec<_>>();
+        assert!(!evs[0].is_player);
+        assert!(evs[1].is_player);
+        assert!(evs[1].actors.is_empty());
+    }
+
+    #[test]
+    fn animate_event_with_actor_entity() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default()
+            .add_actor(Actor::new("bob", "Bob"))
+            .actor_say("bob", "Hello")
+            .animate("bob", "wave");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        let (actor, _) = single::<(Entity, With<Actor>)>(&mut app.world);
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+
+        let evs = app.world.resource::<Events<AnimateActorEvent>>();
+        let ev = evs.get_reader().read(evs).next().unwrap();
+        assert_eq!(ev.clip, "wave");
+        assert_eq!(ev.actor, Some(actor));
+        assert_eq!(app.world.resource::<Events<TextNodeEvent>>().len(), 1);
+    }
+
+    #[test]
+    fn animate_event_with_actor_of_the_talk() {
+        let mut app = talks_minimal_app();
+        app.world.spawn(Actor::new("carl", "Carl"));
+        let builder = TalkBuilder::default()
+            .add_actor(Actor::new("bob", "Bob"))
+            .actor_say("bob", "Hello")
+            .say("Bob waves.")
+            .animate("bob", "wave")
+            .say("Carl, not in the talk, doesn't.")
+            .animate("carl", "wave");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        let bob = app
+            .world
+            .query::<(Entity, &Actor)>()
+            .iter(&app.world)
+            .find_map(|(e, a)| (a.slug == "bob").then_some(e));
+
+        for _ in 0..3 {
+            app.world.send_event(NextNodeRequest::new(talk));
+            app.update();
+        }
+
+        let evs = app.world.resource::<Events<AnimateActorEvent>>();
+        let evs = evs.get_reader().read(evs).collect::<Vec<_>>();
+        assert_eq!(evs.len(), 2);
+        // bob doesn't perform the node but performs another node of the talk
+        assert_eq!(evs[0].actor, bob);
+        assert!(bob.is_some());
+        // carl is an actor of the world, but doesn't perform any node of the talk
+        assert_eq!(evs[1].slug, "carl");
+        assert_eq!(evs[1].actor, None);
+    }
+
+    #[test]
+    fn text_event_keeps_speaking_order() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default()
+            .add_actors(vec![Actor::new("bob", "Bob"), Actor::new("alice", "Alice")])
+            .actors_say_lines(vec![("alice", "Happy birthday"), ("bob", "to you!")]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+
+        let evs = app.world.resource::<Events<TextNodeEvent>>();
+        let ev = evs.get_reader().read(evs).next().unwrap();
+        assert_eq!(ev.actors, vec!["Alice".to_string(), "Bob".to_string()]);
+        assert_eq!(
+            ev.lines,
+            vec!["Happy birthday".to_string(), "to you!".to_string()]
+        );
+        assert_eq!(ev.text, "Happy birthday\nto you!");
+    }
+
+    #[test]
+    fn current_nodes_index_follows_traversal() {
+        let script = indexmap! {
+            0 => Action { text: "Hello".to_string(), ..default() },
+        };
+        let mut app = setup_and_next(&TalkData::new(script, vec![]));
+        let (talk, _) = single::<(Entity, With<Talk>)>(&mut app.world);
+        let (current, _) = single::<(Entity, With<CurrentNode>)>(&mut app.world);
+        assert_eq!(
+            app.world.resource::<CurrentNodes>().get(talk),
+            Some(current)
+        );
+
+        app.world.entity_mut(talk).despawn_recursive();
+        app.update();
+        assert_eq!(app.world.resource::<CurrentNodes>().get(talk), None);
+    }
+
+    #[test]
+    fn validation_errors_carry_context() {
+        let mut world = World::default();
+        let (talk, node, a, b) = (
+            world.spawn_empty().id(),
+            world.spawn_empty().id(),
+            world.spawn_empty().id(),
+            world.spawn_empty().id(),
+        );
+
+        assert_eq!(
+            validate_next_node(&[], talk, node),
+            Err(NextActionError::NoNextAction { talk, node })
+        );
+        assert_eq!(
+            validate_next_node(&[a, b], talk, node),
+            Err(NextActionError::ChoicesNotHandled {
+                talk,
+                node,
+                choices: vec![a, b]
+            })
+        );
+        assert_eq!(validate_next_node(&[a], talk, node), Ok(a));
+        assert_eq!(
+            validate_chosen_node(&[a], b, talk, node),
+            Err(NextActionError::BadChoice {
+                talk,
+                node,
+                chosen: b
+            })
+        );
+    }
+
     #[test]
     fn has_started_becomes_true() {
         let script = indexmap! {
@@ -284,7 +1600,407 @@ mod tests {
         };
         let mut app = setup_and_next(&TalkData::new(script, vec![]));
 
-        let talk = single::<&Talk>(&mut app.world);
-        assert!(talk.has_started);
+        assert_eq!(count::<(Entity, With<HasStarted>)>(&mut app.world), 1);
+    }
+
+    #[test]
+    fn has_started_on_jumps_from_start() {
+        let mut app = talks_minimal_app();
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, TalkBuilder::default().say("Hello").say("Bye"))
+            .apply(&mut app.world);
+        let (bye, _) = app
+            .world
+            .query::<(Entity, &TextNode)>()
+            .iter(&app.world)
+            .find(|(_, t)| t.0 == "Bye")
+            .unwrap();
+
+        app.world
+            .run_system_once(move |mut traversal: Traversal| traversal.advance_to(talk, bye))
+            .unwrap();
+
+        assert!(app.world.get::<HasStarted>(talk).is_some());
+    }
+
+    #[test]
+    fn refire_at_start_does_not_start() {
+        let mut app = talks_minimal_app();
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, TalkBuilder::default().say("Hello")).apply(&mut app.world);
+
+        app.world.send_event(RefireNodeRequest::new(talk));
+        app.update();
+
+        assert!(app.world.get::<HasStarted>(talk).is_none());
+    }
+
+    #[test]
+    fn set_has_started_is_idempotent() {
+        let mut app = talks_minimal_app();
+        let talk = app.world.spawn(Talk::default()).id();
+        SetHasStarted { talk }.apply(&mut app.world);
+        app.world.clear_trackers();
+        app.world.increment_change_tick();
+
+        SetHasStarted { talk }.apply(&mut app.world);
+        assert!(!app
+            .world
+            .entity(talk)
+            .get_ref::<HasStarted>()
+            .unwrap()
+            .is_changed());
+
+        // not a talk: nothing happens
+        let not_talk = app.world.spawn_empty().id();
+        SetHasStarted { talk: not_talk }.apply(&mut app.world);
+        assert!(app.world.get::<HasStarted>(not_talk).is_none());
+    }
+
+    #[test]
+    fn despawned_actor_is_skipped() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default()
+            .add_actor(Actor::new("bob", "Bob"))
+            .actor_say("bob", "Hello");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        let (actor, _) = single::<(Entity, With<Actor>)>(&mut app.world);
+        app.world.despawn(actor);
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+
+        let evs = app.world.resource::<Events<TextNodeEvent>>();
+        assert!(evs.get_reader().read(evs).next().unwrap().actors.is_empty());
+        let evs = app.world.resource::<Events<MissingActorEvent>>();
+        assert_eq!(evs.get_reader().read(evs).next().unwrap().actor, actor);
+    }
+
+    #[test]
+    fn event_actor_named_from_entity_name() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default()
+            .add_actor(Actor::new("bob", ""))
+            .actor_say("bob", "Hello");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        let (actor, _) = single::<(Entity, With<Actor>)>(&mut app.world);
+        app.world.entity_mut(actor).insert(Name::new("Bob"));
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+
+        let evs = app.world.resource::<Events<TextNodeEvent>>();
+        assert_eq!(
+            evs.get_reader().read(evs).next().unwrap().actors,
+            vec!["Bob"]
+        );
+    }
+
+    #[test]
+    fn aliased_actor_named_after_reveal() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default()
+            .add_actor(Actor::new("stranger", "Geralt"))
+            .alias("???", "stranger")
+            .actor_say("???", "Hmm.")
+            .actor_say("???", "The name's Geralt.")
+            .actor_say("stranger", "Of Rivia.");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        let next_actors = |app: &mut App| {
+            app.world.send_event(NextNodeRequest::new(talk));
+            app.update();
+            let evs = app.world.resource::<Events<TextNodeEvent>>();
+            evs.get_reader().read(evs).next().unwrap().actors.clone()
+        };
+
+        assert_eq!(next_actors(&mut app), vec!["???"]);
+        assert!(app
+            .world
+            .get_mut::<ActorAliases>(talk)
+            .unwrap()
+            .reveal("???"));
+        assert_eq!(next_actors(&mut app), vec!["Geralt"]);
+        assert_eq!(next_actors(&mut app), vec!["Geralt"]);
+        // the same actor entity performs all the nodes
+        assert_eq!(count::<&Actor>(&mut app.world), 1);
+    }
+
+    #[test]
+    fn despawned_actor_halts_with_policy() {
+        let mut app = talks_minimal_app();
+        app.insert_resource(MissingActorPolicy::Halt);
+        let builder = TalkBuilder::default()
+            .add_actor(Actor::new("bob", "Bob"))
+            .actor_say("bob", "Hello");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        let start = app.world.resource::<CurrentNodes>().get(talk);
+        let (actor, _) = single::<(Entity, With<Actor>)>(&mut app.world);
+        app.world.despawn(actor);
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+
+        assert_eq!(app.world.resource::<CurrentNodes>().get(talk), start);
+        assert!(app.world.resource::<Events<TextNodeEvent>>().is_empty());
+        assert_eq!(app.world.resource::<Events<MissingActorEvent>>().len(), 1);
+    }
+
+    /// Builds the talk with the given end behavior and sends the first `NextNodeRequest`.
+    fn talk_with_on_end(app: &mut App, builder: TalkBuilder, on_end: OnEnd) -> Entity {
+        let talk = app.world.spawn((Talk::default(), on_end)).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        talk
+    }
+
+    #[test]
+    fn on_end_loop_goes_back_to_start() {
+        let mut app = talks_minimal_app();
+        let talk = talk_with_on_end(&mut app, TalkBuilder::default().say("Hello"), OnEnd::Loop);
+
+        let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        assert!(app.world.get::<StartNode>(current).is_some());
+        assert!(app.world.get::<CurrentNode>(current).is_some());
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(app.world.resource::<Events<StartEvent>>().len(), 2);
+    }
+
+    #[test]
+    fn ended_talk_resumes_at_resume_node() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default()
+            .say("Welcome!")
+            .say("What else?")
+            .resume_here()
+            .say("Bye");
+        let talk = talk_with_on_end(&mut app, builder, OnEnd::Loop);
+        let next_text = |app: &mut App| {
+            app.world.send_event(NextNodeRequest::new(talk));
+            app.update();
+            let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+            app.world.get::<TextNode>(current).map(|t| t.0.clone())
+        };
+
+        // the first time from the start
+        assert_eq!(next_text(&mut app).unwrap(), "What else?");
+        assert!(app.world.get::<HasEnded>(talk).is_none());
+        assert_eq!(next_text(&mut app), None); // Bye, then back to the start node
+        assert!(app.world.get::<HasEnded>(talk).is_some());
+
+        assert_eq!(next_text(&mut app).unwrap(), "What else?");
+        let evs = app.world.resource::<Events<TextNodeEvent>>();
+        assert_eq!(
+            evs.get_reader().read(evs).last().unwrap().text,
+            "What else?"
+        );
+    }
+
+    #[test]
+    fn current_node_changed_from_all_paths() {
+        let mut app = talks_minimal_app();
+        let talk = talk_with_on_end(&mut app, TalkBuilder::default().say("Hello"), OnEnd::Loop);
+        let (hello, _) = single::<(Entity, With<TextNode>)>(&mut app.world);
+        let (start, _) = single::<(Entity, With<StartNode>)>(&mut app.world);
+
+        let evs = app.world.resource::<Events<CurrentNodeChanged>>();
+        let changes = evs.get_reader().read(evs).copied().collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                // the next request
+                CurrentNodeChanged {
+                    talk,
+                    previous: start,
+                    current: hello,
+                },
+                // the loop back to the start
+                CurrentNodeChanged {
+                    talk,
+                    previous: hello,
+                    current: start,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn on_end_freeze_ignores_requests() {
+        let mut app = talks_minimal_app();
+        let builder = TalkBuilder::default().say("Hello").end().say("Bye");
+        let talk = talk_with_on_end(&mut app, builder, OnEnd::Freeze);
+        let end = app.world.resource::<CurrentNodes>().get(talk);
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(app.world.resource::<CurrentNodes>().get(talk), end);
+    }
+
+    #[test]
+    fn on_end_despawn_removes_the_talk() {
+        let mut app = talks_minimal_app();
+        let talk = talk_with_on_end(
+            &mut app,
+            TalkBuilder::default().say("Hello"),
+            OnEnd::Despawn,
+        );
+
+        assert!(app.world.get_entity(talk).is_none());
+        assert_eq!(count::<(Entity, With<TextNode>)>(&mut app.world), 0);
+    }
+
+    #[test]
+    fn on_end_starts_another_talk() {
+        let mut app = talks_minimal_app();
+        let other = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(other, TalkBuilder::default().say("Next")).apply(&mut app.world);
+        let other_start = app.world.resource::<CurrentNodes>().get(other);
+
+        talk_with_on_end(
+            &mut app,
+            TalkBuilder::default().say("Hello"),
+            OnEnd::StartTalk(other),
+        );
+        app.update();
+
+        assert_ne!(app.world.resource::<CurrentNodes>().get(other), other_start);
+    }
+
+    #[test]
+    fn on_end_sends_event() {
+        let mut app = talks_minimal_app();
+        let on_end = OnEnd::SendEvent("quest_given".to_string());
+        let talk = talk_with_on_end(&mut app, TalkBuilder::default().say("Hello"), on_end);
+
+        let evs = app.world.resource::<Events<OnEndEvent>>();
+        let ev = evs.get_reader().read(evs).next().unwrap().clone();
+        assert_eq!(
+            ev,
+            OnEndEvent {
+                talk,
+                tag: "quest_given".to_string()
+            }
+        );
+    }
+
+    /// Collects the texts of the text events.
+    fn texts(app: &App) -> Vec<String> {
+        let evs = app.world.resource::<Events<TextNodeEvent>>();
+        evs.get_reader().read(evs).map(|t| t.text.clone()).collect()
+    }
+
+    #[test]
+    fn interjection_resumes_the_talk() {
+        let mut app = talks_minimal_app();
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, TalkBuilder::default().say("Hello").say("Bye"))
+            .apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let hello = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+
+        app.world.run_system_once(move |mut commands: Commands| {
+            commands.interject(talk, TalkBuilder::default().say("Wait!"));
+        });
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(texts(&app), vec!["Hello".to_string(), "Wait!".to_string()]);
+        assert!(app.world.get::<Interjection>(talk).is_some());
+
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(texts(&app), vec!["Wait!".to_string(), "Bye".to_string()]);
+        assert!(app.world.get::<Interjection>(talk).is_none());
+        assert_eq!(count::<(Entity, With<TextNode>)>(&mut app.world), 2);
+
+        // the original graph is restored
+        let bye = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        let edges = app
+            .world
+            .query::<Relations<FollowedBy>>()
+            .get(&app.world, hello)
+            .unwrap()
+            .targets(FollowedBy)
+            .to_vec();
+        assert_eq!(edges, vec![bye]);
+    }
+
+    #[test]
+    fn interjection_skipped_on_choice_node() {
+        let mut app = talks_minimal_app();
+        let talk = app.world.spawn(Talk::default()).id();
+        let builder = TalkBuilder::default().choose(vec![
+            ("A", TalkBuilder::default().say("a")),
+            ("B", TalkBuilder::default().say("b")),
+        ]);
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+
+        InterjectCommand::new(talk, TalkBuilder::default().say("Wait!")).apply(&mut app.world);
+
+        assert!(app.world.get::<Interjection>(talk).is_none());
+        assert_eq!(count::<(Entity, With<TextNode>)>(&mut app.world), 2);
+    }
+
+    #[derive(Resource, Default)]
+    struct Counter(usize);
+
+    #[test]
+    fn logic_nodes_are_passed_through() {
+        let mut app = talks_minimal_app();
+        app.init_resource::<Counter>();
+        let talk = app.world.spawn(Talk::default()).id();
+        let builder = TalkBuilder::default()
+            .say("Hello")
+            .do_(|world: &mut World| world.resource_mut::<Counter>().0 += 1)
+            .do_(|world: &mut World| world.resource_mut::<Counter>().0 *= 10)
+            .say("Bye");
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(app.world.resource::<Counter>().0, 0);
+
+        // a single request runs both effects in order and reaches "Bye"
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        assert_eq!(app.world.resource::<Counter>().0, 10);
+        assert_eq!(texts(&app), vec!["Hello", "Bye"]);
+
+        // the talk never stopped on the logic nodes: a single move from "Hello" to "Bye"
+        let evs = app.world.resource::<Events<CurrentNodeChanged>>();
+        let moves: Vec<&CurrentNodeChanged> = evs.get_reader().read(evs).collect();
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[1].previous, moves[0].current);
+        let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        assert_eq!(moves[1].current, current);
+        assert!(app.world.get::<TextNode>(current).is_some());
+    }
+
+    #[test]
+    fn logic_node_loop_stops() {
+        let mut app = talks_minimal_app();
+        app.init_resource::<Counter>();
+        let talk = app.world.spawn(Talk::default()).id();
+        let builder =
+            TalkBuilder::default().do_(|world: &mut World| world.resource_mut::<Counter>().0 += 1);
+        let first = builder.last_node_id();
+        let builder = builder
+            .do_(|world: &mut World| world.resource_mut::<Counter>().0 += 1)
+            .connect_to(first);
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+
+        // both effects ran once and the talk waits on the second logic node
+        let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        assert!(app.world.get::<LogicNode>(current).is_some());
+        assert_eq!(app.world.resource::<Counter>().0, 2);
     }
 }
@@ -0,0 +1,355 @@
+//! The Twine (Twee 3) Asset Loader.
+//!
+//! Each passage becomes a text node with the passage text (links removed) and, if the passage
+//! has links, a choice node after it with a choice for each link.
+//! The supported link formats are `[[Target]]`, `[[Text->Target]]`, `[[Target<-Text]]` and `[[Text|Target]]`.
+//! Story formats macros (Harlowe, SugarCube...) are not interpreted and are left in the text.
+//!
+//! The starting passage is the `start` of the `StoryData` passage, or the passage named `Start`,
+//! or the first passage in the file.
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    utils::{BoxedFuture, HashMap},
+};
+use thiserror::Error;
+
+use crate::prelude::{IrChoice, IrError, IrNode, NodeKind, TalkData, TalkIr};
+
+/// Load Talks from twee assets.
+pub struct TwineLoader;
+
+/// The error type for the Twine Talks loader.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum TwineLoaderError {
+    /// An [IO Error](std::io::Error)
+    #[error("Could not read the file: {0}")]
+    Io(#[from] std::io::Error),
+    /// The file is not valid UTF-8
+    #[error("Could not read the file as UTF-8: {0}")]
+    Utf8(#[from] std::string::FromUtf8Error),
+    /// The file has no story passages
+    #[error("No passages found in the twee file")]
+    NoPassages,
+    /// A link points to a passage that does not exist
+    #[error("the passage {0} links to passage {1} which was not found")]
+    UnknownPassage(String, String),
+    /// The talk is not well formed
+    #[error("Invalid talk: {0}")]
+    Invalid(#[from] IrError),
+}
+
+impl AssetLoader for TwineLoader {
+    type Asset = TalkData;
+    type Settings = ();
+    type Error = TwineLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let talk_ir = parse_twee(&String::from_utf8(bytes)?)?;
+            Ok(TalkData::try_from(talk_ir)?.with_asset_id(load_context.path(), None))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["twee", "tw"]
+    }
+}
+
+/// A passage of a twee file.
+#[derive(Debug, Default)]
+struct Passage {
+    /// The name of the passage.
+    name: String,
+    /// The tags of the passage.
+    tags: Vec<String>,
+    /// The text of the passage, links included.
+    body: String,
+}
+
+/// A link inside a passage.
+#[derive(Debug, PartialEq, Eq)]
+struct Link {
+    /// The text shown for the link.
+    text: String,
+    /// The name of the target passage.
+    target: String,
+}
+
+/// Parses a twee source into the IR.
+pub(crate) fn parse_twee(source: &str) -> Result<TalkIr, TwineLoaderError> {
+    let passages = split_passages(source);
+
+    let start_name = passages
+        .iter()
+        .find(|p| p.name == "StoryData")
+        .and_then(|p| story_data_start(&p.body));
+
+    // remove the special passages
+    let mut story: Vec<Passage> = passages
+        .into_iter()
+        .filter(|p| !matches!(p.name.as_str(), "StoryTitle" | "StoryData"))
+        .filter(|p| !p.tags.iter().any(|t| t == "script" || t == "stylesheet"))
+        .collect();
+
+    if story.is_empty() {
+        return Err(TwineLoaderError::NoPassages);
+    }
+
+    // put the starting passage first, so it becomes the entry point of the talk
+    let start_index = start_name
+        .and_then(|name| story.iter().position(|p| p.name == name))
+        .or_else(|| story.iter().position(|p| p.name == "Start"))
+        .unwrap_or(0);
+    let start = story.remove(start_index);
+    story.insert(0, start);
+
+    // each passage has (at most) a text node with id 2*i and a choice node with id 2*i+1
+    let parsed: Vec<(String, Vec<Link>)> = story.iter().map(|p| split_links(&p.body)).collect();
+    let entry_ids: HashMap<&str, usize> = story
+        .iter()
+        .zip(parsed.iter())
+        .enumerate()
+        .map(|(i, (p, (text, links)))| {
+            let id = if text.is_empty() && !links.is_empty() {
+                2 * i + 1
+            } else {
+                2 * i
+            };
+            (p.name.as_str(), id)
+        })
+        .collect();
+
+    let mut nodes = vec![];
+    for (i, (passage, (text, links))) in story.iter().zip(parsed.iter()).enumerate() {
+        let mut choices = Vec::with_capacity(links.len());
+        for link in links {
+            let Some(next) = entry_ids.get(link.target.as_str()) else {
+                return Err(TwineLoaderError::UnknownPassage(
+                    passage.name.clone(),
+                    link.target.clone(),
+                ));
+            };
+            choices.push(IrChoice {
+                text: link.text.clone(),
+                next: *next,
+                description: None,
+                sound: None,
+                key: None,
+            });
+        }
+
+        if !text.is_empty() || links.is_empty() {
+            nodes.push(IrNode {
+                id: 2 * i,
+                text: text.clone(),
+                next: (!links.is_empty()).then_some(2 * i + 1),
+                ..Default::default()
+            });
+        }
+        if !links.is_empty() {
+            nodes.push(IrNode {
+                id: 2 * i + 1,
+                kind: NodeKind::Choice,
+                choices,
+                ..Default::default()
+            });
+        }
+    }
+
+    Ok(TalkIr {
+        nodes,
+        ..Default::default()
+    })
+}
+
+/// Splits the twee source in passages. Lines before the first passage header are ignored.
+fn split_passages(source: &str) -> Vec<Passage> {
+    let mut passages: Vec<Passage> = vec![];
+    for line in source.lines() {
+        if let Some(header) = line.strip_prefix("::") {
+            passages.push(parse_header(header));
+        } else if let Some(passage) = passages.last_mut() {
+            passage.body.push_str(line);
+            passage.body.push('\n');
+        }
+    }
+    passages
+}
+
+/// Parses a passage header (`:: Name [tag1 tag2] {"position":"0,0"}`), without the `::`.
+fn parse_header(header: &str) -> Passage {
+    let mut name = String::new();
+    let mut rest = "";
+    let mut chars = header.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some((_, escaped)) = chars.next() {
+                    name.push(escaped);
+                }
+            }
+            '[' | '{' => {
+                rest = &header[i..];
+                break;
+            }
+            _ => name.push(c),
+        }
+    }
+
+    let tags = rest
+        .strip_prefix('[')
+        .and_then(|r| r.split_once(']'))
+        .map(|(tags, _)| tags.split_whitespace().map(String::from).collect())
+        .unwrap_or_default();
+
+    Passage {
+        name: name.trim().to_string(),
+        tags,
+        body: String::new(),
+    }
+}
+
+/// Extracts the `start` passage name from the JSON of the `StoryData` passage.
+fn story_data_start(json: &str) -> Option<String> {
+    let after_key = &json[json.find("\"start\"")? + "\"start\"".len()..];
+    let after_colon = after_key.trim_start().strip_prefix(':')?.trim_start();
+    let value = after_colon.strip_prefix('"')?;
+    Some(value[..value.find('"')?].to_string())
+}
+
+/// Removes the links from the passage body, returning the cleaned text and the links.
+fn split_links(body: &str) -> (String, Vec<Link>) {
+    let mut text = String::with_capacity(body.len());
+    let mut links = vec![];
+    let mut rest = body;
+    while let Some(open) = rest.find("[[") {
+        let Some(close) = rest[open..].find("]]") else {
+            break;
+        };
+        text.push_str(&rest[..open]);
+        links.push(parse_link(&rest[open + 2..open + close]));
+        rest = &rest[open + close + 2..];
+    }
+    text.push_str(rest);
+
+    let text = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    (text, links)
+}
+
+/// Parses the content of a link (what is inside the `[[ ]]`).
+fn parse_link(content: &str) -> Link {
+    let (text, target) = if let Some((text, target)) = content.rsplit_once("->") {
+        (text, target)
+    } else if let Some((target, text)) = content.split_once("<-") {
+        (text, target)
+    } else if let Some((text, target)) = content.rsplit_once('|') {
+        (text, target)
+    } else {
+        (content, content)
+    };
+    Link {
+        text: text.trim().to_string(),
+        target: target.trim().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STORY: &str = r#":: StoryTitle
+A test
+
+:: StoryData
+{
+  "ifid": "D674C58C-DEFA-4F70-B7A2-27742230C0FC",
+  "start": "Intro"
+}
+
+:: Style [stylesheet]
+body { color: red; }
+
+:: Outro
+Bye!
+
+:: Intro [some-tag] {"position":"100,100"}
+Hello there.
+[[Leave->Outro]]
+[[Stay]]
+
+:: Stay
+[[Outro<-Ok, I'll go]]
+"#;
+
+    #[test]
+    fn parses_passages_and_links() {
+        let ir = parse_twee(STORY).unwrap();
+        assert!(ir.validate().is_ok());
+
+        // Intro is the start: text node 0 and choice node 1
+        assert_eq!(ir.nodes[0].id, 0);
+        assert_eq!(ir.nodes[0].text, "Hello there.");
+        assert_eq!(ir.nodes[0].next, Some(1));
+        assert_eq!(ir.nodes[1].kind, NodeKind::Choice);
+        assert_eq!(
+            ir.nodes[1].choices,
+            vec![
+                IrChoice {
+                    text: "Leave".to_string(),
+                    next: 2,
+                    ..Default::default()
+                },
+                IrChoice {
+                    text: "Stay".to_string(),
+                    next: 5,
+                    ..Default::default()
+                },
+            ]
+        );
+        // Outro is a simple end text node
+        assert_eq!(ir.nodes[2].text, "Bye!");
+        assert_eq!(ir.nodes[2].next, None);
+        // Stay has only a link, so it's just a choice node
+        assert_eq!(ir.nodes[3].id, 5);
+        assert_eq!(ir.nodes[3].choices[0].text, "Ok, I'll go");
+        assert_eq!(ir.nodes.len(), 4);
+    }
+
+    #[test]
+    fn error_unknown_passage() {
+        let res = parse_twee(":: Start\n[[Nowhere]]\n");
+        assert!(matches!(res, Err(TwineLoaderError::UnknownPassage(_, _))));
+    }
+
+    #[test]
+    fn error_no_passages() {
+        let res = parse_twee(":: StoryTitle\nNothing\n");
+        assert!(matches!(res, Err(TwineLoaderError::NoPassages)));
+    }
+
+    #[test]
+    fn test_parse_link() {
+        let expected = Link {
+            text: "Go".to_string(),
+            target: "Room".to_string(),
+        };
+        assert_eq!(parse_link("Go->Room"), expected);
+        assert_eq!(parse_link("Room<-Go"), expected);
+        assert_eq!(parse_link("Go|Room"), expected);
+        assert_eq!(parse_link("Room").target, "Room");
+    }
+}
@@ -0,0 +1,3 @@
+//! Asset loader for Talks from Twine "twee" files.
+
+pub(crate) mod loader;
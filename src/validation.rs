@@ -0,0 +1,144 @@
+//! Validation helpers shared by the builder and the loaders, to give helpful errors on typos.
+
+use bevy::{
+    ecs::reflect::ReflectComponent,
+    reflect::{DynamicTypePath, TypeRegistry},
+};
+
+use crate::{
+    builder::{all_build_nodes, TalkBuilder},
+    prelude::{Actor, ActorSlug, BuildError},
+};
+
+/// Returns the Levenshtein edit distance (in chars) between two strings.
+pub(crate) fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Returns the known slugs that are near-misses of the unknown one, nearest first.
+///
+/// A slug is a near-miss if its edit distance is at most a third of the unknown slug length (at least 2).
+pub(crate) fn suggest_slugs<'a>(
+    slug: &str,
+    known: impl IntoIterator<Item = &'a str>,
+) -> Vec<ActorSlug> {
+    let max_distance = (slug.chars().count() / 3).max(2);
+    let mut suggestions = known
+        .into_iter()
+        .map(|k| (levenshtein(slug, k), k))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect::<Vec<_>>();
+    suggestions.sort();
+    suggestions.dedup();
+    suggestions
+        .into_iter()
+        .map(|(_, k)| k.to_string())
+        .collect()
+}
+
+/// Formats the suggestions for the error messages (empty if there are none).
+pub(crate) fn did_you_mean(suggestions: &[ActorSlug]) -> String {
+    if suggestions.is_empty() {
+        return String::new();
+    }
+    let quoted = suggestions
+        .iter()
+        .map(|s| format!("\"{s}\""))
+        .collect::<Vec<_>>();
+    format!(" Did you mean {}?", quoted.join(" or "))
+}
+
+/// Checks that all the nodes of the builder (branches included) are performed by known actors
+/// (or the "player" pseudo-actor).
+pub(crate) fn validate_builder_actors(
+    builder: &TalkBuilder,
+    known: &[ActorSlug],
+) -> Result<(), BuildError> {
+    for node in all_build_nodes(&builder.queue) {
+        for slug in node.actors.iter() {
+            if !known.contains(slug) && slug != Actor::PLAYER_SLUG {
+                return Err(BuildError::InvalidActor {
+                    slug: slug.clone(),
+                    suggestions: suggest_slugs(slug, known.iter().map(String::as_str)),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that all the components of the nodes of the builder (branches included) are registered
+/// with `ReflectComponent`, so they can be inserted in the node entities.
+pub(crate) fn validate_builder_components(
+    builder: &TalkBuilder,
+    registry: &TypeRegistry,
+) -> Result<(), BuildError> {
+    for node in all_build_nodes(&builder.queue) {
+        for component in node.components.iter() {
+            if registry
+                .get_type_data::<ReflectComponent>((**component).type_id())
+                .is_none()
+            {
+                return Err(BuildError::UnregisteredComponent {
+                    component: component.reflect_type_path().to_string(),
+                    node: node.id,
+                    location: node.location,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("bob", "bob", 0)]
+    #[case("bob", "bib", 1)]
+    #[case("alice", "alcie", 2)]
+    #[case("", "abc", 3)]
+    #[case("kitten", "sitting", 3)]
+    fn levenshtein_distance(#[case] a: &str, #[case] b: &str, #[case] distance: usize) {
+        assert_eq!(levenshtein(a, b), distance);
+        assert_eq!(levenshtein(b, a), distance);
+    }
+
+    #[test]
+    fn near_miss_suggestions() {
+        let known = ["alice", "alicia", "bob", "narrator"];
+        assert_eq!(suggest_slugs("alcie", known), vec!["alice", "alicia"]);
+        assert_eq!(suggest_slugs("narator", known), vec!["narrator"]);
+        assert!(suggest_slugs("zed", known).is_empty());
+    }
+
+    #[test]
+    fn builder_actors_are_validated() {
+        let known = vec!["alice".to_string()];
+        let builder = TalkBuilder::default().choose(vec![(
+            "Hi",
+            TalkBuilder::default().actor_say("alcie", "Hello"),
+        )]);
+        let err = validate_builder_actors(&builder, &known).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Tried to use non-existent actor alcie in the builder. Did you forget to add it? Did you mean \"alice\"?"
+        );
+        let builder = TalkBuilder::default().actor_say(Actor::PLAYER_SLUG, "Hello");
+        assert!(validate_builder_actors(&builder, &known).is_ok());
+    }
+}
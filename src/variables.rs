@@ -0,0 +1,207 @@
+//! The talk variables and the switch nodes routing on them.
+
+use aery::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::prelude::FollowedBy;
+
+/// Resource with the variables the [`SwitchNode`]s route on (variable name => value).
+///
+/// # Example
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy_talks::prelude::*;
+///
+/// fn make_angry(mut variables: ResMut<TalkVariables>) {
+///     variables.set("mood", "angry");
+/// }
+/// ```
+#[derive(Resource, Default, Debug, Clone, PartialEq, Eq)]
+pub struct TalkVariables(HashMap<String, String>);
+
+impl TalkVariables {
+    /// Sets the value of the variable, returning the previous one.
+    pub fn set(&mut self, variable: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        self.0.insert(variable.into(), value.into())
+    }
+
+    /// Returns the value of the variable, if it is set.
+    pub fn get(&self, variable: &str) -> Option<&str> {
+        self.0.get(variable).map(String::as_str)
+    }
+
+    /// Removes the variable, returning its value.
+    pub fn remove(&mut self, variable: &str) -> Option<String> {
+        self.0.remove(variable)
+    }
+
+    /// Returns an iterator over the variables and their values.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+/// Component for the nodes that route the talk silently, based on the value of a [`TalkVariables`] variable.
+///
+/// Once the talk moves to a switch node, it moves right away to the target of the case matching the
+/// value of the variable, or to the `default` target if no case matches (or the variable is not set).
+/// A `None` target (an empty branch) goes on with the node after the switch.
+///
+/// The switch nodes are added with [`TalkBuilder::switch`](crate::prelude::TalkBuilder::switch).
+#[derive(Component, Reflect, Default, Debug, Clone, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct SwitchNode {
+    /// The name of the variable to route on.
+    pub variable: String,
+    /// The cases (value => target node).
+    pub cases: Vec<(String, Option<Entity>)>,
+    /// The target when no case matches.
+    pub default: Option<Entity>,
+}
+
+impl SwitchNode {
+    /// Returns the target for the given value of the variable. `None` goes on with the node after the switch.
+    pub fn target(&self, value: Option<&str>) -> Option<Entity> {
+        value
+            .and_then(|value| self.cases.iter().find(|(case, _)| case == value))
+            .map_or(self.default, |(_, target)| *target)
+    }
+
+    /// Returns whether the given node is the target of a case (or the default one).
+    fn is_branch(&self, node: Entity) -> bool {
+        self.default == Some(node) || self.cases.iter().any(|(_, t)| *t == Some(node))
+    }
+}
+
+/// Returns the node the talk is routed to from the given switch node, read from the [`TalkVariables`].
+///
+/// It runs in the routing command of the traversal, so the effects of the logic nodes
+/// passed on the way have already set the variables.
+pub(crate) fn switch_target(world: &mut World, node: Entity) -> Option<Entity> {
+    let switch = world.get::<SwitchNode>(node)?.clone();
+    let value = world
+        .get_resource::<TalkVariables>()
+        .and_then(|variables| variables.get(&switch.variable))
+        .map(str::to_owned);
+    switch
+        .target(value.as_deref())
+        .or_else(|| node_after(world, node, |f| switch.is_branch(f)))
+}
+
+/// Returns the node after the given switch or random node: its follower that is not a branch.
+pub(crate) fn node_after(
+    world: &mut World,
+    node: Entity,
+    is_branch: impl Fn(Entity) -> bool,
+) -> Option<Entity> {
+    world
+        .query::<Relations<FollowedBy>>()
+        .get(world, node)
+        .ok()?
+        .targets(FollowedBy)
+        .iter()
+        .copied()
+        .find(|f| !is_branch(*f))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::Command;
+
+    use crate::{
+        prelude::*,
+        tests::{single, talks_minimal_app},
+    };
+
+    use super::*;
+
+    /// Builds the talk with a switch on the mood and returns the talk entity.
+    fn mood_talk(app: &mut App) -> Entity {
+        let builder = TalkBuilder::default().say("Hi").switch(
+            "mood",
+            vec![
+                ("angry", TalkBuilder::default().say("Go away!")),
+                ("happy", TalkBuilder::default()),
+            ],
+            TalkBuilder::default().say("Hm."),
+        );
+        let builder = builder.say("Nice to see you.");
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        talk
+    }
+
+    /// Sends a next request and updates the app, returning the current text.
+    fn next_text(app: &mut App, talk: Entity) -> String {
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+        let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        app.world.get::<TextNode>(current).unwrap().0.clone()
+    }
+
+    #[test]
+    fn switch_routes_on_the_variable() {
+        let mut app = talks_minimal_app();
+        let talk = mood_talk(&mut app);
+        let switch = single::<&SwitchNode>(&mut app.world).clone();
+        assert_eq!(switch.variable, "mood");
+        assert_eq!(switch.cases.len(), 2);
+        assert_eq!(switch.cases[1].1, None);
+
+        assert_eq!(next_text(&mut app, talk), "Hi");
+        app.world
+            .resource_mut::<TalkVariables>()
+            .set("mood", "angry");
+        assert_eq!(next_text(&mut app, talk), "Go away!");
+
+        let talk = mood_talk(&mut app);
+        app.world
+            .resource_mut::<TalkVariables>()
+            .set("mood", "happy");
+        next_text(&mut app, talk);
+        // the empty branch goes on after the switch
+        assert_eq!(next_text(&mut app, talk), "Nice to see you.");
+    }
+
+    #[test]
+    fn switch_default_when_unset() {
+        let mut app = talks_minimal_app();
+        let talk = mood_talk(&mut app);
+        next_text(&mut app, talk);
+        assert_eq!(next_text(&mut app, talk), "Hm.");
+    }
+
+    #[test]
+    fn switch_routed_after_a_locked_out_choice() {
+        let mut app = talks_minimal_app();
+        app.insert_resource(ChoiceLockout::Frame);
+        app.world
+            .resource_mut::<TalkVariables>()
+            .set("mood", "angry");
+        let builder = TalkBuilder::default().choose(vec![(
+            "Hello",
+            TalkBuilder::default().switch(
+                "mood",
+                vec![("angry", TalkBuilder::default().say("Go away!"))],
+                TalkBuilder::default().say("Hm."),
+            ),
+        )]);
+        let talk = app.world.spawn(Talk::default()).id();
+        BuildTalkCommand::new(talk, builder).apply(&mut app.world);
+        app.world.send_event(NextNodeRequest::new(talk));
+        app.update();
+
+        let switch = app
+            .world
+            .query_filtered::<Entity, With<SwitchNode>>()
+            .single(&app.world);
+        app.world.send_event(ChooseNodeRequest::new(talk, switch));
+        app.update();
+
+        // routed in the same update, the talk never stops on the switch
+        let current = app.world.resource::<CurrentNodes>().get(talk).unwrap();
+        assert_eq!(app.world.get::<TextNode>(current).unwrap().0, "Go away!");
+        let evs = app.world.resource::<Events<CurrentNodeChanged>>();
+        assert!(evs.get_reader().read(evs).all(|ev| ev.current != switch));
+    }
+}
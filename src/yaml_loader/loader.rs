@@ -0,0 +1,100 @@
+//! The YAML Asset Loader.
+//!
+//! A `.talk.yaml` file has the same structure as a `.talk.ron` file, with the YAML syntax:
+//!
+//! ```yaml
+//! actors:
+//!   - slug: bob
+//!     name: Bob
+//! script:
+//!   - id: 1
+//!     action: Join
+//!     actors: [bob]
+//!     next: 2
+//!   - id: 2
+//!     actors: [bob]
+//!     text: Hello!
+//! ```
+//!
+//! The optional fields can be omitted, the enum values with data are tagged (`action: !Custom shop`).
+//! The named talks of the packs and the locales are supported too, with the same RON sidecar files.
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    utils::BoxedFuture,
+};
+use thiserror::Error;
+
+use crate::prelude::{RonLoaderError, TalkData};
+use crate::ron_loader::{loader::load_talk_data, types::RonTalk};
+
+/// Load Talks from yaml assets.
+pub struct YamlTalksLoader;
+
+/// The error type for the YAML Talks loader.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum YamlLoaderError {
+    /// An [IO Error](std::io::Error)
+    #[error("Could not read the file: {0}")]
+    Io(#[from] std::io::Error),
+    /// A [YAML Error](serde_yaml::Error)
+    #[error("Could not parse YAML: {0}")]
+    YamlError(#[from] serde_yaml::Error),
+    /// The talk, a named talk of the pack or a locale is not valid, with the same checks as the RON talks
+    #[error(transparent)]
+    Talk(#[from] RonLoaderError),
+}
+
+impl AssetLoader for YamlTalksLoader {
+    type Asset = TalkData;
+    type Settings = ();
+    type Error = YamlLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let yaml_talk = serde_yaml::from_slice::<RonTalk>(&bytes)?;
+            Ok(load_talk_data(yaml_talk, load_context).await?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["talk.yaml", "talk.yml"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{asset::LoadState, prelude::*};
+
+    use crate::tests::load_talk_asset;
+
+    use super::*;
+
+    #[test]
+    fn yaml_pack_loaded_with_labels() {
+        // JSON is YAML, the same fixture as the JSON loader
+        let pack = include_str!("../../assets/talks/pack.talk.json");
+        let (app, handle) =
+            load_talk_asset(&[("talks/pack.talk.yaml", pack)], "talks/pack.talk.yaml");
+        let server = app.world.resource::<AssetServer>();
+        assert_eq!(server.load_state(&handle), LoadState::Loaded);
+
+        let talks = app.world.resource::<Assets<TalkData>>();
+        let intro = server.load::<TalkData>("talks/pack.talk.yaml#intro");
+        assert_eq!(talks.get(&intro).unwrap().script.len(), 2);
+        assert_eq!(talks.get(&handle).unwrap().talks.len(), 2);
+
+        let err = serde_yaml::from_str::<RonTalk>("script:\n  - text: Hi\n")
+            .map_err(YamlLoaderError::from)
+            .unwrap_err();
+        assert!(err.to_string().starts_with("Could not parse YAML"));
+    }
+}
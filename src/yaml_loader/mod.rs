@@ -0,0 +1,3 @@
+//! Asset loader for Talks from "talk.yaml" files. Available with the `yaml` feature.
+
+pub(crate) mod loader;
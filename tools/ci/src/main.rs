@@ -13,6 +13,7 @@ bitflags! {
         const DOC_TEST = 0b00010000;
         const DOC_CHECK = 0b00100000;
         const COMPILE_CHECK = 0b100000000;
+        const BENCH = 0b1000000000;
     }
 }
 
@@ -38,6 +39,7 @@ fn main() {
         ("clippy", Check::CLIPPY),
         ("doc-check", Check::DOC_CHECK),
         ("doc-test", Check::DOC_TEST),
+        ("bench", Check::BENCH),
     ];
 
     let what_to_run = if let Some(arg) = std::env::args().nth(1).as_deref() {
@@ -105,4 +107,49 @@ fn main() {
             .run()
             .expect("Please fix compiler errors in above output.");
     }
+
+    if what_to_run.contains(Check::BENCH) {
+        // Measure the performance budget, see benches/perf_budget.rs
+        cmd!(sh, "cargo bench --bench perf_budget")
+            .run()
+            .expect("Please fix the benchmarks in output above.");
+        warn_over_budget(&sh);
+    }
+}
+
+/// The advisory budgets of the `perf_budget` workloads (criterion id, mean time in milliseconds).
+/// Scale them with the `TALKS_PERF_BUDGET_SCALE` env var (e.g. `2` on a slow machine).
+const PERF_BUDGETS: [(&str, f64); 5] = [
+    ("build_linear_10k", 300.0),
+    ("build_branching_10k", 600.0),
+    ("advance_1k_talks", 50.0),
+    ("emit_10k_events", 100.0),
+    ("load_ron_10k", 200.0),
+];
+
+/// Prints a warning for each `perf_budget` workload whose mean time measured by criterion is over its budget.
+/// The budgets are advisory: it never fails.
+fn warn_over_budget(sh: &Shell) {
+    let scale = std::env::var("TALKS_PERF_BUDGET_SCALE")
+        .ok()
+        .and_then(|scale| scale.parse::<f64>().ok())
+        .unwrap_or(1.0);
+    for (id, budget) in PERF_BUDGETS {
+        let path = format!("target/criterion/{id}/new/estimates.json");
+        let mean = sh
+            .read_file(&path)
+            .ok()
+            .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+            .and_then(|estimates| estimates["mean"]["point_estimate"].as_f64());
+        let Some(mean) = mean.map(|nanos| nanos / 1_000_000.0) else {
+            println!("::warning::No criterion estimates for {id} in {path}");
+            continue;
+        };
+        let budget = budget * scale;
+        if mean > budget {
+            println!("::warning::{id} is over its performance budget: {mean:.2}ms (budget {budget:.2}ms)");
+        } else {
+            println!("{id}: {mean:.2}ms (budget {budget:.2}ms)");
+        }
+    }
 }